@@ -104,4 +104,105 @@ export function parseTree(e: Extension): Command {
       preview: true
     })
   }
-}
\ No newline at end of file
+}
+export function showReferences(e: Extension): Command {
+  return async (uri: string, position: lsp.Position, locations: lsp.Location[]) => {
+    await vscode.commands.executeCommand(
+      'editor.action.showReferences',
+      vscode.Uri.parse(uri),
+      e.lspClient.protocol2CodeConverter.asPosition(position),
+      locations.map(e.lspClient.protocol2CodeConverter.asLocation),
+    )
+  }
+}
+
+export function applyWorkspaceEdit(e: Extension): Command {
+  return async (edit: lsp.WorkspaceEdit) => {
+    await vscode.workspace.applyEdit(await e.lspClient.protocol2CodeConverter.asWorkspaceEdit(edit))
+  }
+}
+
+export function copySignature(e: Extension): Command {
+  return async () => {
+    const editor = vscode.window.activeTextEditor
+    if (editor.document.languageId != 'glsl') return
+
+    const signature = await e.lspClient.sendRequest<string | null>(lsp.ExecuteCommandRequest.type.method, {
+      command: 'copySignature',
+      arguments: [editor.document.uri.path, e.lspClient.code2ProtocolConverter.asPosition(editor.selection.active)],
+    })
+    if (signature === null) {
+      vscode.window.showInformationMessage('No declaration found at the cursor')
+      return
+    }
+
+    await vscode.env.clipboard.writeText(signature)
+  }
+}
+
+export function workspaceDiagnostics(e: Extension): Command {
+  const collection = vscode.languages.createDiagnosticCollection('mcglsl-workspace')
+  e.context.subscriptions.push(collection)
+
+  return async () => {
+    const diagnostics = await vscode.window.withProgress(
+      { location: vscode.ProgressLocation.Notification, title: 'Linting workspace' },
+      () => e.lspClient.sendRequest<{ [uri: string]: lsp.Diagnostic[] }>(lsp.ExecuteCommandRequest.type.method, {
+        command: 'workspaceDiagnostics',
+        arguments: [],
+      }),
+    )
+
+    collection.clear()
+    for (const [uri, fileDiagnostics] of Object.entries(diagnostics)) {
+      collection.set(
+        vscode.Uri.parse(uri),
+        await e.lspClient.protocol2CodeConverter.asDiagnostics(fileDiagnostics),
+      )
+    }
+    await vscode.commands.executeCommand('workbench.actions.view.problems')
+  }
+}
+
+export function exportSymbols(e: Extension): Command {
+  return async () => {
+    const output = await vscode.window.showSaveDialog({
+      defaultUri: vscode.Uri.joinPath(vscode.workspace.workspaceFolders[0].uri, 'symbols.json'),
+      filters: { 'JSON': ['json'] },
+    })
+    if (output === undefined) return
+
+    const count = await e.lspClient.sendRequest<number>(lsp.ExecuteCommandRequest.type.method, {
+      command: 'exportSymbols',
+      arguments: [output.path],
+    })
+    vscode.window.showInformationMessage(`Exported ${count} symbols to ${output.fsPath}`)
+  }
+}
+
+export function serverInfo(e: Extension): Command {
+  return async () => {
+    const info = await e.lspClient.sendRequest<object>(lsp.ExecuteCommandRequest.type.method, {
+      command: 'serverInfo',
+      arguments: [],
+    })
+    const document = await vscode.workspace.openTextDocument({ language: 'json', content: JSON.stringify(info, null, 2) })
+    await vscode.window.showTextDocument(document)
+  }
+}
+
+export function builtinDocumentProvider(e: Extension): vscode.TextDocumentContentProvider {
+  return new class implements vscode.TextDocumentContentProvider {
+    async provideTextDocumentContent(uri: vscode.Uri, _: vscode.CancellationToken): Promise<string> {
+      let content: string | null = null
+      try {
+        content = await e.lspClient.sendRequest<string | null>(lsp.ExecuteCommandRequest.type.method, {
+          command: 'builtinDocument',
+          arguments: [uri.toString()]
+        })
+      } catch (e) { }
+
+      return content ?? ''
+    }
+  }
+}
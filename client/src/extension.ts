@@ -49,6 +49,12 @@ export class Extension {
     this.registerCommand('restart', commands.restartExtension)
     this.registerCommand('virtualMerge', commands.virtualMergedDocument)
     this.registerCommand('parseTree', commands.parseTree)
+    this.registerCommand('showReferences', commands.showReferences)
+    this.registerCommand('applyWorkspaceEdit', commands.applyWorkspaceEdit)
+    this.registerCommand('copySignature', commands.copySignature)
+    this.registerCommand('workspaceDiagnostics', commands.workspaceDiagnostics)
+    this.registerCommand('exportSymbols', commands.exportSymbols)
+    this.registerCommand('serverInfo', commands.serverInfo)
 
     log.info('starting language server...')
 
@@ -61,6 +67,11 @@ export class Extension {
 
     this.client = await new LanguageClient(this, lspBinary, filewatcherGlob).startServer()
 
+    // the read-only declarations of built-ins, which going to their definition opens
+    this.context.subscriptions.push(
+      vscode.workspace.registerTextDocumentContentProvider('glsl-builtin', commands.builtinDocumentProvider(this))
+    )
+
     log.info('language server started!')
   }
 
@@ -4,6 +4,16 @@ import { Extension } from './extension'
 import { log, lspOutputChannel } from './log'
 import { ConfigUpdateParams, statusMethod, StatusParams, updateConfigMethod } from './lspExt'
 
+// the settings the server only reads once, when it's initialized, see `InitializationOptions` of the server
+const initializationOptions = () => {
+  const config = workspace.getConfiguration('mcglsl')
+  return {
+    includeRoots: config.get<string[]>('includeRoots'),
+    parseTimeoutMs: config.get<number>('parseTimeoutMs'),
+    diagnosticSeverities: config.get<{ [code: string]: string }>('diagnosticSeverities'),
+  }
+}
+
 export class LanguageClient extends lsp.LanguageClient {
   private extension: Extension
 
@@ -13,6 +23,7 @@ export class LanguageClient extends lsp.LanguageClient {
     }, {
       documentSelector: [{ scheme: 'file', language: 'glsl' }],
       outputChannel: lspOutputChannel,
+      initializationOptions: initializationOptions(),
       synchronize: {
         configurationSection: 'mcglsl',
         fileEvents: workspace.createFileSystemWatcher(filewatcherGlob)
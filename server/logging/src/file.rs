@@ -0,0 +1,146 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// The size a log file may grow to before it's moved aside to `<path>.1`, replacing the one moved aside before it.
+pub const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Writes the records logged to a file from a thread of its own, so that logging to a slow disk doesn't hold up
+/// the requests being logged. Each flush hands the record written since the last one over to the thread. Once
+/// dropped, the records still queued are written out before the file is closed.
+pub struct RotatingFileWriter {
+    buffer: Vec<u8>,
+    sender: Option<Sender<Vec<u8>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RotatingFileWriter {
+    pub fn open(path: &Path, max_size: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_path_buf();
+        let thread = thread::Builder::new()
+            .name("log-file".into())
+            .spawn(move || write_records(path, BufWriter::new(file), size, max_size, receiver))?;
+
+        Ok(RotatingFileWriter {
+            buffer: vec![],
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let record = std::mem::take(&mut self.buffer);
+        match &self.sender {
+            Some(sender) => sender
+                .send(record)
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "log file thread stopped")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RotatingFileWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        // the thread stops once the channel is closed and everything sent through it is written
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// writes the records received until the channel closes, flushing whenever there are none queued. Errors can't be
+// logged without logging to the file again, so they're printed to stderr instead.
+fn write_records(path: PathBuf, mut file: BufWriter<File>, mut size: u64, max_size: u64, receiver: Receiver<Vec<u8>>) {
+    while let Ok(record) = receiver.recv() {
+        let mut next = Some(record);
+        while let Some(record) = next {
+            if size > 0 && size + record.len() as u64 > max_size {
+                match rotate(&path, &mut file) {
+                    Ok(rotated) => {
+                        file = rotated;
+                        size = 0;
+                    }
+                    Err(e) => eprintln!("failed to rotate log file {:?}: {}", path, e),
+                }
+            }
+
+            match file.write_all(&record) {
+                Ok(_) => size += record.len() as u64,
+                Err(e) => eprintln!("failed to write to log file {:?}: {}", path, e),
+            }
+            next = receiver.try_recv().ok();
+        }
+
+        if let Err(e) = file.flush() {
+            eprintln!("failed to flush log file {:?}: {}", path, e);
+        }
+    }
+}
+
+// moves the full log file aside, returning the new one started in its place.
+fn rotate(path: &Path, file: &mut BufWriter<File>) -> io::Result<BufWriter<File>> {
+    file.flush()?;
+
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    // renaming over an existing file fails on Windows
+    if let Err(e) = fs::remove_file(&rotated) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    fs::rename(path, &rotated)?;
+
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+#[cfg(test)]
+mod file_test {
+    use std::{fs, io::Write};
+
+    use crate::file::RotatingFileWriter;
+
+    #[test]
+    fn test_rotating_file_writer() {
+        let path = std::env::temp_dir().join(format!("mcshader-lsp-{}.log", std::process::id()));
+        let rotated = path.with_extension("log.1");
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+
+        // nine lines of seven bytes fit in 64 bytes, the tenth starts another file
+        {
+            let mut writer = RotatingFileWriter::open(&path, 64).unwrap();
+            for line in 0..10 {
+                writeln!(writer, "line {}", line).unwrap();
+                writer.flush().unwrap();
+            }
+        }
+
+        let expected: String = (0..9).map(|line| format!("line {}\n", line)).collect();
+        assert_eq!(fs::read_to_string(&rotated).unwrap(), expected);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line 9\n");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+}
@@ -2,14 +2,16 @@ use rand::{rngs, Rng};
 use slog::slog_o;
 use slog_scope::GlobalLoggerGuard;
 use slog_term::{FullFormat, PlainSyncDecorator};
-use std::{cell::RefCell, sync::Arc};
+use std::{cell::RefCell, path::Path, sync::Arc};
 
-use std::io::Stderr;
+use std::io::{self, Stderr};
 
 use lazy_static::lazy_static;
 use slog::*;
 use slog_atomic::*;
 
+pub mod file;
+
 fn new_trace_id() -> String {
     let rng = CURRENT_RNG.with(|rng| rng.borrow_mut().gen::<[u8; 4]>());
     return format!("{:04x}", u32::from_be_bytes(rng));
@@ -25,6 +27,18 @@ pub fn set_logger_with_level(level: Level) -> GlobalLoggerGuard {
     slog_scope::set_global_logger(Logger::root(drain, o!()))
 }
 
+/// Sets the global logger as with `set_logger_with_level`, additionally logging to the file at the path at its own
+/// level, such as to trace a bug without stderr filling up. The file is appended to and rotated once it grows past
+/// `file::MAX_LOG_FILE_SIZE`.
+pub fn set_logger_with_file(level: Level, path: &Path, file_level: Level) -> io::Result<GlobalLoggerGuard> {
+    let writer = file::RotatingFileWriter::open(path, file::MAX_LOG_FILE_SIZE)?;
+    let file_drain = FullFormat::new(PlainSyncDecorator::new(writer)).build().fuse();
+
+    let drain = Arc::new(Duplicate::new(logger_base(level), file_drain.filter_level(file_level)).fuse());
+    DRAIN_SWITCH.ctrl().set(drain.clone());
+    Ok(slog_scope::set_global_logger(Logger::root(drain, o!())))
+}
+
 fn logger_base(level: Level) -> LevelFilter<Fuse<FullFormat<PlainSyncDecorator<Stderr>>>> {
     let plain = slog_term::PlainSyncDecorator::new(std::io::stderr());
     let drain = slog_term::FullFormat::new(plain).build().fuse();
@@ -0,0 +1,154 @@
+//! Benchmarks of the navigation operations against a large shader, and of parsing a whole pack across the parser
+//! pool, run with `cargo bench`. The crate has no library target, so the modules navigation depends on are included
+//! directly.
+#![allow(dead_code)]
+
+use std::{
+    fs::{self, read_to_string},
+    path::PathBuf,
+    thread,
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rust_lsp::lsp_types::Position;
+use tempdir::TempDir;
+use tree_sitter::Parser;
+
+#[path = "../src/builtins.rs"]
+mod builtins;
+#[path = "../src/graph.rs"]
+mod graph;
+#[path = "../src/include_resolver.rs"]
+mod include_resolver;
+#[path = "../src/linemap.rs"]
+mod linemap;
+#[path = "../src/lsp_ext.rs"]
+mod lsp_ext;
+#[path = "../src/navigation.rs"]
+mod navigation;
+#[path = "../src/overloads.rs"]
+mod overloads;
+#[path = "../src/parser_pool.rs"]
+mod parser_pool;
+#[path = "../src/preprocessor.rs"]
+mod preprocessor;
+#[path = "../src/url_norm.rs"]
+mod url_norm;
+
+use navigation::ParserContext;
+use parser_pool::ParserPool;
+
+// the number of files in the pack the parser pool is benchmarked against, as many as a large shader pack has.
+const PACK_FILES: usize = 320;
+
+fn fixture() -> (PathBuf, String) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/10/large.fsh");
+    let source = read_to_string(&path).unwrap();
+    (path, source)
+}
+
+fn new_parser() -> Parser {
+    let mut parser = Parser::new();
+    parser.set_language(tree_sitter_glsl::language()).unwrap();
+    parser
+}
+
+// the position of the start of the last occurrence of the pattern in the source, plus an offset in bytes.
+fn position_of(ctx: &ParserContext, pattern: &str, offset: usize) -> Position {
+    ctx.position_for_offset(ctx.source().rfind(pattern).unwrap() + offset)
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let (_, source) = fixture();
+    let mut parser = new_parser();
+
+    c.bench_function("parse", |b| {
+        b.iter(|| {
+            let ctx = ParserContext::from_source(&mut parser, source.clone()).unwrap();
+            ctx.root_node().child_count()
+        })
+    });
+}
+
+fn bench_find_definitions(c: &mut Criterion) {
+    let (path, source) = fixture();
+    let mut parser = new_parser();
+    let ctx = ParserContext::from_source(&mut parser, source).unwrap();
+
+    let function = position_of(&ctx, "luma(color)", 0);
+    c.bench_function("find_definitions/function", |b| {
+        b.iter(|| ctx.find_definitions(&path, function).unwrap())
+    });
+
+    let variable = position_of(&ctx, "sun.power", 0);
+    c.bench_function("find_definitions/variable", |b| {
+        b.iter(|| ctx.find_definitions(&path, variable).unwrap())
+    });
+}
+
+fn bench_find_references(c: &mut Criterion) {
+    let (path, source) = fixture();
+    let mut parser = new_parser();
+    let ctx = ParserContext::from_source(&mut parser, source).unwrap();
+
+    let function = position_of(&ctx, "float luma(vec3 color) {", "float ".len());
+    c.bench_function("find_references", |b| b.iter(|| ctx.find_references(&path, function).unwrap()));
+}
+
+fn bench_list_symbols(c: &mut Criterion) {
+    let (path, source) = fixture();
+    let mut parser = new_parser();
+    let ctx = ParserContext::from_source(&mut parser, source).unwrap();
+
+    c.bench_function("list_symbols", |b| b.iter(|| ctx.list_symbols(&path).unwrap()));
+}
+
+// lists the symbols of every file of a pack as the workspace symbols are, with pools of up to as many workers as
+// there are cores, for the speedup of each worker added to show.
+fn bench_parser_pool(c: &mut Criterion) {
+    let (_, source) = fixture();
+    let pack = TempDir::new("mcshader-bench").unwrap();
+    let files: Vec<PathBuf> = (0..PACK_FILES)
+        .map(|i| {
+            let path = pack.path().join(format!("file{}.fsh", i));
+            fs::write(&path, &source).unwrap();
+            path
+        })
+        .collect();
+
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut workers = vec![1];
+    while workers.last().unwrap() * 2 <= cores {
+        workers.push(workers.last().unwrap() * 2);
+    }
+    if *workers.last().unwrap() != cores {
+        workers.push(cores);
+    }
+
+    let mut group = c.benchmark_group("workspace_symbols");
+    group.sample_size(10);
+    for n in workers {
+        let mut pool = ParserPool::new(n);
+        group.bench_with_input(BenchmarkId::new("workers", n), &files, |b, files| {
+            b.iter(|| {
+                pool.map(files, |parser, path| {
+                    let ctx = ParserContext::new(parser, path).unwrap();
+                    navigation::flatten_symbols(path, ctx.list_symbols(path).unwrap().unwrap_or_default())
+                        .unwrap()
+                        .len()
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parse,
+    bench_find_definitions,
+    bench_find_references,
+    bench_list_symbols,
+    bench_parser_pool
+);
+criterion_main!(benches);
@@ -0,0 +1,135 @@
+use rust_lsp::lsp_types::{Location, Position, Range, Url};
+
+use crate::builtins;
+use crate::navigation::is_ident_char;
+use crate::overloads;
+
+/// The scheme of the URIs of the read-only documents declaring built-ins, whose text the client asks for through
+/// the `builtinDocument` command to show in place of a definition in the shader pack.
+pub const SCHEME: &str = "glsl-builtin";
+
+/// Returns the URI of the document declaring the built-in, as `glsl-builtin:///texture.glsl`.
+pub fn uri(name: &str) -> Url {
+    Url::parse(&format!("{}:///{}.glsl", SCHEME, name)).unwrap()
+}
+
+/// Returns the name of the built-in declared by the document at the URI, if it's one of these documents.
+pub fn name(uri: &Url) -> Option<&str> {
+    if uri.scheme() != SCHEME {
+        return None;
+    }
+    uri.path().strip_prefix('/')?.strip_suffix(".glsl")
+}
+
+/// Generates the declarations of the built-in function or variable of the given name, from the same tables the
+/// rest of the server knows built-ins by, so that the same name always generates the same text. Functions are
+/// declared by each of their overloads and variables by the stages declaring them. Returns `None` for names
+/// declaring nothing, as built-in types don't.
+pub fn document(name: &str) -> Option<String> {
+    let mut sections: Vec<String> = vec![];
+
+    let overloads = overloads::overloads(name);
+    if !overloads.is_empty() {
+        let declarations: Vec<String> = overloads.iter().map(|overload| format!("{};", overload)).collect();
+        sections.push(declarations.join("\n"));
+    }
+
+    if let Some(builtin) = builtins::builtin_struct(name) {
+        // interface blocks are declared along with their fields, while structs are declared ahead of the variable
+        if builtin.declaration.contains('{') {
+            sections.push(builtin.declaration.to_string());
+        } else {
+            let fields: Vec<String> = builtin
+                .fields
+                .iter()
+                .map(|field| format!("    {} {}; // {}", field.ty, field.name, field.doc))
+                .collect();
+            sections.push(format!(
+                "struct {} {{\n{}\n}};\n{}",
+                builtin.ty,
+                fields.join("\n"),
+                builtin.declaration
+            ));
+        }
+    }
+
+    for (stage, variable) in builtins::variable_stages(name, None) {
+        sections.push(format!("// in {} shaders\n{}", stage, variable.declaration));
+    }
+
+    if sections.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "// The built-in declarations of `{}`, generated from those known to mcshader-lsp.\n\n{}\n",
+        name,
+        sections.join("\n\n")
+    ))
+}
+
+/// Returns the location of the first declaration of the built-in in its document.
+pub fn location(name: &str) -> Option<Location> {
+    let document = document(name)?;
+
+    // past the heading, the first line naming the built-in as a whole word declares it
+    let (line, character) = document.lines().enumerate().skip(1).find_map(|(line, text)| {
+        text.match_indices(name)
+            .map(|(character, _)| character)
+            .find(|&character| {
+                let before = text[..character].chars().next_back();
+                let after = text[character + name.len()..].chars().next();
+                !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char)
+            })
+            .map(|character| (line, character))
+    })?;
+
+    let start = Position::new(line as u32, character as u32);
+    let end = Position::new(line as u32, (character + name.len()) as u32);
+    Some(Location::new(uri(name), Range::new(start, end)))
+}
+
+#[cfg(test)]
+mod builtin_document_test {
+    use rust_lsp::lsp_types::{Position, Range, Url};
+
+    use crate::builtin_document::{document, location, name, uri};
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_builtin_document() {
+        assert_eq!(uri("texture").as_str(), "glsl-builtin:///texture.glsl");
+        assert_eq!(name(&uri("texture")), Some("texture"));
+        assert_eq!(name(&Url::parse("file:///texture.glsl").unwrap()), None);
+
+        // functions are declared by each of their overloads, deterministically
+        let asin = document("asin").unwrap();
+        assert_eq!(asin, document("asin").unwrap());
+        assert!(asin.contains("\ngenType asin(genType x);\n"));
+
+        // variables by each stage declaring them
+        let frag_coord = document("gl_FragCoord").unwrap();
+        assert!(frag_coord.contains("// in fragment shaders\nin vec4 gl_FragCoord;"));
+
+        // structs along with their fields
+        let depth_range = document("gl_DepthRange").unwrap();
+        assert!(depth_range.contains("struct gl_DepthRangeParameters {\n"));
+        assert!(depth_range.contains("\nuniform gl_DepthRangeParameters gl_DepthRange;\n"));
+
+        // the declaration is located at the name, rather than within another name it prefixes
+        let location = location("gl_DepthRange").unwrap();
+        let line = depth_range.lines().nth(location.range.start.line as usize).unwrap();
+        assert_eq!(line, "uniform gl_DepthRangeParameters gl_DepthRange;");
+        assert_eq!(
+            location.range,
+            Range::new(
+                Position::new(location.range.start.line, 32),
+                Position::new(location.range.start.line, 45)
+            )
+        );
+
+        // types declare nothing
+        assert_eq!(document("vec3"), None);
+        assert_eq!(location("vec3"), None);
+    }
+}
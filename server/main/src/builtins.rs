@@ -0,0 +1,721 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+/// Built-in functions provided by GLSL, across the versions in use by shader packs.
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    // angle and trigonometry
+    "radians",
+    "degrees",
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "sinh",
+    "cosh",
+    "tanh",
+    "asinh",
+    "acosh",
+    "atanh",
+    // exponential
+    "pow",
+    "exp",
+    "log",
+    "exp2",
+    "log2",
+    "sqrt",
+    "inversesqrt",
+    // common
+    "abs",
+    "sign",
+    "floor",
+    "trunc",
+    "round",
+    "roundEven",
+    "ceil",
+    "fract",
+    "mod",
+    "modf",
+    "min",
+    "max",
+    "clamp",
+    "mix",
+    "step",
+    "smoothstep",
+    "isnan",
+    "isinf",
+    "floatBitsToInt",
+    "floatBitsToUint",
+    "intBitsToFloat",
+    "uintBitsToFloat",
+    "fma",
+    "frexp",
+    "ldexp",
+    // packing
+    "packUnorm2x16",
+    "packSnorm2x16",
+    "packUnorm4x8",
+    "packSnorm4x8",
+    "unpackUnorm2x16",
+    "unpackSnorm2x16",
+    "unpackUnorm4x8",
+    "unpackSnorm4x8",
+    "packHalf2x16",
+    "unpackHalf2x16",
+    "packDouble2x32",
+    "unpackDouble2x32",
+    // geometric
+    "length",
+    "distance",
+    "dot",
+    "cross",
+    "normalize",
+    "ftransform",
+    "faceforward",
+    "reflect",
+    "refract",
+    // matrix
+    "matrixCompMult",
+    "outerProduct",
+    "transpose",
+    "determinant",
+    "inverse",
+    // vector relational
+    "lessThan",
+    "lessThanEqual",
+    "greaterThan",
+    "greaterThanEqual",
+    "equal",
+    "notEqual",
+    "any",
+    "all",
+    "not",
+    // integer
+    "uaddCarry",
+    "usubBorrow",
+    "umulExtended",
+    "imulExtended",
+    "bitfieldExtract",
+    "bitfieldInsert",
+    "bitfieldReverse",
+    "bitCount",
+    "findLSB",
+    "findMSB",
+    // texture
+    "textureSize",
+    "textureQueryLod",
+    "textureQueryLevels",
+    "texture",
+    "textureProj",
+    "textureLod",
+    "textureOffset",
+    "texelFetch",
+    "texelFetchOffset",
+    "textureProjOffset",
+    "textureLodOffset",
+    "textureProjLod",
+    "textureProjLodOffset",
+    "textureGrad",
+    "textureGradOffset",
+    "textureProjGrad",
+    "textureProjGradOffset",
+    "textureGather",
+    "textureGatherOffset",
+    "textureGatherOffsets",
+    "texture1D",
+    "texture1DProj",
+    "texture1DLod",
+    "texture1DProjLod",
+    "texture2D",
+    "texture2DProj",
+    "texture2DLod",
+    "texture2DProjLod",
+    "texture3D",
+    "texture3DProj",
+    "texture3DLod",
+    "texture3DProjLod",
+    "textureCube",
+    "textureCubeLod",
+    "shadow1D",
+    "shadow2D",
+    "shadow1DProj",
+    "shadow2DProj",
+    "shadow1DLod",
+    "shadow2DLod",
+    "shadow1DProjLod",
+    "shadow2DProjLod",
+    "texture2DGradARB",
+    // image
+    "imageSize",
+    "imageLoad",
+    "imageStore",
+    "imageAtomicAdd",
+    "imageAtomicMin",
+    "imageAtomicMax",
+    "imageAtomicAnd",
+    "imageAtomicOr",
+    "imageAtomicXor",
+    "imageAtomicExchange",
+    "imageAtomicCompSwap",
+    // atomic
+    "atomicAdd",
+    "atomicMin",
+    "atomicMax",
+    "atomicAnd",
+    "atomicOr",
+    "atomicXor",
+    "atomicExchange",
+    "atomicCompSwap",
+    "atomicCounter",
+    "atomicCounterIncrement",
+    "atomicCounterDecrement",
+    // fragment processing
+    "dFdx",
+    "dFdy",
+    "dFdxFine",
+    "dFdyFine",
+    "dFdxCoarse",
+    "dFdyCoarse",
+    "fwidth",
+    "fwidthFine",
+    "fwidthCoarse",
+    "interpolateAtCentroid",
+    "interpolateAtSample",
+    "interpolateAtOffset",
+    // noise
+    "noise1",
+    "noise2",
+    "noise3",
+    "noise4",
+    // geometry shader
+    "EmitStreamVertex",
+    "EndStreamPrimitive",
+    "EmitVertex",
+    "EndPrimitive",
+    // shader invocation control and memory control
+    "barrier",
+    "memoryBarrier",
+    "memoryBarrierAtomicCounter",
+    "memoryBarrierBuffer",
+    "memoryBarrierShared",
+    "memoryBarrierImage",
+    "groupMemoryBarrier",
+];
+
+/// Built-in types, which may be parsed as identifiers when used as constructors.
+pub const BUILTIN_TYPES: &[&str] = &[
+    "void",
+    "bool",
+    "int",
+    "uint",
+    "float",
+    "double",
+    "vec2",
+    "vec3",
+    "vec4",
+    "dvec2",
+    "dvec3",
+    "dvec4",
+    "bvec2",
+    "bvec3",
+    "bvec4",
+    "ivec2",
+    "ivec3",
+    "ivec4",
+    "uvec2",
+    "uvec3",
+    "uvec4",
+    "mat2",
+    "mat3",
+    "mat4",
+    "mat2x2",
+    "mat2x3",
+    "mat2x4",
+    "mat3x2",
+    "mat3x3",
+    "mat3x4",
+    "mat4x2",
+    "mat4x3",
+    "mat4x4",
+    "dmat2",
+    "dmat3",
+    "dmat4",
+    "sampler1D",
+    "sampler2D",
+    "sampler3D",
+    "samplerCube",
+    "sampler2DRect",
+    "sampler1DShadow",
+    "sampler2DShadow",
+    "samplerCubeShadow",
+    "sampler1DArray",
+    "sampler2DArray",
+    "sampler2DArrayShadow",
+    "sampler2DMS",
+    "samplerBuffer",
+    "isampler2D",
+    "isampler3D",
+    "usampler2D",
+    "usampler3D",
+    "image2D",
+    "image3D",
+    "iimage2D",
+    "uimage2D",
+];
+
+/// Returns whether the name refers to something provided by GLSL itself rather than declared by the user. All
+/// names starting with `gl_` are reserved for built-in variables.
+pub fn is_builtin(name: &str) -> bool {
+    name.starts_with("gl_") || BUILTIN_FUNCTIONS.contains(&name) || BUILTIN_TYPES.contains(&name)
+}
+
+/// A built-in variable of a struct or interface block type, such as `gl_DepthRange`. Most are only available in the
+/// compatibility profile, which the fixed function state shader packs read from is part of.
+pub struct BuiltinStruct {
+    pub variable: &'static str,
+    // the name of the struct or block type
+    pub ty: &'static str,
+    // the declaration of the variable as it would be written in GLSL
+    pub declaration: &'static str,
+    pub fields: &'static [BuiltinField],
+}
+
+pub struct BuiltinField {
+    pub name: &'static str,
+    pub ty: &'static str,
+    pub doc: &'static str,
+}
+
+const DEPTH_RANGE_FIELDS: &[BuiltinField] = &[
+    BuiltinField {
+        name: "near",
+        ty: "float",
+        doc: "The near value set by `glDepthRange`, to which the near clipping plane maps in window coordinates.",
+    },
+    BuiltinField {
+        name: "far",
+        ty: "float",
+        doc: "The far value set by `glDepthRange`, to which the far clipping plane maps in window coordinates.",
+    },
+    BuiltinField {
+        name: "diff",
+        ty: "float",
+        doc: "The difference `far - near`.",
+    },
+];
+
+const FOG_FIELDS: &[BuiltinField] = &[
+    BuiltinField {
+        name: "color",
+        ty: "vec4",
+        doc: "The color of the fog.",
+    },
+    BuiltinField {
+        name: "density",
+        ty: "float",
+        doc: "The density of exponential fog.",
+    },
+    BuiltinField {
+        name: "start",
+        ty: "float",
+        doc: "The eye space distance at which linear fog starts.",
+    },
+    BuiltinField {
+        name: "end",
+        ty: "float",
+        doc: "The eye space distance at which linear fog reaches its full strength.",
+    },
+    BuiltinField {
+        name: "scale",
+        ty: "float",
+        doc: "The factor `1.0 / (end - start)` used by linear fog.",
+    },
+];
+
+const MATERIAL_FIELDS: &[BuiltinField] = &[
+    BuiltinField {
+        name: "emission",
+        ty: "vec4",
+        doc: "The color emitted by the material.",
+    },
+    BuiltinField {
+        name: "ambient",
+        ty: "vec4",
+        doc: "The ambient reflectance of the material.",
+    },
+    BuiltinField {
+        name: "diffuse",
+        ty: "vec4",
+        doc: "The diffuse reflectance of the material.",
+    },
+    BuiltinField {
+        name: "specular",
+        ty: "vec4",
+        doc: "The specular reflectance of the material.",
+    },
+    BuiltinField {
+        name: "shininess",
+        ty: "float",
+        doc: "The specular exponent of the material.",
+    },
+];
+
+const LIGHT_SOURCE_FIELDS: &[BuiltinField] = &[
+    BuiltinField {
+        name: "ambient",
+        ty: "vec4",
+        doc: "The ambient intensity of the light.",
+    },
+    BuiltinField {
+        name: "diffuse",
+        ty: "vec4",
+        doc: "The diffuse intensity of the light.",
+    },
+    BuiltinField {
+        name: "specular",
+        ty: "vec4",
+        doc: "The specular intensity of the light.",
+    },
+    BuiltinField {
+        name: "position",
+        ty: "vec4",
+        doc: "The eye space position of the light, or its direction if `w` is `0.0`.",
+    },
+    BuiltinField {
+        name: "halfVector",
+        ty: "vec4",
+        doc: "The half vector between the light direction and an infinitely distant viewer.",
+    },
+    BuiltinField {
+        name: "spotDirection",
+        ty: "vec3",
+        doc: "The eye space direction of a spot light.",
+    },
+    BuiltinField {
+        name: "spotExponent",
+        ty: "float",
+        doc: "How concentrated the intensity of a spot light is towards its direction.",
+    },
+    BuiltinField {
+        name: "spotCutoff",
+        ty: "float",
+        doc: "The half angle of a spot light's cone in degrees, or `180.0` for lights that aren't spot lights.",
+    },
+    BuiltinField {
+        name: "spotCosCutoff",
+        ty: "float",
+        doc: "The cosine of `spotCutoff`.",
+    },
+    BuiltinField {
+        name: "constantAttenuation",
+        ty: "float",
+        doc: "The constant term of the light's attenuation.",
+    },
+    BuiltinField {
+        name: "linearAttenuation",
+        ty: "float",
+        doc: "The term of the light's attenuation growing linearly with distance.",
+    },
+    BuiltinField {
+        name: "quadraticAttenuation",
+        ty: "float",
+        doc: "The term of the light's attenuation growing with the square of the distance.",
+    },
+];
+
+const PER_VERTEX_FIELDS: &[BuiltinField] = &[
+    BuiltinField {
+        name: "gl_Position",
+        ty: "vec4",
+        doc: "The clip space position of the vertex written by the previous stage.",
+    },
+    BuiltinField {
+        name: "gl_PointSize",
+        ty: "float",
+        doc: "The size of the point rasterized for the vertex, in pixels.",
+    },
+    BuiltinField {
+        name: "gl_ClipDistance",
+        ty: "float[]",
+        doc: "The distances of the vertex to each of the user clipping planes.",
+    },
+];
+
+/// The built-in variables of struct and interface block types, for their fields to be documented.
+pub const BUILTIN_STRUCTS: &[BuiltinStruct] = &[
+    BuiltinStruct {
+        variable: "gl_DepthRange",
+        ty: "gl_DepthRangeParameters",
+        declaration: "uniform gl_DepthRangeParameters gl_DepthRange;",
+        fields: DEPTH_RANGE_FIELDS,
+    },
+    BuiltinStruct {
+        variable: "gl_Fog",
+        ty: "gl_FogParameters",
+        declaration: "uniform gl_FogParameters gl_Fog;",
+        fields: FOG_FIELDS,
+    },
+    BuiltinStruct {
+        variable: "gl_FrontMaterial",
+        ty: "gl_MaterialParameters",
+        declaration: "uniform gl_MaterialParameters gl_FrontMaterial;",
+        fields: MATERIAL_FIELDS,
+    },
+    BuiltinStruct {
+        variable: "gl_BackMaterial",
+        ty: "gl_MaterialParameters",
+        declaration: "uniform gl_MaterialParameters gl_BackMaterial;",
+        fields: MATERIAL_FIELDS,
+    },
+    BuiltinStruct {
+        variable: "gl_LightSource",
+        ty: "gl_LightSourceParameters",
+        declaration: "uniform gl_LightSourceParameters gl_LightSource[gl_MaxLights];",
+        fields: LIGHT_SOURCE_FIELDS,
+    },
+    BuiltinStruct {
+        variable: "gl_in",
+        ty: "gl_PerVertex",
+        declaration: "in gl_PerVertex { vec4 gl_Position; float gl_PointSize; float gl_ClipDistance[]; } gl_in[];",
+        fields: PER_VERTEX_FIELDS,
+    },
+];
+
+/// Returns the built-in variable of a struct or interface block type with the given name, if there is one.
+pub fn builtin_struct(variable: &str) -> Option<&'static BuiltinStruct> {
+    BUILTIN_STRUCTS.iter().find(|builtin| builtin.variable == variable)
+}
+
+/// The stage of the pipeline a shader is compiled for, inferred from the extension of its file. The stage decides
+/// which of the built-in variables are available, as with `gl_FragCoord` only existing in fragment shaders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub const ALL: [ShaderStage; 4] = [
+        ShaderStage::Vertex,
+        ShaderStage::Geometry,
+        ShaderStage::Fragment,
+        ShaderStage::Compute,
+    ];
+
+    /// Returns the stage of the file, or `None` for files such as `.glsl` includes, which may be included into
+    /// shaders of any stage.
+    pub fn from_path(path: &Path) -> Option<ShaderStage> {
+        match path.extension().and_then(|ext| ext.to_str())? {
+            "vsh" => Some(ShaderStage::Vertex),
+            "gsh" => Some(ShaderStage::Geometry),
+            "fsh" => Some(ShaderStage::Fragment),
+            "csh" => Some(ShaderStage::Compute),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ShaderStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Geometry => write!(f, "geometry"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+            ShaderStage::Compute => write!(f, "compute"),
+        }
+    }
+}
+
+/// A built-in variable only available in some of the stages. The same name may be declared differently between
+/// stages, as `gl_Color` is a vertex attribute but an interpolated input to fragment shaders.
+pub struct BuiltinVariable {
+    pub name: &'static str,
+    // the declaration of the variable as it would be written in GLSL
+    pub declaration: &'static str,
+}
+
+const VERTEX_VARIABLES: &[BuiltinVariable] = &[
+    BuiltinVariable {
+        name: "gl_VertexID",
+        declaration: "in int gl_VertexID;",
+    },
+    BuiltinVariable {
+        name: "gl_InstanceID",
+        declaration: "in int gl_InstanceID;",
+    },
+    BuiltinVariable {
+        name: "gl_Vertex",
+        declaration: "attribute vec4 gl_Vertex;",
+    },
+    BuiltinVariable {
+        name: "gl_Normal",
+        declaration: "attribute vec3 gl_Normal;",
+    },
+    BuiltinVariable {
+        name: "gl_Color",
+        declaration: "attribute vec4 gl_Color;",
+    },
+    BuiltinVariable {
+        name: "gl_MultiTexCoord0",
+        declaration: "attribute vec4 gl_MultiTexCoord0;",
+    },
+    BuiltinVariable {
+        name: "gl_MultiTexCoord1",
+        declaration: "attribute vec4 gl_MultiTexCoord1;",
+    },
+    BuiltinVariable {
+        name: "gl_Position",
+        declaration: "out vec4 gl_Position;",
+    },
+    BuiltinVariable {
+        name: "gl_PointSize",
+        declaration: "out float gl_PointSize;",
+    },
+    BuiltinVariable {
+        name: "gl_ClipDistance",
+        declaration: "out float gl_ClipDistance[];",
+    },
+    BuiltinVariable {
+        name: "gl_FrontColor",
+        declaration: "varying vec4 gl_FrontColor;",
+    },
+    BuiltinVariable {
+        name: "gl_TexCoord",
+        declaration: "varying vec4 gl_TexCoord[];",
+    },
+];
+
+const GEOMETRY_VARIABLES: &[BuiltinVariable] = &[
+    BuiltinVariable {
+        name: "gl_PrimitiveIDIn",
+        declaration: "in int gl_PrimitiveIDIn;",
+    },
+    BuiltinVariable {
+        name: "gl_InvocationID",
+        declaration: "in int gl_InvocationID;",
+    },
+    BuiltinVariable {
+        name: "gl_Position",
+        declaration: "out vec4 gl_Position;",
+    },
+    BuiltinVariable {
+        name: "gl_PointSize",
+        declaration: "out float gl_PointSize;",
+    },
+    BuiltinVariable {
+        name: "gl_ClipDistance",
+        declaration: "out float gl_ClipDistance[];",
+    },
+    BuiltinVariable {
+        name: "gl_PrimitiveID",
+        declaration: "out int gl_PrimitiveID;",
+    },
+    BuiltinVariable {
+        name: "gl_Layer",
+        declaration: "out int gl_Layer;",
+    },
+    BuiltinVariable {
+        name: "gl_ViewportIndex",
+        declaration: "out int gl_ViewportIndex;",
+    },
+];
+
+const FRAGMENT_VARIABLES: &[BuiltinVariable] = &[
+    BuiltinVariable {
+        name: "gl_FragCoord",
+        declaration: "in vec4 gl_FragCoord;",
+    },
+    BuiltinVariable {
+        name: "gl_FrontFacing",
+        declaration: "in bool gl_FrontFacing;",
+    },
+    BuiltinVariable {
+        name: "gl_PointCoord",
+        declaration: "in vec2 gl_PointCoord;",
+    },
+    BuiltinVariable {
+        name: "gl_PrimitiveID",
+        declaration: "in int gl_PrimitiveID;",
+    },
+    BuiltinVariable {
+        name: "gl_Color",
+        declaration: "varying vec4 gl_Color;",
+    },
+    BuiltinVariable {
+        name: "gl_TexCoord",
+        declaration: "varying vec4 gl_TexCoord[];",
+    },
+    BuiltinVariable {
+        name: "gl_FragColor",
+        declaration: "out vec4 gl_FragColor;",
+    },
+    BuiltinVariable {
+        name: "gl_FragData",
+        declaration: "out vec4 gl_FragData[gl_MaxDrawBuffers];",
+    },
+    BuiltinVariable {
+        name: "gl_FragDepth",
+        declaration: "out float gl_FragDepth;",
+    },
+];
+
+const COMPUTE_VARIABLES: &[BuiltinVariable] = &[
+    BuiltinVariable {
+        name: "gl_NumWorkGroups",
+        declaration: "in uvec3 gl_NumWorkGroups;",
+    },
+    BuiltinVariable {
+        name: "gl_WorkGroupSize",
+        declaration: "const uvec3 gl_WorkGroupSize;",
+    },
+    BuiltinVariable {
+        name: "gl_WorkGroupID",
+        declaration: "in uvec3 gl_WorkGroupID;",
+    },
+    BuiltinVariable {
+        name: "gl_LocalInvocationID",
+        declaration: "in uvec3 gl_LocalInvocationID;",
+    },
+    BuiltinVariable {
+        name: "gl_GlobalInvocationID",
+        declaration: "in uvec3 gl_GlobalInvocationID;",
+    },
+    BuiltinVariable {
+        name: "gl_LocalInvocationIndex",
+        declaration: "in uint gl_LocalInvocationIndex;",
+    },
+];
+
+/// Returns the built-in variables available in shaders of the stage.
+pub fn stage_variables(stage: ShaderStage) -> &'static [BuiltinVariable] {
+    match stage {
+        ShaderStage::Vertex => VERTEX_VARIABLES,
+        ShaderStage::Geometry => GEOMETRY_VARIABLES,
+        ShaderStage::Fragment => FRAGMENT_VARIABLES,
+        ShaderStage::Compute => COMPUTE_VARIABLES,
+    }
+}
+
+/// Returns the stages the built-in variable of the given name is available in, along with its declaration in each.
+/// Only the given stage is looked in if there is one.
+pub fn variable_stages(name: &str, stage: Option<ShaderStage>) -> Vec<(ShaderStage, &'static BuiltinVariable)> {
+    let stages = match stage {
+        Some(stage) => vec![stage],
+        None => ShaderStage::ALL.to_vec(),
+    };
+
+    stages
+        .into_iter()
+        .filter_map(|stage| {
+            stage_variables(stage)
+                .iter()
+                .find(|variable| variable.name == name)
+                .map(|variable| (stage, variable))
+        })
+        .collect()
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use serde_json::{json, Value};
+use slog_scope::{info, warn};
+use url::Url;
+
+/// Tells a long search that the request it's answering was cancelled by the client, for it to stop between files
+/// and answer with what it has found so far. A default token is never cancelled.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The requests received from the client that haven't been answered yet, each with the token cancelling it once a
+/// `$/cancelRequest` for it is received. Messages are handled one at a time, so the cancellation of the request
+/// being handled can only be seen by reading ahead of it, as `CancellableInput` does. Declaration requests are
+/// passed on as definition requests and prepare rename requests as the `prepareRename` command, see
+/// `is_declaration` and `is_prepare_rename`.
+#[derive(Clone, Default)]
+pub struct Cancellations {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    pending: HashMap<String, CancellationToken>,
+    // the ids of the pending requests passed on as another method, with the method they were received as
+    rewritten: HashMap<String, &'static str>,
+    // the id of the request being handled, if the message being handled is a request, its token and the method it
+    // was received as if it was passed on as another
+    current: Option<String>,
+    current_token: CancellationToken,
+    current_rewritten: Option<&'static str>,
+}
+
+// the server's LSP library doesn't route declaration or prepare rename requests, which are passed on to it as
+// definition requests and as the `prepareRename` command
+const DECLARATION_METHOD: &str = "textDocument/declaration";
+const DEFINITION_METHOD: &str = "textDocument/definition";
+const PREPARE_RENAME_METHOD: &str = "textDocument/prepareRename";
+const EXECUTE_COMMAND_METHOD: &str = "workspace/executeCommand";
+const PREPARE_RENAME_COMMAND: &str = "prepareRename";
+
+impl Cancellations {
+    /// Returns the token of the request being handled, or one never cancelled while handling a notification.
+    pub fn current(&self) -> CancellationToken {
+        self.state.lock().unwrap().current_token.clone()
+    }
+
+    /// Returns whether the definition request being handled was received as a `textDocument/declaration` request,
+    /// for the definition handler to answer it with the declarations instead.
+    pub fn is_declaration(&self) -> bool {
+        self.state.lock().unwrap().current_rewritten == Some(DECLARATION_METHOD)
+    }
+
+    /// Returns whether the command being executed was received as a `textDocument/prepareRename` request, for the
+    /// command handler to answer it without the popups shown for commands run by the user.
+    pub fn is_prepare_rename(&self) -> bool {
+        self.state.lock().unwrap().current_rewritten == Some(PREPARE_RENAME_METHOD)
+    }
+
+    // registers the requests read ahead of the one being handled, and cancels those the client cancels.
+    fn received(&self, message: &Value) {
+        let mut state = self.state.lock().unwrap();
+        match (message.get("method").and_then(Value::as_str), message.get("id")) {
+            (Some("$/cancelRequest"), _) => {
+                if let Some(id) = message.pointer("/params/id").map(Value::to_string) {
+                    if let Some(token) = state.pending.get(&id) {
+                        info!("request cancelled"; "id" => &id);
+                        token.cancel();
+                    }
+                }
+            }
+            (Some(_), Some(id)) => {
+                state.pending.insert(id.to_string(), CancellationToken::default());
+            }
+            _ => {}
+        }
+    }
+
+    // registers the request read ahead as passed on as another method than the one it was received as.
+    fn rewritten(&self, id: String, method: &'static str) {
+        self.state.lock().unwrap().rewritten.insert(id, method);
+    }
+
+    // marks the message as the one being handled, the one handled before it having been answered.
+    fn started(&self, id: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(previous) = state.current.take() {
+            state.pending.remove(&previous);
+            state.rewritten.remove(&previous);
+        }
+        state.current_token = id.as_ref().and_then(|id| state.pending.get(id)).cloned().unwrap_or_default();
+        state.current_rewritten = id.as_ref().and_then(|id| state.rewritten.get(id)).copied();
+        state.current = id;
+    }
+}
+
+/// Reads the client's messages from a thread of its own, passing them on to the server one at a time while
+/// watching the messages queued behind the one being handled for cancellations, see `Cancellations`. Declaration
+/// requests are passed on as definition requests, being otherwise the same, and prepare rename requests as
+/// requests executing the `prepareRename` command, keeping their id for the answer to go back to the client.
+pub struct CancellableInput {
+    receiver: Receiver<(Option<String>, Vec<u8>)>,
+    cancellations: Cancellations,
+    message: Vec<u8>,
+    read: usize,
+}
+
+impl CancellableInput {
+    pub fn new<R: BufRead + Send + 'static>(input: R, cancellations: Cancellations) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let reader_cancellations = cancellations.clone();
+        // the thread logs to the logger of the thread it's started from, rather than whichever is global once it logs
+        let logger = slog_scope::logger();
+        thread::Builder::new()
+            .name("input".into())
+            .spawn(move || slog_scope::scope(&logger, || read_messages(input, sender, reader_cancellations)))?;
+
+        Ok(CancellableInput {
+            receiver,
+            cancellations,
+            message: vec![],
+            read: 0,
+        })
+    }
+}
+
+impl Read for CancellableInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for CancellableInput {
+    // the next message is only taken once the server reads past the last, which it does after handling it
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.read == self.message.len() {
+            // the channel only closes once the input ends
+            if let Ok((id, message)) = self.receiver.recv() {
+                self.cancellations.started(id);
+                self.message = message;
+                self.read = 0;
+            }
+        }
+        Ok(&self.message[self.read..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.read = (self.read + amount).min(self.message.len());
+    }
+}
+
+// splits the input into messages by their headers, passing each on whole with the id of the request it is if any.
+// Input that can't be split is passed on as read for the server to report.
+fn read_messages<R: BufRead>(mut input: R, sender: Sender<(Option<String>, Vec<u8>)>, cancellations: Cancellations) {
+    loop {
+        let mut message = vec![];
+        let mut content_length = None;
+        loop {
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                Ok(0) => return,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("failed to read message header"; "error" => format!("{:?}", e));
+                    return;
+                }
+            }
+            message.extend_from_slice(line.as_bytes());
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        let mut id = None;
+        if let Some(content_length) = content_length {
+            let mut content = vec![0; content_length];
+            if let Err(e) = input.read_exact(&mut content) {
+                warn!("failed to read message content"; "error" => format!("{:?}", e));
+                return;
+            }
+            if let Ok(mut parsed) = serde_json::from_slice::<Value>(&content) {
+                cancellations.received(&parsed);
+                if parsed.get("method").is_some() {
+                    id = parsed.get("id").map(Value::to_string);
+                }
+                let rewritten = match parsed.get("method").and_then(Value::as_str) {
+                    Some(DECLARATION_METHOD) => {
+                        parsed["method"] = DEFINITION_METHOD.into();
+                        Some(DECLARATION_METHOD)
+                    }
+                    Some(PREPARE_RENAME_METHOD) => prepare_rename_command(&parsed["params"]).map(|params| {
+                        parsed["method"] = EXECUTE_COMMAND_METHOD.into();
+                        parsed["params"] = params;
+                        PREPARE_RENAME_METHOD
+                    }),
+                    _ => None,
+                };
+                if let Some(method) = rewritten {
+                    if let Some(id) = &id {
+                        cancellations.rewritten(id.clone(), method);
+                    }
+                    content = serde_json::to_vec(&parsed).unwrap();
+                    message = format!("Content-Length: {}\r\n\r\n", content.len()).into_bytes();
+                }
+            }
+            message.extend(content);
+        }
+
+        if sender.send((id, message)).is_err() {
+            return;
+        }
+    }
+}
+
+// the params executing the `prepareRename` command for the params of a prepare rename request, the document being
+// given by the path of its url as the client gives it to commands, see `PathBuf::from_json`. Params that aren't
+// those of a prepare rename request are `None`, passing the request on as it is for the server to reject.
+fn prepare_rename_command(params: &Value) -> Option<Value> {
+    let uri = Url::parse(params.pointer("/textDocument/uri")?.as_str()?).ok()?;
+    let position = params.get("position")?;
+    Some(json!({
+        "command": PREPARE_RENAME_COMMAND,
+        "arguments": [uri.path(), position],
+    }))
+}
+
+#[cfg(test)]
+mod cancellation_test {
+    use std::io::{BufRead, Cursor};
+
+    use crate::cancellation::{CancellableInput, Cancellations};
+
+    fn message(content: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_cancellable_input() {
+        let input: String = [
+            message(r#"{"jsonrpc":"2.0","id":1,"method":"workspace/symbol","params":{"query":""}}"#),
+            message(r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":1}}"#),
+            message(r#"{"jsonrpc":"2.0","id":"2","method":"workspace/symbol","params":{"query":""}}"#),
+            message(r#"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":2}}"#),
+        ]
+        .concat();
+
+        let cancellations = Cancellations::default();
+        let mut reader = CancellableInput::new(Cursor::new(input.clone()), cancellations.clone()).unwrap();
+
+        // each message is passed on whole and as read, one at a time
+        let mut read = vec![];
+        let first = reader.fill_buf().unwrap().to_vec();
+        assert_eq!(
+            String::from_utf8(first.clone()).unwrap(),
+            message(r#"{"jsonrpc":"2.0","id":1,"method":"workspace/symbol","params":{"query":""}}"#)
+        );
+        reader.consume(first.len());
+        read.extend(first);
+        let first_token = cancellations.current();
+
+        // the first request is cancelled by the message queued behind it, while it's still being handled
+        let cancel = reader.fill_buf().unwrap().to_vec();
+        reader.consume(cancel.len());
+        read.extend(cancel);
+        assert!(first_token.is_cancelled());
+        assert!(!cancellations.current().is_cancelled());
+
+        // ids of different types are different requests
+        let second = reader.fill_buf().unwrap().to_vec();
+        reader.consume(second.len());
+        read.extend(second);
+        let second_token = cancellations.current();
+        let rest = reader.fill_buf().unwrap().to_vec();
+        reader.consume(rest.len());
+        read.extend(rest);
+        assert!(!second_token.is_cancelled());
+
+        assert!(reader.fill_buf().unwrap().is_empty());
+        assert_eq!(String::from_utf8(read).unwrap(), input);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_declaration_requests() {
+        let input: String = [
+            message(r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/declaration","params":{}}"#),
+            message(r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{}}"#),
+        ]
+        .concat();
+
+        let cancellations = Cancellations::default();
+        let mut reader = CancellableInput::new(Cursor::new(input), cancellations.clone()).unwrap();
+
+        // declaration requests are passed on as definition requests, told apart by the cancellations
+        let declaration = reader.fill_buf().unwrap().to_vec();
+        reader.consume(declaration.len());
+        assert_eq!(
+            String::from_utf8(declaration).unwrap(),
+            message(r#"{"id":1,"jsonrpc":"2.0","method":"textDocument/definition","params":{}}"#)
+        );
+        assert!(cancellations.is_declaration());
+
+        let definition = reader.fill_buf().unwrap().to_vec();
+        reader.consume(definition.len());
+        assert_eq!(
+            String::from_utf8(definition).unwrap(),
+            message(r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{}}"#)
+        );
+        assert!(!cancellations.is_declaration());
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_prepare_rename_requests() {
+        let input: String = [
+            message(
+                r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/prepareRename","params":{"textDocument":{"uri":"file:///shaders/a%20b.fsh"},"position":{"line":2,"character":5}}}"#,
+            ),
+            message(r#"{"jsonrpc":"2.0","id":4,"method":"textDocument/prepareRename","params":{}}"#),
+        ]
+        .concat();
+
+        let cancellations = Cancellations::default();
+        let mut reader = CancellableInput::new(Cursor::new(input), cancellations.clone()).unwrap();
+
+        // prepare rename requests are passed on as the command, with the same id for the answer to go back to
+        let prepare_rename = reader.fill_buf().unwrap().to_vec();
+        reader.consume(prepare_rename.len());
+        assert_eq!(
+            String::from_utf8(prepare_rename).unwrap(),
+            message(
+                r#"{"id":3,"jsonrpc":"2.0","method":"workspace/executeCommand","params":{"arguments":["/shaders/a%20b.fsh",{"character":5,"line":2}],"command":"prepareRename"}}"#
+            )
+        );
+        assert!(cancellations.is_prepare_rename());
+        assert!(!cancellations.is_declaration());
+
+        // while malformed ones are left for the server to reject
+        let malformed = reader.fill_buf().unwrap().to_vec();
+        reader.consume(malformed.len());
+        assert_eq!(
+            String::from_utf8(malformed).unwrap(),
+            message(r#"{"jsonrpc":"2.0","id":4,"method":"textDocument/prepareRename","params":{}}"#)
+        );
+        assert!(!cancellations.is_prepare_rename());
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use rust_lsp::lsp_types::{Color, ColorInformation, ColorPresentation, Range, TextEdit};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::navigation::ParserContext;
+
+const LIST_CONSTRUCTORS_STR: &str = r#"
+    (call_expression
+        function: (_) @constructor
+        arguments: (argument_list) @arguments)
+"#;
+
+/// Finds the `vec3` and `vec4` constructor calls whose arguments are all numeric literals in `[0, 1]`, so that
+/// the editor can show them as colors. Constructors taking any expression, or a single value to splat, are not
+/// considered colors.
+pub fn document_colors(ctx: &ParserContext) -> Result<Vec<ColorInformation>> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_CONSTRUCTORS_STR)?;
+    let mut query_cursor = QueryCursor::new();
+
+    let mut colors = vec![];
+
+    for m in query_cursor.matches(&query, ctx.root_node(), ctx.source().as_bytes()) {
+        let (constructor, arguments) = (m.captures[0].node, m.captures[1].node);
+
+        let components = match constructor.utf8_text(ctx.source().as_bytes())? {
+            "vec3" => 3,
+            "vec4" => 4,
+            _ => continue,
+        };
+
+        let values = match literal_arguments(ctx, arguments) {
+            Some(values) if values.len() == components => values,
+            _ => continue,
+        };
+
+        let call = constructor.parent().unwrap();
+        colors.push(ColorInformation {
+            range: ctx.range_for_node(&call),
+            color: Color {
+                red: values[0],
+                green: values[1],
+                blue: values[2],
+                alpha: values.get(3).copied().unwrap_or(1.0),
+            },
+        });
+    }
+
+    Ok(colors)
+}
+
+/// Formats a color picked in the editor back into a constructor replacing the one at the range. A `vec3` stays
+/// a `vec3` unless the color was given transparency.
+pub fn color_presentations(ctx: &ParserContext, color: Color, range: Range) -> Vec<ColorPresentation> {
+    let start = ctx.offset_for_position(range.start);
+    let was_vec4 = ctx.source()[start..].starts_with("vec4");
+
+    let mut components = vec![color.red, color.green, color.blue];
+    if was_vec4 || color.alpha < 1.0 {
+        components.push(color.alpha);
+    }
+
+    let label = format!(
+        "vec{}({})",
+        components.len(),
+        components.iter().map(|c| format_component(*c)).collect::<Vec<_>>().join(", ")
+    );
+
+    vec![ColorPresentation {
+        text_edit: Some(TextEdit {
+            range,
+            new_text: label.clone(),
+        }),
+        label,
+        additional_text_edits: None,
+    }]
+}
+
+// the values of the arguments if they are all numeric literals in [0, 1].
+fn literal_arguments(ctx: &ParserContext, arguments: Node) -> Option<Vec<f32>> {
+    let mut cursor = arguments.walk();
+    arguments
+        .named_children(&mut cursor)
+        .map(|arg| {
+            if arg.kind() != "number_literal" {
+                return None;
+            }
+            let text = arg.utf8_text(ctx.source().as_bytes()).ok()?;
+            let value = text.trim_end_matches(|c| c == 'f' || c == 'F').parse::<f32>().ok()?;
+            (0.0..=1.0).contains(&value).then_some(value)
+        })
+        .collect()
+}
+
+// formats the component with at most 3 decimals, keeping a decimal point so it stays a float literal.
+fn format_component(component: f32) -> String {
+    let text = format!("{:.3}", component.clamp(0.0, 1.0));
+    let text = text.trim_end_matches('0');
+    if text.ends_with('.') {
+        format!("{}0", text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod colors_test {
+    use rust_lsp::lsp_types::{Color, Position, Range};
+    use tree_sitter::Parser;
+
+    use crate::colors::{color_presentations, document_colors};
+    use crate::navigation::ParserContext;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_document_colors() {
+        let source = r#"const vec3 sky = vec3(0.4, 0.6, 1.0);
+const vec4 fog = vec4(1, 0.5f, 0.25, 0.5);
+
+void main() {
+    vec3 bright = vec3(2.0, 0.5, 0.5);
+    vec3 grey = vec3(0.5);
+    vec3 mixed = vec3(sky.r, 0.5, 0.5);
+    vec2 uv = vec2(0.5, 0.5);
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let colors = document_colors(&ctx).unwrap();
+
+        assert_eq!(colors.len(), 2);
+
+        assert_eq!(colors[0].range.start, Position::new(0, 17));
+        assert_eq!(colors[0].range.end, Position::new(0, 36));
+        assert_eq!((colors[0].color.red, colors[0].color.blue, colors[0].color.alpha), (0.4, 1.0, 1.0));
+
+        assert_eq!(colors[1].range.start.line, 1);
+        assert_eq!((colors[1].color.red, colors[1].color.green, colors[1].color.alpha), (1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_color_presentations() {
+        let source = "vec3 a = vec3(0.4, 0.6, 1.0);\nvec4 b = vec4(1.0, 1.0, 1.0, 1.0);\n";
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let color = Color {
+            red: 0.25,
+            green: 0.5,
+            blue: 1.0,
+            alpha: 1.0,
+        };
+
+        let vec3_range = Range::new(Position::new(0, 9), Position::new(0, 28));
+        let presentations = color_presentations(&ctx, color, vec3_range);
+        assert_eq!(presentations[0].label, "vec3(0.25, 0.5, 1.0)");
+        assert_eq!(presentations[0].text_edit.as_ref().unwrap().range, vec3_range);
+
+        // picking a transparent color turns a vec3 into a vec4
+        let presentations = color_presentations(&ctx, Color { alpha: 0.3333, ..color }, vec3_range);
+        assert_eq!(presentations[0].label, "vec4(0.25, 0.5, 1.0, 0.333)");
+
+        // vec4s keep their alpha component
+        let vec4_range = Range::new(Position::new(1, 9), Position::new(1, 33));
+        let presentations = color_presentations(&ctx, color, vec4_range);
+        assert_eq!(presentations[0].label, "vec4(0.25, 0.5, 1.0, 1.0)");
+    }
+}
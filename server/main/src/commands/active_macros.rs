@@ -0,0 +1,58 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Position;
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::graph::CachedStableGraph;
+use crate::preprocessor::{self, MacroSource};
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Lists the macros in effect at a position, along with where each comes from, for working out the state of the
+/// preprocessor at a point of a file, see `preprocessor::active_macros`.
+pub struct ActiveMacrosCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for ActiveMacrosCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let node = match self.graph.borrow_mut().find_node(&path) {
+            Some(node) => node,
+            None => return Err(format_err!("node not found {:?}", path)),
+        };
+
+        let graph = self.graph.borrow();
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let macros = preprocessor::active_macros(&graph, parser, node, position)?;
+
+        let macros: Vec<Value> = macros
+            .into_iter()
+            .map(|info| {
+                let (source, location) = match info.source {
+                    MacroSource::Glsl => ("glsl", None),
+                    MacroSource::OptiFine => ("optifine", None),
+                    MacroSource::Define(location) => ("define", Some(location)),
+                };
+                serde_json::json!({
+                    "name": info.name,
+                    "parameters": info.parameters,
+                    "value": info.value,
+                    "source": source,
+                    "location": location,
+                })
+            })
+            .collect();
+
+        Ok(Value::Array(macros))
+    }
+}
@@ -0,0 +1,39 @@
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::{Diagnostic, Position, Url};
+use serde_json::Value;
+
+use crate::navigation;
+use crate::url_norm::{url_for, FromJson};
+
+use super::{Invokeable, Workspace};
+
+/// Finds the next or previous of the diagnostics last published for a file relative to a position, wrapping around
+/// at either end, for clients to step through the problems in a file. Takes the path, the position and either
+/// `"next"` or `"previous"`.
+pub struct AdjacentDiagnosticCommand {
+    pub diagnostics: Rc<RefCell<HashMap<Url, Vec<Diagnostic>>>>,
+}
+
+impl Invokeable for AdjacentDiagnosticCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+        let forward = match arguments.get(2).and_then(|direction| direction.as_str()) {
+            Some("next") => true,
+            Some("previous") => false,
+            _ => return Err(format_err!("direction argument must be \"next\" or \"previous\"")),
+        };
+
+        let diagnostics = self.diagnostics.borrow();
+        let file_diagnostics = diagnostics.get(&url_for(&path)?).map_or(&[][..], |d| d.as_slice());
+
+        let location = navigation::adjacent_diagnostic(&path, file_diagnostics, position, forward)?;
+
+        Ok(serde_json::to_value(location)?)
+    }
+}
@@ -0,0 +1,55 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::graph::CachedStableGraph;
+use crate::is_top_level;
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Lists the top-level stage files whose flattened source changes with an edit to a file, being those including
+/// it directly or transitively, for pack build tooling to only recompile the programs an edit affects. The files
+/// are listed sorted by path.
+pub struct AffectedOutputsCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+}
+
+impl AffectedOutputsCommand {
+    pub fn affected_outputs(&self, workspace: &dyn Workspace, changed: &Path) -> Vec<PathBuf> {
+        let node = match self.graph.borrow_mut().find_node(changed) {
+            Some(node) => node,
+            None => return vec![],
+        };
+
+        let graph = self.graph.borrow();
+        let mut outputs: Vec<PathBuf> = graph
+            .dependents(node)
+            .into_iter()
+            .map(|node| graph.get_node(node))
+            // whether a file is a stage file depends on its path within its own workspace folder
+            .filter(|path| {
+                workspace
+                    .workspace_folder(path)
+                    .and_then(|folder| path.strip_prefix(folder).ok())
+                    .map_or(false, is_top_level)
+            })
+            .collect();
+        outputs.sort();
+
+        outputs
+    }
+}
+
+impl Invokeable for AffectedOutputsCommand {
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+
+        Ok(serde_json::to_value(self.affected_outputs(workspace, &path))?)
+    }
+}
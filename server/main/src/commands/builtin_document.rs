@@ -0,0 +1,27 @@
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Url;
+use serde_json::Value;
+
+use crate::builtin_document;
+
+use super::{Invokeable, Workspace};
+
+/// Serves the read-only documents declaring built-ins that going to the definition of a built-in leads to, by
+/// their `glsl-builtin:` URI, see `builtin_document::document`.
+pub struct BuiltinDocumentCommand;
+
+impl Invokeable for BuiltinDocumentCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let uri = match arguments.get(0).and_then(Value::as_str) {
+            Some(uri) => Url::parse(uri)?,
+            None => return Err(format_err!("missing uri argument")),
+        };
+
+        let name = match builtin_document::name(&uri) {
+            Some(name) => name,
+            None => return Err(format_err!("not a built-in document: {}", uri)),
+        };
+
+        Ok(builtin_document::document(name).map_or(Value::Null, Value::String))
+    }
+}
@@ -0,0 +1,39 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Position;
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::navigation::{or_nothing_at_point, ParserContext};
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Finds the calls of the function declared at a position along with the arguments passed at each, for clients
+/// to preview alongside the plain `textDocument/references` results.
+pub struct CallSitesCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for CallSitesCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        let call_sites = or_nothing_at_point(ctx.find_call_sites(&path, position))?.map(|call_sites| {
+            call_sites
+                .into_iter()
+                .map(|call| serde_json::json!({ "location": call.location, "arguments": call.arguments }))
+                .collect::<Vec<_>>()
+        });
+
+        Ok(serde_json::to_value(call_sites)?)
+    }
+}
@@ -0,0 +1,54 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::{Color, Range};
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::colors;
+use crate::navigation::ParserContext;
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Serves the color literals of a file, as the server's LSP library doesn't route `textDocument/documentColor`
+/// requests.
+pub struct DocumentColorsCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for DocumentColorsCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        Ok(serde_json::to_value(colors::document_colors(&ctx)?)?)
+    }
+}
+
+/// Formats a color picked for one of the file's color literals, as the server's LSP library doesn't route
+/// `textDocument/colorPresentation` requests.
+pub struct ColorPresentationsCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for ColorPresentationsCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let color: Color = match arguments.get(1) {
+            Some(color) => serde_json::from_value(color.clone())?,
+            None => return Err(format_err!("missing color argument")),
+        };
+        let range: Range = match arguments.get(2) {
+            Some(range) => serde_json::from_value(range.clone())?,
+            None => return Err(format_err!("missing range argument")),
+        };
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        Ok(serde_json::to_value(colors::color_presentations(&ctx, color, range))?)
+    }
+}
@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+use anyhow::{format_err, Result};
+use serde_json::Value;
+
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Writes the symbols of every shader file of the workspace to the file at the path given, for tooling indexing a
+/// pack without running the server. Answers with the number of symbols written.
+pub struct ExportSymbolsCommand;
+
+impl Invokeable for ExportSymbolsCommand {
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let output = match arguments.get(0) {
+            Some(output) => PathBuf::from_json(output)?,
+            None => return Err(format_err!("expected the path of the file to export the symbols to")),
+        };
+
+        Ok(Value::from(workspace.export_symbols(&output)?))
+    }
+}
@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use petgraph::dot::Config;
@@ -13,16 +13,22 @@ use anyhow::{format_err, Result};
 use slog_scope::info;
 
 use crate::graph::CachedStableGraph;
+use crate::url_norm::FromJson;
 
-use super::Invokeable;
+use super::{Invokeable, Workspace};
 
 pub struct GraphDotCommand {
     pub graph: Rc<RefCell<CachedStableGraph>>,
 }
 
 impl Invokeable for GraphDotCommand {
-    fn run_command(&self, root: &Path, _: &[Value]) -> Result<Value> {
-        let filepath = root.join("graph.dot");
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        // the graph spans every workspace folder, and is written to the one given or else the root
+        let folder = match arguments.get(0) {
+            Some(folder) => PathBuf::from_json(folder)?,
+            None => workspace.folders()[0].to_path_buf(),
+        };
+        let filepath = folder.join("graph.dot");
 
         info!("generating dot file"; "path" => filepath.as_os_str().to_str());
 
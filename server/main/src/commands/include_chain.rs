@@ -0,0 +1,74 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::{Location, Position, Range};
+use serde_json::Value;
+use slog_scope::debug;
+use tree_sitter::Parser;
+
+use crate::graph::CachedStableGraph;
+use crate::navigation::ParserContext;
+use crate::url_norm::{url_for, FromJson, FromUrl};
+
+use super::{Invokeable, Workspace};
+
+/// Finds where the symbol at a position is defined among the files the file includes, along with the chain of
+/// `#include` directives it's reachable through, for working out how a symbol ends up visible in a file. The
+/// definition nearest by includes is taken, and of the chains leading to it the shortest, see
+/// `CachedStableGraph::include_chain`. The definition is looked up as with going to definitions, see
+/// `Workspace::find_included_definitions`.
+pub struct IncludeChainCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for IncludeChainCommand {
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let name = {
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let ctx = ParserContext::new(parser, &path)?;
+            match ctx.find_node_at_point(position) {
+                Some(node) if matches!(node.kind(), "identifier" | "type_identifier") => {
+                    node.utf8_text(ctx.source().as_bytes())?.to_string()
+                }
+                _ => return Ok(Value::Null),
+            }
+        };
+
+        let definition = match workspace.find_included_definitions(&path, &name)?.into_iter().next() {
+            Some(definition) => definition,
+            None => return Ok(Value::Null),
+        };
+
+        let graph = &mut self.graph.borrow_mut();
+        let (from, to) = match (graph.find_node(&path), graph.find_node(&PathBuf::from_url(definition.uri.clone()))) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Ok(Value::Null),
+        };
+        let chain = graph.include_chain(from, to).unwrap_or_default();
+
+        debug!("found include chain"; "name" => &name, "length" => chain.len());
+
+        let mut includes = vec![];
+        for (parent, child, position) in chain {
+            includes.push(serde_json::json!({
+                "location": Location {
+                    uri: url_for(&graph.get_node(parent))?,
+                    range: Range::new(
+                        Position::new(position.line as u32, position.start as u32),
+                        Position::new(position.line as u32, position.end as u32),
+                    ),
+                },
+                "target": url_for(&graph.get_node(child))?,
+            }));
+        }
+
+        Ok(serde_json::json!({ "definition": definition, "includes": includes }))
+    }
+}
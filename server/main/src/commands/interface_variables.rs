@@ -0,0 +1,49 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::Result;
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::interface;
+use crate::navigation::ParserContext;
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Lists the uniforms, inputs, outputs and buffers declared by a file, for clients to give an overview of what
+/// each stage of a pack takes and produces.
+pub struct InterfaceVariablesCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for InterfaceVariablesCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        let variables = interface::interface_variables(&ctx)
+            .into_iter()
+            .map(|var| {
+                let layout: Vec<Value> = var
+                    .layout
+                    .into_iter()
+                    .map(|qualifier| serde_json::json!({ "key": qualifier.key, "value": qualifier.value }))
+                    .collect();
+
+                serde_json::json!({
+                    "name": var.name,
+                    "type": var.ty,
+                    "storage": var.storage,
+                    "qualifiers": var.qualifiers,
+                    "layout": layout,
+                    "range": var.range,
+                    "block": var.is_block,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Value::Array(variables))
+    }
+}
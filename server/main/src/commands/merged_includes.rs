@@ -18,7 +18,7 @@ use crate::merge_views::FilialTuple;
 use crate::source_mapper::SourceMapper;
 use crate::{graph::CachedStableGraph, merge_views, url_norm::FromJson};
 
-use super::Invokeable;
+use super::{Invokeable, Workspace};
 
 pub struct VirtualMergedDocument {
     pub graph: Rc<RefCell<CachedStableGraph>>,
@@ -70,7 +70,7 @@ impl VirtualMergedDocument {
 }
 
 impl Invokeable for VirtualMergedDocument {
-    fn run_command(&self, root: &Path, arguments: &[Value]) -> Result<Value> {
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
         let path = PathBuf::from_json(arguments.get(0).unwrap())?;
 
         let file_ancestors = match self.get_file_toplevel_ancestors(&path) {
@@ -108,7 +108,10 @@ impl Invokeable for VirtualMergedDocument {
         }
         return Err(format_err!(
             "{:?} is not a top-level file aka has ancestors",
-            path.strip_prefix(root).unwrap()
+            workspace
+                .workspace_folder(&path)
+                .and_then(|folder| path.strip_prefix(folder).ok())
+                .unwrap_or(&path)
         ));
     }
 }
@@ -1,13 +1,37 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
+use rust_lsp::lsp_types::{Diagnostic, Location, Url};
 use serde_json::Value;
 
+use crate::configuration::Configuration;
+
 use anyhow::{format_err, Result};
 use slog_scope::info;
 
+pub mod active_macros;
+pub mod adjacent_diagnostic;
+pub mod affected_outputs;
+pub mod builtin_document;
+pub mod call_sites;
+pub mod document_colors;
+pub mod export_symbols;
 pub mod graph_dot;
+pub mod include_chain;
+pub mod interface_variables;
 pub mod merged_includes;
 pub mod parse_tree;
+pub mod peek_definition;
+pub mod prepare_rename;
+pub mod semantic_tokens;
+pub mod server_info;
+pub mod signature;
+pub mod symbol_id;
+pub mod type_hierarchy;
+pub mod unresolved_symbols;
+pub mod workspace_diagnostics;
 
 pub struct CustomCommandProvider {
     commands: HashMap<String, Box<dyn Invokeable>>,
@@ -20,17 +44,54 @@ impl CustomCommandProvider {
         }
     }
 
-    pub fn execute(&self, command: &str, args: &[Value], root_path: &Path) -> Result<Value> {
+    pub fn execute(&self, command: &str, args: &[Value], workspace: &dyn Workspace) -> Result<Value> {
         if self.commands.contains_key(command) {
             info!("running command";
                 "command" => command,
                 "args" => format!("[{}]", args.iter().map(|v| serde_json::to_string(v).unwrap()).collect::<Vec<String>>().join(", ")));
-            return self.commands.get(command).unwrap().run_command(root_path, args);
+            return self.commands.get(command).unwrap().run_command(workspace, args);
         }
         Err(format_err!("command doesn't exist"))
     }
+
+    /// Returns the names of the commands, sorted.
+    pub fn command_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.commands.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
 }
 
 pub trait Invokeable {
-    fn run_command(&self, root: &Path, arguments: &[Value]) -> Result<Value>;
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value>;
+}
+
+/// The server's workspace as a command sees it, for the commands needing more of the server than the handles they
+/// hold: the folders, include roots and settings it's configured with, the commands it runs, and the operations
+/// over every file of the workspace, which report their progress to the request running the command.
+pub trait Workspace {
+    /// Returns the root and the further workspace folders, in the order the client gave them.
+    fn folders(&self) -> Vec<&Path>;
+
+    /// Returns the workspace folder the file belongs to, being the innermost one containing it.
+    fn workspace_folder(&self, path: &Path) -> Option<&Path>;
+
+    /// Returns the directories absolute includes are looked up in, by the workspace folder they're resolved against.
+    fn include_roots(&self) -> &HashMap<PathBuf, Vec<PathBuf>>;
+
+    /// Finds the global definitions of the name in the file and the files it includes, nearest by includes first
+    /// and ties ordered by path, as with going to definitions.
+    fn find_included_definitions(&self, path: &Path, name: &str) -> Result<Vec<Location>>;
+
+    /// Returns the `mcglsl` settings the client last gave.
+    fn configuration(&self) -> &Configuration;
+
+    /// Returns the names of the commands, sorted.
+    fn command_names(&self) -> Vec<&str>;
+
+    /// Lints every shader file of the workspace, returning the diagnostics of each file.
+    fn workspace_diagnostics(&self) -> HashMap<Url, Vec<Diagnostic>>;
+
+    /// Writes the symbols of every shader file of the workspace to the file, returning the number written.
+    fn export_symbols(&self, output: &Path) -> Result<usize>;
 }
@@ -1,9 +1,4 @@
-use std::{
-    cell::RefCell,
-    fs,
-    path::{Path, PathBuf},
-    rc::Rc,
-};
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc};
 
 use anyhow::{format_err, Result};
 use serde_json::Value;
@@ -12,14 +7,14 @@ use tree_sitter::{Parser, TreeCursor};
 
 use crate::url_norm::FromJson;
 
-use super::Invokeable;
+use super::{Invokeable, Workspace};
 
 pub struct TreeSitterSExpr {
     pub tree_sitter: Rc<RefCell<Parser>>,
 }
 
 impl Invokeable for TreeSitterSExpr {
-    fn run_command(&self, _: &Path, arguments: &[Value]) -> Result<Value> {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
         let path = PathBuf::from_json(arguments.get(0).unwrap())?;
 
         warn!("parsing"; "path" => path.to_str().unwrap().to_string());
@@ -0,0 +1,82 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Position;
+use serde_json::Value;
+use slog_scope::warn;
+use tree_sitter::Parser;
+
+use crate::navigation::{or_nothing_at_point, ParserContext};
+use crate::url_norm::{FromJson, FromUrl};
+
+use super::{Invokeable, Workspace};
+
+/// Finds the definitions of the symbol at a position along with the statements declaring them, for clients
+/// to show in peek views without reading the files themselves. Names not defined in the file are looked up in
+/// the files it includes, as with going to definitions, each statement being read from the file it's in.
+pub struct PeekDefinitionCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for PeekDefinitionCommand {
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let (peeks, name) = {
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let ctx = ParserContext::new(parser, &path)?;
+
+            let peeks = match or_nothing_at_point(ctx.peek_definition(&path, position))? {
+                Some(peeks) => peeks,
+                None => return Ok(Value::Null),
+            };
+            let name = ctx
+                .find_node_at_point(position)
+                .filter(|node| node.kind() == "identifier")
+                .map(|node| node.utf8_text(ctx.source().as_bytes()).map(str::to_string))
+                .transpose()?;
+            (peeks, name)
+        };
+
+        // the parser is only borrowed again once the included definitions are found, as finding them parses too
+        let peeks = match name {
+            Some(name) if peeks.is_empty() => {
+                let locations = workspace.find_included_definitions(&path, &name)?;
+                let files: Vec<PathBuf> = locations.iter().map(|location| PathBuf::from_url(location.uri.clone())).collect();
+
+                let mut contexts = vec![String::new(); locations.len()];
+                let mut distinct = files.clone();
+                distinct.sort();
+                distinct.dedup();
+
+                let parser = &mut self.tree_sitter.borrow_mut();
+                for file in distinct {
+                    let ctx = match ParserContext::new(parser, &file) {
+                        Ok(ctx) => ctx,
+                        Err(e) => {
+                            warn!("skipping unreadable include"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap());
+                            continue;
+                        }
+                    };
+                    for (i, location) in locations.iter().enumerate().filter(|(i, _)| files[*i] == file) {
+                        contexts[i] = ctx.peek_context(location);
+                    }
+                }
+
+                locations.into_iter().zip(contexts).collect()
+            }
+            _ => peeks,
+        };
+
+        let peeks: Vec<Value> = peeks
+            .into_iter()
+            .map(|(location, context)| serde_json::json!({ "location": location, "context": context }))
+            .collect();
+
+        Ok(serde_json::to_value(peeks)?)
+    }
+}
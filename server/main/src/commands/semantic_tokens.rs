@@ -0,0 +1,33 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::Result;
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::navigation::ParserContext;
+use crate::semantic_tokens;
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Serves semantic tokens for a file along with their legend, as the server's LSP library doesn't route
+/// `textDocument/semanticTokens/full` requests. Comments are only tokenized where `mcglsl.semanticTokens.comments` is
+/// set, as it would be for a client without highlighting of its own for comments.
+pub struct SemanticTokensCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for SemanticTokensCommand {
+    fn run_command(&self, workspace: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let comments = workspace.configuration().semantic_tokens.comments;
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        Ok(serde_json::json!({
+            "legend": semantic_tokens::legend(),
+            "tokens": semantic_tokens::semantic_tokens(&ctx, comments),
+        }))
+    }
+}
@@ -0,0 +1,42 @@
+use std::{collections::BTreeMap, path::Path, rc::Rc};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::opengl::ShaderValidator;
+use crate::server_capabilities;
+
+use super::{Invokeable, Workspace};
+
+/// Describes the running server for clients and bug reports: the capabilities it was initialized with, the commands
+/// it runs, the workspace folders and include roots it's configured with, the version of the grammar shaders are
+/// parsed with and the validator linting them. Nothing is parsed or compiled to answer.
+pub struct ServerInfoCommand {
+    pub opengl_context: Rc<dyn ShaderValidator>,
+}
+
+impl Invokeable for ServerInfoCommand {
+    fn run_command(&self, workspace: &dyn Workspace, _: &[Value]) -> Result<Value> {
+        let include_roots: BTreeMap<&Path, _> = workspace
+            .include_roots()
+            .iter()
+            .map(|(folder, roots)| (folder.as_path(), roots))
+            .collect();
+
+        Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "capabilities": server_capabilities(),
+            "commands": workspace.command_names(),
+            "workspaceFolders": workspace.folders(),
+            "includeRoots": include_roots,
+            "grammar": {
+                "name": "glsl",
+                "abiVersion": tree_sitter_glsl::language().version(),
+            },
+            "validator": {
+                "backend": "opengl",
+                "vendor": self.opengl_context.vendor(),
+            },
+        }))
+    }
+}
@@ -0,0 +1,32 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Position;
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::navigation::ParserContext;
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Returns the text declaring the symbol at a position for the client to copy, such as the signature of a function
+/// to paste as a prototype, or `null` where nothing is declared.
+pub struct SignatureCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for SignatureCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        Ok(serde_json::to_value(ctx.signature(&path, position)?)?)
+    }
+}
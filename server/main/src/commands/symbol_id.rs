@@ -0,0 +1,32 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Position;
+use serde_json::Value;
+use tree_sitter::Parser;
+
+use crate::navigation::{or_nothing_at_point, ParserContext};
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Returns the stable ID of the symbol at a position, for external indexers to correlate symbols across runs.
+/// See `ParserContext::symbol_id` for the format.
+pub struct SymbolIdCommand {
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for SymbolIdCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let ctx = ParserContext::new(parser, &path)?;
+
+        Ok(serde_json::to_value(or_nothing_at_point(ctx.symbol_id(&path, position))?)?)
+    }
+}
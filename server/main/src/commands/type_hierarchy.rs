@@ -0,0 +1,112 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::{format_err, Result};
+use rust_lsp::lsp_types::Position;
+use serde_json::Value;
+use slog_scope::warn;
+use tree_sitter::Parser;
+
+use crate::graph::CachedStableGraph;
+use crate::lsp_ext::TypeHierarchyItem;
+use crate::navigation::ParserContext;
+use crate::type_hierarchy::StructIndex;
+use crate::url_norm::{FromJson, FromUrl};
+
+use super::{Invokeable, Workspace};
+
+// indexes the structs of the files sharing includes with the file, as a struct can only be composed of those
+// the files it's compiled along with define, or the file alone if it's outside of the include graph.
+fn struct_index(graph: &RefCell<CachedStableGraph>, parser: &mut Parser, path: &Path) -> Result<StructIndex> {
+    let node = graph.borrow_mut().find_node(path);
+    let files = match node {
+        Some(node) => {
+            let graph = graph.borrow();
+            graph
+                .proximity_distances(node)
+                .into_iter()
+                .map(|(node, _)| graph.get_node(node))
+                .collect()
+        }
+        None => vec![path.to_path_buf()],
+    };
+
+    let mut structs = vec![];
+    for file in files {
+        match ParserContext::new(parser, &file) {
+            Ok(ctx) => structs.extend(ctx.struct_definitions(&file)?),
+            Err(e) => warn!("skipping unreadable file"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap()),
+        }
+    }
+
+    Ok(StructIndex::new(structs))
+}
+
+fn item_argument(arguments: &[Value]) -> Result<TypeHierarchyItem> {
+    match arguments.get(0) {
+        Some(item) => Ok(serde_json::from_value(item.clone())?),
+        None => Err(format_err!("missing type hierarchy item argument")),
+    }
+}
+
+/// Returns the type hierarchy items of the struct at a position, as the server's LSP library doesn't route
+/// `textDocument/prepareTypeHierarchy` requests. Structs relate by composition, see `StructIndex`.
+pub struct PrepareTypeHierarchyCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for PrepareTypeHierarchyCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+        let position: Position = match arguments.get(1) {
+            Some(position) => serde_json::from_value(position.clone())?,
+            None => return Err(format_err!("missing position argument")),
+        };
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let index = struct_index(&self.graph, parser, &path)?;
+        let ctx = ParserContext::new(parser, &path)?;
+
+        Ok(serde_json::to_value(index.prepare(&ctx, position))?)
+    }
+}
+
+/// Returns the structs with fields of the given item's struct, as the server's LSP library doesn't route
+/// `typeHierarchy/supertypes` requests.
+pub struct TypeHierarchySupertypesCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for TypeHierarchySupertypesCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let item = item_argument(arguments)?;
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let index = struct_index(&self.graph, parser, &PathBuf::from_url(item.uri.clone()))?;
+
+        Ok(serde_json::to_value(index.supertypes(&item))?)
+    }
+}
+
+/// Returns the structs the given item's struct has fields of, as the server's LSP library doesn't route
+/// `typeHierarchy/subtypes` requests.
+pub struct TypeHierarchySubtypesCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl Invokeable for TypeHierarchySubtypesCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let item = item_argument(arguments)?;
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let index = struct_index(&self.graph, parser, &PathBuf::from_url(item.uri.clone()))?;
+
+        Ok(serde_json::to_value(index.subtypes(&item))?)
+    }
+}
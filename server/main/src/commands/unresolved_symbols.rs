@@ -0,0 +1,92 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use serde_json::Value;
+use slog_scope::{debug, warn};
+use tree_sitter::Parser;
+
+use crate::builtins;
+use crate::graph::CachedStableGraph;
+use crate::navigation::{ParserContext, SymbolUse};
+use crate::url_norm::FromJson;
+
+use super::{Invokeable, Workspace};
+
+/// Lists the functions called and types named in a file that aren't built-in nor defined by any file compiled
+/// along with it, being the files included by the top-level files it's part of. Each name is listed once, with
+/// every location it's used at, to spot missing includes at a glance.
+pub struct UnresolvedSymbolsCommand {
+    pub graph: Rc<RefCell<CachedStableGraph>>,
+    pub tree_sitter: Rc<RefCell<Parser>>,
+}
+
+impl UnresolvedSymbolsCommand {
+    // the files whose definitions are visible to the file, including itself.
+    fn compiled_with(&self, path: &Path) -> Vec<PathBuf> {
+        let node = match self.graph.borrow_mut().find_node(path) {
+            Some(node) => node,
+            None => return vec![path.to_path_buf()],
+        };
+
+        let graph = self.graph.borrow();
+        let mut roots = graph.collect_root_ancestors(node);
+        if roots.is_empty() {
+            roots.push(node);
+        }
+
+        let mut nodes: Vec<_> = roots
+            .into_iter()
+            .flat_map(|root| graph.include_distances(root))
+            .map(|(node, _)| node)
+            .collect();
+        nodes.sort();
+        nodes.dedup();
+
+        nodes.into_iter().map(|node| graph.get_node(node)).collect()
+    }
+}
+
+impl Invokeable for UnresolvedSymbolsCommand {
+    fn run_command(&self, _: &dyn Workspace, arguments: &[Value]) -> Result<Value> {
+        let path = PathBuf::from_json(arguments.get(0).unwrap())?;
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+
+        let mut defined = HashSet::new();
+        for file in self.compiled_with(&path) {
+            match ParserContext::new(parser, &file) {
+                Ok(ctx) => defined.extend(ctx.defined_names()?),
+                Err(e) => warn!("skipping unreadable file"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap()),
+            }
+        }
+
+        let uses = ParserContext::new(parser, &path)?.symbol_uses(&path)?;
+
+        let mut unresolved: Vec<(SymbolUse, Vec<Value>)> = vec![];
+        for symbol in uses {
+            if builtins::is_builtin(&symbol.name) || defined.contains(&symbol.name) {
+                continue;
+            }
+
+            let location = serde_json::to_value(&symbol.location)?;
+            match unresolved.iter_mut().find(|(first, _)| first.name == symbol.name) {
+                Some((_, locations)) => locations.push(location),
+                None => unresolved.push((symbol, vec![location])),
+            }
+        }
+
+        debug!("found unresolved symbols"; "path" => path.to_str().unwrap(), "count" => unresolved.len(), "defined" => defined.len());
+
+        Ok(Value::Array(
+            unresolved
+                .into_iter()
+                .map(|(symbol, locations)| serde_json::json!({ "name": symbol.name, "kind": symbol.kind, "locations": locations }))
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,14 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use super::{Invokeable, Workspace};
+
+/// Lints every shader file of the workspace as each is linted when opened, for a view of the problems across the
+/// whole pack. Answers with the diagnostics of each file by its URL.
+pub struct WorkspaceDiagnosticsCommand;
+
+impl Invokeable for WorkspaceDiagnosticsCommand {
+    fn run_command(&self, workspace: &dyn Workspace, _: &[Value]) -> Result<Value> {
+        Ok(serde_json::to_value(workspace.workspace_diagnostics())?)
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{CompletionItem, CompletionItemKind};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::{
+    builtins::{self, ShaderStage},
+    navigation::ParserContext,
+    preprocessor::{self, Condition},
+};
+
+const LIST_COMPLETABLE_SYMBOLS_STR: &str = r#"
+    (function_declarator
+        declarator: (identifier) @function)
+
+    (declaration
+        declarator: (identifier) @variable)
+
+    (declaration
+        declarator: (init_declarator
+            declarator: (identifier) @variable))
+
+    (struct_specifier
+        name: (type_identifier) @struct
+        body: (field_declaration_list))
+
+    (preproc_def
+        name: (identifier) @macro)
+
+    (preproc_function_def
+        name: (identifier) @macro)
+"#;
+
+struct Candidate {
+    kind: CompletionItemKind,
+    // the unmet conditions of the least restricted declaration of the symbol, empty if any declaration is active
+    unmet: Vec<Condition>,
+}
+
+/// Lists the file-level symbols available for completion, followed by the built-in variables of the shader stage.
+/// Symbols only declared under preprocessor branches that are inactive given the macros defined in the file are
+/// still offered, but annotated with the conditions they require and sorted after the symbols that are available.
+/// Without a stage, as for includes, the built-ins of every stage are offered.
+pub fn completions(ctx: &ParserContext, stage: Option<ShaderStage>) -> Result<Vec<CompletionItem>> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_COMPLETABLE_SYMBOLS_STR)?;
+    let mut query_cursor = QueryCursor::new();
+
+    let defines = preprocessor::defined_macros(ctx);
+
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+
+    for m in query_cursor.matches(&query, ctx.root_node(), ctx.source().as_bytes()) {
+        for capture in m.captures {
+            if is_local(capture.node) {
+                continue;
+            }
+
+            let label = capture.node.utf8_text(ctx.source().as_bytes())?.to_string();
+            let kind = match query.capture_names()[capture.index as usize].as_str() {
+                "function" => CompletionItemKind::FUNCTION,
+                "variable" => CompletionItemKind::VARIABLE,
+                "struct" => CompletionItemKind::STRUCT,
+                _ => CompletionItemKind::CONSTANT,
+            };
+            let unmet = unmet_conditions(capture.node, ctx.source(), &defines);
+
+            let candidate = candidates.entry(label).or_insert(Candidate {
+                kind,
+                unmet: unmet.clone(),
+            });
+            if unmet.len() < candidate.unmet.len() {
+                candidate.unmet = unmet;
+            }
+        }
+    }
+
+    let stage_builtins: Vec<CompletionItem> = match stage {
+        Some(stage) => vec![stage],
+        None => ShaderStage::ALL.to_vec(),
+    }
+    .into_iter()
+    .flat_map(builtins::stage_variables)
+    .filter(|variable| !candidates.contains_key(variable.name))
+    .map(|variable| CompletionItem {
+        kind: Some(CompletionItemKind::VARIABLE),
+        detail: Some(variable.declaration.to_string()),
+        sort_text: Some(format!("1{}", variable.name)),
+        label: variable.name.to_string(),
+        ..CompletionItem::default()
+    })
+    .collect();
+
+    let mut items: Vec<CompletionItem> = candidates
+        .into_iter()
+        .map(|(label, candidate)| {
+            let (detail, sort_text) = if candidate.unmet.is_empty() {
+                (None, format!("0{}", label))
+            } else {
+                let requires = candidate.unmet.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ");
+                (Some(format!("(requires {})", requires)), format!("2{}", label))
+            };
+
+            CompletionItem {
+                kind: Some(candidate.kind),
+                detail,
+                sort_text: Some(sort_text),
+                label,
+                ..CompletionItem::default()
+            }
+        })
+        .collect();
+
+    // stages declaring the same built-in differently offer it once
+    for builtin in stage_builtins {
+        if !items.iter().any(|item| item.label == builtin.label) {
+            items.push(builtin);
+        }
+    }
+
+    items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+
+    Ok(items)
+}
+
+fn unmet_conditions(node: Node, source: &str, defines: &HashSet<String>) -> Vec<Condition> {
+    let mut unmet: Vec<Condition> = preprocessor::gating_conditions(node, source)
+        .into_iter()
+        .filter(|c| !c.is_met(defines))
+        .collect();
+    // report outermost conditions first, matching the order they appear in the source
+    unmet.reverse();
+    unmet
+}
+
+// whether the symbol is declared inside a function, and as such isn't available file-wide.
+fn is_local(node: Node) -> bool {
+    let mut parent = node.parent();
+    while let Some(p) = parent {
+        match p.kind() {
+            "compound_statement" | "parameter_list" | "field_declaration_list" => return true,
+            _ => parent = p.parent(),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod completion_test {
+    use tree_sitter::Parser;
+
+    use crate::builtins::ShaderStage;
+    use crate::completion;
+    use crate::navigation::ParserContext;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_completions_annotate_inactive_branches() {
+        let source = r#"#define BLOOM
+
+#ifdef SHADOWS
+float sampleShadow(vec3 pos) { return 1.0; }
+#else
+float noShadow = 1.0;
+#endif
+
+#ifdef BLOOM
+vec3 bloom(vec3 color) { float local = 1.0; return color; }
+#endif
+
+void main() {}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let items = completion::completions(&ctx, None).unwrap();
+        let find = |label: &str| items.iter().find(|item| item.label == label);
+
+        assert_eq!(find("sampleShadow").unwrap().detail.as_deref(), Some("(requires SHADOWS)"));
+        assert_eq!(find("noShadow").unwrap().detail, None);
+        assert_eq!(find("bloom").unwrap().detail, None);
+        assert_eq!(find("main").unwrap().detail, None);
+        assert!(find("BLOOM").is_some());
+        assert!(find("local").is_none());
+        assert!(find("pos").is_none());
+
+        // symbols gated behind inactive branches are listed last
+        assert_eq!(items.last().unwrap().label, "sampleShadow");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_completions_stage_builtins() {
+        let source = r#"void main() {}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let labels = |stage| -> Vec<String> {
+            completion::completions(&ctx, stage)
+                .unwrap()
+                .into_iter()
+                .map(|item| item.label)
+                .collect()
+        };
+
+        let compute = labels(Some(ShaderStage::Compute));
+        assert!(compute.contains(&"gl_GlobalInvocationID".to_string()));
+        assert!(!compute.contains(&"gl_FragCoord".to_string()));
+        // the file's own symbols come first
+        assert_eq!(compute[0], "main");
+
+        let fragment = labels(Some(ShaderStage::Fragment));
+        assert!(fragment.contains(&"gl_FragCoord".to_string()));
+        assert!(!fragment.contains(&"gl_GlobalInvocationID".to_string()));
+
+        // includes are offered the built-ins of every stage, once each
+        let any = labels(None);
+        assert!(any.contains(&"gl_GlobalInvocationID".to_string()));
+        assert_eq!(any.iter().filter(|label| *label == "gl_Position").count(), 1);
+    }
+}
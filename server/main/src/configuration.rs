@@ -1,8 +1,141 @@
-use std::str::FromStr;
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
 
+use serde::Deserialize;
 use slog::Level;
 use slog_scope::error;
 
+/// The `mcglsl` section of the client's settings.
+#[derive(Deserialize, Clone)]
+pub struct Configuration {
+    #[serde(alias = "logLevel")]
+    pub log_level: String,
+    // a file to also log to, at its own level, for logs to attach to bug reports. Empty for none
+    #[serde(alias = "logFile", default)]
+    pub log_file: String,
+    #[serde(alias = "logFileLevel", default = "default_log_file_level")]
+    pub log_file_level: String,
+    // whether to hint at integer literal divisions used as floats, off by default as it's opinionated
+    #[serde(alias = "integerDivisionHint", default)]
+    pub integer_division_hint: bool,
+    // how many references are returned at most, as a widely used function can be called thousands of times
+    #[serde(alias = "referencesLimit", default = "default_references_limit")]
+    pub references_limit: usize,
+    // what going to the definition does when the cursor is already on the declaration it would go to
+    #[serde(alias = "definitionOnDeclaration", default)]
+    pub definition_on_declaration: DefinitionOnDeclaration,
+    // whether going to the definition of a name found nowhere else guesses at same-named symbols anywhere in the
+    // workspace, off by default as the guess can jump to unrelated code
+    #[serde(alias = "definitionWorkspaceFallback", default)]
+    pub definition_workspace_fallback: bool,
+    // whether going to the definition of a name found nowhere else guesses at same-named symbols in the documents
+    // open in the client, as edited, for files not included or saved yet. Off by default like the workspace guess
+    #[serde(alias = "definitionOpenDocumentsFallback", default)]
+    pub definition_open_documents_fallback: bool,
+    // whether hovers start with the stage of the shader and whether the cursor is in `main`, off by default as it
+    // clutters every hover
+    #[serde(alias = "hoverContext", default)]
+    pub hover_context: bool,
+    #[serde(alias = "semanticTokens", default)]
+    pub semantic_tokens: SemanticTokensConfiguration,
+}
+
+/// The `mcglsl.semanticTokens` settings.
+#[derive(Deserialize, Clone, Default)]
+pub struct SemanticTokensConfiguration {
+    // whether comments are tokenized, off by default as most clients already highlight them with their grammar
+    #[serde(default)]
+    pub comments: bool,
+}
+
+/// What going to the definition of a declaration's own name gives, rather than the declaration itself.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DefinitionOnDeclaration {
+    /// The references to the declaration, like clangd does.
+    #[default]
+    References,
+    /// No locations at all.
+    None,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Configuration {
+            log_level: String::new(),
+            log_file: String::new(),
+            log_file_level: default_log_file_level(),
+            integer_division_hint: false,
+            references_limit: default_references_limit(),
+            definition_on_declaration: DefinitionOnDeclaration::default(),
+            definition_workspace_fallback: false,
+            definition_open_documents_fallback: false,
+            hover_context: false,
+            semantic_tokens: SemanticTokensConfiguration::default(),
+        }
+    }
+}
+
+fn default_references_limit() -> usize {
+    1000
+}
+
+fn default_log_file_level() -> String {
+    "trace".into()
+}
+
+impl Configuration {
+    /// The file to also log to and the level to log to it at, if any. An unexpected level logs everything.
+    pub fn log_file(&self) -> Option<(PathBuf, Level)> {
+        if self.log_file.trim().is_empty() {
+            return None;
+        }
+
+        let level = Level::from_str(&self.log_file_level).unwrap_or_else(|_| {
+            error!("got unexpected log file level from config"; "level" => &self.log_file_level);
+            Level::Trace
+        });
+        Some((PathBuf::from(self.log_file.trim()), level))
+    }
+}
+
+/// The options given by the client with the `initialize` request.
+#[derive(Deserialize)]
+pub struct InitializationOptions {
+    // how long parsing a file may take before it's given up on, so that a pathological file can't hang the server
+    #[serde(alias = "parseTimeoutMs", default = "default_parse_timeout_ms")]
+    pub parse_timeout_ms: u64,
+    // further directories absolute includes are looked up in, relative to the workspace root
+    #[serde(alias = "includeRoots", default)]
+    pub include_roots: Vec<String>,
+    // the severities diagnostics are published with by their code, overriding their own
+    #[serde(alias = "diagnosticSeverities", default)]
+    pub diagnostic_severities: HashMap<String, SeverityOverride>,
+}
+
+/// The severity given to diagnostics of a code instead of their own, or that they're not published at all.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum SeverityOverride {
+    Error,
+    Warning,
+    Information,
+    Hint,
+    Off,
+}
+
+impl Default for InitializationOptions {
+    fn default() -> Self {
+        InitializationOptions {
+            parse_timeout_ms: default_parse_timeout_ms(),
+            include_roots: vec![],
+            diagnostic_severities: HashMap::new(),
+        }
+    }
+}
+
+fn default_parse_timeout_ms() -> u64 {
+    5000
+}
 
 pub fn handle_log_level_change<F: FnOnce(Level)>(log_level: String, callback: F) {
     match Level::from_str(log_level.as_str()) {
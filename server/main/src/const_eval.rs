@@ -0,0 +1,293 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    iter::Peekable,
+    vec::IntoIter,
+};
+
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::{
+    navigation::{self, ParserContext},
+    preprocessor,
+};
+
+const LIST_DECLARATIONS_STR: &str = r#"
+    (declaration) @declaration
+"#;
+
+/// The value of a constant expression. Integer arithmetic is kept apart from floating point arithmetic, as
+/// division truncates for integers in GLSL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+}
+
+impl Constant {
+    fn as_float(self) -> f64 {
+        match self {
+            Constant::Int(i) => i as f64,
+            Constant::Float(f) => f,
+        }
+    }
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(i) => write!(f, "{}", i),
+            // floats keep a decimal point to tell them apart from integers
+            Constant::Float(v) if v.is_finite() && v.fract() == 0.0 => write!(f, "{:.1}", v),
+            Constant::Float(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Constant),
+    Name(String),
+    Operator(char),
+}
+
+/// Evaluates an expression of numeric literals combined with `+`, `-`, `*`, `/` and parentheses, where names
+/// refer to the `const` variables and object-like `#define`s of the file. Returns `None` for anything else, such
+/// as function calls, swizzles or names without a constant value.
+pub fn evaluate(ctx: &ParserContext, expression: &str) -> Option<Constant> {
+    evaluate_resolving(ctx, expression, &mut HashSet::new())
+}
+
+/// Whether the expression is a single numeric literal, and so needs no evaluating.
+pub fn is_literal(expression: &str) -> bool {
+    matches!(tokenize(expression).as_deref(), Some([Token::Number(_)]))
+}
+
+// evaluates the expression, with the names whose values are being resolved to guard against cyclic definitions.
+fn evaluate_resolving(ctx: &ParserContext, expression: &str, resolving: &mut HashSet<String>) -> Option<Constant> {
+    let mut tokens = tokenize(expression)?.into_iter().peekable();
+    let value = sum(ctx, &mut tokens, resolving)?;
+    // trailing tokens mean the expression wasn't understood in full
+    tokens.next().is_none().then_some(value)
+}
+
+fn sum(ctx: &ParserContext, tokens: &mut Peekable<IntoIter<Token>>, resolving: &mut HashSet<String>) -> Option<Constant> {
+    let mut value = product(ctx, tokens, resolving)?;
+    while let Some(Token::Operator(op @ ('+' | '-'))) = tokens.peek().cloned() {
+        tokens.next();
+        value = apply(value, op, product(ctx, tokens, resolving)?)?;
+    }
+    Some(value)
+}
+
+fn product(ctx: &ParserContext, tokens: &mut Peekable<IntoIter<Token>>, resolving: &mut HashSet<String>) -> Option<Constant> {
+    let mut value = factor(ctx, tokens, resolving)?;
+    while let Some(Token::Operator(op @ ('*' | '/'))) = tokens.peek().cloned() {
+        tokens.next();
+        value = apply(value, op, factor(ctx, tokens, resolving)?)?;
+    }
+    Some(value)
+}
+
+fn factor(ctx: &ParserContext, tokens: &mut Peekable<IntoIter<Token>>, resolving: &mut HashSet<String>) -> Option<Constant> {
+    match tokens.next()? {
+        Token::Number(value) => Some(value),
+        Token::Name(name) => resolve(ctx, &name, resolving),
+        Token::Operator('-') => apply(Constant::Int(0), '-', factor(ctx, tokens, resolving)?),
+        Token::Operator('+') => factor(ctx, tokens, resolving),
+        Token::Operator('(') => {
+            let value = sum(ctx, tokens, resolving)?;
+            (tokens.next()? == Token::Operator(')')).then_some(value)
+        }
+        Token::Operator(_) => None,
+    }
+}
+
+fn apply(left: Constant, op: char, right: Constant) -> Option<Constant> {
+    match (left, right) {
+        (Constant::Int(l), Constant::Int(r)) => match op {
+            '+' => l.checked_add(r),
+            '-' => l.checked_sub(r),
+            '*' => l.checked_mul(r),
+            '/' => l.checked_div(r),
+            _ => None,
+        }
+        .map(Constant::Int),
+        _ => {
+            let (l, r) = (left.as_float(), right.as_float());
+            match op {
+                '+' => Some(l + r),
+                '-' => Some(l - r),
+                '*' => Some(l * r),
+                '/' if r != 0.0 => Some(l / r),
+                _ => None,
+            }
+            .map(Constant::Float)
+        }
+    }
+}
+
+// the value of a name, from the body of the `#define` or the initializer of the `const` variable declaring it.
+fn resolve(ctx: &ParserContext, name: &str, resolving: &mut HashSet<String>) -> Option<Constant> {
+    if !resolving.insert(name.to_string()) {
+        return None;
+    }
+
+    let expression = match preprocessor::find_define(ctx, name) {
+        Some(define) if define.kind() == "preproc_def" => define.child_by_field_name("value").map(|value| node_text(ctx, value)),
+        Some(_) => None,
+        None => const_initializer(ctx, name),
+    };
+    let value = expression.and_then(|expression| evaluate_resolving(ctx, expression, resolving));
+
+    resolving.remove(name);
+    value
+}
+
+// the initializer of the first `const` variable of the name declared in the file.
+fn const_initializer<'a>(ctx: &'a ParserContext, name: &str) -> Option<&'a str> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_DECLARATIONS_STR).unwrap();
+    let mut query_cursor = QueryCursor::new();
+
+    let declarations: Vec<Node> = query_cursor
+        .matches(&query, ctx.root_node(), ctx.source().as_bytes())
+        .flat_map(|m| m.captures.iter().map(|c| c.node).collect::<Vec<_>>())
+        .collect();
+
+    declarations.into_iter().find_map(|declaration| initializer(ctx, declaration, name))
+}
+
+/// Returns the initializer of the variable of the given name in a `const` declaration, such as `2.0 * PI` in
+/// `const float TAU = 2.0 * PI;`.
+pub fn initializer<'a>(ctx: &'a ParserContext, declaration: Node, name: &str) -> Option<&'a str> {
+    let type_node = declaration.child_by_field_name("type")?;
+    let qualifiers = &ctx.source()[declaration.start_byte()..type_node.start_byte()];
+    if !qualifiers.split_whitespace().any(|word| word == "const") {
+        return None;
+    }
+
+    let mut cursor = declaration.walk();
+    let declarators: Vec<Node> = declaration.children_by_field_name("declarator", &mut cursor).collect();
+    declarators
+        .into_iter()
+        .filter(|declarator| declarator.kind() == "init_declarator")
+        .find(|declarator| declarator.child_by_field_name("declarator").map(|ident| node_text(ctx, ident)) == Some(name))
+        .and_then(|declarator| declarator.child_by_field_name("value"))
+        .map(|value| node_text(ctx, value))
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        if "+-*/()".contains(c) {
+            tokens.push(Token::Operator(c));
+        } else if c.is_ascii_digit() || (c == '.' && matches!(chars.peek(), Some((_, d)) if d.is_ascii_digit())) {
+            let is_hex = matches!(expression[start..].get(..2), Some("0x" | "0X"));
+            let mut end = start + c.len_utf8();
+            let mut previous = c;
+            while let Some(&(i, next)) = chars.peek() {
+                // exponents carry their sign along, as in `1e-5`
+                let in_exponent = !is_hex && matches!(previous, 'e' | 'E') && matches!(next, '+' | '-');
+                if !(next.is_ascii_alphanumeric() || next == '.' || in_exponent) {
+                    break;
+                }
+                chars.next();
+                end = i + next.len_utf8();
+                previous = next;
+            }
+            tokens.push(Token::Number(parse_number(&expression[start..end])?));
+        } else if navigation::is_ident_start(c) {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, next)) = chars.peek().filter(|(_, next)| navigation::is_ident_char(*next)) {
+                chars.next();
+                end = i + next.len_utf8();
+            }
+            tokens.push(Token::Name(expression[start..end].to_string()));
+        } else {
+            return None;
+        }
+    }
+
+    Some(tokens)
+}
+
+// parses a GLSL numeric literal such as `2`, `0x1F`, `017`, `3u`, `1.5f`, `1e-5` or `2.0lf`. Integers with a
+// leading `0` are octal as in C, so `010` is 8.
+fn parse_number(literal: &str) -> Option<Constant> {
+    let lower = literal.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x") {
+        return i64::from_str_radix(hex.trim_end_matches('u'), 16).ok().map(Constant::Int);
+    }
+
+    if lower.contains('.') || lower.contains('e') || lower.ends_with('f') {
+        let digits = lower.trim_end_matches("lf").trim_end_matches('f');
+        return digits.parse::<f64>().ok().map(Constant::Float);
+    }
+
+    let digits = lower.trim_end_matches('u');
+    match digits.strip_prefix('0').filter(|octal| !octal.is_empty()) {
+        Some(octal) => i64::from_str_radix(octal, 8).ok().map(Constant::Int),
+        None => digits.parse::<i64>().ok().map(Constant::Int),
+    }
+}
+
+fn node_text<'a>(ctx: &'a ParserContext, node: Node) -> &'a str {
+    &ctx.source()[node.byte_range()]
+}
+
+#[cfg(test)]
+mod const_eval_test {
+    use tree_sitter::Parser;
+
+    use crate::const_eval::{evaluate, is_literal, Constant};
+    use crate::navigation::ParserContext;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_evaluate() {
+        let source = r#"#define STEPS 8
+#define HALF_STEPS (STEPS / 2)
+#define LOOP LOOP
+const float PI = 3.14159;
+const float TAU = 2.0 * PI;
+const int COUNT = 7 / 2, REMAINDER = 7 - COUNT * 2;
+float notConst = 1.0;
+
+void main() {}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        assert_eq!(evaluate(&ctx, "2.0 * PI"), Some(Constant::Float(6.28318)));
+        assert_eq!(evaluate(&ctx, "TAU / 2.0").unwrap().to_string(), "3.14159");
+        assert_eq!(evaluate(&ctx, "-(1 + 2) * 3"), Some(Constant::Int(-9)));
+        assert_eq!(evaluate(&ctx, "HALF_STEPS"), Some(Constant::Int(4)));
+        assert_eq!(evaluate(&ctx, "COUNT"), Some(Constant::Int(3)));
+        assert_eq!(evaluate(&ctx, "REMAINDER"), Some(Constant::Int(1)));
+        assert_eq!(evaluate(&ctx, "1.5f + 0x10"), Some(Constant::Float(17.5)));
+        assert_eq!(evaluate(&ctx, "1e-1 * 10"), Some(Constant::Float(1.0)));
+        assert_eq!(evaluate(&ctx, "010 + 0u"), Some(Constant::Int(8)));
+        assert_eq!(evaluate(&ctx, "010.0"), Some(Constant::Float(10.0)));
+        assert_eq!(Constant::Float(1.0).to_string(), "1.0");
+
+        // anything not understood gives up
+        assert_eq!(evaluate(&ctx, "notConst * 2.0"), None);
+        assert_eq!(evaluate(&ctx, "sin(PI)"), None);
+        assert_eq!(evaluate(&ctx, "LOOP + 1"), None);
+        assert_eq!(evaluate(&ctx, "1 / 0"), None);
+        assert_eq!(evaluate(&ctx, "(1 + 2"), None);
+        assert_eq!(evaluate(&ctx, "09"), None);
+
+        assert!(is_literal("3.14159"));
+        assert!(!is_literal("2.0 * PI"));
+    }
+}
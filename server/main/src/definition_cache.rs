@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use rust_lsp::lsp_types::Location;
+use slog_scope::debug;
+
+// enough for the symbols navigated between while working on a handful of files.
+const CAPACITY: usize = 256;
+
+/// How a definition was searched for, as lookups of the same name from the same file differ by how far they look.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    /// Through the file and the files it includes.
+    Included,
+    /// By name through the rest of the workspace folder.
+    Heuristic,
+}
+
+struct CachedDefinitions {
+    locations: Vec<Location>,
+    // the files read to find the definitions, any of which changing may change them
+    files: Vec<PathBuf>,
+    last_used: u64,
+}
+
+/// Caches the definitions found by name for a file, as the same symbol is often looked up repeatedly, e.g. when
+/// peeking at it, and each lookup otherwise parses and queries every file it searches. The least recently used
+/// entries are dropped past `CAPACITY`. Entries are invalidated by `invalidate` whenever a file they were found
+/// by reading changes, while workspace wide lookups are invalidated by any change, as a change to a file they
+/// didn't read, such as a new one, may as well add a definition of the name.
+#[derive(Default)]
+pub struct DefinitionCache {
+    entries: HashMap<(PathBuf, String, DefinitionKind), CachedDefinitions>,
+    // incremented on each use, ordering the entries by how recently they were used
+    clock: u64,
+    hits: usize,
+    misses: usize,
+}
+
+impl DefinitionCache {
+    /// Returns the cached definitions of the name looked up from the file, counting the lookup towards the hit rate.
+    pub fn get(&mut self, path: &Path, name: &str, kind: DefinitionKind) -> Option<Vec<Location>> {
+        self.clock += 1;
+
+        let locations = match self.entries.get_mut(&(path.to_path_buf(), name.to_string(), kind)) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.locations.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        };
+
+        debug!("looked up cached definitions"; "name" => name, "hit" => locations.is_some(), "hit rate" => format!("{:.2}", self.hit_rate()));
+        locations
+    }
+
+    /// Caches the definitions of the name looked up from the file, found by reading the given files.
+    pub fn insert(&mut self, path: &Path, name: &str, kind: DefinitionKind, locations: Vec<Location>, files: Vec<PathBuf>) {
+        self.clock += 1;
+
+        let key = (path.to_path_buf(), name.to_string(), kind);
+        if self.entries.len() >= CAPACITY && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.entries.insert(
+            key,
+            CachedDefinitions {
+                locations,
+                files,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Drops the definitions that may have changed with the file.
+    pub fn invalidate(&mut self, path: &Path) {
+        self.entries
+            .retain(|(_, _, kind), entry| *kind != DefinitionKind::Heuristic && !entry.files.iter().any(|file| file == path));
+    }
+
+    /// The fraction of lookups answered from the cache.
+    pub fn hit_rate(&self) -> f32 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f32 / lookups as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod definition_cache_test {
+    use std::path::{Path, PathBuf};
+
+    use rust_lsp::lsp_types::{Location, Position, Range, Url};
+
+    use crate::definition_cache::{DefinitionCache, DefinitionKind, CAPACITY};
+
+    fn location(path: &str, line: u32) -> Location {
+        Location::new(
+            Url::from_file_path(path).unwrap(),
+            Range::new(Position::new(line, 0), Position::new(line, 4)),
+        )
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_definition_cache() {
+        let final_fsh = Path::new("/pack/shaders/final.fsh");
+        let common = PathBuf::from("/pack/shaders/lib/common.glsl");
+        let files = vec![final_fsh.to_path_buf(), common.clone()];
+
+        let mut cache = DefinitionCache::default();
+
+        // looking up the same symbol repeatedly while peeking at it, as a session does
+        assert_eq!(cache.get(final_fsh, "tonemap", DefinitionKind::Included), None);
+        cache.insert(
+            final_fsh,
+            "tonemap",
+            DefinitionKind::Included,
+            vec![location("/pack/shaders/lib/common.glsl", 3)],
+            files,
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                cache.get(final_fsh, "tonemap", DefinitionKind::Included),
+                Some(vec![location("/pack/shaders/lib/common.glsl", 3)])
+            );
+        }
+        assert_eq!(cache.hit_rate(), 0.75);
+
+        // the lookups are told apart by how far they searched
+        assert_eq!(cache.get(final_fsh, "tonemap", DefinitionKind::Heuristic), None);
+        cache.insert(final_fsh, "tonemap", DefinitionKind::Heuristic, vec![], vec![]);
+
+        // a change to a file unrelated to these lookups only drops the workspace wide one
+        cache.invalidate(Path::new("/pack/shaders/composite.fsh"));
+        assert!(cache.get(final_fsh, "tonemap", DefinitionKind::Included).is_some());
+        assert_eq!(cache.get(final_fsh, "tonemap", DefinitionKind::Heuristic), None);
+
+        // while a change to an include read by the lookup drops it too
+        cache.invalidate(&common);
+        assert_eq!(cache.get(final_fsh, "tonemap", DefinitionKind::Included), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_definition_cache_eviction() {
+        let path = Path::new("/pack/shaders/final.fsh");
+        let mut cache = DefinitionCache::default();
+
+        for i in 0..CAPACITY {
+            cache.insert(path, &format!("name{}", i), DefinitionKind::Included, vec![], vec![]);
+        }
+        // using the first name leaves the second as the least recently used
+        assert!(cache.get(path, "name0", DefinitionKind::Included).is_some());
+        cache.insert(path, "overflow", DefinitionKind::Included, vec![], vec![]);
+
+        assert!(cache.get(path, "name0", DefinitionKind::Included).is_some());
+        assert!(cache.get(path, "name1", DefinitionKind::Included).is_none());
+        assert!(cache.get(path, "overflow", DefinitionKind::Included).is_some());
+    }
+}
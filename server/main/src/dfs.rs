@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use petgraph::stable_graph::NodeIndex;
 
 use crate::{graph::CachedStableGraph, merge_views::FilialTuple};
@@ -15,6 +17,8 @@ pub struct Dfs<'a> {
     stack: Vec<NodeIndex>,
     graph: &'a CachedStableGraph,
     cycle: Vec<VisitCount>,
+    // the `#pragma once` files visited so far, whose includes aren't descended into again
+    visited_once: HashSet<NodeIndex>,
 }
 
 impl<'a> Dfs<'a> {
@@ -23,6 +27,7 @@ impl<'a> Dfs<'a> {
             stack: vec![start],
             graph,
             cycle: Vec::new(),
+            visited_once: HashSet::new(),
         }
     }
 
@@ -57,16 +62,20 @@ impl<'a> Iterator for Dfs<'a> {
         let parent = self.cycle.last().map(|p| p.node);
 
         if let Some(child) = self.stack.pop() {
+            // a repeated #pragma once file is merged as empty, so its includes are too
+            let repeated = self.graph.is_pragma_once(child) && !self.visited_once.insert(child);
+
             self.cycle.push(VisitCount {
                 node: child,
-                children: self.graph.graph.edges(child).count(),
+                children: if repeated { 0 } else { self.graph.graph.edges(child).count() },
                 touch: 1,
             });
 
-            let mut children: Vec<_> = self
-                .graph
-                .get_all_child_positions(child)
-                .collect();
+            let mut children: Vec<_> = if repeated {
+                vec![]
+            } else {
+                self.graph.get_all_child_positions(child).collect()
+            };
             children.reverse();
 
             if !children.is_empty() {
@@ -101,7 +110,7 @@ pub mod error {
 
     use crate::{consts, graph::CachedStableGraph};
 
-    use rust_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+    use rust_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
 
     #[derive(Debug)]
     pub struct CycleError(Vec<PathBuf>);
@@ -135,7 +144,7 @@ pub mod error {
                 range: Range::new(Position::new(0, 0), Position::new(0, 500)),
                 source: Some(consts::SOURCE.into()),
                 message: e.into(),
-                code: None,
+                code: Some(NumberOrString::String("include-cycle".into())),
                 tags: None,
                 related_information: None,
                 code_description: Option::None,
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, lazy::OnceCell, path::Path};
+use std::{collections::HashMap, fs, lazy::OnceCell, path::Path};
 
 use regex::Regex;
 use rust_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
@@ -8,6 +8,7 @@ use url::Url;
 use crate::{
     consts,
     graph::CachedStableGraph,
+    linemap::LineMap,
     opengl,
     source_mapper::{SourceMapper, SourceNum},
 };
@@ -49,6 +50,7 @@ impl<'a, T: opengl::ShaderValidator + ?Sized> DiagnosticsParser<'a, T> {
     ) -> HashMap<Url, Vec<Diagnostic>> {
         let output_lines = output.split('\n').collect::<Vec<&str>>();
         let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::with_capacity(output_lines.len());
+        let mut sources: HashMap<String, Option<(String, LineMap)>> = HashMap::new();
 
         debug!("diagnostics regex selected"; "regex" => self.get_line_regex() .as_str());
 
@@ -89,12 +91,28 @@ impl<'a, T: opengl::ShaderValidator + ?Sized> DiagnosticsParser<'a, T> {
                 None => uri.to_str().unwrap().to_string(),
             };
 
+            // a line continued with a backslash is a single logical line to the compiler, so the diagnostic
+            // covers all of its physical lines
+            let source = sources.entry(origin.clone()).or_insert_with(|| {
+                fs::read_to_string(&origin).ok().map(|source| {
+                    let linemap = LineMap::new(&source);
+                    (source, linemap)
+                })
+            });
+            let (first_line, last_line) = match source {
+                Some((source, linemap)) => {
+                    let (first, last) = linemap.logical_line(source, line as usize);
+                    (first as u32, last as u32)
+                }
+                None => (line, line),
+            };
+
             let diagnostic = Diagnostic {
                 range: Range::new(
                     /* Position::new(line, leading_whitespace as u64),
                     Position::new(line, line_text.len() as u64) */
-                    Position::new(line, 0),
-                    Position::new(line, 1000),
+                    Position::new(first_line, 0),
+                    Position::new(last_line, 1000),
                 ),
                 code: None,
                 severity: Some(severity),
@@ -0,0 +1,390 @@
+use std::{collections::HashMap, ops::Range as ByteRange, path::Path};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Range, TextEdit, WorkspaceEdit};
+use tree_sitter::Node;
+
+use crate::{navigation::ParserContext, url_norm::url_for};
+
+// a variable of the enclosing function used by the extracted statements, passed to the new function.
+struct Parameter<'tree> {
+    declaration: Node<'tree>,
+    // whether the statements assign to it, making it an `inout` parameter so that the caller sees the changes
+    written: bool,
+}
+
+/// Builds the edit moving the statements within the range into a new function declared before the function they
+/// are in, replacing them with a call of it. The variables of the enclosing function that the statements use are
+/// passed as parameters, `inout` ones where the statements assign to them.
+///
+/// Returns `None` where the range doesn't span whole statements of a block, where the statements return or jump
+/// out of the block, and where variables they declare are used after them, as those would have to be returned.
+pub fn extract_function(ctx: &ParserContext, path: &Path, range: Range) -> Result<Option<WorkspaceEdit>> {
+    let (start, end) = (ctx.offset_for_position(range.start), ctx.offset_for_position(range.end));
+    let selected = &ctx.source()[start..end];
+    let start = start + selected.len() - selected.trim_start().len();
+    let end = end - (selected.len() - selected.trim_end().len());
+    if start >= end {
+        return Ok(None);
+    }
+
+    let statements = match selected_statements(ctx, start, end) {
+        Some(statements) => statements,
+        None => return Ok(None),
+    };
+    let function = match enclosing_function(statements[0]) {
+        Some(function) => function,
+        None => return Ok(None),
+    };
+    if statements.iter().any(|statement| jumps_out(*statement)) {
+        return Ok(None);
+    }
+
+    let selection = statements[0].start_byte()..statements[statements.len() - 1].end_byte();
+
+    let mut identifiers = vec![];
+    for statement in &statements {
+        collect_identifiers(*statement, &mut identifiers);
+    }
+
+    let mut parameters: Vec<Parameter> = vec![];
+    let mut declared = vec![];
+    for identifier in identifiers {
+        let declaration = match ctx.variable_declaration(identifier)? {
+            Some(declaration) => declaration,
+            None => continue,
+        };
+
+        if contains(&selection, declaration) {
+            if declaration == identifier {
+                declared.push(declaration);
+            }
+            continue;
+        }
+        // globals are as visible to the new function
+        if !contains(&function.byte_range(), declaration) {
+            continue;
+        }
+
+        let written = is_written(identifier);
+        match parameters.iter_mut().find(|parameter| parameter.declaration == declaration) {
+            Some(parameter) => parameter.written |= written,
+            None => parameters.push(Parameter { declaration, written }),
+        }
+    }
+
+    let mut later = vec![];
+    if let Some(body) = function.child_by_field_name("body") {
+        collect_identifiers(body, &mut later);
+    }
+    for identifier in later.into_iter().filter(|identifier| identifier.start_byte() >= selection.end) {
+        if let Some(declaration) = ctx.variable_declaration(identifier)? {
+            if declared.contains(&declaration) {
+                return Ok(None);
+            }
+        }
+    }
+
+    let mut signature = vec![];
+    for parameter in &parameters {
+        let ty = match parameter_type(ctx, parameter.declaration) {
+            Some(ty) => ty,
+            None => return Ok(None),
+        };
+        let qualifier = if parameter.written { "inout " } else { "" };
+        signature.push(format!("{}{} {}", qualifier, ty, node_text(ctx, parameter.declaration)));
+    }
+
+    let name = function_name(ctx)?;
+    let arguments: Vec<&str> = parameters.iter().map(|parameter| node_text(ctx, parameter.declaration)).collect();
+
+    let unit = function
+        .child_by_field_name("body")
+        .and_then(|body| body.named_child(0))
+        .map_or("    ", |statement| indentation(ctx, statement.start_byte()));
+    let body = reindent(&ctx.source()[selection.clone()], indentation(ctx, selection.start), unit);
+
+    let function_start = ctx.position_for_offset(function.start_byte());
+    let edits = vec![
+        TextEdit {
+            range: Range::new(function_start, function_start),
+            new_text: format!("void {}({}) {{\n{}\n}}\n\n", name, signature.join(", "), body),
+        },
+        TextEdit {
+            range: Range::new(ctx.position_for_offset(selection.start), ctx.position_for_offset(selection.end)),
+            new_text: format!("{}({});", name, arguments.join(", ")),
+        },
+    ];
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(HashMap::from([(url_for(path)?, edits)])),
+        ..WorkspaceEdit::default()
+    }))
+}
+
+// the statements of the innermost block the range is within, if the range spans whole statements of it.
+fn selected_statements<'tree>(ctx: &'tree ParserContext, start: usize, end: usize) -> Option<Vec<Node<'tree>>> {
+    let mut block = ctx.root_node().descendant_for_byte_range(start, end)?;
+    while !(block.kind() == "compound_statement" && block.start_byte() < start && end < block.end_byte()) {
+        block = block.parent()?;
+    }
+
+    let mut cursor = block.walk();
+    let overlapping: Vec<Node> = block
+        .named_children(&mut cursor)
+        .filter(|child| child.start_byte() < end && start < child.end_byte())
+        .collect();
+
+    let whole = overlapping
+        .iter()
+        .all(|child| start <= child.start_byte() && child.end_byte() <= end);
+    let has_statement = overlapping.iter().any(|child| child.kind() != "comment");
+
+    (whole && has_statement).then_some(overlapping)
+}
+
+/// Returns the function definition the node is in, if any.
+pub fn enclosing_function(node: Node) -> Option<Node> {
+    let mut parent = node.parent();
+    while let Some(node) = parent {
+        if node.kind() == "function_definition" {
+            return Some(node);
+        }
+        parent = node.parent();
+    }
+    None
+}
+
+// whether the statement returns, or breaks or continues a loop or switch it isn't part of.
+fn jumps_out(statement: Node) -> bool {
+    let mut cursor = statement.walk();
+    let mut stack = vec![statement];
+
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "return_statement" => return true,
+            "break_statement" | "continue_statement" if !is_within_target(node, statement) => return true,
+            _ => stack.extend(node.named_children(&mut cursor)),
+        }
+    }
+
+    false
+}
+
+// whether the loop or switch the jump targets is within the statement.
+fn is_within_target(jump: Node, statement: Node) -> bool {
+    let mut parent = jump.parent();
+    while let Some(node) = parent {
+        match node.kind() {
+            "for_statement" | "while_statement" | "do_statement" => return true,
+            "switch_statement" if jump.kind() == "break_statement" => return true,
+            _ if node == statement => return false,
+            _ => parent = node.parent(),
+        }
+    }
+    false
+}
+
+/// Collects the identifiers under the node in the order they appear, other than the names of called functions.
+pub fn collect_identifiers<'tree>(node: Node<'tree>, identifiers: &mut Vec<Node<'tree>>) {
+    let is_callee = node.parent().map_or(false, |parent| {
+        parent.kind() == "call_expression" && parent.child_by_field_name("function") == Some(node)
+    });
+    if node.kind() == "identifier" && !is_callee {
+        identifiers.push(node);
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_identifiers(child, identifiers);
+    }
+}
+
+/// Returns whether the identifier is assigned to, directly or through a field or element of it.
+pub fn is_written(identifier: Node) -> bool {
+    let mut node = identifier;
+    while let Some(parent) = node.parent() {
+        match parent.kind() {
+            "field_expression" | "subscript_expression" if parent.child_by_field_name("argument") == Some(node) => node = parent,
+            "assignment_expression" => return parent.child_by_field_name("left") == Some(node),
+            "update_expression" => return true,
+            _ => return false,
+        }
+    }
+    false
+}
+
+// the type of the declared variable, including any array dimensions given after its name as in `float weights[4]`.
+fn parameter_type(ctx: &ParserContext, declaration: Node) -> Option<String> {
+    let mut declarator = declaration;
+    let mut parent = declaration.parent()?;
+    while matches!(parent.kind(), "array_declarator" | "init_declarator") {
+        if parent.kind() == "array_declarator" {
+            declarator = parent;
+        }
+        parent = parent.parent()?;
+    }
+
+    if !matches!(parent.kind(), "declaration" | "parameter_declaration") {
+        return None;
+    }
+
+    let ty = node_text(ctx, parent.child_by_field_name("type")?);
+    let dimensions: String = ctx.source()[declaration.end_byte()..declarator.end_byte()]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    Some(format!("{}{}", ty, dimensions))
+}
+
+// a name for the new function that the file doesn't already define.
+fn function_name(ctx: &ParserContext) -> Result<String> {
+    let defined = ctx.defined_names()?;
+    let name = (1..)
+        .map(|i| {
+            if i == 1 {
+                "extracted".to_string()
+            } else {
+                format!("extracted{}", i)
+            }
+        })
+        .find(|name| !defined.contains(name))
+        .unwrap();
+    Ok(name)
+}
+
+// the whitespace the line of the offset starts with, up to the offset.
+fn indentation(ctx: &ParserContext, offset: usize) -> &str {
+    let line_start = ctx.source()[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &ctx.source()[line_start..offset];
+    &prefix[..prefix.len() - prefix.trim_start().len()]
+}
+
+// reindents the text, of which the first line has already been stripped of its indentation, from the base
+// indentation to that of a function body.
+fn reindent(text: &str, base: &str, unit: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if line.trim().is_empty() {
+                String::new()
+            } else if i == 0 {
+                format!("{}{}", unit, line)
+            } else {
+                format!("{}{}", unit, line.strip_prefix(base).unwrap_or_else(|| line.trim_start()))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn contains(range: &ByteRange<usize>, node: Node) -> bool {
+    range.start <= node.start_byte() && node.end_byte() <= range.end
+}
+
+fn node_text<'a>(ctx: &'a ParserContext, node: Node) -> &'a str {
+    &ctx.source()[node.byte_range()]
+}
+
+#[cfg(test)]
+mod extract_function_test {
+    use std::path::PathBuf;
+
+    use rust_lsp::lsp_types::{Position, Range};
+    use tree_sitter::Parser;
+    use url::Url;
+
+    use crate::extract_function::extract_function;
+    use crate::navigation::ParserContext;
+
+    // the source with the edit extracting the range applied, if there is one.
+    fn extracted(source: &str, range: Range) -> Option<String> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let path = PathBuf::from("/pack/shaders/final.fsh");
+        let edit = extract_function(&ctx, &path, range).unwrap()?;
+
+        let mut edits = edit.changes.unwrap().remove(&Url::from_file_path(&path).unwrap()).unwrap();
+        edits.sort_by_key(|edit| std::cmp::Reverse(ctx.offset_for_position(edit.range.start)));
+
+        let mut result = source.to_string();
+        for edit in edits {
+            let range = ctx.offset_for_position(edit.range.start)..ctx.offset_for_position(edit.range.end);
+            result.replace_range(range, &edit.new_text);
+        }
+        Some(result)
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_extract_function() {
+        let source = r#"float extracted;
+
+void main() {
+    vec3 color = vec3(1.0);
+    float exposure = 2.0;
+    color *= exposure;
+    if (color.r > 1.0) {
+        color = pow(color, vec3(1.0 / 2.2));
+    }
+    gl_FragColor = vec4(color, extracted);
+}
+"#;
+
+        assert_eq!(
+            extracted(source, Range::new(Position::new(5, 0), Position::new(8, 5))).unwrap(),
+            r#"float extracted;
+
+void extracted2(inout vec3 color, float exposure) {
+    color *= exposure;
+    if (color.r > 1.0) {
+        color = pow(color, vec3(1.0 / 2.2));
+    }
+}
+
+void main() {
+    vec3 color = vec3(1.0);
+    float exposure = 2.0;
+    extracted2(color, exposure);
+    gl_FragColor = vec4(color, extracted);
+}
+"#
+        );
+
+        // statements only partially selected
+        assert_eq!(extracted(source, Range::new(Position::new(5, 4), Position::new(5, 10))), None);
+        // declarations used after the selection would have to be returned
+        assert_eq!(extracted(source, Range::new(Position::new(4, 0), Position::new(4, 25))), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_extract_function_jumps() {
+        let source = r#"float weights[4];
+
+float total(int count) {
+    float sum = 0.0;
+    for (int i = 0; i < count; i++) {
+        if (i > 2) break;
+        sum += weights[i];
+    }
+    if (sum > 1.0) {
+        return 1.0;
+    }
+    return sum;
+}
+"#;
+
+        // the loop the break targets is extracted along with it, while the global array isn't a parameter
+        let result = extracted(source, Range::new(Position::new(4, 4), Position::new(7, 5))).unwrap();
+        assert!(result.starts_with("float weights[4];\n\nvoid extracted(int count, inout float sum) {\n    for (int i = 0;"));
+        assert!(result.contains("    float sum = 0.0;\n    extracted(count, sum);\n    if (sum > 1.0) {"));
+
+        // returning from the enclosing function can't be extracted
+        assert_eq!(extracted(source, Range::new(Position::new(8, 4), Position::new(10, 5))), None);
+        assert_eq!(extracted(source, Range::new(Position::new(5, 8), Position::new(5, 25))), None);
+    }
+}
@@ -0,0 +1,80 @@
+// matching the start of a word in the candidate, as the `S` of `voronoiSimplexNoise` or the `n` of `fbm_noise`.
+const WORD_START_BONUS: u32 = 8;
+// matching the character right after the one matched before it, above a word start so that the query typed out
+// as it is written in the candidate ranks first.
+const CONSECUTIVE_BONUS: u32 = 10;
+
+/// Scores how well a query matches a candidate as a case-insensitive subsequence, the way editors' symbol pickers
+/// match, with `vsn` and `noise` alike matching `voronoiSimplexNoise`. Each character of the query scores a point,
+/// plus bonuses for matching at the start of a word or right after the previous match, taking whichever way of
+/// matching the query scores the highest. Returns `None` where the query isn't a subsequence of the candidate.
+pub fn score(query: &str, candidate: &str) -> Option<u32> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let candidate: Vec<char> = candidate.chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let word_start = |i: usize| {
+        i == 0
+            || (!candidate[i - 1].is_alphanumeric() && candidate[i].is_alphanumeric())
+            || (candidate[i - 1].is_lowercase() && candidate[i].is_uppercase())
+            || (!candidate[i - 1].is_ascii_digit() && candidate[i].is_ascii_digit())
+    };
+    let matches = |q: char, i: usize| candidate[i].to_lowercase().eq(std::iter::once(q));
+
+    // the best score of the query so far with its last character matched at each position of the candidate
+    let mut best: Vec<Option<u32>> = (0..candidate.len())
+        .map(|i| matches(query[0], i).then(|| 1 + if word_start(i) { WORD_START_BONUS } else { 0 }))
+        .collect();
+
+    for &q in &query[1..] {
+        let mut next = vec![None; candidate.len()];
+        // the best score of the previous characters matched anywhere before the position
+        let mut before: Option<u32> = None;
+        for (i, score) in next.iter_mut().enumerate() {
+            if matches(q, i) {
+                let bonus = if word_start(i) { WORD_START_BONUS } else { 0 };
+                let consecutive = i.checked_sub(1).and_then(|prev| best[prev]).map(|score| score + CONSECUTIVE_BONUS);
+                *score = before.max(consecutive).map(|score| score + 1 + bonus);
+            }
+            before = before.max(best[i]);
+        }
+        best = next;
+    }
+
+    best.into_iter().flatten().max()
+}
+
+#[cfg(test)]
+mod fuzzy_test {
+    use crate::fuzzy::score;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_fuzzy_score() {
+        assert_eq!(score("", "luma"), Some(0));
+        assert_eq!(score("xyz", "luma"), None);
+        assert_eq!(score("amul", "luma"), None);
+
+        // the start of each word matched ranks far above the same letters matched anywhere
+        let mut candidates = vec![
+            "vignetteStrength",
+            "voronoiSimplexNoise",
+            "viewSpaceNormal",
+            "sampleVoronoi",
+            "vsn_debug",
+            "luma",
+        ];
+        candidates.retain(|candidate| score("vsn", candidate).is_some());
+        candidates.sort_by_key(|candidate| (std::cmp::Reverse(score("vsn", candidate)), *candidate));
+        assert_eq!(
+            candidates,
+            vec!["vsn_debug", "viewSpaceNormal", "voronoiSimplexNoise", "vignetteStrength"]
+        );
+
+        // consecutive matches beat scattered ones, and case doesn't matter
+        assert!(score("noise", "voronoiSimplexNoise") > score("noise", "normalizeIse"));
+        assert_eq!(score("LUMA", "luma"), score("luma", "luma"));
+    }
+}
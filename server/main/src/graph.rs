@@ -5,12 +5,33 @@ use petgraph::visit::EdgeRef;
 use petgraph::Direction;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Debug, Display, Formatter},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use super::IncludePosition;
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IncludePosition {
+    // the 0-indexed line on which the include lives.
+    pub line: usize,
+    // the 0-indexed char offset defining the start of the include path string.
+    pub start: usize,
+    // the 0-indexed char offset defining the end of the include path string.
+    pub end: usize,
+}
+
+impl Debug for IncludePosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{line: {}}}", self.line)
+    }
+}
+
+impl Display for IncludePosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{{line: {}}}", self.line)
+    }
+}
 
 /// Wraps a `StableDiGraph` with caching behaviour for node search by maintaining
 /// an index for node value to node index and a reverse index.
@@ -23,6 +44,8 @@ pub struct CachedStableGraph {
     // Maps a node index to its abstracted string representation.
     // Mainly used as the graph is based on NodeIndex.
     reverse_index: HashMap<NodeIndex, PathBuf>,
+    // the files starting with `#pragma once`, which are only inlined the first time they're included.
+    pragma_once: HashSet<NodeIndex>,
 }
 
 impl CachedStableGraph {
@@ -32,6 +55,7 @@ impl CachedStableGraph {
             graph: StableDiGraph::new(),
             cache: HashMap::new(),
             reverse_index: HashMap::new(),
+            pragma_once: HashSet::new(),
         }
     }
 
@@ -103,6 +127,30 @@ impl CachedStableGraph {
         self.graph.add_edge(parent, child, meta)
     }
 
+    /// Removes the node for a file along with all the include edges to and from it.
+    pub fn remove_node(&mut self, name: &Path) {
+        let idx = self.cache.remove(name);
+        if let Some(idx) = idx {
+            self.graph.remove_node(idx);
+            self.reverse_index.remove(&idx);
+            self.pragma_once.remove(&idx);
+        }
+    }
+
+    pub fn set_pragma_once(&mut self, node: NodeIndex, pragma_once: bool) {
+        if pragma_once {
+            self.pragma_once.insert(node);
+        } else {
+            self.pragma_once.remove(&node);
+        }
+    }
+
+    /// Whether the file of the node starts with `#pragma once`, and so is only inlined the first time it's included
+    /// into a tree.
+    pub fn is_pragma_once(&self, node: NodeIndex) -> bool {
+        self.pragma_once.contains(&node)
+    }
+
     pub fn remove_edge(&mut self, parent: NodeIndex, child: NodeIndex, position: IncludePosition) {
         self.graph
             .edges(parent)
@@ -115,13 +163,116 @@ impl CachedStableGraph {
         self.graph.neighbors(node)
     }
 
+    /// Returns the node and all the nodes it includes, directly or transitively, paired with their include distance
+    /// from it: 0 for the node itself, 1 for its direct includes and so on. Each node appears once, at the shortest
+    /// distance it is reachable by.
+    pub fn include_distances(&self, node: NodeIndex) -> Vec<(NodeIndex, usize)> {
+        let mut visited = HashSet::from([node]);
+        let mut queue = VecDeque::from([(node, 0)]);
+        let mut distances = vec![];
+
+        while let Some((current, distance)) = queue.pop_front() {
+            distances.push((current, distance));
+            for child in self.child_node_indexes(current) {
+                if visited.insert(child) {
+                    queue.push_back((child, distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Returns the node and all the nodes connected to it through includes in either direction, paired with their
+    /// distance from it as with `include_distances`. The files including the node are as near as the files it includes.
+    pub fn proximity_distances(&self, node: NodeIndex) -> Vec<(NodeIndex, usize)> {
+        let mut visited = HashSet::from([node]);
+        let mut queue = VecDeque::from([(node, 0)]);
+        let mut distances = vec![];
+
+        while let Some((current, distance)) = queue.pop_front() {
+            distances.push((current, distance));
+            for neighbour in self.graph.neighbors_undirected(current) {
+                if visited.insert(neighbour) {
+                    queue.push_back((neighbour, distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Returns the node and all the nodes including it, directly or transitively, each once. Includes cycling back to
+    /// a node already found aren't followed again, so cycles end the search rather than going round them.
+    pub fn dependents(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        let mut visited = HashSet::from([node]);
+        let mut queue = VecDeque::from([node]);
+        let mut dependents = vec![];
+
+        while let Some(current) = queue.pop_front() {
+            dependents.push(current);
+            for parent in self.parent_node_indexes(current) {
+                if visited.insert(parent) {
+                    queue.push_back(parent);
+                }
+            }
+        }
+
+        dependents
+    }
+
+    /// Returns the shortest chain of includes leading from one node to another, as the includes in turn with the node
+    /// containing each and the node it includes. Of chains equally short, the one through the earliest includes of
+    /// each file is taken. The chain is empty from a node to itself, and there's none if the other node isn't
+    /// included from it at all.
+    pub fn include_chain(&self, from: NodeIndex, to: NodeIndex) -> Option<Vec<(NodeIndex, NodeIndex, IncludePosition)>> {
+        let mut included_by: HashMap<NodeIndex, (NodeIndex, IncludePosition)> = HashMap::new();
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut chain = vec![];
+                let mut node = to;
+                while let Some((parent, position)) = included_by.get(&node) {
+                    chain.push((*parent, node, *position));
+                    node = *parent;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+
+            for (child, position) in self.get_all_child_positions(current) {
+                if visited.insert(child) {
+                    included_by.insert(child, (current, position));
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the includes of the node leading back to it, each paired with the shortest cycle it starts as the nodes
+    /// in turn, beginning and ending with the node. A node including itself is a cycle of the node alone.
+    pub fn include_cycles(&self, node: NodeIndex) -> Vec<(IncludePosition, Vec<NodeIndex>)> {
+        self.get_all_child_positions(node)
+            .filter_map(|(child, position)| {
+                let chain = self.include_chain(child, node)?;
+                let mut cycle = vec![node, child];
+                cycle.extend(chain.into_iter().map(|(_, included, _)| included));
+                Some((position, cycle))
+            })
+            .collect()
+    }
+
     pub fn collect_root_ancestors(&self, node: NodeIndex) -> Vec<NodeIndex> {
         let mut visited = HashSet::new();
         self.get_root_ancestors(node, node, &mut visited)
     }
 
     // TODO: impl Iterator
-    fn parent_node_indexes(&self, node: NodeIndex) -> Vec<NodeIndex> {
+    pub fn parent_node_indexes(&self, node: NodeIndex) -> Vec<NodeIndex> {
         self.graph.neighbors_directed(node, Direction::Incoming).collect()
     }
 
@@ -165,13 +316,6 @@ impl CachedStableGraph {
             .map(|n| self.reverse_index.get(&n).unwrap().clone())
             .collect()
     }
-
-    fn remove_node(&mut self, name: &Path) {
-        let idx = self.cache.remove(name);
-        if let Some(idx) = idx {
-            self.graph.remove_node(idx);
-        }
-    }
 }
 
 #[cfg(test)]
@@ -371,4 +515,133 @@ mod graph_test {
             assert_eq!(roots, vec![]);
         }
     }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_include_distances() {
+        let mut graph = CachedStableGraph::new();
+
+        let idx0 = graph.add_node(&PathBuf::from("0"));
+        let idx1 = graph.add_node(&PathBuf::from("1"));
+        let idx2 = graph.add_node(&PathBuf::from("2"));
+        let idx3 = graph.add_node(&PathBuf::from("3"));
+
+        // 0 includes 1 and 2, 1 includes 3 which also includes 2
+        graph.add_edge(idx0, idx1, IncludePosition { line: 2, start: 0, end: 0 });
+        graph.add_edge(idx0, idx2, IncludePosition { line: 3, start: 0, end: 0 });
+        graph.add_edge(idx1, idx3, IncludePosition { line: 1, start: 0, end: 0 });
+        graph.add_edge(idx3, idx2, IncludePosition { line: 1, start: 0, end: 0 });
+
+        let mut distances = graph.include_distances(idx0);
+        distances.sort();
+
+        assert_eq!(distances, vec![(idx0, 0), (idx1, 1), (idx2, 1), (idx3, 2)]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_proximity_distances() {
+        let mut graph = CachedStableGraph::new();
+
+        let idx0 = graph.add_node(&PathBuf::from("0"));
+        let idx1 = graph.add_node(&PathBuf::from("1"));
+        let idx2 = graph.add_node(&PathBuf::from("2"));
+        let idx3 = graph.add_node(&PathBuf::from("3"));
+        let idx4 = graph.add_node(&PathBuf::from("4"));
+
+        // 0 includes 1 which includes 3, also included by 2. 4 is unrelated
+        graph.add_edge(idx0, idx1, IncludePosition { line: 2, start: 0, end: 0 });
+        graph.add_edge(idx1, idx3, IncludePosition { line: 1, start: 0, end: 0 });
+        graph.add_edge(idx2, idx3, IncludePosition { line: 1, start: 0, end: 0 });
+
+        let mut distances = graph.proximity_distances(idx3);
+        distances.sort();
+
+        assert_eq!(distances, vec![(idx0, 2), (idx1, 1), (idx2, 1), (idx3, 0)]);
+        assert!(!distances.iter().any(|(node, _)| *node == idx4));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_include_chain() {
+        let mut graph = CachedStableGraph::new();
+
+        let idx0 = graph.add_node(&PathBuf::from("0"));
+        let idx1 = graph.add_node(&PathBuf::from("1"));
+        let idx2 = graph.add_node(&PathBuf::from("2"));
+        let idx3 = graph.add_node(&PathBuf::from("3"));
+        let idx4 = graph.add_node(&PathBuf::from("4"));
+
+        let position = |line| IncludePosition { line, start: 10, end: 15 };
+
+        // 0 reaches 3 through either 1 or 2, including 2 on an earlier line, and 3 includes 4
+        graph.add_edge(idx0, idx1, position(4));
+        graph.add_edge(idx0, idx2, position(2));
+        graph.add_edge(idx1, idx3, position(1));
+        graph.add_edge(idx2, idx3, position(3));
+        graph.add_edge(idx3, idx4, position(0));
+
+        assert_eq!(
+            graph.include_chain(idx0, idx4),
+            Some(vec![
+                (idx0, idx2, position(2)),
+                (idx2, idx3, position(3)),
+                (idx3, idx4, position(0)),
+            ])
+        );
+        assert_eq!(graph.include_chain(idx1, idx1), Some(vec![]));
+        assert_eq!(graph.include_chain(idx4, idx0), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_dependents() {
+        let mut graph = CachedStableGraph::new();
+
+        let idx0 = graph.add_node(&PathBuf::from("0"));
+        let idx1 = graph.add_node(&PathBuf::from("1"));
+        let idx2 = graph.add_node(&PathBuf::from("2"));
+        let idx3 = graph.add_node(&PathBuf::from("3"));
+        let idx4 = graph.add_node(&PathBuf::from("4"));
+
+        let position = |line| IncludePosition { line, start: 10, end: 15 };
+
+        // 0 and 4 both include 1, which is in a cycle with 2, and 3 includes nothing
+        graph.add_edge(idx0, idx1, position(0));
+        graph.add_edge(idx4, idx1, position(0));
+        graph.add_edge(idx1, idx2, position(1));
+        graph.add_edge(idx2, idx1, position(1));
+
+        let mut dependents = graph.dependents(idx2);
+        dependents.sort();
+        assert_eq!(dependents, vec![idx0, idx1, idx2, idx4]);
+
+        assert_eq!(graph.dependents(idx3), vec![idx3]);
+        assert_eq!(graph.dependents(idx0), vec![idx0]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_include_cycles() {
+        let mut graph = CachedStableGraph::new();
+
+        let idx0 = graph.add_node(&PathBuf::from("0"));
+        let idx1 = graph.add_node(&PathBuf::from("1"));
+        let idx2 = graph.add_node(&PathBuf::from("2"));
+        let idx3 = graph.add_node(&PathBuf::from("3"));
+
+        let position = |line| IncludePosition { line, start: 10, end: 15 };
+
+        // 1 and 2 include each other, 0 only leads into the cycle, and 3 includes itself
+        graph.add_edge(idx0, idx1, position(0));
+        graph.add_edge(idx1, idx2, position(1));
+        graph.add_edge(idx2, idx1, position(2));
+        graph.add_edge(idx2, idx3, position(3));
+        graph.add_edge(idx3, idx3, position(4));
+
+        assert_eq!(graph.include_cycles(idx0), vec![]);
+        assert_eq!(graph.include_cycles(idx1), vec![(position(1), vec![idx1, idx2, idx1])]);
+        assert_eq!(graph.include_cycles(idx2), vec![(position(2), vec![idx2, idx1, idx2])]);
+        assert_eq!(graph.include_cycles(idx3), vec![(position(4), vec![idx3, idx3])]);
+    }
 }
@@ -0,0 +1,889 @@
+use std::path::Path;
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+use slog_scope::debug;
+use tree_sitter::Node;
+
+use crate::{
+    builtins::{self, ShaderStage},
+    const_eval,
+    extract_function::enclosing_function,
+    interface, layout,
+    navigation::{NodeSelection, ParserContext},
+    overloads, preprocessor,
+};
+
+/// Builds the hover contents for the identifier under the cursor, showing the declaration it refers to
+/// along with any `layout(...)` qualifiers applied to it.
+pub fn hover(ctx: &ParserContext, path: &Path, point: Position) -> Result<Option<Hover>> {
+    let expression = match ctx.select_node_at_point(point, NodeSelection::Expression) {
+        Some(expression) => expression,
+        None => return Ok(None),
+    };
+    let node = match ctx.find_node_at_point(point) {
+        Some(node) if node.kind() == "field_identifier" => return Ok(builtin_field_hover(ctx, node, expression)),
+        Some(node) if node.kind() == "identifier" || node.kind() == "type_identifier" => node,
+        _ => return Ok(None),
+    };
+    let name = node.utf8_text(ctx.source().as_bytes())?;
+
+    // names starting with `gl_` are reserved, so built-ins can't be shadowed by the user's declarations
+    let stages = builtins::variable_stages(name, ShaderStage::from_path(path));
+    if let Some((_, variable)) = stages.first() {
+        let names = stages.iter().map(|(stage, _)| stage.to_string()).collect::<Vec<_>>().join(", ");
+        let plural = if stages.len() > 1 { "s" } else { "" };
+        return Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!(
+                    "```glsl\n{}\n```\n\n---\n\nbuilt-in of the {} stage{}",
+                    variable.declaration, names, plural
+                ),
+            }),
+            range: Some(ctx.range_for_node(&node)),
+        }));
+    }
+
+    if let Some(builtin) = builtins::builtin_struct(name) {
+        return Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```glsl\n{}\n```", builtin.declaration),
+            }),
+            range: Some(ctx.range_for_node(&node)),
+        }));
+    }
+
+    // macros are shown in full, including any lines their body is continued onto
+    if let Some(define) = preprocessor::find_define(ctx, name) {
+        let mut value = format!("```glsl\n{}\n```", define.utf8_text(ctx.source().as_bytes())?.trim());
+
+        // aliases such as `#define Color vec3` also show the type they end up resolving to
+        let aliases = preprocessor::resolve_alias(ctx, name);
+        if let Some(resolved) = aliases.last().and_then(|alias| preprocessor::alias_target(ctx, *alias)) {
+            value += format!("\n\n---\n\nresolves to `{}`", resolved).as_str();
+        }
+
+        // constants derived from others, as in `#define HALF (STEPS / 2)`, also show their value
+        if let Some(body) = define.child_by_field_name("value").filter(|_| define.kind() == "preproc_def") {
+            value += evaluated_value(ctx, body.utf8_text(ctx.source().as_bytes())?).as_str();
+        }
+
+        // the name is left as it is where the macro has been undefined, which is easily missed
+        if let Some(undef) = preprocessor::find_undef(ctx, name, node.start_byte()) {
+            value += format!(
+                "\n\n---\n\nthis macro is `#undef`'d at line {} and not in effect here",
+                undef.start_position().row + 1
+            )
+            .as_str();
+        }
+
+        return Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(ctx.range_for_node(&node)),
+        }));
+    }
+
+    // other type names are only handled when they are aliases
+    if node.kind() == "type_identifier" {
+        return Ok(None);
+    }
+
+    let declaration = match find_declaration(ctx, path, point, node)? {
+        Some(declaration) => declaration,
+        None => return Ok(builtin_function_hover(ctx, path, node, name)),
+    };
+
+    debug!("found declaration for hover"; "kind" => declaration.kind(), "text" => declaration.utf8_text(ctx.source().as_bytes()).unwrap());
+
+    let mut value = format!("```glsl\n{}\n```", declaration.utf8_text(ctx.source().as_bytes())?.trim());
+
+    if let Some(initializer) = const_eval::initializer(ctx, declaration, name) {
+        value += evaluated_value(ctx, initializer).as_str();
+    }
+
+    if let Some(layout_node) = layout::find_layout_node(declaration) {
+        let qualifiers = layout::parse_layout_qualifiers(layout_node, ctx.source());
+        if !qualifiers.is_empty() {
+            value += "\n\n---\n\n| layout | value |\n| --- | --- |\n";
+            for qualifier in qualifiers {
+                value += format!(
+                    "| `{}` | {} |\n",
+                    qualifier.key,
+                    qualifier.value.map_or("".into(), |v| format!("`{}`", v))
+                )
+                .as_str();
+            }
+        }
+
+        // a location taken by another variable of the file fails to compile, see `interface::location_conflicts`
+        let variables = interface::interface_variables(ctx);
+        let range = ctx.range_for_node(&declaration);
+        let hovered = variables
+            .iter()
+            .find(|var| var.name == name && range.start <= var.range.start && var.range.end <= range.end);
+        if let Some(var) = hovered {
+            let conflicts = interface::location_conflicts(&variables, var);
+            if !conflicts.is_empty() {
+                value += format!("\n\n---\n\n**warning**: {}", interface::location_conflict_message(var, &conflicts)).as_str();
+            }
+        }
+    }
+
+    // the name of an array or vector being subscripted, as the `weights` of `weights[i]`
+    if expression.kind() == "subscript_expression" {
+        value += subscript_section(ctx, declaration, name, expression).as_str();
+    }
+
+    Ok(Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(ctx.range_for_node(&node)),
+    }))
+}
+
+/// Starts the hover with a line orienting the cursor in the file, giving the stage of the shader going by its
+/// extension and whether the cursor is in the `main` entry point. Positions without a hover of their own are given
+/// the line alone.
+pub fn with_context(ctx: &ParserContext, path: &Path, point: Position, hover: Option<Hover>) -> Hover {
+    let stage = match ShaderStage::from_path(path) {
+        Some(stage) => format!("{} shader", stage),
+        None => "included file".to_string(),
+    };
+
+    let at = ctx.point_for_position(point);
+    let function = ctx
+        .root_node()
+        .descendant_for_point_range(at, at)
+        .and_then(|node| match node.kind() {
+            "function_definition" => Some(node),
+            _ => enclosing_function(node),
+        });
+    let name = function
+        .and_then(|function| function.child_by_field_name("declarator"))
+        .and_then(|declarator| declarator.child_by_field_name("declarator"))
+        .map(|name| &ctx.source()[name.byte_range()]);
+
+    let place = match name {
+        Some("main") => "in the `main` entry point".to_string(),
+        Some(name) => format!("in `{}`, outside of `main`", name),
+        None => "outside of any function".to_string(),
+    };
+    let header = format!("*{}, {}*", stage, place);
+
+    match hover {
+        Some(Hover {
+            contents: HoverContents::Markup(markup),
+            range,
+        }) => Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: markup.kind,
+                value: format!("{}\n\n---\n\n{}", header, markup.value),
+            }),
+            range,
+        },
+        Some(hover) => hover,
+        None => Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: header,
+            }),
+            range: None,
+        },
+    }
+}
+
+// The hover documenting a field of a built-in struct variable, as the `near` of `gl_DepthRange.near`. Elements of
+// built-in arrays such as `gl_LightSource[0]` are documented the same. Fields of the user's structs have no hover.
+fn builtin_field_hover(ctx: &ParserContext, field: Node, expression: Node) -> Option<Hover> {
+    if expression.kind() != "field_expression" {
+        return None;
+    }
+
+    let mut base = expression.child_by_field_name("argument")?;
+    while base.kind() == "subscript_expression" {
+        base = base.child_by_field_name("argument")?;
+    }
+    if base.kind() != "identifier" {
+        return None;
+    }
+
+    let builtin = builtins::builtin_struct(base.utf8_text(ctx.source().as_bytes()).ok()?)?;
+    let name = field.utf8_text(ctx.source().as_bytes()).ok()?;
+    let member = builtin.fields.iter().find(|member| member.name == name)?;
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```glsl\n{} {}.{}\n```\n\n{}", member.ty, builtin.ty, member.name, member.doc),
+        }),
+        range: Some(ctx.range_for_node(&field)),
+    })
+}
+
+// The hover of a built-in function, giving the overload called going by the types of the arguments, with the
+// others listed after it. Where the argument types don't decide on one, all the overloads are listed alike.
+fn builtin_function_hover(ctx: &ParserContext, path: &Path, node: Node, name: &str) -> Option<Hover> {
+    let overloads = overloads::overloads(name);
+    if overloads.is_empty() {
+        return None;
+    }
+
+    let best = node
+        .parent()
+        .filter(|call| call.kind() == "call_expression" && call.child_by_field_name("function") == Some(node))
+        .and_then(|call| call.child_by_field_name("arguments"))
+        .and_then(|arguments| {
+            let mut cursor = arguments.walk();
+            let types: Vec<Option<String>> = arguments
+                .named_children(&mut cursor)
+                .filter(|argument| argument.kind() != "comment")
+                .map(|argument| expression_type(ctx, path, argument))
+                .collect();
+            overloads::best_fit(&overloads, &types)
+        });
+
+    let value = match best {
+        Some((index, signature)) => {
+            let others: Vec<String> = overloads
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != index)
+                .map(|(_, overload)| overload.to_string())
+                .collect();
+            match others.len() {
+                0 => format!("```glsl\n{}\n```", signature),
+                n => format!(
+                    "```glsl\n{}\n```\n\n---\n\n{} other overload{}\n\n```glsl\n{}\n```",
+                    signature,
+                    n,
+                    if n > 1 { "s" } else { "" },
+                    others.join("\n")
+                ),
+            }
+        }
+        None => {
+            let all: Vec<String> = overloads.iter().map(|overload| overload.to_string()).collect();
+            format!("```glsl\n{}\n```", all.join("\n"))
+        }
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(ctx.range_for_node(&node)),
+    })
+}
+
+// Infers the type of an expression as far as it's easily told from the syntax tree, from literals, constructors,
+// variables, swizzles and built-in calls, along with the arithmetic combining them.
+fn expression_type(ctx: &ParserContext, path: &Path, node: Node) -> Option<String> {
+    let text = node.utf8_text(ctx.source().as_bytes()).ok()?;
+
+    match node.kind() {
+        "number_literal" => {
+            let literal = text.to_lowercase();
+            if literal.ends_with('u') {
+                Some("uint".into())
+            } else if !literal.starts_with("0x") && (literal.contains(|c| c == '.' || c == 'e') || literal.ends_with('f')) {
+                Some("float".into())
+            } else {
+                Some("int".into())
+            }
+        }
+        "true" | "false" => Some("bool".into()),
+        "identifier" => {
+            let definition = ctx.find_definitions(path, ctx.range_for_node(&node).start).ok()??;
+            let identifier = ctx.find_node_at_point(definition.first()?.range.start)?;
+            ctx.declared_type(identifier)
+        }
+        "parenthesized_expression" => expression_type(ctx, path, node.named_child(0)?),
+        "unary_expression" => match node.child_by_field_name("operator")?.kind() {
+            "!" => Some("bool".into()),
+            _ => expression_type(ctx, path, node.child_by_field_name("argument")?),
+        },
+        "conditional_expression" => expression_type(ctx, path, node.child_by_field_name("consequence")?),
+        "field_expression" => {
+            // swizzles, as in `color.rgb`
+            let (scalar, size) = overloads::components(&expression_type(ctx, path, node.child_by_field_name("argument")?)?)?;
+            let swizzle = node.child_by_field_name("field")?.utf8_text(ctx.source().as_bytes()).ok()?;
+            let in_set = |set: &str| swizzle.chars().all(|c| set.contains(c));
+            if size == 1 || !(in_set("xyzw") || in_set("rgba") || in_set("stpq")) {
+                return None;
+            }
+            overloads::vector_type(scalar, swizzle.len())
+        }
+        "subscript_expression" => {
+            let (scalar, size) = overloads::components(&expression_type(ctx, path, node.child_by_field_name("argument")?)?)?;
+            (size > 1).then(|| scalar.to_string())
+        }
+        "binary_expression" => {
+            let operator = node.child_by_field_name("operator")?.kind();
+            if matches!(operator, "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||" | "^^") {
+                return Some("bool".into());
+            }
+
+            let left = expression_type(ctx, path, node.child_by_field_name("left")?)?;
+            let right = expression_type(ctx, path, node.child_by_field_name("right")?)?;
+            // a scalar combined with a vector applies to each of its components
+            match (overloads::components(&left)?, overloads::components(&right)?) {
+                _ if left == right => Some(left),
+                ((_, 1), (_, _)) => Some(right),
+                ((_, _), (_, 1)) => Some(left),
+                _ => None,
+            }
+        }
+        "call_expression" => {
+            let function = node.child_by_field_name("function")?;
+            let name = function.utf8_text(ctx.source().as_bytes()).ok()?;
+
+            // constructors are named after the type they construct
+            if overloads::components(name).is_some() || name.starts_with("mat") {
+                return Some(name.into());
+            }
+
+            let arguments = node.child_by_field_name("arguments")?;
+            let mut cursor = arguments.walk();
+            let types: Vec<Option<String>> = arguments
+                .named_children(&mut cursor)
+                .filter(|argument| argument.kind() != "comment")
+                .map(|argument| expression_type(ctx, path, argument))
+                .collect();
+            let (_, signature) = overloads::best_fit(&overloads::overloads(name), &types)?;
+            let ret = signature.split_once(' ')?.0;
+            overloads::components(ret).map(|_| ret.to_string())
+        }
+        _ => None,
+    }
+}
+
+// The section describing the element of an array or vector subscripted by name, giving its type and, where the
+// number of elements is known, the indices in bounds along with whether a constant index is out of them. Arrays
+// of arrays are subscripted by their first dimension, leaving an array of the rest.
+fn subscript_section(ctx: &ParserContext, declaration: Node, name: &str, subscript: Node) -> String {
+    let ty = match declaration.child_by_field_name("type") {
+        Some(ty) => &ctx.source()[ty.byte_range()],
+        None => return String::new(),
+    };
+
+    let (element, length) = match array_sizes(ctx, declaration, name) {
+        Some(sizes) => {
+            let rest: String = sizes[1..]
+                .iter()
+                .map(|size| format!("[{}]", size.map_or("", |size| &ctx.source()[size.byte_range()])))
+                .collect();
+            let length = sizes[0].and_then(|size| const_eval::evaluate(ctx, &ctx.source()[size.byte_range()]));
+            (format!("{}{}", ty, rest), length)
+        }
+        None => match overloads::components(ty) {
+            Some((scalar, size)) if size > 1 => (scalar.to_string(), Some(const_eval::Constant::Int(size as i64))),
+            _ => return String::new(),
+        },
+    };
+
+    let length = match length {
+        Some(const_eval::Constant::Int(length)) if length > 0 => length,
+        _ => return format!("\n\n---\n\nelement of type `{}`", element),
+    };
+    let mut section = format!("\n\n---\n\nelement of type `{}`, indices `0` to `{}`", element, length - 1);
+
+    let index = subscript
+        .child_by_field_name("index")
+        .and_then(|index| const_eval::evaluate(ctx, &ctx.source()[index.byte_range()]));
+    if let Some(const_eval::Constant::Int(index)) = index {
+        if !(0..length).contains(&index) {
+            section += format!("\n\nindex `{}` is out of bounds", index).as_str();
+        }
+    }
+
+    section
+}
+
+// the size expressions of each dimension of the array the declaration declares by the name, in the order they're
+// subscripted in, each `None` where the size is left to the initializer as in `float weights[] = ...`. Returns
+// `None` where the name isn't declared as an array.
+fn array_sizes<'tree>(ctx: &ParserContext, declaration: Node<'tree>, name: &str) -> Option<Vec<Option<Node<'tree>>>> {
+    let mut cursor = declaration.walk();
+    let declarators: Vec<Node> = declaration.children_by_field_name("declarator", &mut cursor).collect();
+
+    declarators.into_iter().find_map(|declarator| {
+        // the outermost array declarator is the last dimension, as in `float grid[2][3]`
+        let mut sizes = vec![];
+        let mut current = declarator;
+        loop {
+            match current.kind() {
+                "init_declarator" => current = current.child_by_field_name("declarator")?,
+                "array_declarator" => {
+                    sizes.push(current.child_by_field_name("size"));
+                    current = current.child_by_field_name("declarator")?;
+                }
+                _ => break,
+            }
+        }
+        sizes.reverse();
+
+        (&ctx.source()[current.byte_range()] == name && !sizes.is_empty()).then_some(sizes)
+    })
+}
+
+// the section showing the value of a constant expression, if it can be evaluated and isn't a plain literal already.
+fn evaluated_value(ctx: &ParserContext, expression: &str) -> String {
+    if const_eval::is_literal(expression) {
+        return String::new();
+    }
+
+    match const_eval::evaluate(ctx, expression) {
+        Some(constant) => format!("\n\n---\n\nevaluates to `{}`", constant),
+        None => String::new(),
+    }
+}
+
+// Returns the declaration statement for an identifier, either the one it is being declared in or the one
+// found by resolving its definition.
+fn find_declaration<'tree>(ctx: &'tree ParserContext, path: &Path, point: Position, node: Node<'tree>) -> Result<Option<Node<'tree>>> {
+    if let Some(declaration) = enclosing_declaration(node) {
+        if is_declared_name(declaration, node) {
+            return Ok(Some(declaration));
+        }
+    }
+
+    let definition = match ctx.find_definitions(path, point)? {
+        Some(locations) if !locations.is_empty() => locations[0].range,
+        _ => return Ok(None),
+    };
+
+    let start = ctx.point_for_position(definition.start);
+    let end = ctx.point_for_position(definition.end);
+
+    Ok(ctx
+        .root_node()
+        .named_descendant_for_point_range(start, end)
+        .and_then(enclosing_declaration))
+}
+
+fn enclosing_declaration(node: Node) -> Option<Node> {
+    let mut node = node.parent();
+    while let Some(n) = node {
+        match n.kind() {
+            "declaration" | "parameter_declaration" | "field_declaration" => return Some(n),
+            "compound_statement" | "function_definition" | "translation_unit" => return None,
+            _ => node = n.parent(),
+        }
+    }
+    None
+}
+
+fn is_declared_name(declaration: Node, node: Node) -> bool {
+    let mut cursor = declaration.walk();
+    let declarators: Vec<Node> = declaration.children_by_field_name("declarator", &mut cursor).collect();
+
+    declarators.into_iter().any(|declarator| {
+        let mut decl = Some(declarator);
+        while let Some(d) = decl {
+            if d == node {
+                return true;
+            }
+            decl = d.child_by_field_name("declarator");
+        }
+        false
+    })
+}
+
+#[cfg(test)]
+mod hover_test {
+    use std::env::temp_dir;
+
+    use rust_lsp::lsp_types::{HoverContents, Position, Range};
+    use tree_sitter::Parser;
+
+    use crate::hover;
+    use crate::navigation::ParserContext;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_multiline_macro() {
+        let source = r#"#define BLUR(x) \
+    (x * 0.5 + \
+     x * 0.25)
+void main() {
+    float y = BLUR(1.0);
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let hover = hover::hover(&ctx, &temp_dir().join("test.fsh"), Position { line: 4, character: 16 })
+            .unwrap()
+            .unwrap();
+
+        match hover.contents {
+            HoverContents::Markup(markup) => {
+                assert_eq!(markup.value, "```glsl\n#define BLUR(x) \\\n    (x * 0.5 + \\\n     x * 0.25)\n```")
+            }
+            _ => panic!("expected markup hover contents"),
+        }
+
+        // the lines after the macro are not shifted by its continuations
+        assert_eq!(
+            hover.range,
+            Some(Range {
+                start: Position { line: 4, character: 14 },
+                end: Position { line: 4, character: 18 },
+            })
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_type_alias() {
+        let source = r#"#define Color vec3
+#define Albedo Color
+
+void main() {
+    Albedo a = vec3(1.0);
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let hover = hover::hover(&ctx, &temp_dir().join("test.fsh"), Position { line: 4, character: 4 })
+            .unwrap()
+            .unwrap();
+
+        match hover.contents {
+            HoverContents::Markup(markup) => {
+                assert_eq!(markup.value, "```glsl\n#define Albedo Color\n```\n\n---\n\nresolves to `vec3`")
+            }
+            _ => panic!("expected markup hover contents"),
+        }
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_constant_value() {
+        let source = r#"#define STEPS 8
+#define HALF_STEPS (STEPS / 2)
+const float PI = 3.14159;
+const float TAU = 2.0 * PI;
+
+void main() {
+    float angle = TAU * float(HALF_STEPS);
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let markup_at = |line, character| match hover::hover(&ctx, &path, Position { line, character }).unwrap().unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("expected markup hover contents"),
+        };
+
+        assert_eq!(
+            markup_at(6, 19),
+            "```glsl\nconst float TAU = 2.0 * PI;\n```\n\n---\n\nevaluates to `6.28318`"
+        );
+        assert_eq!(
+            markup_at(6, 32),
+            "```glsl\n#define HALF_STEPS (STEPS / 2)\n```\n\n---\n\nevaluates to `4`"
+        );
+        // literals aren't repeated
+        assert_eq!(markup_at(2, 12), "```glsl\nconst float PI = 3.14159;\n```");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_location_conflict() {
+        let source = r#"layout(location = 0) out vec4 color;
+layout(location = 0) out vec4 bloom;
+layout(location = 1) out vec4 normal;
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let markup_at = |line, character| match hover::hover(&ctx, &path, Position { line, character }).unwrap().unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("expected markup hover contents"),
+        };
+
+        assert_eq!(
+            markup_at(0, 32),
+            "```glsl\nlayout(location = 0) out vec4 color;\n```\n\n---\n\n| layout | value |\n| --- | --- |\n| `location` | `0` |\n\n\n---\n\n**warning**: `color` shares output location 0 with `bloom`"
+        );
+        assert!(!markup_at(2, 32).contains("warning"));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_subscript() {
+        let source = r#"#define COUNT 4
+uniform float weights[COUNT];
+uniform vec3 grid[2][3];
+
+void main() {
+    vec3 color = vec3(0.0);
+    float total = weights[1] + weights[COUNT] + grid[1][2].x + color[1];
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let markup_at = |line, character| match hover::hover(&ctx, &path, Position { line, character }).unwrap().unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("expected markup hover contents"),
+        };
+
+        // arrays sized by a constant expression, with constant indices checked against it
+        assert_eq!(
+            markup_at(6, 20),
+            "```glsl\nuniform float weights[COUNT];\n```\n\n---\n\nelement of type `float`, indices `0` to `3`"
+        );
+        assert_eq!(
+            markup_at(6, 33),
+            "```glsl\nuniform float weights[COUNT];\n```\n\n---\n\nelement of type `float`, indices `0` to `3`\n\nindex `4` is out of bounds"
+        );
+        // arrays of arrays by their first dimension
+        assert_eq!(
+            markup_at(6, 49),
+            "```glsl\nuniform vec3 grid[2][3];\n```\n\n---\n\nelement of type `vec3[3]`, indices `0` to `1`"
+        );
+        // and vectors by their components
+        assert_eq!(
+            markup_at(6, 64),
+            "```glsl\nvec3 color = vec3(0.0);\n```\n\n---\n\nelement of type `float`, indices `0` to `2`"
+        );
+        // names that aren't subscripted are left as they are
+        assert_eq!(markup_at(5, 9), "```glsl\nvec3 color = vec3(0.0);\n```");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_builtin_fields() {
+        let source = r#"struct Fog { float near; };
+uniform Fog fog;
+
+void main() {
+    float depth = gl_DepthRange.near + fog.near;
+    vec4 light = gl_LightSource[0].position + gl_Fog.unknown;
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let markup_at = |line, character| {
+            hover::hover(&ctx, &path, Position { line, character })
+                .unwrap()
+                .map(|hover| match hover.contents {
+                    HoverContents::Markup(markup) => markup.value,
+                    _ => panic!("expected markup hover contents"),
+                })
+        };
+
+        assert_eq!(
+            markup_at(4, 33).unwrap(),
+            "```glsl\nfloat gl_DepthRangeParameters.near\n```\n\nThe near value set by `glDepthRange`, to which the near \
+             clipping plane maps in window coordinates."
+        );
+        assert!(markup_at(5, 37)
+            .unwrap()
+            .starts_with("```glsl\nvec4 gl_LightSourceParameters.position\n```"));
+        assert_eq!(
+            markup_at(4, 20).unwrap(),
+            "```glsl\nuniform gl_DepthRangeParameters gl_DepthRange;\n```"
+        );
+
+        // fields of the user's structs, and fields built-ins don't have
+        assert_eq!(markup_at(4, 45), None);
+        assert_eq!(markup_at(5, 55), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_stage_builtins() {
+        let source = r#"void main() {
+    gl_FragColor = vec4(gl_FragCoord.xy, 0.0, 1.0) * gl_Color;
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let markup_at = |file: &str, line, character| {
+            hover::hover(&ctx, &temp_dir().join(file), Position { line, character })
+                .unwrap()
+                .map(|hover| match hover.contents {
+                    HoverContents::Markup(markup) => markup.value,
+                    _ => panic!("expected markup hover contents"),
+                })
+        };
+
+        assert_eq!(
+            markup_at("test.fsh", 1, 28).unwrap(),
+            "```glsl\nin vec4 gl_FragCoord;\n```\n\n---\n\nbuilt-in of the fragment stage"
+        );
+        // stages declare the same built-in differently
+        assert_eq!(
+            markup_at("test.vsh", 1, 54).unwrap(),
+            "```glsl\nattribute vec4 gl_Color;\n```\n\n---\n\nbuilt-in of the vertex stage"
+        );
+        // includes may be included into any stage having the built-in
+        assert_eq!(
+            markup_at("test.glsl", 1, 8).unwrap(),
+            "```glsl\nout vec4 gl_FragColor;\n```\n\n---\n\nbuilt-in of the fragment stage"
+        );
+        assert!(markup_at("test.glsl", 1, 54)
+            .unwrap()
+            .ends_with("built-in of the vertex, fragment stages"));
+
+        // built-ins of other stages aren't available
+        assert_eq!(markup_at("test.vsh", 1, 28), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_context() {
+        let source = r#"uniform float exposure;
+
+vec3 tonemap(vec3 color) {
+    return color * exposure;
+}
+
+void main() {
+    gl_FragColor = vec4(tonemap(gl_Color.rgb), 1.0);
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let markup_at = |file: &str, line, character| {
+            let path = temp_dir().join(file);
+            let point = Position { line, character };
+            match hover::with_context(&ctx, &path, point, hover::hover(&ctx, &path, point).unwrap()).contents {
+                HoverContents::Markup(markup) => markup.value,
+                _ => panic!("expected markup hover contents"),
+            }
+        };
+
+        // the line heads the hover of the name
+        assert_eq!(
+            markup_at("test.fsh", 3, 21),
+            "*fragment shader, in `tonemap`, outside of `main`*\n\n---\n\n```glsl\nuniform float exposure;\n```"
+        );
+        // and stands on its own elsewhere
+        assert_eq!(markup_at("test.vsh", 7, 0), "*vertex shader, in the `main` entry point*");
+        assert_eq!(markup_at("test.glsl", 1, 0), "*included file, outside of any function*");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_undefined_macro() {
+        let source = r#"#define SAMPLES 4
+
+float blur() {
+    return SAMPLES * 0.5;
+}
+
+#undef SAMPLES
+
+float sharpen() {
+    return SAMPLES * 0.5;
+}
+
+#define SAMPLES 8
+
+float denoise() {
+    return SAMPLES * 0.5;
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let markup_at = |line, character| match hover::hover(&ctx, &path, Position { line, character }).unwrap().unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("expected markup hover contents"),
+        };
+
+        assert_eq!(markup_at(3, 11), "```glsl\n#define SAMPLES 4\n```");
+        assert_eq!(
+            markup_at(9, 11),
+            "```glsl\n#define SAMPLES 4\n```\n\n---\n\nthis macro is `#undef`'d at line 7 and not in effect here"
+        );
+        // defining it again brings it back into effect
+        assert!(!markup_at(15, 11).contains("#undef"));
+
+        // the definition is still found from where it's undefined
+        let definitions = ctx.find_definitions(&path, Position { line: 9, character: 11 }).unwrap().unwrap();
+        assert_eq!(definitions[0].range.start, Position { line: 0, character: 8 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_hover_builtin_overload() {
+        let source = r#"uniform sampler2D colortex0;
+uniform usampler2D colortex1;
+
+void main() {
+    vec2 uv = gl_FragCoord.xy;
+    vec3 color = texture(colortex0, uv).rgb;
+    uvec4 data = texture(colortex1, uv * 0.5);
+    color = mix(color, vec3(1.0), color.r > 0.5 ? 1.0 : 0.0);
+    uint light = clamp(data.x, 0u, 15u);
+    gl_FragColor = vec4(mix(unknown, other, 0.5), 1.0);
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let markup_at = |line, character| match hover::hover(&ctx, &path, Position { line, character }).unwrap().unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            _ => panic!("expected markup hover contents"),
+        };
+
+        // the overload called leads, with the others following
+        let texture = markup_at(5, 18);
+        assert!(texture.starts_with("```glsl\nvec4 texture(sampler2D sampler, vec2 P)\n```\n\n---\n\n5 other overloads\n\n"));
+        assert!(texture.contains("float texture(sampler2DShadow sampler, vec3 P)"));
+        assert!(markup_at(6, 18).starts_with("```glsl\nuvec4 texture(usampler2D sampler, vec2 P)\n```"));
+
+        assert!(markup_at(7, 12).starts_with("```glsl\nvec3 mix(vec3 x, vec3 y, float a)\n```"));
+        assert!(markup_at(8, 17).starts_with("```glsl\nuint clamp(uint x, uint minVal, uint maxVal)\n```"));
+
+        // an argument of unknown type leaves all the overloads to choose from
+        assert_eq!(
+            markup_at(9, 25),
+            "```glsl\ngenType mix(genType x, genType y, genType a)\ngenType mix(genType x, genType y, float a)\ngenType mix(genType x, genType y, genBType a)\n```"
+        );
+    }
+}
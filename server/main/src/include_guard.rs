@@ -0,0 +1,138 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Position, Range, TextEdit, WorkspaceEdit};
+use tree_sitter::Node;
+
+use crate::{navigation::ParserContext, url_norm::url_for};
+
+/// Builds the edit wrapping a header in an include guard named after the file, e.g. `COMMON_GLSL` for
+/// `common.glsl`. Returns `None` for files other than headers, and for headers already guarded by either an
+/// `#ifndef`/`#define` pair or `#pragma once`.
+pub fn add_include_guard(ctx: &ParserContext, path: &Path) -> Result<Option<WorkspaceEdit>> {
+    if !matches!(path.extension().and_then(|ext| ext.to_str()), Some("glsl" | "inc")) || has_include_guard(ctx) {
+        return Ok(None);
+    }
+
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => guard_name(name),
+        None => return Ok(None),
+    };
+
+    let end = ctx.position_for_offset(ctx.source().len());
+    let endif = if ctx.source().is_empty() || ctx.source().ends_with('\n') {
+        "\n#endif\n"
+    } else {
+        "\n\n#endif\n"
+    };
+
+    let edits = vec![
+        TextEdit {
+            range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+            new_text: format!("#ifndef {}\n#define {}\n\n", name, name),
+        },
+        TextEdit {
+            range: Range::new(end, end),
+            new_text: endif.into(),
+        },
+    ];
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(HashMap::from([(url_for(path)?, edits)])),
+        ..WorkspaceEdit::default()
+    }))
+}
+
+// whether a top-level `#pragma once` or `#ifndef X` directly followed by `#define X` guards the file.
+fn has_include_guard(ctx: &ParserContext) -> bool {
+    let root = ctx.root_node();
+    root.named_children(&mut root.walk()).any(|node| match node.kind() {
+        "preproc_call" => node_text(ctx, node).split_whitespace().collect::<Vec<_>>() == ["#pragma", "once"],
+        "preproc_ifdef" => is_guard(ctx, node),
+        _ => false,
+    })
+}
+
+fn is_guard(ctx: &ParserContext, ifdef: Node) -> bool {
+    let is_ifndef = ifdef.child(0).map_or(false, |directive| directive.kind() == "#ifndef");
+    let name = match ifdef.child_by_field_name("name") {
+        Some(name) if is_ifndef => name,
+        _ => return false,
+    };
+
+    let mut cursor = ifdef.walk();
+    let define = ifdef
+        .named_children(&mut cursor)
+        .find(|child| *child != name && child.kind() != "comment");
+
+    match define.filter(|define| define.kind() == "preproc_def") {
+        Some(define) => define.child_by_field_name("name").map(|n| node_text(ctx, n)) == Some(node_text(ctx, name)),
+        None => false,
+    }
+}
+
+// the file name in upper case with anything that can't be part of an identifier replaced by underscores.
+fn guard_name(file_name: &str) -> String {
+    let name: String = file_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn node_text<'a>(ctx: &'a ParserContext, node: Node) -> &'a str {
+    &ctx.source()[node.byte_range()]
+}
+
+#[cfg(test)]
+mod include_guard_test {
+    use std::path::PathBuf;
+
+    use rust_lsp::lsp_types::Position;
+    use tree_sitter::Parser;
+    use url::Url;
+
+    use crate::include_guard::add_include_guard;
+    use crate::navigation::ParserContext;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_add_include_guard() {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+
+        let path = PathBuf::from("/pack/shaders/lib/common-2.glsl");
+
+        let source = "float luma(vec3 c) {\n    return c.g;\n}\n";
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let edit = add_include_guard(&ctx, &path).unwrap().unwrap();
+        let edits = &edit.changes.unwrap()[&Url::from_file_path(&path).unwrap()];
+
+        assert_eq!(edits[0].new_text, "#ifndef COMMON_2_GLSL\n#define COMMON_2_GLSL\n\n");
+        assert_eq!(edits[0].range.start, Position::new(0, 0));
+        assert_eq!(edits[1].new_text, "\n#endif\n");
+        assert_eq!(edits[1].range.start, Position::new(3, 0));
+
+        // already guarded headers and stage files aren't offered a guard
+        for source in [
+            "#ifndef COMMON\n#define COMMON\nfloat a;\n#endif\n",
+            "// shared helpers\n#pragma once\nfloat a;\n",
+        ] {
+            let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+            assert!(add_include_guard(&ctx, &path).unwrap().is_none(), "{}", source);
+        }
+
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        assert!(add_include_guard(&ctx, &PathBuf::from("/pack/shaders/final.fsh"))
+            .unwrap()
+            .is_none());
+
+        // an #ifndef that isn't followed by its #define isn't a guard
+        let ctx = ParserContext::from_source(&mut parser, "#ifndef SHADOWS\nfloat a;\n#endif\n".to_string()).unwrap();
+        assert!(add_include_guard(&ctx, &path).unwrap().is_some());
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use path_slash::PathBufExt;
+
+// deep enough for macros naming headers through another macro or two, while stopping at macros expanding to themselves.
+const MAX_EXPANSIONS: usize = 8;
+
+/// Resolves the path operand of an `#include` directive to the file it refers to. Absolute includes
+/// are resolved against the `shaders` directory of the workspace root, or the first of any further
+/// include roots having the file, while relative includes are resolved against the directory of the
+/// including file.
+pub struct IncludeResolver {
+    // the directories absolute includes are looked up in, in order of precedence
+    roots: Vec<PathBuf>,
+}
+
+impl IncludeResolver {
+    pub fn new(root: &Path) -> Self {
+        IncludeResolver {
+            roots: vec![root.join("shaders")],
+        }
+    }
+
+    /// Resolves absolute includes against the directory itself, for files opened outside of any workspace folder,
+    /// which have no `shaders` directory to resolve them against.
+    pub fn for_directory(directory: &Path) -> Self {
+        IncludeResolver {
+            roots: vec![directory.to_path_buf()],
+        }
+    }
+
+    /// Adds directories for absolute includes to be looked up in after the `shaders` directory, in the given order.
+    pub fn with_include_roots(mut self, roots: &[PathBuf]) -> Self {
+        for root in roots {
+            if !self.roots.contains(root) {
+                self.roots.push(root.clone());
+            }
+        }
+        self
+    }
+
+    pub fn resolve(&self, file: &Path, include: &str) -> PathBuf {
+        match include.strip_prefix('/') {
+            Some(include) => {
+                let include = PathBuf::from_slash(include);
+                // includes found in none of the roots resolve against the `shaders` directory, to be reported missing there
+                self.roots
+                    .iter()
+                    .map(|root| root.join(&include))
+                    .find(|path| path.is_file())
+                    .unwrap_or_else(|| self.roots[0].join(&include))
+            }
+            None => file.parent().unwrap().join(PathBuf::from_slash(include)),
+        }
+    }
+}
+
+/// Returns the path an `#include` operand spells, without its quotes. Packs may include a macro expanding to the
+/// path rather than the path itself, as in `#define SKY_HEADER "/lib/sky.glsl"` and `#include SKY_HEADER`, where
+/// the macro is expanded by `expand` first, through any further macros it expands to. Returns `None` where a macro
+/// isn't defined, or doesn't expand to a quoted path.
+pub fn path_spec<'a, F>(operand: &'a str, expand: F) -> Option<&'a str>
+where
+    F: Fn(&str) -> Option<&'a str>,
+{
+    let mut operand = operand.trim();
+    for _ in 0..MAX_EXPANSIONS {
+        let quoted = (operand.starts_with('"') && operand.ends_with('"')) || (operand.starts_with('<') && operand.ends_with('>'));
+        if quoted && operand.len() > 2 {
+            return Some(&operand[1..operand.len() - 1]);
+        }
+
+        if !operand.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+        operand = expand(operand)?.trim();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod include_resolver_test {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use crate::include_resolver::{path_spec, IncludeResolver};
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_resolve() {
+        let root = PathBuf::from("/pack");
+        let resolver = IncludeResolver::new(&root);
+
+        let file = root.join("shaders").join("world0").join("composite.fsh");
+
+        assert_eq!(
+            resolver.resolve(&file, "/lib/common.glsl"),
+            root.join("shaders").join("lib").join("common.glsl")
+        );
+        assert_eq!(
+            resolver.resolve(&file, "utils/noise.glsl"),
+            root.join("shaders").join("world0").join("utils").join("noise.glsl")
+        );
+
+        // a file outside of the workspace resolves either kind of include next to it
+        let loose = PathBuf::from("/tmp").join("test.fsh");
+        let resolver = IncludeResolver::for_directory(loose.parent().unwrap());
+        assert_eq!(resolver.resolve(&loose, "/common.glsl"), PathBuf::from("/tmp").join("common.glsl"));
+        assert_eq!(resolver.resolve(&loose, "common.glsl"), PathBuf::from("/tmp").join("common.glsl"));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_resolve_include_roots() {
+        let tmp_dir = TempDir::new("mcshader").unwrap();
+        let root = tmp_dir.path().to_path_buf();
+
+        for dir in ["shaders/lib", "common/lib", "vendor/lib"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("shaders/lib/noise.glsl"), "").unwrap();
+        fs::write(root.join("common/lib/noise.glsl"), "").unwrap();
+        fs::write(root.join("common/lib/sky.glsl"), "").unwrap();
+        fs::write(root.join("vendor/lib/sky.glsl"), "").unwrap();
+        fs::write(root.join("vendor/lib/water.glsl"), "").unwrap();
+
+        let resolver = IncludeResolver::new(&root).with_include_roots(&[root.join("common"), root.join("vendor")]);
+        let file = root.join("shaders").join("composite.fsh");
+
+        // the `shaders` directory comes first, then the include roots in order
+        assert_eq!(resolver.resolve(&file, "/lib/noise.glsl"), root.join("shaders/lib/noise.glsl"));
+        assert_eq!(resolver.resolve(&file, "/lib/sky.glsl"), root.join("common/lib/sky.glsl"));
+        assert_eq!(resolver.resolve(&file, "/lib/water.glsl"), root.join("vendor/lib/water.glsl"));
+
+        assert_eq!(resolver.resolve(&file, "/lib/missing.glsl"), root.join("shaders/lib/missing.glsl"));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_path_spec() {
+        let macros = HashMap::from([
+            ("SKY_HEADER", "\"/lib/sky.glsl\""),
+            ("WATER_HEADER", " <lib/water.glsl> "),
+            ("HEADER", "SKY_HEADER"),
+            ("QUALITY", "2"),
+            ("EMPTY", "\"\""),
+            ("LOOP", "LOOP"),
+        ]);
+        let expand = |name: &str| macros.get(name).copied();
+
+        assert_eq!(path_spec("\"/lib/common.glsl\"", expand), Some("/lib/common.glsl"));
+        assert_eq!(path_spec("SKY_HEADER", expand), Some("/lib/sky.glsl"));
+        assert_eq!(path_spec("WATER_HEADER", expand), Some("lib/water.glsl"));
+        assert_eq!(path_spec("HEADER", expand), Some("/lib/sky.glsl"));
+
+        // macros that don't expand to a path, or at all
+        assert_eq!(path_spec("QUALITY", expand), None);
+        assert_eq!(path_spec("EMPTY", expand), None);
+        assert_eq!(path_spec("LOOP", expand), None);
+        assert_eq!(path_spec("UNDEFINED", expand), None);
+    }
+}
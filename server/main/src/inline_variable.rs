@@ -0,0 +1,290 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Position, Range, TextEdit, WorkspaceEdit};
+use tree_sitter::Node;
+
+use crate::{
+    builtins,
+    extract_function::{collect_identifiers, enclosing_function, is_written},
+    navigation::ParserContext,
+    url_norm::url_for,
+};
+
+/// Builds the edit inlining the local variable at the point, replacing each read of it with its initializer and
+/// removing its declaration. Returns `None` unless the variable is declared on its own with an initializer and
+/// never assigned to again, and the initializer evaluates the same wherever it's moved to: it mustn't call
+/// functions that could have side effects, nor read variables assigned to after the declaration or shadowed where
+/// the variable is read.
+pub fn inline_variable(ctx: &ParserContext, path: &Path, point: Position) -> Result<Option<WorkspaceEdit>> {
+    let node = match ctx.find_node_at_point(point) {
+        Some(node) if node.kind() == "identifier" => node,
+        _ => return Ok(None),
+    };
+    let declaration = match ctx.variable_declaration(node)? {
+        Some(declaration) => declaration,
+        None => return Ok(None),
+    };
+
+    let declarator = match declaration.parent() {
+        Some(parent) if parent.kind() == "init_declarator" && parent.child_by_field_name("declarator") == Some(declaration) => parent,
+        _ => return Ok(None),
+    };
+    let (initializer, statement) = match (declarator.child_by_field_name("value"), declarator.parent()) {
+        (Some(initializer), Some(statement)) if statement.kind() == "declaration" => (initializer, statement),
+        _ => return Ok(None),
+    };
+
+    // only a declaration of the one variable can be removed as a whole
+    let mut cursor = statement.walk();
+    if statement.children_by_field_name("declarator", &mut cursor).count() != 1 {
+        return Ok(None);
+    }
+
+    let body = match enclosing_function(statement).and_then(|function| function.child_by_field_name("body")) {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+    if has_side_effects(ctx, initializer) {
+        return Ok(None);
+    }
+
+    let mut identifiers = vec![];
+    collect_identifiers(body, &mut identifiers);
+
+    let mut reads = vec![];
+    for identifier in &identifiers {
+        if *identifier != declaration && ctx.variable_declaration(*identifier)? == Some(declaration) {
+            if is_written(*identifier) || is_user_function_argument(ctx, *identifier) {
+                return Ok(None);
+            }
+            reads.push(*identifier);
+        }
+    }
+
+    let mut initializer_identifiers = vec![];
+    collect_identifiers(initializer, &mut initializer_identifiers);
+    for identifier in initializer_identifiers {
+        let declared = ctx.variable_declaration(identifier)?;
+
+        // the names read must refer to the same variables where the initializer is moved to, not ones shadowing them
+        let name = identifier.utf8_text(ctx.source().as_bytes())?;
+        for read in &reads {
+            if ctx.name_declaration(name, *read)? != declared {
+                return Ok(None);
+            }
+        }
+
+        let read = match declared {
+            Some(read) => read,
+            None => continue,
+        };
+        for other in identifiers.iter().filter(|other| other.start_byte() > statement.end_byte()) {
+            if is_written(*other) && ctx.variable_declaration(*other)? == Some(read) {
+                return Ok(None);
+            }
+        }
+    }
+
+    let text = node_text(ctx, initializer);
+    let mut edits: Vec<TextEdit> = reads
+        .into_iter()
+        .map(|read| TextEdit {
+            range: ctx.range_for_node(&read),
+            new_text: if needs_parentheses(initializer, read) {
+                format!("({})", text)
+            } else {
+                text.to_string()
+            },
+        })
+        .collect();
+    edits.push(removal(ctx, statement));
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(HashMap::from([(url_for(path)?, edits)])),
+        ..WorkspaceEdit::default()
+    }))
+}
+
+// whether evaluating the expression could change anything, by assigning or calling a function that may. Built-in
+// functions are free of side effects other than those writing to images and buffers.
+fn has_side_effects(ctx: &ParserContext, expression: Node) -> bool {
+    let mut cursor = expression.walk();
+    let mut stack = vec![expression];
+
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "assignment_expression" | "update_expression" => return true,
+            "call_expression" => {
+                let name = node.child_by_field_name("function").map_or("", |function| node_text(ctx, function));
+                let writes = name.starts_with("atomic") || name.starts_with("imageAtomic") || name == "imageStore";
+                if writes || !builtins::is_builtin(name) {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        stack.extend(node.named_children(&mut cursor));
+    }
+
+    false
+}
+
+// whether the identifier is passed on its own to a function declared by the user, which may take it as an `out`
+// parameter and assign to it.
+fn is_user_function_argument(ctx: &ParserContext, identifier: Node) -> bool {
+    let call = identifier
+        .parent()
+        .filter(|parent| parent.kind() == "argument_list")
+        .and_then(|arguments| arguments.parent());
+
+    match call.and_then(|call| call.child_by_field_name("function")) {
+        Some(function) => !builtins::is_builtin(node_text(ctx, function)),
+        None => false,
+    }
+}
+
+// whether the initializer has to be parenthesized where it replaces the read, to keep binding as tightly.
+fn needs_parentheses(initializer: Node, read: Node) -> bool {
+    let is_primary = matches!(
+        initializer.kind(),
+        "identifier"
+            | "number_literal"
+            | "true"
+            | "false"
+            | "call_expression"
+            | "field_expression"
+            | "subscript_expression"
+            | "parenthesized_expression"
+    );
+    if is_primary {
+        return false;
+    }
+
+    match read.parent() {
+        Some(parent) if parent.kind() == "assignment_expression" => parent.child_by_field_name("right") != Some(read),
+        Some(parent) => !matches!(
+            parent.kind(),
+            "argument_list"
+                | "parenthesized_expression"
+                | "init_declarator"
+                | "initializer_list"
+                | "return_statement"
+                | "expression_statement"
+        ),
+        None => true,
+    }
+}
+
+// the edit removing the declaration, along with its line where nothing else is on it.
+fn removal(ctx: &ParserContext, statement: Node) -> TextEdit {
+    let source = ctx.source();
+    let line_start = source[..statement.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[statement.end_byte()..]
+        .find('\n')
+        .map_or(source.len(), |i| statement.end_byte() + i + 1);
+
+    let alone = source[line_start..statement.start_byte()].trim().is_empty() && source[statement.end_byte()..line_end].trim().is_empty();
+    let (start, end) = if alone {
+        (line_start, line_end)
+    } else {
+        (statement.start_byte(), statement.end_byte())
+    };
+
+    TextEdit {
+        range: Range::new(ctx.position_for_offset(start), ctx.position_for_offset(end)),
+        new_text: String::new(),
+    }
+}
+
+fn node_text<'a>(ctx: &'a ParserContext, node: Node) -> &'a str {
+    &ctx.source()[node.byte_range()]
+}
+
+#[cfg(test)]
+mod inline_variable_test {
+    use std::path::PathBuf;
+
+    use rust_lsp::lsp_types::Position;
+    use tree_sitter::Parser;
+    use url::Url;
+
+    use crate::inline_variable::inline_variable;
+    use crate::navigation::ParserContext;
+
+    // the source with the edit inlining the variable at the point applied, if there is one.
+    fn inlined(source: &str, point: Position) -> Option<String> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let path = PathBuf::from("/pack/shaders/final.fsh");
+        let edit = inline_variable(&ctx, &path, point).unwrap()?;
+
+        let mut edits = edit.changes.unwrap().remove(&Url::from_file_path(&path).unwrap()).unwrap();
+        edits.sort_by_key(|edit| std::cmp::Reverse(ctx.offset_for_position(edit.range.start)));
+
+        let mut result = source.to_string();
+        for edit in edits {
+            let range = ctx.offset_for_position(edit.range.start)..ctx.offset_for_position(edit.range.end);
+            result.replace_range(range, &edit.new_text);
+        }
+        Some(result)
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_inline_variable() {
+        let source = r#"void main() {
+    float a = 1.0;
+    float b = 2.0;
+    float t = a + b;
+    gl_FragColor = vec4(t) * t;
+}
+"#;
+
+        let expected = r#"void main() {
+    float a = 1.0;
+    float b = 2.0;
+    gl_FragColor = vec4(a + b) * (a + b);
+}
+"#;
+
+        // from the declaration as well as from a read
+        assert_eq!(inlined(source, Position::new(3, 10)).unwrap(), expected);
+        assert_eq!(inlined(source, Position::new(4, 29)).unwrap(), expected);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_inline_variable_refused() {
+        let source = r#"float shade(vec3 color);
+float global = 1.0;
+
+void main() {
+    float a = 1.0;
+    float reassigned = a;
+    reassigned += 1.0;
+    float called = shade(vec3(a));
+    float moved = a * 2.0;
+    a = 3.0;
+    float pure = max(a, 0.0), other = 1.0;
+    float shadowed = global * 2.0;
+    {
+        float global = 0.5;
+        gl_FragColor = vec4(reassigned, called, moved, pure + other + shadowed);
+    }
+}
+"#;
+
+        // reassigned, calling a user function, reading a variable assigned to later, and declared with another
+        assert_eq!(inlined(source, Position::new(5, 10)), None);
+        assert_eq!(inlined(source, Position::new(7, 10)), None);
+        assert_eq!(inlined(source, Position::new(8, 10)), None);
+        assert_eq!(inlined(source, Position::new(10, 10)), None);
+        // reading a global shadowed by a local where it's read
+        assert_eq!(inlined(source, Position::new(11, 10)), None);
+        // globals
+        assert_eq!(inlined(source, Position::new(1, 6)), None);
+    }
+}
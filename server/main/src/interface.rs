@@ -0,0 +1,491 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, NumberOrString, Range, Url};
+use tree_sitter::Node;
+
+use crate::{
+    consts,
+    layout::{self, LayoutQualifier},
+    navigation::{self, ParserContext},
+    preprocessor::{self, Condition},
+    url_norm::url_for,
+};
+
+/// The storage qualifiers making a global declaration part of a shader stage's interface. `attribute` and
+/// `varying` are the pre-130 spellings of vertex inputs and of outputs passed between stages.
+pub const STORAGE_QUALIFIERS: &[&str] = &["uniform", "in", "out", "buffer", "attribute", "varying"];
+
+/// A global variable or interface block through which a shader stage communicates with the program or with other
+/// stages, e.g. `layout(location = 0) out vec4 color;`.
+#[derive(Debug, Clone)]
+pub struct InterfaceVar {
+    pub name: String,
+    // the declared type including any array dimensions, or the block name for interface blocks
+    pub ty: String,
+    // the storage qualifier as written, one of `STORAGE_QUALIFIERS`
+    pub storage: String,
+    // the remaining qualifiers such as `flat` or `highp`, in the order written
+    pub qualifiers: Vec<String>,
+    pub layout: Vec<LayoutQualifier>,
+    // the range of the declared name, or of the block name for interface blocks without an instance name
+    pub range: Range,
+    pub is_block: bool,
+    // the preprocessor conditions to be met for the variable to be compiled, innermost first
+    pub conditions: Vec<Condition>,
+}
+
+/// Lists the `uniform`, `in`, `out` and `buffer` (and legacy `attribute` and `varying`) declarations at the top
+/// level of the file, including those within preprocessor conditionals, in source order. A declaration of several
+/// variables results in one entry per variable.
+pub fn interface_variables(ctx: &ParserContext) -> Vec<InterfaceVar> {
+    let mut variables = vec![];
+    collect_interface_variables(ctx, ctx.root_node(), &mut variables);
+    variables
+}
+
+fn collect_interface_variables(ctx: &ParserContext, node: Node, variables: &mut Vec<InterfaceVar>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "declaration" => variables.extend(declaration_variables(ctx, child)),
+            "preproc_ifdef" | "preproc_if" | "preproc_elif" | "preproc_else" => collect_interface_variables(ctx, child, variables),
+            _ => (),
+        }
+    }
+}
+
+fn declaration_variables(ctx: &ParserContext, declaration: Node) -> Vec<InterfaceVar> {
+    let type_node = match declaration.child_by_field_name("type") {
+        Some(type_node) => type_node,
+        None => return vec![],
+    };
+
+    let layout_node = layout::find_layout_node(declaration);
+    let layout = layout_node.map_or(vec![], |node| layout::parse_layout_qualifiers(node, ctx.source()));
+
+    let mut cursor = declaration.walk();
+    let declarators: Vec<Node> = declaration.children_by_field_name("declarator", &mut cursor).collect();
+
+    let mut cursor = declaration.walk();
+    let words: Vec<String> = declaration
+        .children(&mut cursor)
+        .filter(|child| *child != type_node && !declarators.contains(child) && child.kind() != "comment")
+        .flat_map(|child| qualifier_words(ctx, child, layout_node))
+        .collect();
+
+    let storage = match words.iter().find(|word| STORAGE_QUALIFIERS.contains(&word.as_str())) {
+        Some(storage) => storage.clone(),
+        None => return vec![],
+    };
+    let qualifiers: Vec<String> = words.into_iter().filter(|word| *word != storage).collect();
+
+    // interface blocks are parsed with their body as the type, e.g. `uniform Block { float a; } block;`
+    let block_name = type_node.child_by_field_name("body").and(type_node.child_by_field_name("name"));
+    let ty = match block_name {
+        Some(name) => node_text(ctx, name),
+        None => strip_whitespace(&node_text(ctx, type_node)),
+    };
+
+    let conditions = preprocessor::gating_conditions(declaration, ctx.source());
+    let new_var = |name: String, ty: String, range: Range| InterfaceVar {
+        name,
+        ty,
+        storage: storage.clone(),
+        qualifiers: qualifiers.clone(),
+        layout: layout.clone(),
+        range,
+        is_block: block_name.is_some(),
+        conditions: conditions.clone(),
+    };
+
+    if declarators.is_empty() {
+        return match block_name {
+            Some(name) => vec![new_var(ty.clone(), ty, ctx.range_for_node(&name))],
+            None => vec![],
+        };
+    }
+
+    declarators
+        .into_iter()
+        .filter_map(|declarator| {
+            // initializers aren't part of the declared type, but array dimensions are
+            let declarator = match declarator.kind() {
+                "init_declarator" => declarator.child_by_field_name("declarator")?,
+                _ => declarator,
+            };
+            let mut ident = declarator;
+            while ident.kind() != "identifier" {
+                ident = ident.child_by_field_name("declarator")?;
+            }
+            let array_suffix = strip_whitespace(&ctx.source()[ident.end_byte()..declarator.end_byte()]);
+
+            Some(new_var(
+                node_text(ctx, ident),
+                format!("{}{}", ty, array_suffix),
+                ctx.range_for_node(&ident),
+            ))
+        })
+        .collect()
+}
+
+// the qualifier keywords of a child of the declaration, leaving out those of the layout qualifier if it's within.
+fn qualifier_words(ctx: &ParserContext, child: Node, layout_node: Option<Node>) -> Vec<String> {
+    let text = match layout_node {
+        Some(layout) if child.start_byte() <= layout.start_byte() && layout.end_byte() <= child.end_byte() => format!(
+            "{} {}",
+            &ctx.source()[child.start_byte()..layout.start_byte()],
+            &ctx.source()[layout.end_byte()..child.end_byte()]
+        ),
+        _ => node_text(ctx, child),
+    };
+
+    text.split(|c: char| c.is_whitespace() || c == ';' || c == ',')
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Returns the fragment shader paired with a vertex shader or vice versa, being the file of the other stage with
+/// the same name in the same directory, e.g. `composite.fsh` for `composite.vsh`. Programs with a geometry stage
+/// in between are not paired, as its inputs and outputs differ in form from those of the other stages.
+pub fn paired_stage(path: &Path) -> Option<PathBuf> {
+    let counterpart = match path.extension()?.to_str()? {
+        "vsh" => path.with_extension("fsh"),
+        "fsh" => path.with_extension("vsh"),
+        _ => return None,
+    };
+
+    if !counterpart.is_file() || path.with_extension("gsh").is_file() {
+        return None;
+    }
+
+    Some(counterpart)
+}
+
+/// Compares the outputs of a vertex stage against the inputs of the fragment stage following it, each given along
+/// with the file declaring them. Variables of the same name declared with different types are errors on both
+/// sides, each pointing at its counterpart, while outputs that the fragment stage has no input for are warned
+/// about as they are computed for nothing. Interface blocks are not compared.
+pub fn interface_mismatches(
+    vertex: &[(PathBuf, InterfaceVar)], fragment: &[(PathBuf, InterfaceVar)],
+) -> Result<HashMap<Url, Vec<Diagnostic>>> {
+    let outputs: Vec<&(PathBuf, InterfaceVar)> = vertex
+        .iter()
+        .filter(|(_, var)| !var.is_block && (var.storage == "out" || var.storage == "varying"))
+        .collect();
+    let inputs: Vec<&(PathBuf, InterfaceVar)> = fragment
+        .iter()
+        .filter(|(_, var)| !var.is_block && (var.storage == "in" || var.storage == "varying"))
+        .collect();
+
+    let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+
+    for (is_output, own, counterparts, counterpart_stage) in [
+        (true, &outputs, &inputs, "fragment stage input"),
+        (false, &inputs, &outputs, "vertex stage output"),
+    ] {
+        for (path, var) in own.iter() {
+            let same_name: Vec<_> = counterparts.iter().filter(|(_, other)| other.name == var.name).collect();
+
+            if same_name.is_empty() {
+                // inputs without an output are left to the compiler, which reports them once linked
+                if is_output {
+                    diagnostics.entry(url_for(path)?).or_default().push(new_diagnostic(
+                        var.range,
+                        DiagnosticSeverity::WARNING,
+                        "unmatched-output",
+                        format!("vertex stage output `{}` is not an input of the fragment stage", var.name),
+                        None,
+                    ));
+                }
+                continue;
+            }
+
+            if same_name.iter().any(|(_, other)| other.ty == var.ty) {
+                continue;
+            }
+
+            let (other_path, other) = same_name[0];
+            diagnostics.entry(url_for(path)?).or_default().push(new_diagnostic(
+                var.range,
+                DiagnosticSeverity::ERROR,
+                "interface-mismatch",
+                format!(
+                    "`{} {}` doesn't match the type of the {} `{} {}`",
+                    var.ty, var.name, counterpart_stage, other.ty, other.name
+                ),
+                Some(vec![DiagnosticRelatedInformation {
+                    location: navigation::location_for(other_path, other.range.start, other.range.end)?,
+                    message: format!("{} declared here", counterpart_stage),
+                }]),
+            ));
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// Returns the other variables given declared at the same explicit `layout(location = N)` as the variable with the
+/// same storage, such as two fragment outputs written to location 0, which fails to compile. The variables are
+/// those of one file, and only the location given is compared, not the further locations arrays and matrices take
+/// up. Variables in branches of a preprocessor conditional that can't both be compiled don't conflict.
+pub fn location_conflicts<'a>(variables: &'a [InterfaceVar], var: &InterfaceVar) -> Vec<&'a InterfaceVar> {
+    let location = match explicit_location(var) {
+        Some(location) => location,
+        None => return vec![],
+    };
+
+    variables
+        .iter()
+        .filter(|other| other.range != var.range && storage_class(other) == storage_class(var))
+        .filter(|other| explicit_location(other) == Some(location))
+        .filter(|other| !preprocessor::mutually_exclusive(&other.conditions, &var.conditions))
+        .collect()
+}
+
+/// Describes the variable sharing its location with the conflicting variables, as diagnosed and hovered.
+pub fn location_conflict_message(var: &InterfaceVar, conflicts: &[&InterfaceVar]) -> String {
+    let names: Vec<String> = conflicts.iter().map(|other| format!("`{}`", other.name)).collect();
+    format!(
+        "`{}` shares {} location {} with {}",
+        var.name,
+        storage_class(var),
+        explicit_location(var).unwrap_or_default(),
+        names.join(", ")
+    )
+}
+
+/// Errors on each of the file's interface variables declared at the location of another, see `location_conflicts`,
+/// pointing at the others.
+pub fn location_conflict_diagnostics(path: &Path, variables: &[InterfaceVar]) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics = vec![];
+
+    for var in variables {
+        let conflicts = location_conflicts(variables, var);
+        if conflicts.is_empty() {
+            continue;
+        }
+
+        let related_information = conflicts
+            .iter()
+            .map(|other| {
+                Ok(DiagnosticRelatedInformation {
+                    location: navigation::location_for(path, other.range.start, other.range.end)?,
+                    message: format!("`{}` declared at the same location here", other.name),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        diagnostics.push(new_diagnostic(
+            var.range,
+            DiagnosticSeverity::ERROR,
+            "location-conflict",
+            location_conflict_message(var, &conflicts),
+            Some(related_information),
+        ));
+    }
+
+    Ok(diagnostics)
+}
+
+fn explicit_location(var: &InterfaceVar) -> Option<u32> {
+    let qualifier = var.layout.iter().find(|qualifier| qualifier.key == "location")?;
+    qualifier.value.as_deref()?.parse().ok()
+}
+
+// inputs, outputs and uniforms are each numbered apart, `attribute` being the legacy spelling of a vertex input.
+fn storage_class(var: &InterfaceVar) -> &str {
+    match var.storage.as_str() {
+        "in" | "attribute" => "input",
+        "out" => "output",
+        storage => storage,
+    }
+}
+
+fn new_diagnostic(
+    range: Range, severity: DiagnosticSeverity, code: &str, message: String, related_information: Option<Vec<DiagnosticRelatedInformation>>,
+) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.into())),
+        code_description: None,
+        source: Some(consts::SOURCE.into()),
+        message,
+        related_information,
+        tags: None,
+        data: None,
+    }
+}
+
+fn node_text(ctx: &ParserContext, node: Node) -> String {
+    node.utf8_text(ctx.source().as_bytes()).unwrap_or_default().to_string()
+}
+
+fn strip_whitespace(text: &str) -> String {
+    text.split_whitespace().collect()
+}
+
+#[cfg(test)]
+mod interface_test {
+    use std::env::temp_dir;
+
+    use rust_lsp::lsp_types::{NumberOrString, Position};
+    use tree_sitter::Parser;
+
+    use crate::interface::{interface_variables, location_conflict_diagnostics, location_conflicts};
+    use crate::navigation::ParserContext;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_interface_variables() {
+        let source = r#"#version 330
+
+uniform sampler2D colortex0;
+uniform float viewWidth, viewHeight;
+layout(location = 0) out vec4 color;
+flat in int blockId;
+in vec2 texcoords[2];
+varying vec3 normal;
+const float PI = 3.14159;
+float helper;
+
+void main() {
+    vec4 local;
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let variables = interface_variables(&ctx);
+        let summary: Vec<(&str, &str, &str)> = variables
+            .iter()
+            .map(|var| (var.storage.as_str(), var.ty.as_str(), var.name.as_str()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("uniform", "sampler2D", "colortex0"),
+                ("uniform", "float", "viewWidth"),
+                ("uniform", "float", "viewHeight"),
+                ("out", "vec4", "color"),
+                ("in", "int", "blockId"),
+                ("in", "vec2[2]", "texcoords"),
+                ("varying", "vec3", "normal"),
+            ]
+        );
+
+        assert_eq!(variables[3].layout.len(), 1);
+        assert_eq!(variables[3].layout[0].key, "location");
+        assert_eq!(variables[3].layout[0].value.as_deref(), Some("0"));
+        assert!(variables[3].qualifiers.is_empty());
+
+        assert_eq!(variables[4].qualifiers, vec!["flat".to_string()]);
+        assert_eq!(variables[4].range.start.line, 5);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_location_conflicts() {
+        let source = r#"#version 430
+
+layout(location = 0) in vec3 position;
+layout(location = 0) out vec4 color;
+layout(location = 1) out vec4 normal;
+layout(location = 0) out vec4 bloom;
+layout(location = 2) uniform float exposure;
+layout(location = 2) uniform float gamma;
+layout(std140) uniform Block { float a; };
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let variables = interface_variables(&ctx);
+        let conflicts = |name: &str| -> Vec<&str> {
+            let var = variables.iter().find(|var| var.name == name).unwrap();
+            location_conflicts(&variables, var)
+                .into_iter()
+                .map(|other| other.name.as_str())
+                .collect()
+        };
+
+        // inputs, outputs and uniforms are each numbered apart
+        assert!(conflicts("position").is_empty());
+        assert_eq!(conflicts("color"), vec!["bloom"]);
+        assert_eq!(conflicts("bloom"), vec!["color"]);
+        assert!(conflicts("normal").is_empty());
+        assert_eq!(conflicts("exposure"), vec!["gamma"]);
+        assert!(conflicts("Block").is_empty());
+
+        let path = temp_dir().join("test.fsh");
+        let diagnostics = location_conflict_diagnostics(&path, &variables).unwrap();
+        let summary: Vec<(u32, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (3, "`color` shares output location 0 with `bloom`"),
+                (5, "`bloom` shares output location 0 with `color`"),
+                (6, "`exposure` shares uniform location 2 with `gamma`"),
+                (7, "`gamma` shares uniform location 2 with `exposure`"),
+            ]
+        );
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("location-conflict".into())));
+
+        // each pointing at the declarations it conflicts with
+        let related = diagnostics[0].related_information.as_ref().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].location.range.start, Position { line: 5, character: 30 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_location_conflicts_across_branches() {
+        let source = r#"#version 430
+
+#ifdef DEFERRED
+layout(location = 0) out vec4 albedo;
+#else
+layout(location = 0) out vec4 color;
+#endif
+
+#ifdef BLOOM
+layout(location = 1) out vec4 bloom;
+#endif
+#ifdef DEFERRED
+layout(location = 1) out vec4 normal;
+#endif
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let variables = interface_variables(&ctx);
+        let conflicts = |name: &str| -> Vec<&str> {
+            let var = variables.iter().find(|var| var.name == name).unwrap();
+            location_conflicts(&variables, var)
+                .into_iter()
+                .map(|other| other.name.as_str())
+                .collect()
+        };
+
+        // only one branch of a conditional is compiled
+        assert_eq!(variables.len(), 4);
+        assert!(conflicts("albedo").is_empty());
+        assert!(conflicts("color").is_empty());
+        // while both of two conditionals may be
+        assert_eq!(conflicts("bloom"), vec!["normal"]);
+    }
+}
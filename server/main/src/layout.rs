@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use tree_sitter::Node;
+
+/// A single entry of a `layout(...)` qualifier, such as `location = 0` or `std140`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutQualifier {
+    pub key: String,
+    pub value: Option<String>,
+    // byte ranges into the source of the key and, if present, the value.
+    pub key_span: Range<usize>,
+    pub value_span: Option<Range<usize>>,
+}
+
+/// Finds the `layout(...)` qualifier node of a declaration, if it has one. The search does not descend into
+/// declarators or bodies, so only the qualifiers applying to the declaration itself are considered.
+pub fn find_layout_node<'tree>(declaration: Node<'tree>) -> Option<Node<'tree>> {
+    let mut cursor = declaration.walk();
+    let children: Vec<Node<'tree>> = declaration.named_children(&mut cursor).collect();
+
+    for child in children {
+        if child.kind().starts_with("layout") {
+            return Some(child);
+        }
+
+        match child.kind() {
+            "identifier" | "init_declarator" | "function_declarator" | "field_declaration_list" | "compound_statement" => continue,
+            _ => {
+                if let Some(layout) = find_layout_node(child) {
+                    return Some(layout);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Parses the key-value pairs out of a `layout(...)` qualifier node.
+pub fn parse_layout_qualifiers(layout: Node, source: &str) -> Vec<LayoutQualifier> {
+    let text = &source[layout.start_byte()..layout.end_byte()];
+    let base = layout.start_byte();
+
+    let (open, close) = match (text.find('('), text.rfind(')')) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return vec![],
+    };
+
+    let mut qualifiers = vec![];
+
+    // split on top-level commas only, values may be expressions containing parentheses
+    let mut depth = 0;
+    let mut part_start = open + 1;
+    for (i, c) in text[..=close].char_indices().skip_while(|(i, _)| *i <= open) {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ',' | ')' if depth == 0 => {
+                if let Some(qualifier) = parse_qualifier(&text[part_start..i], base + part_start) {
+                    qualifiers.push(qualifier);
+                }
+                part_start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    qualifiers
+}
+
+fn parse_qualifier(part: &str, offset: usize) -> Option<LayoutQualifier> {
+    let (key_part, value_part) = match part.split_once('=') {
+        Some((key, value)) => (key, Some((value, offset + key.len() + 1))),
+        None => (part, None),
+    };
+
+    let key = key_part.trim();
+    if key.is_empty() {
+        return None;
+    }
+    let key_start = offset + (key_part.len() - key_part.trim_start().len());
+
+    let (value, value_span) = match value_part {
+        Some((value_part, value_offset)) => {
+            let value = value_part.trim();
+            let value_start = value_offset + (value_part.len() - value_part.trim_start().len());
+            (Some(value.to_string()), Some(value_start..value_start + value.len()))
+        }
+        None => (None, None),
+    };
+
+    Some(LayoutQualifier {
+        key: key.to_string(),
+        value,
+        key_span: key_start..key_start + key.len(),
+        value_span,
+    })
+}
+
+#[cfg(test)]
+mod layout_test {
+    use tree_sitter::Parser;
+
+    use crate::layout::{find_layout_node, parse_layout_qualifiers};
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_parse_layout_qualifiers() {
+        let source = "layout(location = 0, binding=2) out vec4 color;\nlayout (std140) uniform Block { float a; };\n";
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let first = tree.root_node().named_child(0).unwrap();
+        let qualifiers = parse_layout_qualifiers(find_layout_node(first).unwrap(), source);
+
+        assert_eq!(qualifiers.len(), 2);
+        assert_eq!(qualifiers[0].key, "location");
+        assert_eq!(qualifiers[0].value.as_deref(), Some("0"));
+        assert_eq!(&source[qualifiers[0].key_span.clone()], "location");
+        assert_eq!(&source[qualifiers[0].value_span.clone().unwrap()], "0");
+        assert_eq!(qualifiers[1].key, "binding");
+        assert_eq!(qualifiers[1].value.as_deref(), Some("2"));
+
+        let second = tree.root_node().named_child(1).unwrap();
+        let qualifiers = parse_layout_qualifiers(find_layout_node(second).unwrap(), source);
+
+        assert_eq!(qualifiers.len(), 1);
+        assert_eq!(qualifiers[0].key, "std140");
+        assert_eq!(qualifiers[0].value, None);
+    }
+}
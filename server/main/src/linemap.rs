@@ -1,6 +1,12 @@
-use rust_lsp::lsp_types::Position;
+use rust_lsp::lsp_types::{Position, Range};
+use tree_sitter::InputEdit;
 
+/// Maps between byte offsets into a source string and LSP positions. Position characters are
+/// counted in UTF-16 code units as required by the LSP spec, while tree-sitter and string
+/// slicing operate on bytes, so all conversions between the two must go through here.
+#[derive(Clone)]
 pub struct LineMap {
+    // the byte offset at which each line starts
     positions: Vec<usize>,
 }
 
@@ -16,16 +22,139 @@ impl LineMap {
         LineMap { positions }
     }
 
-    pub fn offset_for_position(&self, position: Position) -> usize {
-        self.positions[position.line as usize] + (position.character as usize)
+    /// Updates the map of the source before an edit to map the source after it. Only the lines the edit touched are
+    /// split again, while the lines starting past it are shifted by how much it changed the length of the source.
+    pub fn apply_edit(&mut self, new: &str, edit: &InputEdit) {
+        // lines starting up to the start of the edit stay, those starting within the replaced text are gone
+        let kept = self.positions.partition_point(|&start| start <= edit.start_byte);
+        let replaced = self.positions.partition_point(|&start| start <= edit.old_end_byte);
+
+        let inserted: Vec<usize> = new[edit.start_byte..edit.new_end_byte]
+            .match_indices('\n')
+            .map(|(i, _)| edit.start_byte + i + 1)
+            .collect();
+        let inserted_len = inserted.len();
+        self.positions.splice(kept..replaced, inserted);
+
+        for start in &mut self.positions[kept + inserted_len..] {
+            *start = *start - edit.old_end_byte + edit.new_end_byte;
+        }
+    }
+
+    /// Returns the byte offset of the line start for the 0-indexed line, clamped to the end of the source.
+    pub fn line_start(&self, source: &str, line: usize) -> usize {
+        self.positions.get(line).copied().unwrap_or(source.len())
+    }
+
+    fn line_end(&self, source: &str, line: usize) -> usize {
+        self.positions.get(line + 1).copied().unwrap_or(source.len())
+    }
+
+    /// Returns the first and last 0-indexed physical lines of the logical line containing the given line. Lines
+    /// ending in a backslash continue onto the next, as in multi-line macro definitions.
+    pub fn logical_line(&self, source: &str, line: usize) -> (usize, usize) {
+        let mut first = line;
+        while first > 0 && self.continues(source, first - 1) {
+            first -= 1;
+        }
+
+        let mut last = line;
+        while last + 1 < self.positions.len() && self.continues(source, last) {
+            last += 1;
+        }
+
+        (first, last)
+    }
+
+    // whether the line ends in a line continuation.
+    fn continues(&self, source: &str, line: usize) -> bool {
+        let text = &source[self.line_start(source, line)..self.line_end(source, line)];
+        text.trim_end_matches(|c| c == '\n' || c == '\r').ends_with('\\')
+    }
+
+    /// Returns the byte offset for a UTF-16 encoded position. Positions past the end of a line are clamped to it.
+    pub fn offset_for_position(&self, source: &str, position: Position) -> usize {
+        let line_start = self.line_start(source, position.line as usize);
+        let line_end = self.line_end(source, position.line as usize);
+        // the line's text without its line break, so columns past its end stay on the line
+        let line = source[line_start..line_end].trim_end_matches(|c| c == '\n' || c == '\r');
+
+        let mut utf16_count = 0;
+        for (i, char) in line.char_indices() {
+            if utf16_count >= position.character as usize {
+                return line_start + i;
+            }
+            utf16_count += char.len_utf16();
+        }
+
+        line_start + line.len()
+    }
+
+    /// Returns the UTF-16 encoded position for a byte offset.
+    pub fn position_for_offset(&self, source: &str, offset: usize) -> Position {
+        // index of the last line starting at or before the offset
+        let line = match self.positions.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+
+        let line_start = self.positions[line];
+
+        Position {
+            line: line as u32,
+            character: source[line_start..offset.min(source.len())].encode_utf16().count() as u32,
+        }
+    }
+}
+
+/// Maps positions in a source from before an edit to where the same text is after it, keeping results held onto
+/// across edits, such as the diagnostics last published, pointing at what they were found at.
+pub struct PositionShift<'a> {
+    old: &'a str,
+    new: &'a str,
+    old_map: &'a LineMap,
+    new_map: &'a LineMap,
+    edit: InputEdit,
+}
+
+impl<'a> PositionShift<'a> {
+    /// Shifts through the edit from the old source to the new, each given with its line map.
+    pub fn new(old: &'a str, old_map: &'a LineMap, new: &'a str, new_map: &'a LineMap, edit: InputEdit) -> Self {
+        PositionShift {
+            old,
+            new,
+            old_map,
+            new_map,
+            edit,
+        }
+    }
+
+    /// Returns the position after the edit of the text at the position before it. Text ahead of the edit stays
+    /// where it is and text past it moves with it, while the text the edit replaced is gone, so positions within
+    /// it are clamped to the start of the edit.
+    pub fn position(&self, position: Position) -> Position {
+        let offset = self.old_map.offset_for_position(self.old, position);
+        let shifted = if offset <= self.edit.start_byte {
+            offset
+        } else if offset >= self.edit.old_end_byte {
+            offset - self.edit.old_end_byte + self.edit.new_end_byte
+        } else {
+            self.edit.start_byte
+        };
+        self.new_map.position_for_offset(self.new, shifted)
+    }
+
+    pub fn range(&self, range: Range) -> Range {
+        Range::new(self.position(range.start), self.position(range.end))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use rust_lsp::lsp_types::Position;
+    use rust_lsp::lsp_types::{Position, Range};
 
-    use crate::linemap::LineMap;
+    use crate::linemap::{LineMap, PositionShift};
+    use crate::lint_cache::input_edit;
 
     #[test]
     #[logging_macro::log_scope]
@@ -67,14 +196,139 @@ mod test {
                 pos: Position { line: 1, character: 0 },
                 offset: 8,
             },
+            Test {
+                string: "sämple\ntext",
+                pos: Position { line: 0, character: 2 },
+                offset: 3,
+            },
+            Test {
+                string: "// 🍔\nfloat x;",
+                pos: Position { line: 0, character: 5 },
+                offset: 7,
+            },
+            Test {
+                string: "short\ntext",
+                pos: Position { line: 0, character: 100 },
+                offset: 5,
+            },
+            Test {
+                string: "short\r\ntext",
+                pos: Position { line: 0, character: 100 },
+                offset: 5,
+            },
+            Test {
+                string: "short\ntext",
+                pos: Position { line: 1, character: 100 },
+                offset: 10,
+            },
         ];
 
         for case in cases {
             let linemap = LineMap::new(case.string);
 
-            let offset = linemap.offset_for_position(case.pos);
+            let offset = linemap.offset_for_position(case.string, case.pos);
 
             assert_eq!(offset, case.offset, "{:?}", case.string);
         }
     }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_linemap_position_for_offset() {
+        let source = "sämple\n// 🍔 text";
+        let linemap = LineMap::new(source);
+
+        assert_eq!(linemap.position_for_offset(source, 0), Position { line: 0, character: 0 });
+        assert_eq!(linemap.position_for_offset(source, 3), Position { line: 0, character: 2 });
+        assert_eq!(linemap.position_for_offset(source, 8), Position { line: 1, character: 0 });
+        assert_eq!(linemap.position_for_offset(source, 16), Position { line: 1, character: 6 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_linemap_logical_line() {
+        let source = "#define BLUR(x) \\\n    (x * 0.5 + \\\r\n     x * 0.25)\nfloat y = 1.0; \\ not a continuation\n";
+        let linemap = LineMap::new(source);
+
+        assert_eq!(linemap.logical_line(source, 0), (0, 2));
+        assert_eq!(linemap.logical_line(source, 1), (0, 2));
+        assert_eq!(linemap.logical_line(source, 2), (0, 2));
+        assert_eq!(linemap.logical_line(source, 3), (3, 3));
+
+        // positions are physical, the continuation doesn't shift the lines following it
+        assert_eq!(
+            linemap.position_for_offset(source, source.find("float").unwrap()),
+            Position { line: 3, character: 0 }
+        );
+        assert_eq!(
+            linemap.offset_for_position(source, Position { line: 2, character: 5 }),
+            source.find("x * 0.25").unwrap()
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_linemap_apply_edit() {
+        // a fixed xorshift generator, for edits that are random but the same on every run
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        let pieces = ["a", "float", " ", "\n", "\r\n", "é", "🍔"];
+        let mut source = String::new();
+        let mut linemap = LineMap::new(&source);
+
+        for _ in 0..1000 {
+            let boundaries: Vec<usize> = (0..=source.len()).filter(|&i| source.is_char_boundary(i)).collect();
+            let mut start = boundaries[random(boundaries.len())];
+            let mut end = boundaries[random(boundaries.len())];
+            if start > end {
+                std::mem::swap(&mut start, &mut end);
+            }
+            let inserted: String = (0..random(4)).map(|_| pieces[random(pieces.len())]).collect();
+
+            let new = format!("{}{}{}", &source[..start], inserted, &source[end..]);
+            linemap.apply_edit(&new, &input_edit(&source, &new));
+            source = new;
+
+            assert_eq!(linemap.positions, LineMap::new(&source).positions, "{:?}", source);
+        }
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_position_shift() {
+        let shift = |old: &str, new: &str, position: Position| {
+            let (old_map, new_map) = (LineMap::new(old), LineMap::new(new));
+            PositionShift::new(old, &old_map, new, &new_map, input_edit(old, new)).position(position)
+        };
+
+        // lines inserted ahead of the position move it down, while edits past it leave it
+        let old = "float a = 1.0;\nfloat b = a;\n";
+        let new = "float a = 1.0;\n// scale\nfloat b = a;\n";
+        assert_eq!(shift(old, new, Position::new(1, 10)), Position::new(2, 10));
+        assert_eq!(shift(old, new, Position::new(0, 6)), Position::new(0, 6));
+
+        // within the replaced text the position is clamped to the start of the edit
+        let old = "float alpha = 1.0;";
+        let new = "float x = 1.0;";
+        assert_eq!(shift(old, new, Position::new(0, 8)), Position::new(0, 6));
+        assert_eq!(shift(old, new, Position::new(0, 12)), Position::new(0, 8));
+
+        // characters are counted in UTF-16 on both sides of the edit
+        let old = "/* é */ a;";
+        let new = "/* éé */ a;";
+        assert_eq!(shift(old, new, Position::new(0, 8)), Position::new(0, 9));
+
+        let (old_map, new_map) = (LineMap::new(old), LineMap::new(new));
+        let shift = PositionShift::new(old, &old_map, new, &new_map, input_edit(old, new));
+        assert_eq!(
+            shift.range(Range::new(Position::new(0, 3), Position::new(0, 9))),
+            Range::new(Position::new(0, 3), Position::new(0, 10))
+        );
+    }
 }
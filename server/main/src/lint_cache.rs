@@ -0,0 +1,333 @@
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Diagnostic, Position};
+use slog_scope::debug;
+use tree_sitter::{InputEdit, Node, Parser, Point, Tree};
+
+use crate::{configuration::Configuration, lints, navigation::ParserContext};
+
+// past this fraction of the file changed, relinting everything is cheaper than working out what can be kept.
+const FULL_RELINT_RATIO: f32 = 0.5;
+
+struct CachedFile {
+    source: String,
+    tree: Tree,
+    // the diagnostics of each top-level function in the tree, in order, with positions relative to the function.
+    functions: Vec<Vec<Diagnostic>>,
+}
+
+/// Caches the syntax tree lints of each file so that relinting after a change only rechecks the functions it
+/// touched. The change is found by diffing the new source against the cached one, and reparsing incrementally
+/// from the cached tree so that `Tree::changed_ranges` reports where the syntax differs. Functions outside the
+/// changed ranges keep their cached diagnostics, moved to wherever the function now is.
+#[derive(Default)]
+pub struct LintCache {
+    files: HashMap<PathBuf, CachedFile>,
+    // the number of functions linted by the last call, for tests to check what was reused.
+    relinted: usize,
+}
+
+impl LintCache {
+    /// Lints the file, checking its calls against the functions declared in the files it includes besides its own,
+    /// see `lints::argument_counts`.
+    pub fn lint(
+        &mut self, parser: &mut Parser, path: &Path, config: &Configuration, included: &[lints::FunctionArity],
+    ) -> Result<Vec<Diagnostic>> {
+        let source = fs::read_to_string(path)?;
+        self.lint_source(parser, path, source, config, included)
+    }
+
+    /// Drops all cached diagnostics, e.g. when the configuration of the lints changes.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    /// Drops the cached diagnostics of a file that was changed or deleted outside of the editor.
+    pub fn evict(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    fn lint_source(
+        &mut self, parser: &mut Parser, path: &Path, source: String, config: &Configuration, included: &[lints::FunctionArity],
+    ) -> Result<Vec<Diagnostic>> {
+        self.relinted = 0;
+
+        let (ctx, mut reusable, changed) = match self.files.remove(path) {
+            Some(mut cached) => {
+                let edit = input_edit(&cached.source, &source);
+                cached.tree.edit(&edit);
+
+                let ctx = ParserContext::reparse(parser, source, &cached.tree)?;
+
+                let mut changed: Vec<Range<usize>> = cached
+                    .tree
+                    .changed_ranges(ctx.tree())
+                    .map(|range| range.start_byte..range.end_byte)
+                    .collect();
+                // edits within a token, e.g. changing a literal, don't change the syntax but still need relinting
+                changed.push(edit.start_byte..edit.new_end_byte);
+
+                // the old tree now has the positions of the new source, so unchanged functions line up by span
+                let reusable: HashMap<Range<usize>, Vec<Diagnostic>> = top_level_functions(cached.tree.root_node())
+                    .into_iter()
+                    .map(|function| function.byte_range())
+                    .zip(cached.functions)
+                    .collect();
+
+                (ctx, reusable, changed)
+            }
+            None => (ParserContext::from_source(parser, source)?, HashMap::new(), vec![]),
+        };
+
+        let changed_bytes: usize = changed.iter().map(|range| range.len()).sum();
+        if changed_bytes as f32 > ctx.source().len() as f32 * FULL_RELINT_RATIO {
+            debug!("change too broad for incremental linting"; "path" => path.to_str().unwrap(), "changed" => changed_bytes);
+            reusable.clear();
+        }
+
+        let mut diagnostics = vec![];
+        let mut functions = vec![];
+
+        let root = ctx.root_node();
+        let mut cursor = root.walk();
+        for node in root.children(&mut cursor) {
+            if node.kind() != "function_definition" {
+                diagnostics.extend(lints::lint_node(&ctx, path, node));
+                continue;
+            }
+
+            let overlaps_change = changed
+                .iter()
+                .any(|range| range.start < node.end_byte() && node.start_byte() < range.end);
+            let relative = match reusable.remove(&node.byte_range()) {
+                Some(relative) if !overlaps_change => relative,
+                _ => {
+                    self.relinted += 1;
+                    let origin = ctx.range_for_node(&node).start;
+                    lints::lint_node(&ctx, path, node)
+                        .into_iter()
+                        .map(|diagnostic| relative_to(diagnostic, origin))
+                        .collect()
+                }
+            };
+
+            let origin = ctx.range_for_node(&node).start;
+            diagnostics.extend(relative.iter().cloned().map(|diagnostic| absolute_from(diagnostic, origin)));
+            functions.push(relative);
+        }
+
+        diagnostics.extend(lints::lint_file_wide(&ctx, path, config, included));
+
+        debug!("linted syntax tree"; "path" => path.to_str().unwrap(), "functions" => functions.len(), "relinted" => self.relinted);
+
+        self.files.insert(
+            path.to_path_buf(),
+            CachedFile {
+                source: ctx.source().to_string(),
+                tree: ctx.tree().clone(),
+                functions,
+            },
+        );
+
+        Ok(diagnostics)
+    }
+}
+
+fn top_level_functions(root: Node) -> Vec<Node> {
+    let mut cursor = root.walk();
+    root.children(&mut cursor)
+        .filter(|node| node.kind() == "function_definition")
+        .collect()
+}
+
+/// Describes the change from the old to the new source as a single edit spanning everything between their common
+/// prefix and suffix.
+pub fn input_edit(old: &str, new: &str) -> InputEdit {
+    let mut prefix = old.bytes().zip(new.bytes()).take_while(|(o, n)| o == n).count();
+    while !old.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(o, n)| o == n)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old.len() - suffix,
+        new_end_byte: new.len() - suffix,
+        start_position: point_at(old, prefix),
+        old_end_position: point_at(old, old.len() - suffix),
+        new_end_position: point_at(new, new.len() - suffix),
+    }
+}
+
+fn point_at(source: &str, offset: usize) -> Point {
+    let before = &source[..offset];
+    let row = before.matches('\n').count();
+    let column = offset - before.rfind('\n').map_or(0, |i| i + 1);
+    Point { row, column }
+}
+
+// positions on the same line as the origin have their character made relative to it, later lines only their line.
+fn relative_to(mut diagnostic: Diagnostic, origin: Position) -> Diagnostic {
+    let relative = |position: Position| {
+        if position.line == origin.line {
+            Position::new(0, position.character - origin.character)
+        } else {
+            Position::new(position.line - origin.line, position.character)
+        }
+    };
+    diagnostic.range.start = relative(diagnostic.range.start);
+    diagnostic.range.end = relative(diagnostic.range.end);
+    // related information within the function moves along with it
+    for related in diagnostic.related_information.iter_mut().flatten() {
+        related.location.range.start = relative(related.location.range.start);
+        related.location.range.end = relative(related.location.range.end);
+    }
+    diagnostic
+}
+
+fn absolute_from(mut diagnostic: Diagnostic, origin: Position) -> Diagnostic {
+    let absolute = |position: Position| {
+        if position.line == 0 {
+            Position::new(origin.line, origin.character + position.character)
+        } else {
+            Position::new(origin.line + position.line, position.character)
+        }
+    };
+    diagnostic.range.start = absolute(diagnostic.range.start);
+    diagnostic.range.end = absolute(diagnostic.range.end);
+    for related in diagnostic.related_information.iter_mut().flatten() {
+        related.location.range.start = absolute(related.location.range.start);
+        related.location.range.end = absolute(related.location.range.end);
+    }
+    diagnostic
+}
+
+#[cfg(test)]
+mod lint_cache_test {
+    use std::env::temp_dir;
+
+    use rust_lsp::lsp_types::{Diagnostic, NumberOrString, Position};
+    use tree_sitter::Parser;
+
+    use crate::configuration::Configuration;
+    use crate::lint_cache::LintCache;
+
+    fn unreachable_lines(diagnostics: &[Diagnostic]) -> Vec<u32> {
+        let mut lines: Vec<u32> = diagnostics
+            .iter()
+            .filter(|d| d.code == Some(NumberOrString::String("unreachable-code".into())))
+            .map(|d| d.range.start.line)
+            .collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_unchanged_functions_keep_diagnostics() {
+        let source = r#"float a() {
+    return 1.0;
+    float dead = 0.0;
+}
+
+float b() {
+    return 2.0;
+    float dead = 0.0;
+}
+
+float c() {
+    return 3.0;
+    discard;
+}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let mut cache = LintCache::default();
+        let path = temp_dir().join("test.fsh");
+        let config = Configuration::default();
+
+        let diagnostics = cache.lint_source(&mut parser, &path, source.to_string(), &config, &[]).unwrap();
+        assert_eq!(cache.relinted, 3);
+        assert_eq!(unreachable_lines(&diagnostics), vec![2, 7, 12]);
+
+        // fixing `b` only relints `b`
+        let fixed = source.replace(
+            "    return 2.0;\n    float dead = 0.0;\n",
+            "    float live = 0.0;\n    return 2.0;\n",
+        );
+        let diagnostics = cache.lint_source(&mut parser, &path, fixed.clone(), &config, &[]).unwrap();
+        assert_eq!(cache.relinted, 1);
+        assert_eq!(unreachable_lines(&diagnostics), vec![2, 12]);
+
+        // lines added to `b` move the cached diagnostics of the functions after it
+        let edited = fixed.replace("float b() {\n", "float b() {\n    // a comment\n\n");
+        let diagnostics = cache.lint_source(&mut parser, &path, edited.clone(), &config, &[]).unwrap();
+        assert_eq!(cache.relinted, 1);
+        assert_eq!(unreachable_lines(&diagnostics), vec![2, 14]);
+
+        // text inserted in front of a function leaves it untouched
+        let edited = edited.replacen("float a() {", "const float k = 1.0; float a() {", 1);
+        let diagnostics = cache.lint_source(&mut parser, &path, edited, &config, &[]).unwrap();
+        assert_eq!(cache.relinted, 0);
+        assert_eq!(unreachable_lines(&diagnostics), vec![2, 14]);
+        let a = diagnostics.iter().find(|d| d.range.start.line == 2).unwrap();
+        assert_eq!(a.range.start, Position::new(2, 4));
+
+        // rewriting the whole file relints everything
+        let diagnostics = cache
+            .lint_source(&mut parser, &path, "void main() {}\n".to_string(), &config, &[])
+            .unwrap();
+        assert_eq!(cache.relinted, 1);
+        assert!(unreachable_lines(&diagnostics).is_empty());
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_integer_divisions_follow_declarations() {
+        let source = "float scale;\n\nvoid main() {\n    scale = 1 / 2;\n}\n";
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let mut cache = LintCache::default();
+        let path = temp_dir().join("test.fsh");
+        let config = Configuration {
+            integer_division_hint: true,
+            ..Configuration::default()
+        };
+
+        let divisions = |diagnostics: &[Diagnostic]| {
+            diagnostics
+                .iter()
+                .filter(|d| d.code == Some(NumberOrString::String("integer-division".into())))
+                .count()
+        };
+
+        let diagnostics = cache.lint_source(&mut parser, &path, source.to_string(), &config, &[]).unwrap();
+        assert_eq!(divisions(&diagnostics), 1);
+
+        // the declaration changing outside of `main` leaves `main` cached, but not its division
+        let diagnostics = cache
+            .lint_source(&mut parser, &path, source.replace("float scale", "int scale"), &config, &[])
+            .unwrap();
+        assert_eq!(cache.relinted, 0);
+        assert_eq!(divisions(&diagnostics), 0);
+    }
+}
@@ -0,0 +1,1079 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_lsp::lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location, NumberOrString, Range};
+use slog_scope::{debug, error, warn};
+use tree_sitter::{Node, Query, QueryCursor};
+
+use crate::{builtins, configuration::Configuration, consts, interface, navigation::ParserContext, preprocessor, url_norm::url_for};
+
+lazy_static! {
+    static ref RE_INTEGER_LITERAL: Regex = Regex::new(r"^\d+$").unwrap();
+}
+
+const LIST_FUNCTION_SIGNATURES_STR: &str = r#"
+    (declaration
+        (function_declarator) @prototype)
+
+    (function_definition
+        (function_declarator) @definition)
+"#;
+
+const LIST_CALLS_STR: &str = r#"
+    (call_expression
+        function: (identifier) @function
+        arguments: (argument_list) @arguments)
+"#;
+
+const LIST_FIELD_LISTS_STR: &str = r#"
+    (field_declaration_list) @fields
+"#;
+
+/// Runs the syntax tree based lints that only look at the node and its descendants, such that the diagnostics of
+/// e.g. a function can't be affected by changes elsewhere in the file.
+pub fn lint_node(ctx: &ParserContext, path: &Path, node: Node) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    diagnostics.extend(syntax_errors(ctx, path, node));
+    diagnostics.extend(unreachable_code(ctx, node));
+    diagnostics.extend(duplicate_fields(ctx, path, node));
+
+    diagnostics
+}
+
+/// Runs the lints enabled in the configuration that compare declarations across the whole file, or look up the
+/// declarations of what a node uses, as integer divisions do with the variables they're assigned to. Calls are
+/// checked against the functions `included` as well, see `argument_counts`.
+pub fn lint_file_wide(ctx: &ParserContext, path: &Path, config: &Configuration, included: &[FunctionArity]) -> Vec<Diagnostic> {
+    let mut diagnostics = match prototype_mismatches(ctx, path) {
+        Ok(diagnostics) => diagnostics,
+        Err(e) => {
+            error!("error checking function prototypes"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap());
+            vec![]
+        }
+    };
+
+    match argument_counts(ctx, path, included) {
+        Ok(argument_diagnostics) => diagnostics.extend(argument_diagnostics),
+        Err(e) => error!("error checking argument counts"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap()),
+    }
+
+    match interface::location_conflict_diagnostics(path, &interface::interface_variables(ctx)) {
+        Ok(location_diagnostics) => diagnostics.extend(location_diagnostics),
+        Err(e) => error!("error checking layout locations"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap()),
+    }
+
+    if config.integer_division_hint {
+        diagnostics.extend(integer_divisions(ctx, ctx.root_node()));
+    }
+
+    diagnostics
+}
+
+#[derive(PartialEq, Eq)]
+struct Parameter {
+    // the parameter type, including any array dimensions
+    ty: String,
+    // storage and precision qualifiers e.g. `in`, `out`, `highp`, sorted as their order is not significant
+    qualifiers: Vec<String>,
+}
+
+struct Signature<'tree> {
+    name: String,
+    parameters: Vec<(Node<'tree>, Parameter)>,
+}
+
+impl<'tree> Signature<'tree> {
+    fn new(declarator: Node<'tree>, source: &str) -> Option<Self> {
+        let name = declarator
+            .child_by_field_name("declarator")?
+            .utf8_text(source.as_bytes())
+            .ok()?
+            .to_string();
+
+        let parameter_list = declarator.child_by_field_name("parameters")?;
+        let mut cursor = parameter_list.walk();
+        let parameters = parameter_list
+            .named_children(&mut cursor)
+            .filter(|param| param.kind() == "parameter_declaration")
+            .map(|param| (param, Parameter::new(param, source)))
+            .collect();
+
+        Some(Signature { name, parameters })
+    }
+
+    fn types_match(&self, other: &Signature) -> bool {
+        self.parameters.len() == other.parameters.len() && self.parameters.iter().zip(&other.parameters).all(|(l, r)| l.1.ty == r.1.ty)
+    }
+
+    fn matches(&self, other: &Signature) -> bool {
+        self.parameters.len() == other.parameters.len() && self.parameters.iter().zip(&other.parameters).all(|(l, r)| l.1 == r.1)
+    }
+}
+
+impl Parameter {
+    fn new(node: Node, source: &str) -> Self {
+        let type_node = node.child_by_field_name("type");
+        let declarator = node.child_by_field_name("declarator");
+
+        let mut ty = String::new();
+        let mut array_suffix = String::new();
+        let mut qualifiers = vec![];
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.kind() == "comment" {
+                continue;
+            }
+            let text = strip_whitespace(child.utf8_text(source.as_bytes()).unwrap());
+            if Some(child) == type_node {
+                ty = text;
+            } else if Some(child) == declarator {
+                // the parameter name is irrelevant to the signature, but any array dimensions are not
+                if let Some(ident) = innermost_declarator(child) {
+                    array_suffix = strip_whitespace(&source[ident.end_byte()..child.end_byte()]);
+                }
+            } else {
+                qualifiers.push(text);
+            }
+        }
+
+        qualifiers.sort();
+
+        Parameter {
+            ty: ty + &array_suffix,
+            qualifiers,
+        }
+    }
+}
+
+fn innermost_declarator(node: Node) -> Option<Node> {
+    let mut node = node;
+    while node.kind() != "identifier" {
+        node = node.child_by_field_name("declarator")?;
+    }
+    Some(node)
+}
+
+// strips all whitespace so that only genuine signature differences are compared, not formatting.
+fn strip_whitespace(text: &str) -> String {
+    text.split_whitespace().collect()
+}
+
+/// Compares the signatures of function prototypes against their definitions. A definition whose parameter types
+/// match a prototype but whose qualifiers differ is an error, while a lone definition with the same arity but
+/// different parameter types is most likely a prototype that was not updated alongside its definition.
+pub fn prototype_mismatches(ctx: &ParserContext, path: &Path) -> Result<Vec<Diagnostic>> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_FUNCTION_SIGNATURES_STR)?;
+    let mut query_cursor = QueryCursor::new();
+
+    let mut prototypes = vec![];
+    let mut definitions = vec![];
+
+    for m in query_cursor.matches(&query, ctx.root_node(), ctx.source().as_bytes()) {
+        for capture in m.captures {
+            let signature = match Signature::new(capture.node, ctx.source()) {
+                Some(s) => s,
+                None => continue,
+            };
+            match query.capture_names()[capture.index as usize].as_str() {
+                "prototype" => prototypes.push(signature),
+                _ => definitions.push(signature),
+            }
+        }
+    }
+
+    let uri = url_for(path)?;
+    let mut diagnostics = vec![];
+
+    for prototype in &prototypes {
+        let candidates: Vec<&Signature> = definitions
+            .iter()
+            .filter(|def| def.name == prototype.name && def.parameters.len() == prototype.parameters.len())
+            .collect();
+
+        if candidates.is_empty() || candidates.iter().any(|def| def.matches(prototype)) {
+            continue;
+        }
+
+        debug!("found mismatched function prototype"; "function" => &prototype.name);
+
+        let (definition, severity) = match candidates.iter().find(|def| def.types_match(prototype)) {
+            Some(def) => (*def, DiagnosticSeverity::ERROR),
+            None if candidates.len() == 1 => (candidates[0], DiagnosticSeverity::WARNING),
+            None => continue,
+        };
+
+        for (i, (def_param, proto_param)) in definition.parameters.iter().zip(&prototype.parameters).enumerate() {
+            if def_param.1 == proto_param.1 {
+                continue;
+            }
+
+            let describe = |param: &Parameter| {
+                let mut parts = param.qualifiers.clone();
+                parts.push(param.ty.clone());
+                parts.join(" ")
+            };
+
+            diagnostics.push(new_diagnostic(
+                ctx.range_for_node(&def_param.0),
+                severity,
+                "prototype-mismatch",
+                format!(
+                    "parameter {} of `{}` is declared as `{}` but its prototype declares it as `{}`",
+                    i + 1,
+                    definition.name,
+                    describe(&def_param.1),
+                    describe(&proto_param.1)
+                ),
+                Some(vec![DiagnosticRelatedInformation {
+                    location: Location {
+                        uri: uri.clone(),
+                        range: ctx.range_for_node(&proto_param.0),
+                    },
+                    message: "prototype declared here".into(),
+                }]),
+            ));
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// A function declared in a file by the number of arguments it takes, for the calls to it to be checked against,
+/// see `argument_counts`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionArity {
+    pub name: String,
+    pub arity: usize,
+    pub location: Location,
+    pub is_prototype: bool,
+}
+
+/// Lists the functions declared in the file, by their definitions and prototypes alike.
+pub fn function_arities(ctx: &ParserContext, path: &Path) -> Result<Vec<FunctionArity>> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_FUNCTION_SIGNATURES_STR)?;
+    let mut query_cursor = QueryCursor::new();
+
+    let uri = url_for(path)?;
+    let mut functions = vec![];
+    for m in query_cursor.matches(&query, ctx.root_node(), ctx.source().as_bytes()) {
+        for capture in m.captures {
+            let signature = match Signature::new(capture.node, ctx.source()) {
+                Some(s) => s,
+                None => continue,
+            };
+            functions.push(FunctionArity {
+                arity: parameter_count(ctx, &signature),
+                name: signature.name,
+                location: Location {
+                    uri: uri.clone(),
+                    range: ctx.range_for_node(&capture.node),
+                },
+                is_prototype: query.capture_names()[capture.index as usize] == "prototype",
+            });
+        }
+    }
+
+    Ok(functions)
+}
+
+/// Flags the calls to functions declared in the file, or in the files it includes as given by `included`, that pass
+/// more or fewer arguments than the function takes, as GLSL has no default arguments to make up for missing ones.
+/// An overloaded function is only flagged where none of its overloads take as many arguments as the call passes.
+/// Functions declared only in files including the file, built-ins, constructors and function-like macros aren't
+/// checked, as neither the file nor its includes tell what they take.
+pub fn argument_counts(ctx: &ParserContext, path: &Path, included: &[FunctionArity]) -> Result<Vec<Diagnostic>> {
+    // the declarations of each function, the file's own first, with the prototypes after the definitions as
+    // they're only pointed to where the function has no definition
+    let mut functions: HashMap<String, Vec<FunctionArity>> = HashMap::new();
+    for function in function_arities(ctx, path)?.into_iter().chain(included.iter().cloned()) {
+        functions.entry(function.name.clone()).or_default().push(function);
+    }
+    for declarations in functions.values_mut() {
+        declarations.sort_by_key(|function| function.is_prototype);
+    }
+
+    let query = Query::new(tree_sitter_glsl::language(), LIST_CALLS_STR)?;
+    let mut query_cursor = QueryCursor::new();
+
+    let mut diagnostics = vec![];
+
+    for m in query_cursor.matches(&query, ctx.root_node(), ctx.source().as_bytes()) {
+        let (function, arguments) = (m.captures[0].node, m.captures[1].node);
+        let name = node_text(ctx, Some(function));
+
+        let declarations = match functions.get(name) {
+            Some(declarations) => declarations,
+            None => continue,
+        };
+        if builtins::is_builtin(name) || preprocessor::find_define(ctx, name).is_some() {
+            continue;
+        }
+
+        let mut cursor = arguments.walk();
+        let count = arguments.named_children(&mut cursor).filter(|a| a.kind() != "comment").count();
+        if declarations.iter().any(|function| function.arity == count) {
+            continue;
+        }
+
+        debug!("found call with mismatched argument count"; "function" => name, "arguments" => count);
+
+        // a prototype declares the same function its definition does, rather than an overload of it
+        let mut arities: Vec<usize> = declarations.iter().map(|function| function.arity).collect();
+        arities.sort_unstable();
+        arities.dedup();
+
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let message = match arities.as_slice() {
+            [arity] => format!(
+                "too {} arguments to `{}`: it takes {} argument{} but {} {} given",
+                if count > *arity { "many" } else { "few" },
+                name,
+                arity,
+                plural(*arity),
+                count,
+                if count == 1 { "was" } else { "were" }
+            ),
+            _ => format!("no overload of `{}` takes {} argument{}", name, count, plural(count)),
+        };
+
+        let has_definition = declarations.iter().any(|function| !function.is_prototype);
+        let related = declarations
+            .iter()
+            .filter(|function| !has_definition || !function.is_prototype)
+            .map(|function| DiagnosticRelatedInformation {
+                location: function.location.clone(),
+                message: format!(
+                    "`{}` declared here taking {} argument{}",
+                    name,
+                    function.arity,
+                    plural(function.arity)
+                ),
+            })
+            .collect();
+
+        diagnostics.push(new_diagnostic(
+            ctx.range_for_node(&arguments),
+            DiagnosticSeverity::ERROR,
+            "argument-count",
+            message,
+            Some(related),
+        ));
+    }
+
+    Ok(diagnostics)
+}
+
+// the number of arguments a function takes, where a parameter list of just `void` as in `float noise(void)` takes none.
+fn parameter_count(ctx: &ParserContext, signature: &Signature) -> usize {
+    match signature.parameters.as_slice() {
+        [(parameter, _)] if parameter.child_by_field_name("declarator").is_none() => {
+            usize::from(node_text(ctx, parameter.child_by_field_name("type")) != "void")
+        }
+        parameters => parameters.len(),
+    }
+}
+
+/// Flags the fields of structs declared more than once, as happens when copying a field without renaming it, with
+/// the first declaration of the name as related information. This includes fields declared together on one line,
+/// as in `float a, a;`.
+pub fn duplicate_fields(ctx: &ParserContext, path: &Path, node: Node) -> Vec<Diagnostic> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_FIELD_LISTS_STR).unwrap();
+    let mut query_cursor = QueryCursor::new();
+
+    let uri = match url_for(path) {
+        Ok(uri) => uri,
+        Err(e) => {
+            error!("error converting path to url"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap());
+            return vec![];
+        }
+    };
+    let source = ctx.source().as_bytes();
+    let mut diagnostics = vec![];
+
+    for m in query_cursor.matches(&query, node, source) {
+        for capture in m.captures {
+            let mut first: HashMap<&str, Node> = HashMap::new();
+
+            let mut cursor = capture.node.walk();
+            let declarators: Vec<Node> = capture
+                .node
+                .named_children(&mut cursor)
+                .filter(|declaration| declaration.kind() == "field_declaration")
+                .flat_map(|declaration| {
+                    let mut cursor = declaration.walk();
+                    declaration.children_by_field_name("declarator", &mut cursor).collect::<Vec<_>>()
+                })
+                .collect();
+
+            for declarator in declarators {
+                // array fields are named by the declarator they wrap, as in `vec3 normals[4]`
+                let mut field = declarator;
+                while let Some(inner) = field.child_by_field_name("declarator") {
+                    field = inner;
+                }
+                let name = field.utf8_text(source).unwrap();
+
+                let original = match first.get(name) {
+                    Some(original) => *original,
+                    None => {
+                        first.insert(name, field);
+                        continue;
+                    }
+                };
+
+                debug!("found duplicate struct field"; "field" => name);
+
+                diagnostics.push(new_diagnostic(
+                    ctx.range_for_node(&field),
+                    DiagnosticSeverity::ERROR,
+                    "duplicate-field",
+                    format!("field `{}` is already declared in this struct", name),
+                    Some(vec![DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: uri.clone(),
+                            range: ctx.range_for_node(&original),
+                        },
+                        message: "first declared here".into(),
+                    }]),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+// the maximum length of the offending source included in a syntax error message.
+const SNIPPET_LENGTH: usize = 24;
+
+/// Reports the error and missing nodes tree-sitter recovered from while parsing. Each diagnostic spans only the
+/// offending node, but its message names the construct the error occurred in, e.g. "unexpected `)` inside
+/// function body `main`".
+pub fn syntax_errors(ctx: &ParserContext, path: &Path, node: Node) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    collect_syntax_errors(ctx, path, node, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_syntax_errors(ctx: &ParserContext, path: &Path, node: Node, diagnostics: &mut Vec<Diagnostic>) {
+    if node.is_error() || node.is_missing() {
+        let message = if node.is_missing() {
+            format!("missing `{}` {}", node.kind(), describe_context(ctx, node))
+        } else {
+            format!("unexpected {} {}", snippet(ctx, node), describe_context(ctx, node))
+        };
+
+        warn!("syntax error in file"; "path" => path.to_str().unwrap(), "message" => &message, "line" => node.start_position().row);
+
+        diagnostics.push(new_diagnostic(
+            ctx.range_for_node(&node),
+            DiagnosticSeverity::ERROR,
+            "syntax-error",
+            message,
+            None,
+        ));
+        return;
+    }
+
+    if !node.has_error() {
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_syntax_errors(ctx, path, child, diagnostics);
+    }
+}
+
+fn snippet(ctx: &ParserContext, node: Node) -> String {
+    let text = node.utf8_text(ctx.source().as_bytes()).unwrap_or_default();
+    let text: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.is_empty() {
+        return "token".into();
+    }
+
+    match text.char_indices().nth(SNIPPET_LENGTH) {
+        Some((i, _)) => format!("`{}...`", &text[..i]),
+        None => format!("`{}`", text),
+    }
+}
+
+// describes the nearest named construct around an error node, including the function it's in if any.
+fn describe_context(ctx: &ParserContext, node: Node) -> String {
+    let mut parent = node.parent();
+    while let Some(p) = parent.filter(|p| p.is_error()) {
+        parent = p.parent();
+    }
+
+    let parent = match parent {
+        Some(p) if p.kind() != "translation_unit" => p,
+        _ => return "at top level".into(),
+    };
+
+    let mut function = None;
+    let mut ancestor = Some(parent);
+    while let Some(a) = ancestor {
+        if a.kind() == "function_definition" {
+            function = function_name(ctx, a);
+            break;
+        }
+        ancestor = a.parent();
+    }
+
+    let construct = match (parent.kind(), parent.parent().map(|p| p.kind())) {
+        ("compound_statement", Some("function_definition")) => {
+            return format!("inside function body `{}`", function.unwrap_or_default());
+        }
+        ("field_declaration_list", _) => "struct body".to_string(),
+        (kind, _) => kind.replace('_', " "),
+    };
+
+    match function {
+        Some(function) => format!("inside {} in function `{}`", construct, function),
+        None => format!("inside {}", construct),
+    }
+}
+
+fn function_name(ctx: &ParserContext, function: Node) -> Option<String> {
+    let ident = innermost_declarator(function.child_by_field_name("declarator")?)?;
+    ident.utf8_text(ctx.source().as_bytes()).ok().map(String::from)
+}
+
+/// Flags the statements following a `return`, `discard`, `break` or `continue` in the same block, as they can never
+/// run. Each block is analysed separately, so a terminal statement inside a nested block or branch doesn't affect
+/// the statements after that block.
+pub fn unreachable_code(ctx: &ParserContext, node: Node) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    collect_unreachable_code(ctx, node, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_unreachable_code(ctx: &ParserContext, node: Node, diagnostics: &mut Vec<Diagnostic>) {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+
+    if node.kind() == "compound_statement" {
+        let statements: Vec<Node> = children.iter().copied().filter(|child| child.kind() != "comment").collect();
+
+        if let Some(terminal) = statements.iter().position(|statement| is_terminal(ctx, *statement)) {
+            if let (Some(first), Some(last)) = (statements.get(terminal + 1), statements.last()) {
+                let mut diagnostic = new_diagnostic(
+                    Range {
+                        start: ctx.range_for_node(first).start,
+                        end: ctx.range_for_node(last).end,
+                    },
+                    DiagnosticSeverity::WARNING,
+                    "unreachable-code",
+                    format!(
+                        "unreachable code after `{}`",
+                        node_text(ctx, Some(statements[terminal])).trim_end_matches(';').trim()
+                    ),
+                    None,
+                );
+                diagnostic.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    for child in children {
+        collect_unreachable_code(ctx, child, diagnostics);
+    }
+}
+
+fn is_terminal(ctx: &ParserContext, statement: Node) -> bool {
+    match statement.kind() {
+        "return_statement" | "break_statement" | "continue_statement" | "discard_statement" => true,
+        "expression_statement" => strip_whitespace(node_text(ctx, Some(statement))) == "discard;",
+        _ => false,
+    }
+}
+
+// the prefixes of the types whose components are floating point, as opposed to e.g. `ivec3` or `bool`.
+const FLOAT_TYPE_PREFIXES: &[&str] = &["float", "double", "vec", "dvec", "mat", "dmat"];
+
+/// Hints at divisions of two integer literals whose result is used as a float, e.g. `float f = 1 / 2;`, as the
+/// division happens on integers and so truncates before the conversion. The context is found by climbing through
+/// the arithmetic surrounding the division to what consumes it: a declaration, assignment, constructor or user
+/// function argument, or a return statement. Arithmetic with a float operand along the way also counts.
+pub fn integer_divisions(ctx: &ParserContext, node: Node) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    collect_integer_divisions(ctx, node, &mut diagnostics);
+    diagnostics
+}
+
+fn collect_integer_divisions(ctx: &ParserContext, node: Node, diagnostics: &mut Vec<Diagnostic>) {
+    if node.kind() == "binary_expression" && node_text(ctx, node.child_by_field_name("operator")) == "/" {
+        let left = node_text(ctx, node.child_by_field_name("left"));
+        let right = node_text(ctx, node.child_by_field_name("right"));
+
+        if RE_INTEGER_LITERAL.is_match(left) && RE_INTEGER_LITERAL.is_match(right) && in_float_context(ctx, node) {
+            let result = match (left.parse::<u64>(), right.parse::<u64>()) {
+                (Ok(l), Ok(r)) if r != 0 => format!(" evaluates to `{}`", l / r),
+                _ => String::new(),
+            };
+
+            diagnostics.push(new_diagnostic(
+                ctx.range_for_node(&node),
+                DiagnosticSeverity::HINT,
+                "integer-division",
+                format!(
+                    "integer division `{} / {}`{} before being used as a float, did you mean `{}.0 / {}.0`?",
+                    left, right, result, left, right
+                ),
+                None,
+            ));
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_integer_divisions(ctx, child, diagnostics);
+    }
+}
+
+fn in_float_context(ctx: &ParserContext, node: Node) -> bool {
+    let mut child = node;
+    let mut parent = node.parent();
+
+    while let Some(p) = parent {
+        match p.kind() {
+            "parenthesized_expression" => (),
+            "binary_expression" => {
+                if !matches!(node_text(ctx, p.child_by_field_name("operator")), "+" | "-" | "*" | "/") {
+                    return false;
+                }
+                let other = if p.child_by_field_name("left") == Some(child) {
+                    p.child_by_field_name("right")
+                } else {
+                    p.child_by_field_name("left")
+                };
+                if other.map_or(false, |other| is_float_expression(ctx, other)) {
+                    return true;
+                }
+            }
+            "init_declarator" => {
+                return p
+                    .parent()
+                    .and_then(|decl| decl.child_by_field_name("type"))
+                    .map_or(false, |ty| is_float_type(node_text(ctx, Some(ty))))
+            }
+            "assignment_expression" => {
+                return p
+                    .child_by_field_name("left")
+                    .filter(|left| left.kind() == "identifier")
+                    .and_then(|left| declared_type(ctx, left))
+                    .map_or(false, |ty| is_float_type(&ty))
+            }
+            "argument_list" => return is_float_argument(ctx, p, child),
+            "return_statement" => {
+                let mut function = p.parent();
+                while let Some(f) = function.filter(|f| f.kind() != "function_definition") {
+                    function = f.parent();
+                }
+                return function
+                    .and_then(|f| f.child_by_field_name("type"))
+                    .map_or(false, |ty| is_float_type(node_text(ctx, Some(ty))));
+            }
+            _ => return false,
+        }
+
+        child = p;
+        parent = p.parent();
+    }
+
+    false
+}
+
+// whether the argument is passed as a float, either to a constructor of a float type or to a user function.
+fn is_float_argument(ctx: &ParserContext, arguments: Node, argument: Node) -> bool {
+    let function = match arguments.parent().and_then(|call| call.child_by_field_name("function")) {
+        Some(function) => node_text(ctx, Some(function)),
+        None => return false,
+    };
+    if is_float_type(function) {
+        return true;
+    }
+
+    let mut cursor = arguments.walk();
+    let args: Vec<Node> = arguments.named_children(&mut cursor).filter(|a| a.kind() != "comment").collect();
+    let index = match args.iter().position(|a| *a == argument) {
+        Some(index) => index,
+        None => return false,
+    };
+
+    let root = ctx.root_node();
+    let mut cursor = root.walk();
+    let definitions: Vec<Node> = root
+        .named_children(&mut cursor)
+        .filter(|n| n.kind() == "function_definition")
+        .collect();
+
+    definitions.into_iter().any(|definition| {
+        let declarator = match definition.child_by_field_name("declarator") {
+            Some(declarator) if node_text(ctx, declarator.child_by_field_name("declarator")) == function => declarator,
+            _ => return false,
+        };
+        let parameters = match declarator.child_by_field_name("parameters") {
+            Some(parameters) => parameters,
+            None => return false,
+        };
+
+        let mut cursor = parameters.walk();
+        let params: Vec<Node> = parameters
+            .named_children(&mut cursor)
+            .filter(|p| p.kind() == "parameter_declaration")
+            .collect();
+
+        params.len() == args.len()
+            && params[index]
+                .child_by_field_name("type")
+                .map_or(false, |ty| is_float_type(node_text(ctx, Some(ty))))
+    })
+}
+
+fn is_float_expression(ctx: &ParserContext, node: Node) -> bool {
+    match node.kind() {
+        "number_literal" => !RE_INTEGER_LITERAL.is_match(node_text(ctx, Some(node))),
+        "identifier" => declared_type(ctx, node).map_or(false, |ty| is_float_type(&ty)),
+        "parenthesized_expression" => node.named_child(0).map_or(false, |inner| is_float_expression(ctx, inner)),
+        _ => false,
+    }
+}
+
+// finds the type of the variable an identifier refers to, by searching the declarations of each enclosing scope.
+fn declared_type(ctx: &ParserContext, identifier: Node) -> Option<String> {
+    let name = node_text(ctx, Some(identifier));
+
+    let mut scope = identifier.parent();
+    while let Some(s) = scope {
+        let mut declarations = vec![];
+        let mut cursor = s.walk();
+        match s.kind() {
+            "compound_statement" | "translation_unit" => {
+                declarations.extend(s.named_children(&mut cursor).filter(|n| n.kind() == "declaration"));
+            }
+            "function_definition" => {
+                if let Some(parameters) = s
+                    .child_by_field_name("declarator")
+                    .and_then(|d| d.child_by_field_name("parameters"))
+                {
+                    let mut cursor = parameters.walk();
+                    declarations.extend(
+                        parameters
+                            .named_children(&mut cursor)
+                            .filter(|n| n.kind() == "parameter_declaration"),
+                    );
+                }
+            }
+            _ => (),
+        }
+
+        for declaration in declarations {
+            let mut cursor = declaration.walk();
+            let declares_name = declaration
+                .children_by_field_name("declarator", &mut cursor)
+                .filter_map(innermost_declarator)
+                .any(|ident| node_text(ctx, Some(ident)) == name);
+
+            if declares_name {
+                return declaration
+                    .child_by_field_name("type")
+                    .map(|ty| node_text(ctx, Some(ty)).to_string());
+            }
+        }
+
+        scope = s.parent();
+    }
+
+    None
+}
+
+fn is_float_type(ty: &str) -> bool {
+    FLOAT_TYPE_PREFIXES.iter().any(|prefix| ty.starts_with(prefix))
+}
+
+fn node_text<'a>(ctx: &'a ParserContext, node: Option<Node>) -> &'a str {
+    node.and_then(|n| n.utf8_text(ctx.source().as_bytes()).ok()).unwrap_or_default()
+}
+
+fn new_diagnostic(
+    range: Range, severity: DiagnosticSeverity, code: &str, message: String, related_information: Option<Vec<DiagnosticRelatedInformation>>,
+) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.into())),
+        code_description: None,
+        source: Some(consts::SOURCE.into()),
+        message,
+        related_information,
+        tags: None,
+        data: None,
+    }
+}
+
+#[cfg(test)]
+mod lints_test {
+    use std::env::temp_dir;
+
+    use rust_lsp::lsp_types::{DiagnosticSeverity, DiagnosticTag, Location, Position, Range};
+    use tree_sitter::Parser;
+
+    use crate::lints;
+    use crate::navigation::ParserContext;
+    use crate::url_norm::url_for;
+
+    fn new_parser() -> Parser {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        parser
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_prototype_mismatches() {
+        let source = r#"void foo(in float a);
+void foo(out float a) {}
+
+float bar(vec2 b);
+float bar(vec2   b) { return b.x; }
+
+float baz(float c);
+float baz(vec3 c) { return c.x; }
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let diagnostics = lints::prototype_mismatches(&ctx, &temp_dir().join("test.fsh")).unwrap();
+
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].range.start.line, 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].related_information.as_ref().unwrap()[0].location.range.start.line, 0);
+
+        assert_eq!(diagnostics[1].range.start.line, 7);
+        assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_argument_counts() {
+        let source = r#"#define SQUARE(x) ((x) * (x))
+struct Light { vec3 color; float power; };
+
+float luma(vec3 color);
+float luma(vec3 color) { return dot(color, vec3(0.3, 0.6, 0.1)); }
+float blend(float a, float b) { return a + b; }
+float blend(vec3 a, vec3 b, float t) { return luma(mix(a, b, t)); }
+float noise(void) { return 0.5; }
+
+void main() {
+    float a = luma(vec3(1.0), 2.0);
+    float b = blend(1.0);
+    float c = blend(vec3(0.0), vec3(1.0), 0.5) + noise() + noise(1.0);
+    float d = SQUARE(a) + max(a, b) + external(1.0, 2.0);
+    Light light = Light(vec3(1.0), 2.0);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let diagnostics = lints::argument_counts(&ctx, &temp_dir().join("test.fsh"), &[]).unwrap();
+        let found: Vec<(u32, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message.as_str()))
+            .collect();
+
+        // macros, built-ins, constructors and functions declared elsewhere aren't checked
+        assert_eq!(
+            found,
+            vec![
+                (10, "too many arguments to `luma`: it takes 1 argument but 2 were given"),
+                (11, "no overload of `blend` takes 1 argument"),
+                (12, "too many arguments to `noise`: it takes 0 arguments but 1 was given"),
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.severity == Some(DiagnosticSeverity::ERROR)));
+
+        // the definition is pointed to rather than the prototype, and each overload is
+        let related_lines = |diagnostic: &rust_lsp::lsp_types::Diagnostic| -> Vec<u32> {
+            let related = diagnostic.related_information.as_ref().unwrap();
+            related.iter().map(|related| related.location.range.start.line).collect()
+        };
+        assert_eq!(related_lines(&diagnostics[0]), vec![4]);
+        assert_eq!(related_lines(&diagnostics[1]), vec![5, 6]);
+        assert_eq!(diagnostics[0].range.start.character, 18);
+
+        // functions declared in included files are checked too, their overloads counting along with the file's own
+        let included_function = |name: &str, arity| lints::FunctionArity {
+            name: name.into(),
+            arity,
+            location: Location {
+                uri: url_for(&temp_dir().join("common.glsl")).unwrap(),
+                range: Range::default(),
+            },
+            is_prototype: false,
+        };
+        let included = [included_function("external", 1), included_function("blend", 1)];
+        let diagnostics = lints::argument_counts(&ctx, &temp_dir().join("test.fsh"), &included).unwrap();
+        let found: Vec<(u32, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (10, "too many arguments to `luma`: it takes 1 argument but 2 were given"),
+                (12, "too many arguments to `noise`: it takes 0 arguments but 1 was given"),
+                (13, "too many arguments to `external`: it takes 1 argument but 2 were given"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[2].related_information.as_ref().unwrap()[0].location,
+            included[0].location
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_duplicate_fields() {
+        let source = r#"struct Vertex {
+    vec3 position;
+    vec3 normal;
+    vec2 uv;
+    vec3 normal;
+    float weight, weight;
+    vec4 joints[4], joints[2];
+};
+
+struct Other {
+    vec3 position;
+};
+
+void main() {
+    struct Local { float a; float a; };
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let diagnostics = lints::duplicate_fields(&ctx, &temp_dir().join("test.fsh"), ctx.root_node());
+
+        // fields are only compared within their own struct, wherever it's declared
+        let duplicates: Vec<(Position, Position)> = diagnostics
+            .iter()
+            .map(|d| (d.range.start, d.related_information.as_ref().unwrap()[0].location.range.start))
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![
+                (Position::new(4, 9), Position::new(2, 9)),
+                (Position::new(5, 18), Position::new(5, 10)),
+                (Position::new(6, 20), Position::new(6, 9)),
+                (Position::new(14, 34), Position::new(14, 25)),
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.severity == Some(DiagnosticSeverity::ERROR)));
+        assert_eq!(diagnostics[0].message, "field `normal` is already declared in this struct");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_syntax_errors() {
+        let source = r#"void main() {
+    float a = 1.0 +;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let diagnostics = lints::syntax_errors(&ctx, &temp_dir().join("test.fsh"), ctx.root_node());
+
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].range.start.line, 1);
+        assert!(diagnostics[0].message.ends_with("`main`"), "{}", diagnostics[0].message);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_integer_divisions() {
+        let source = r#"float a = 1 / 2;
+int b = 1 / 2;
+float c = 1.0 / 2.0;
+float half(float x) { return x / 2.0; }
+void main() {
+    float d;
+    d = (1 / 2) * 4;
+    vec3 e = vec3(1 / 3);
+    int f = 3 / 2 + 1;
+    float g = half(1 / 2);
+    int h = int(d * (1 / 2));
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let diagnostics = lints::integer_divisions(&ctx, ctx.root_node());
+
+        assert_eq!(
+            diagnostics.iter().map(|d| d.range.start.line).collect::<Vec<_>>(),
+            vec![0, 6, 7, 9, 10]
+        );
+        assert!(diagnostics.iter().all(|d| d.severity == Some(DiagnosticSeverity::HINT)));
+        assert_eq!(
+            diagnostics[0].message,
+            "integer division `1 / 2` evaluates to `0` before being used as a float, did you mean `1.0 / 2.0`?"
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_unreachable_code() {
+        let source = r#"float f(float x) {
+    if (x > 1.0) {
+        return 1.0;
+        x = 2.0;
+    }
+    return x;
+    // trailing comment
+}
+void main() {
+    if (gl_FragCoord.x < 0.5) {
+        discard;
+        gl_FragColor = vec4(1.0);
+        gl_FragColor.a = 0.5;
+    }
+    gl_FragColor = vec4(0.0);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let diagnostics = lints::unreachable_code(&ctx, ctx.root_node());
+
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].range.start.line, 3);
+        assert_eq!(diagnostics[0].range.end.line, 3);
+        assert_eq!(diagnostics[0].message, "unreachable code after `return 1.0`");
+        assert_eq!(diagnostics[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        assert_eq!(diagnostics[1].range.start.line, 11);
+        assert_eq!(diagnostics[1].range.end.line, 12);
+        assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::WARNING));
+    }
+}
@@ -1,4 +1,4 @@
-use rust_lsp::lsp_types::notification::Notification;
+use rust_lsp::lsp_types::{notification::Notification, Position, Range, SymbolKind, Url};
 use serde::{Deserialize, Serialize};
 
 pub enum Status {}
@@ -14,3 +14,23 @@ pub struct StatusParams {
     pub message: Option<String>,
     pub icon: Option<String>,
 }
+
+/// Payload carried by unresolved code lenses so that `codeLens/resolve` can find
+/// the function the lens belongs to.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct CodeLensData {
+    pub uri: Url,
+    pub position: Position,
+}
+
+/// An item of a type hierarchy, shaped as `textDocument/prepareTypeHierarchy` would return it. The server's LSP
+/// library predates type hierarchies, so they're served through commands instead.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeHierarchyItem {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub uri: Url,
+    pub range: Range,
+    pub selection_range: Range,
+}
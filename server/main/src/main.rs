@@ -7,58 +7,88 @@ use rust_lsp::lsp::*;
 use rust_lsp::lsp_types::{notification::*, *};
 
 use petgraph::stable_graph::NodeIndex;
-use path_slash::PathExt;
+use path_slash::{PathBufExt, PathExt};
 
-use serde::Deserialize;
 use serde_json::{from_value, Value};
 
+use cancellation::CancellationToken;
+use definition_cache::DefinitionKind;
+use graph::IncludePosition;
 use tree_sitter::Parser;
-use url_norm::FromUrl;
+use url_norm::{FromJson, FromUrl};
 
 use walkdir::WalkDir;
 
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
 use std::convert::TryFrom;
-use std::fmt::{Debug, Display, Formatter};
 use std::fs;
-use std::io::{stdin, stdout, BufRead, BufReader};
+use std::hash::{Hash, Hasher};
+use std::io::{stdin, stdout, BufRead, BufReader, BufWriter};
 use std::iter::{Extend, FromIterator};
 use std::rc::Rc;
 use std::str::FromStr;
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     path::{Path, PathBuf},
 };
 
 use slog::Level;
 use slog_scope::{debug, error, info, warn};
 
-use path_slash::PathBufExt;
-
 use anyhow::{anyhow, Result};
 
 use regex::Regex;
 
 use lazy_static::lazy_static;
 
+mod builtin_document;
+mod builtins;
+mod cancellation;
+mod colors;
 mod commands;
+mod completion;
 mod configuration;
+mod const_eval;
 mod consts;
+mod definition_cache;
 mod dfs;
 mod diagnostics_parser;
+mod extract_function;
+mod fuzzy;
 mod graph;
+mod hover;
+mod include_guard;
+mod include_resolver;
+mod inline_variable;
+mod interface;
+mod layout;
 mod linemap;
+mod lint_cache;
+mod lints;
 mod lsp_ext;
 mod merge_views;
 mod navigation;
 mod opengl;
+mod organize_includes;
+mod overloads;
+mod parser_pool;
+mod preprocessor;
+mod progress;
+mod semantic_tokens;
+mod severities;
 mod source_mapper;
+mod symbol_export;
+mod type_hierarchy;
 mod url_norm;
 
 #[cfg(test)]
 mod test;
 
+// the number of files parsed for workspace symbols between progress reports.
+const SYMBOL_BATCH_SIZE: usize = 64;
+
 pub fn is_top_level(path: &Path) -> bool {
     let path = path.to_slash().unwrap();
     if !RE_WORLD_FOLDER.is_match(&path) {
@@ -69,8 +99,33 @@ pub fn is_top_level(path: &Path) -> bool {
     (len == 3 || len == 2) && TOPLEVEL_FILES.contains(parts[len - 1])
 }
 
+// TODO: include user added extensions with a set
+pub fn has_shader_extension(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("vsh" | "fsh" | "csh" | "gsh" | "glsl" | "inc")
+    )
+}
+
+// whether the first line of the file that isn't blank or a line comment is `#pragma once`.
+fn starts_with_pragma_once(file: &Path) -> bool {
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(_) => return false,
+    };
+
+    source
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("//"))
+        .map_or(false, |line| RE_PRAGMA_ONCE.is_match(line))
+}
+
 lazy_static! {
-    static ref RE_INCLUDE: Regex = Regex::new(r#"^(?:\s)*?(?:#include) "(.+)"\r?"#).unwrap();
+    // the operand being either a quoted path or the name of a macro expanding to one
+    static ref RE_INCLUDE: Regex = Regex::new(r#"^(?:\s)*?(?:#include) ("[^"]+"|[A-Za-z_]\w*)\r?"#).unwrap();
+    static ref RE_DEFINE: Regex = Regex::new(r#"^\s*#\s*define\s+(\w+)\s+(.+?)\s*$"#).unwrap();
+    static ref RE_PRAGMA_ONCE: Regex = Regex::new(r#"^#\s*pragma\s+once\b"#).unwrap();
     static ref RE_WORLD_FOLDER: Regex = Regex::new(r#"^shaders(/world-?\d+)?"#).unwrap();
     static ref TOPLEVEL_FILES: HashSet<String> = {
         let mut set = HashSet::with_capacity(1716);
@@ -119,6 +174,60 @@ lazy_static! {
     };
 }
 
+// the capabilities the server answers `initialize` with, and lists in `serverInfo`.
+fn server_capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        declaration_provider: Some(DeclarationCapability::Simple(true)),
+        references_provider: Some(OneOf::Right(ReferencesOptions {
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: Some(true),
+            },
+        })),
+        document_highlight_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Right(RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+        })),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Right(WorkspaceSymbolOptions {
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: Some(true),
+            },
+            resolve_provider: None,
+        })),
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(true),
+        }),
+        document_link_provider: Some(DocumentLinkOptions {
+            resolve_provider: None,
+            work_done_progress_options: WorkDoneProgressOptions { work_done_progress: None },
+        }),
+        workspace: Some(WorkspaceServerCapabilities {
+            workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                supported: Some(true),
+                change_notifications: None,
+            }),
+            file_operations: None,
+        }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec!["graphDot".into()],
+            work_done_progress_options: WorkDoneProgressOptions { work_done_progress: None },
+        }),
+        text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+            open_close: Some(true),
+            will_save: None,
+            will_save_wait_until: None,
+            change: Some(TextDocumentSyncKind::FULL),
+            save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions { include_text: Some(true) })),
+        })),
+        ..ServerCapabilities::default()
+    }
+}
+
 fn main() {
     let guard = logging::set_logger_with_level(Level::Info);
 
@@ -133,11 +242,27 @@ fn main() {
         endpoint: endpoint_output.clone(),
         graph: Rc::new(RefCell::new(cache_graph)),
         root: "".into(),
+        workspace_folders: vec![],
         command_provider: None,
         opengl_context: Rc::new(opengl::OpenGlContext::new()),
         tree_sitter: Rc::new(RefCell::new(parser)),
+        parser_pool: RefCell::new(parser_pool::ParserPool::new(1)),
+        lint_cache: RefCell::new(lint_cache::LintCache::default()),
+        workspace_diagnostics: RefCell::new(HashMap::new()),
+        definition_cache: RefCell::new(definition_cache::DefinitionCache::default()),
+        guessed_definitions_shown: Cell::new(false),
+        diagnostics: Rc::new(RefCell::new(HashMap::new())),
+        configuration: configuration::Configuration::default(),
+        severity_overrides: HashMap::new(),
+        include_roots: HashMap::new(),
+        document_versions: HashMap::new(),
+        document_sources: HashMap::new(),
+        document_linemaps: HashMap::new(),
+        cancellations: cancellation::Cancellations::default(),
+        work_done_progress: false,
         log_guard: Some(guard),
-    };
+    }
+    .with_parser_pool(std::thread::available_parallelism().map_or(1, |n| n.get()));
 
     langserver.command_provider = Some(commands::CustomCommandProvider::new(vec![
         (
@@ -158,40 +283,218 @@ fn main() {
                 tree_sitter: langserver.tree_sitter.clone(),
             }),
         ),
+        (
+            "semanticTokens",
+            Box::new(commands::semantic_tokens::SemanticTokensCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "prepareRename",
+            Box::new(commands::prepare_rename::PrepareRenameCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "peekDefinition",
+            Box::new(commands::peek_definition::PeekDefinitionCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "documentColors",
+            Box::new(commands::document_colors::DocumentColorsCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "colorPresentations",
+            Box::new(commands::document_colors::ColorPresentationsCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "interfaceVariables",
+            Box::new(commands::interface_variables::InterfaceVariablesCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "callSites",
+            Box::new(commands::call_sites::CallSitesCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "adjacentDiagnostic",
+            Box::new(commands::adjacent_diagnostic::AdjacentDiagnosticCommand {
+                diagnostics: langserver.diagnostics.clone(),
+            }),
+        ),
+        (
+            "symbolId",
+            Box::new(commands::symbol_id::SymbolIdCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "unresolvedSymbols",
+            Box::new(commands::unresolved_symbols::UnresolvedSymbolsCommand {
+                graph: langserver.graph.clone(),
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "prepareTypeHierarchy",
+            Box::new(commands::type_hierarchy::PrepareTypeHierarchyCommand {
+                graph: langserver.graph.clone(),
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "typeHierarchySupertypes",
+            Box::new(commands::type_hierarchy::TypeHierarchySupertypesCommand {
+                graph: langserver.graph.clone(),
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "typeHierarchySubtypes",
+            Box::new(commands::type_hierarchy::TypeHierarchySubtypesCommand {
+                graph: langserver.graph.clone(),
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "includeChain",
+            Box::new(commands::include_chain::IncludeChainCommand {
+                graph: langserver.graph.clone(),
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "activeMacros",
+            Box::new(commands::active_macros::ActiveMacrosCommand {
+                graph: langserver.graph.clone(),
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        ("builtinDocument", Box::new(commands::builtin_document::BuiltinDocumentCommand)),
+        (
+            "copySignature",
+            Box::new(commands::signature::SignatureCommand {
+                tree_sitter: langserver.tree_sitter.clone(),
+            }),
+        ),
+        (
+            "affectedOutputs",
+            Box::new(commands::affected_outputs::AffectedOutputsCommand {
+                graph: langserver.graph.clone(),
+            }),
+        ),
+        (
+            "serverInfo",
+            Box::new(commands::server_info::ServerInfoCommand {
+                opengl_context: langserver.opengl_context.clone(),
+            }),
+        ),
+        (
+            "workspaceDiagnostics",
+            Box::new(commands::workspace_diagnostics::WorkspaceDiagnosticsCommand),
+        ),
+        ("exportSymbols", Box::new(commands::export_symbols::ExportSymbolsCommand)),
     ]));
 
-    LSPEndpoint::run_server_from_input(&mut stdin().lock(), endpoint_output, langserver);
+    let mut input = cancellation::CancellableInput::new(BufReader::new(stdin()), langserver.cancellations.clone())
+        .expect("failed to start reading input");
+    LSPEndpoint::run_server_from_input(&mut input, endpoint_output, langserver);
 }
 
 pub struct MinecraftShaderLanguageServer {
     endpoint: Endpoint,
     graph: Rc<RefCell<graph::CachedStableGraph>>,
     root: PathBuf,
+    // the workspace folders opened besides `root`, each a shaderpack of its own. Includes are resolved, and files
+    // navigated, only within the folder the file belongs to
+    workspace_folders: Vec<PathBuf>,
     command_provider: Option<commands::CustomCommandProvider>,
     opengl_context: Rc<dyn opengl::ShaderValidator>,
     tree_sitter: Rc<RefCell<Parser>>,
+    // parsers for workspace wide operations, which parse many files at once
+    parser_pool: RefCell<parser_pool::ParserPool>,
+    // syntax tree diagnostics of linted files, so that relinting only rechecks what changed
+    lint_cache: RefCell<lint_cache::LintCache>,
+    // the diagnostics found for each file when listing those of the whole workspace, along with a hash of the files
+    // they were found from, see `workspace_diagnostics`
+    workspace_diagnostics: RefCell<HashMap<PathBuf, (u64, HashMap<Url, Vec<Diagnostic>>)>>,
+    // the definitions last looked up by name, so that looking a symbol up again doesn't search its files again
+    definition_cache: RefCell<definition_cache::DefinitionCache>,
+    // whether the client has been told that definitions matched by name alone are guesses, which it's told only once
+    guessed_definitions_shown: Cell<bool>,
+    // the diagnostics last published for each file, for commands navigating between them
+    diagnostics: Rc<RefCell<HashMap<Url, Vec<Diagnostic>>>>,
+    configuration: configuration::Configuration,
+    // the severities diagnostics are published with by their code, as given by the client on initialization
+    severity_overrides: HashMap<String, configuration::SeverityOverride>,
+    // the directories given by the client for absolute includes to be looked up in besides the `shaders` directory,
+    // resolved against each workspace folder
+    include_roots: HashMap<PathBuf, Vec<PathBuf>>,
+    // the versions of the documents open in the client, for the edits sent to it to be applied against
+    document_versions: HashMap<Url, i32>,
+    // the text of the documents open in the client, for the results held onto across edits to be moved along
+    document_sources: HashMap<Url, String>,
+    // the line maps of the open documents' text, updated along with it
+    document_linemaps: HashMap<Url, linemap::LineMap>,
+    // the requests received and not yet answered, for the searches answering them to stop once they're cancelled
+    cancellations: cancellation::Cancellations,
+    // whether the client creates progresses the server asks for, for work not started by a request
+    work_done_progress: bool,
     log_guard: Option<slog_scope::GlobalLoggerGuard>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub struct IncludePosition {
-    // the 0-indexed line on which the include lives.
-    line: usize,
-    // the 0-indexed char offset defining the start of the include path string.
-    start: usize,
-    // the 0-indexed char offset defining the end of the include path string.
-    end: usize,
+// The server as the commands run by a request see it, the progress of the commands acting on the whole workspace
+// being reported to the request's token.
+struct CommandWorkspace<'a> {
+    server: &'a MinecraftShaderLanguageServer,
+    token: Option<ProgressToken>,
 }
 
-impl Debug for IncludePosition {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{{line: {}}}", self.line)
+impl commands::Workspace for CommandWorkspace<'_> {
+    fn folders(&self) -> Vec<&Path> {
+        self.server.folders()
+    }
+
+    fn workspace_folder(&self, path: &Path) -> Option<&Path> {
+        self.server.workspace_folder(path)
+    }
+
+    fn include_roots(&self) -> &HashMap<PathBuf, Vec<PathBuf>> {
+        &self.server.include_roots
+    }
+
+    fn find_included_definitions(&self, path: &Path, name: &str) -> Result<Vec<Location>> {
+        self.server.find_included_definitions(path, name)
+    }
+
+    fn configuration(&self) -> &configuration::Configuration {
+        &self.server.configuration
+    }
+
+    fn command_names(&self) -> Vec<&str> {
+        self.server
+            .command_provider
+            .as_ref()
+            .map_or(vec![], commands::CustomCommandProvider::command_names)
     }
-}
 
-impl Display for IncludePosition {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        write!(f, "{{line: {}}}", self.line)
+    fn workspace_diagnostics(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        self.server
+            .workspace_diagnostics(self.token.clone(), &self.server.cancellations.current())
+    }
+
+    fn export_symbols(&self, output: &Path) -> Result<usize> {
+        self.server
+            .export_symbols(output, self.token.clone(), &self.server.cancellations.current())
     }
 }
 
@@ -203,6 +506,16 @@ pub enum TreeType {
     Compute,
 }
 
+// the range of an include's path in the source of the file, its byte columns being converted to the UTF-16
+// characters of LSP positions through the file's line map.
+fn include_range(source: &str, linemap: &linemap::LineMap, position: &IncludePosition) -> Range {
+    let line_start = linemap.line_start(source, position.line);
+    Range::new(
+        linemap.position_for_offset(source, line_start + position.start),
+        linemap.position_for_offset(source, line_start + position.end),
+    )
+}
+
 impl MinecraftShaderLanguageServer {
     pub fn error_not_available<DATA>(data: DATA) -> MethodError<DATA> {
         let msg = "Functionality not implemented.".to_string();
@@ -213,12 +526,183 @@ impl MinecraftShaderLanguageServer {
         }
     }
 
-    fn build_initial_graph(&self) {
-        info!("generating graph for current root"; "root" => self.root.to_str().unwrap());
+    pub fn with_parser_pool(mut self, n: usize) -> Self {
+        self.parser_pool = RefCell::new(parser_pool::ParserPool::new(n));
+        self
+    }
+
+    fn set_parse_timeout(&mut self, timeout_ms: u64) {
+        info!("setting parse timeout"; "timeout_ms" => timeout_ms);
+        let timeout_micros = timeout_ms.saturating_mul(1000);
+        self.tree_sitter.borrow_mut().set_timeout_micros(timeout_micros);
+        self.parser_pool.borrow_mut().set_timeout_micros(timeout_micros);
+    }
+
+    // resolves the include roots given relative to each workspace folder, warning about and leaving out those that
+    // aren't directories. The order given is kept, as it decides which root an include is found in first.
+    fn set_include_roots(&mut self, roots: &[String]) {
+        let mut include_roots = HashMap::new();
+        for folder in self.folders() {
+            let mut folder_roots = vec![];
+            for root in roots {
+                let path = folder.join(PathBuf::from_slash(root));
+                if !path.is_dir() {
+                    warn!("include root is not a directory, ignoring it"; "root" => root, "path" => path.to_str().unwrap());
+                    self.endpoint
+                        .send_notification(
+                            ShowMessage::METHOD,
+                            ShowMessageParams {
+                                typ: MessageType::WARNING,
+                                message: format!(
+                                    "Include root `{}` does not exist in `{}` and will be ignored.",
+                                    root,
+                                    folder.display()
+                                ),
+                            },
+                        )
+                        .expect("failed to send popup/show message notification");
+                    continue;
+                }
+                if !folder_roots.contains(&path) {
+                    folder_roots.push(path);
+                }
+            }
+            include_roots.insert(folder.to_path_buf(), folder_roots);
+        }
+        info!("set include roots"; "roots" => format!("{:?}", include_roots));
+        self.include_roots = include_roots;
+    }
+
+    // the root and the further workspace folders, in the order the client gave them.
+    fn folders(&self) -> Vec<&Path> {
+        std::iter::once(self.root.as_path())
+            .chain(self.workspace_folders.iter().map(PathBuf::as_path))
+            .collect()
+    }
+
+    /// Returns the workspace folder the file belongs to, being the innermost one containing it where folders are
+    /// nested. Files outside of all the folders belong to none.
+    fn workspace_folder(&self, path: &Path) -> Option<&Path> {
+        self.folders()
+            .into_iter()
+            .filter(|folder| path.starts_with(folder))
+            .max_by_key(|folder| folder.components().count())
+    }
+
+    // the path of the file within the workspace folder it belongs to, which decides whether it's a top level file.
+    fn folder_relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(self.workspace_folder(path).unwrap_or(&self.root)).unwrap()
+    }
+
+    // the text of the file as open in the client, which may have changes not yet saved, if the file is open.
+    fn open_document_source(&self, path: &Path) -> Option<&String> {
+        let uri = url_norm::url_for(path).ok()?;
+        self.document_sources
+            .iter()
+            .find(|(open_uri, _)| url_norm::url_for(&PathBuf::from_url((*open_uri).clone())).ok().as_ref() == Some(&uri))
+            .map(|(_, source)| source)
+    }
+
+    // parses the file as open in the client if it is, or as it is on disk otherwise, for the edits made from it to
+    // apply to the document as versioned by `versioned_edit`.
+    fn document_context<'a>(&self, parser: &'a mut Parser, path: &Path) -> Result<navigation::ParserContext<'a>> {
+        match self.open_document_source(path) {
+            Some(source) => navigation::ParserContext::from_source(parser, source.clone()),
+            None => navigation::ParserContext::new(parser, path),
+        }
+    }
+
+    // Builds the edit renaming the struct field at the position in the files including or included by the file,
+    // grouped by file, as structs are often defined in a header and accessed by the files including it. The types
+    // accesses are resolved through are those declared in any of those files, and each file is read as open in
+    // the client, see `document_context`. Only struct fields can be renamed, other names being `None`.
+    fn rename_edit(&self, path: &Path, position: Position, new_name: &str) -> Result<Option<WorkspaceEdit>> {
+        let files = self.proximity_files(path);
+        let parser = &mut self.tree_sitter.borrow_mut();
+
+        let mut external = navigation::DeclaredTypes::default();
+        for (_, file) in &files {
+            let parser_ctx = match self.document_context(parser, file) {
+                Ok(parser_ctx) => parser_ctx,
+                Err(e) => {
+                    warn!("skipping unreadable file"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap());
+                    continue;
+                }
+            };
+            external.extend(parser_ctx.declared_types()?);
+        }
+
+        let (struct_name, field) = match self.document_context(parser, path)?.field_at_point(position, &external)? {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+        if !navigation::is_identifier(new_name) {
+            return Err(anyhow!("`{}` is not a valid identifier", new_name));
+        }
+
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for (_, file) in &files {
+            let parser_ctx = match self.document_context(parser, file) {
+                Ok(parser_ctx) => parser_ctx,
+                Err(_) => continue,
+            };
+            for location in parser_ctx.find_field_references(file, &struct_name, &field, &external)? {
+                changes.entry(location.uri).or_default().push(TextEdit {
+                    range: location.range,
+                    new_text: new_name.to_string(),
+                });
+            }
+        }
+
+        info!("renaming struct field"; "struct" => &struct_name, "field" => &field, "new_name" => new_name, "files" => changes.len());
 
+        Ok(Some(self.versioned_edit(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        })))
+    }
+
+    // Turns the per file `changes` of an edit into `documentChanges`, each versioned with the version of the open
+    // document it applies to so that the client can show the edits grouped by file and refuse to apply them over
+    // newer changes. The edits of open documents are made to their text as open, see `document_context`, so an
+    // edit made to an older version is refused rather than applied in the wrong places. Files that aren't open
+    // carry no version, being edited as they are on disk.
+    fn versioned_edit(&self, edit: WorkspaceEdit) -> WorkspaceEdit {
+        let mut changes: Vec<(Url, Vec<TextEdit>)> = edit.changes.unwrap_or_default().into_iter().collect();
+        changes.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+        // the open documents by the normalized urls edits are made with, rather than however the client wrote them
+        let open: HashMap<Url, &Url> = self
+            .document_versions
+            .keys()
+            .filter_map(|uri| Some((url_norm::url_for(&PathBuf::from_url(uri.clone())).ok()?, uri)))
+            .collect();
+
+        let document_edits = changes
+            .into_iter()
+            .map(|(uri, edits)| {
+                let version = open.get(&uri).and_then(|open_uri| self.document_versions.get(*open_uri).copied());
+                TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier { uri, version },
+                    edits: edits.into_iter().map(OneOf::Left).collect(),
+                }
+            })
+            .collect();
+
+        WorkspaceEdit {
+            changes: None,
+            document_changes: Some(DocumentChanges::Edits(document_edits)),
+            ..edit
+        }
+    }
+
+    // lists all the shader files under the workspace folders, sorted so that workspace wide results are deterministic.
+    fn shader_files(&self) -> Vec<PathBuf> {
         // filter directories and files not ending in any of the 3 extensions
-        WalkDir::new(&self.root)
+        let mut files: Vec<PathBuf> = self
+            .folders()
             .into_iter()
+            .flat_map(WalkDir::new)
             .filter_map(|entry| {
                 if entry.is_err() {
                     return None;
@@ -230,23 +714,34 @@ impl MinecraftShaderLanguageServer {
                     return None;
                 }
 
-                let ext = match path.extension() {
-                    Some(e) => e,
-                    None => return None,
-                };
-
-                // TODO: include user added extensions with a set
-                if ext != "vsh" && ext != "fsh" && ext  != "csh" && ext != "gsh" && ext != "glsl" && ext != "inc" {
+                if !has_shader_extension(path) {
                     return None;
                 }
 
                 Some(entry.into_path())
             })
-            .for_each(|path| {
-                // iterate all valid found files, search for includes, add a node into the graph for each
-                // file and add a file->includes KV into the map
-                self.add_file_and_includes_to_graph(&path);
-            });
+            .collect();
+
+        // nested folders walk the same files again
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    fn build_initial_graph(&self) {
+        info!("generating graph for current root"; "root" => self.root.to_str().unwrap(), "folders" => format!("{:?}", self.workspace_folders));
+
+        let files = self.shader_files();
+        // indexing runs after initialization, when the client's work done token is no longer valid, so it's
+        // reported through a progress the server creates
+        let token = progress::create(&self.endpoint, self.work_done_progress, "mcglsl/indexing");
+        let mut progress = progress::ProgressReporter::begin(&self.endpoint, token, "Building dependency graph", files.len());
+        for (i, path) in files.iter().enumerate() {
+            // iterate all valid found files, search for includes, add a node into the graph for each
+            // file and add a file->includes KV into the map
+            self.add_file_and_includes_to_graph(path);
+            progress.report(i + 1);
+        }
 
         info!("finished building project include graph");
     }
@@ -255,6 +750,7 @@ impl MinecraftShaderLanguageServer {
         let includes = self.find_includes(path);
 
         let idx = self.graph.borrow_mut().add_node(path);
+        self.graph.borrow_mut().set_pragma_once(idx, starts_with_pragma_once(path));
 
         debug!("adding includes for new file"; "file" => path.to_str().unwrap(), "includes" => format!("{:?}", includes));
         for include in includes {
@@ -262,40 +758,74 @@ impl MinecraftShaderLanguageServer {
         }
     }
 
+    // drops the includes of a deleted file from the graph. The node itself is kept while other files still include
+    // it, so that their edges are in place again should the file reappear.
+    fn remove_deleted_file(&self, path: &Path, node: NodeIndex, unreferenced: bool) {
+        if unreferenced {
+            self.graph.borrow_mut().remove_node(path);
+            return;
+        }
+
+        let children: Vec<_> = self.graph.borrow().get_all_child_positions(node).collect();
+        for (child, position) in children {
+            self.graph.borrow_mut().remove_edge(node, child, position);
+        }
+    }
+
     fn add_include(&self, include: (PathBuf, IncludePosition), node: NodeIndex) {
         let child = self.graph.borrow_mut().add_node(&include.0);
         self.graph.borrow_mut().add_edge(node, child, include.1);
     }
 
+    // the resolver for the includes of the file, looking them up within the workspace folder it belongs to.
+    fn include_resolver(&self, file: &Path) -> include_resolver::IncludeResolver {
+        let folder = match self.workspace_folder(file) {
+            Some(folder) => folder,
+            None => return include_resolver::IncludeResolver::for_directory(file.parent().unwrap()),
+        };
+        let include_roots = self.include_roots.get(folder).map_or(&[][..], Vec::as_slice);
+        include_resolver::IncludeResolver::new(folder).with_include_roots(include_roots)
+    }
+
+    // The includes of the file, each with the position of its path. Includes of a macro expanding to the path are
+    // resolved through the macros the file defines, see `include_resolver::path_spec`, while those expanding to no
+    // path aren't includes of any file.
     pub fn find_includes(&self, file: &Path) -> Vec<(PathBuf, IncludePosition)> {
-        let mut includes = Vec::default();
+        let resolver = self.include_resolver(file);
 
         let buf = BufReader::new(std::fs::File::open(file).unwrap());
-        buf.lines()
+        let lines: Vec<(usize, String)> = buf
+            .lines()
             .enumerate()
             .filter_map(|line| match line.1 {
                 Ok(t) => Some((line.0, t)),
                 Err(_e) => None,
             })
-            .filter(|line| RE_INCLUDE.is_match(line.1.as_str()))
-            .for_each(|line| {
-                let cap = RE_INCLUDE.captures(line.1.as_str()).unwrap().get(1).unwrap();
-
-                let start = cap.start();
-                let end = cap.end();
-                let mut path: String = cap.as_str().into();
-
-                let full_include = if path.starts_with('/') {
-                    path = path.strip_prefix('/').unwrap().to_string();
-                    self.root.join("shaders").join(PathBuf::from_slash(&path))
+            .collect();
+
+        let defines: HashMap<&str, &str> = lines
+            .iter()
+            .filter_map(|(_, line)| RE_DEFINE.captures(line))
+            .map(|cap| (cap.get(1).unwrap().as_str(), cap.get(2).unwrap().as_str()))
+            .collect();
+        let expand = |name: &str| defines.get(name).copied();
+
+        lines
+            .iter()
+            .filter_map(|(line, text)| {
+                let cap = RE_INCLUDE.captures(text)?.get(1).unwrap();
+                let include = include_resolver::path_spec(cap.as_str(), expand)?;
+
+                // the position of a quoted path is of the path within the quotes
+                let (start, end) = if cap.as_str().starts_with('"') {
+                    (cap.start() + 1, cap.end() - 1)
                 } else {
-                    file.parent().unwrap().join(PathBuf::from_slash(&path))
+                    (cap.start(), cap.end())
                 };
 
-                includes.push((full_include, IncludePosition { line: line.0, start, end }));
-            });
-
-        includes
+                Some((resolver.resolve(file, include), IncludePosition { line: *line, start, end }))
+            })
+            .collect()
     }
 
     fn update_includes(&self, file: &Path) {
@@ -307,32 +837,714 @@ impl MinecraftShaderLanguageServer {
             None => return,
             Some(n) => n,
         };
+        self.graph.borrow_mut().set_pragma_once(idx, starts_with_pragma_once(file));
 
         let prev_children: HashSet<_> = HashSet::from_iter(self.graph.borrow().get_all_child_positions(idx).map(|tup| {
             (self.graph.borrow().get_node(tup.0), tup.1)
         }));
         let new_children: HashSet<_> = includes.iter().cloned().collect();
 
-        let to_be_added = new_children.difference(&prev_children);
-        let to_be_removed = prev_children.difference(&new_children);
+        let to_be_added = new_children.difference(&prev_children);
+        let to_be_removed = prev_children.difference(&new_children);
+
+        debug!(
+            "include sets diff'd";
+            "for removal" => format!("{:?}", to_be_removed),
+            "for addition" => format!("{:?}", to_be_added)
+        );
+
+        for removal in to_be_removed {
+            let child = self.graph.borrow_mut().find_node(&removal.0).unwrap();
+            self.graph.borrow_mut().remove_edge(idx, child, removal.1);
+        }
+
+        for insertion in to_be_added {
+            self.add_include(includes.iter().find(|f| f.0 == *insertion.0).unwrap().clone(), idx);
+        }
+    }
+
+    /// Finds the file-level definitions of a name in the file and all the files it includes, such as a macro
+    /// redefined across headers. Definitions are ordered by include distance, so those in the file itself come
+    /// first, then those in direct includes before transitive ones, with ties ordered by path. Each file is parsed
+    /// with its own line map, so the locations found in a file are encoded by its own lines and line endings. Files
+    /// outside of the workspace aren't part of the include graph, so their includes are followed from disk instead.
+    fn find_included_definitions(&self, path: &Path, name: &str) -> Result<Vec<Location>> {
+        let node = self.graph.borrow_mut().find_node(path);
+        if node.is_none() && self.workspace_folder(path).is_some() {
+            return Ok(vec![]);
+        }
+
+        let cached = self.definition_cache.borrow_mut().get(path, name, DefinitionKind::Included);
+        if let Some(locations) = cached {
+            return Ok(locations);
+        }
+
+        let mut files: Vec<(usize, PathBuf)> = match node {
+            Some(node) => {
+                let graph = self.graph.borrow();
+                graph
+                    .include_distances(node)
+                    .into_iter()
+                    .map(|(node, distance)| (distance, graph.get_node(node)))
+                    .collect()
+            }
+            None => self.loose_include_distances(path),
+        };
+        files.sort();
+
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let mut locations = vec![];
+
+        for (distance, file) in &files {
+            let parser_ctx = match navigation::ParserContext::new(parser, file) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    warn!("skipping unreadable include"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap());
+                    continue;
+                }
+            };
+
+            let definitions = parser_ctx.find_global_definitions(file, name)?;
+            debug!("searched include for definitions"; "path" => file.to_str().unwrap(), "distance" => distance, "count" => definitions.len());
+            locations.extend(definitions);
+        }
+
+        // unreadable includes are kept too, so that the definitions are looked up again once they can be read
+        let files = files.into_iter().map(|(_, file)| file).collect();
+        self.definition_cache
+            .borrow_mut()
+            .insert(path, name, DefinitionKind::Included, locations.clone(), files);
+
+        Ok(locations)
+    }
+
+    // the file and the files it includes by their include distance, for a file outside of the workspace. Each include
+    // is resolved next to the file including it, and only those on disk are followed.
+    fn loose_include_distances(&self, path: &Path) -> Vec<(usize, PathBuf)> {
+        let mut files = vec![(0, path.to_path_buf())];
+        let mut seen = HashSet::from([path.to_path_buf()]);
+        let mut next = 0;
+        while let Some((distance, file)) = files.get(next).cloned() {
+            for (include, _) in self.find_includes(&file) {
+                if include.is_file() && seen.insert(include.clone()) {
+                    files.push((distance + 1, include));
+                }
+            }
+            next += 1;
+        }
+        files
+    }
+
+    // the file and the files including or included by it, paired with their distance from it and sorted nearest
+    // first. A file that isn't in the graph is alone.
+    fn proximity_files(&self, path: &Path) -> Vec<(usize, PathBuf)> {
+        let node = self.graph.borrow_mut().find_node(path);
+        let mut files: Vec<(usize, PathBuf)> = match node {
+            Some(node) => {
+                let graph = self.graph.borrow();
+                graph
+                    .proximity_distances(node)
+                    .into_iter()
+                    .map(|(node, distance)| (distance, graph.get_node(node)))
+                    .collect()
+            }
+            None => vec![(0, path.to_path_buf())],
+        };
+        files.sort();
+        files
+    }
+
+    // Searches the files including or included by the file for calls of the function, nearest files first. Once
+    // `references_limit` calls are found the search stops, so the calls found last are the ones truncated, as it
+    // does when cancelled, answering with the calls found in the files searched until then.
+    fn find_workspace_references(
+        &self, path: &Path, name: &str, token: Option<ProgressToken>, cancellation: &CancellationToken,
+    ) -> Result<Vec<Location>> {
+        let files = self.proximity_files(path);
+        let limit = self.configuration.references_limit;
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let mut locations = vec![];
+        let mut progress = progress::ProgressReporter::begin(&self.endpoint, token, "Finding references", files.len());
+
+        for (scanned, (distance, file)) in files.iter().enumerate() {
+            progress.report(scanned);
+            if cancellation.is_cancelled() {
+                info!("reference search cancelled"; "name" => name, "unscanned_files" => files.len() - scanned);
+                break;
+            }
+            let parser_ctx = match navigation::ParserContext::new(parser, file) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    warn!("skipping unreadable file"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap());
+                    continue;
+                }
+            };
+
+            let references = parser_ctx.find_function_references(file, name)?;
+            debug!("searched file for references"; "path" => file.to_str().unwrap(), "distance" => distance, "count" => references.len());
+            locations.extend(references);
+
+            if locations.len() >= limit {
+                let unscanned = files.len() - scanned - 1;
+                if locations.len() > limit || unscanned > 0 {
+                    warn!("reference results truncated"; "name" => name, "limit" => limit, "unscanned_files" => unscanned);
+                    self.endpoint
+                        .send_notification(
+                            ShowMessage::METHOD,
+                            ShowMessageParams {
+                                typ: MessageType::INFO,
+                                message: format!("Showing the first {} references of `{}`, more results were truncated", limit, name),
+                            },
+                        )
+                        .expect("failed to send popup/show message notification");
+                }
+                locations.truncate(limit);
+                break;
+            }
+        }
+
+        Ok(locations)
+    }
+
+    // Finds the references to the symbol declared at the point. Functions can be called from any of the files
+    // including, or included by, the file declaring them, while anything else is only looked for in the file. The
+    // references are sorted by file and position once found, the search's nearest-first order only deciding which
+    // are truncated.
+    fn find_all_references(
+        &self, path: &Path, position: Position, token: Option<ProgressToken>, cancellation: &CancellationToken,
+    ) -> Result<Vec<Location>> {
+        let function = {
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = navigation::ParserContext::new(parser, path)?;
+
+            let function = parser_ctx
+                .find_node_at_point(position)
+                .filter(|node| node.parent().map(|parent| parent.kind()) == Some("function_declarator"))
+                .map(|node| node.utf8_text(parser_ctx.source().as_bytes()).unwrap().to_string());
+
+            match function {
+                Some(function) => function,
+                None => {
+                    let mut references = navigation::or_nothing_at_point(parser_ctx.find_references(path, position))?.unwrap_or_default();
+                    navigation::sort_locations(&mut references);
+                    return Ok(references);
+                }
+            }
+        };
+
+        let mut references = self.find_workspace_references(path, &function, token, cancellation)?;
+        navigation::sort_locations(&mut references);
+        Ok(references)
+    }
+
+    // Going to a definition from the name it declares would go nowhere, so where the only definition found is the
+    // identifier at the point, `definition_on_declaration` decides what's given instead.
+    fn definitions_or_references(&self, path: &Path, position: Position, definitions: Vec<Location>) -> Result<Vec<Location>> {
+        let cursor = {
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = navigation::ParserContext::new(parser, path)?;
+            parser_ctx.find_node_at_point(position).map(|node| parser_ctx.range_for_node(&node))
+        };
+
+        let on_declaration = match (definitions.as_slice(), cursor) {
+            ([definition], Some(range)) => definition.range == range && PathBuf::from_url(definition.uri.clone()) == path,
+            _ => false,
+        };
+        if !on_declaration {
+            return Ok(definitions);
+        }
+
+        info!("definition is at the point"; "behaviour" => format!("{:?}", self.configuration.definition_on_declaration));
+        match self.configuration.definition_on_declaration {
+            configuration::DefinitionOnDeclaration::References => {
+                self.find_all_references(path, position, None, &CancellationToken::default())
+            }
+            configuration::DefinitionOnDeclaration::None => Ok(vec![]),
+        }
+    }
+
+    // Answers `textDocument/declaration`, which reaches the server as a definition request, see
+    // `cancellation::Cancellations::is_declaration`. Functions declared ahead of their definition are declared by
+    // their prototypes, while anything else is declared where it's defined.
+    fn goto_declaration(&mut self, params: TextDocumentPositionParams, completable: LSCompletable<Vec<Location>>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document.uri);
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = match navigation::ParserContext::new(parser, &path) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return completable.complete(Err(MethodError {
+                        code: 42069,
+                        message: format!("error building parser context: error={}, path={:?}", e, path),
+                        data: (),
+                    }))
+                }
+            };
+
+            match navigation::or_nothing_at_point(parser_ctx.find_declarations(&path, params.position)) {
+                Ok(locations) => completable.complete(Ok(locations.unwrap_or_default())),
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error finding declarations: error={}, path={:?}", e, path),
+                    data: (),
+                })),
+            }
+        });
+    }
+
+    // Lists the symbols of the files that the filter accepts, the files being parsed across the parser pool. The
+    // files are parsed in batches, progress being reported after each. Once cancelled, the files not yet parsed are
+    // skipped and the symbols of those parsed until then are listed.
+    fn list_workspace_symbols<F>(
+        &self, files: &[PathBuf], filter: F, token: Option<ProgressToken>, cancellation: &CancellationToken,
+    ) -> Vec<SymbolInformation>
+    where
+        F: Fn(&SymbolInformation) -> bool + Sync,
+    {
+        let mut progress = progress::ProgressReporter::begin(&self.endpoint, token, "Listing workspace symbols", files.len());
+        let pool = &mut self.parser_pool.borrow_mut();
+
+        let mut symbols = vec![];
+        for (batch, batch_files) in files.chunks(SYMBOL_BATCH_SIZE).enumerate() {
+            if cancellation.is_cancelled() {
+                info!("workspace symbol listing cancelled"; "unlisted_files" => files.len() - batch * SYMBOL_BATCH_SIZE);
+                break;
+            }
+            let per_file = pool.map(batch_files, |parser, path| -> Result<Vec<SymbolInformation>> {
+                if cancellation.is_cancelled() {
+                    return Ok(vec![]);
+                }
+                let parser_ctx = navigation::ParserContext::new(parser, path)?;
+                let symbols = navigation::flatten_symbols(path, parser_ctx.list_symbols(path)?.unwrap_or_default())?;
+                Ok(symbols.into_iter().filter(&filter).collect())
+            });
+
+            for (path, result) in batch_files.iter().zip(per_file) {
+                match result {
+                    Ok(file_symbols) => symbols.extend(file_symbols),
+                    Err(e) => error!("error listing symbols"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap()),
+                }
+            }
+            progress.report(batch * SYMBOL_BATCH_SIZE + batch_files.len());
+        }
+
+        info!("finished listing workspace symbols"; "count" => symbols.len(), "files" => files.len());
+
+        symbols
+    }
+
+    // Writes the symbols of every shader file of the workspace to the file at the path given, as listed for workspace
+    // symbols, see `symbol_export::write_symbols`. Returns the number of symbols written.
+    fn export_symbols(&self, output: &Path, token: Option<ProgressToken>, cancellation: &CancellationToken) -> Result<usize> {
+        let files = self.shader_files();
+        let symbols = self.list_workspace_symbols(&files, |_| true, token, cancellation);
+        // an export missing the files left unlisted would pass for a complete one
+        if cancellation.is_cancelled() {
+            return Err(anyhow!("the export was cancelled"));
+        }
+        let writer = BufWriter::new(fs::File::create(output)?);
+        let count = symbol_export::write_symbols(&mut self.tree_sitter.borrow_mut(), symbols, writer)?;
+
+        info!("exported workspace symbols"; "count" => count, "path" => output.to_str().unwrap());
+        Ok(count)
+    }
+
+    // Looks through the other files of the file's workspace folder for file-level symbols of the name, whether the
+    // file includes them or not, for when includes are misconfigured. The match is by name alone, so it's only a
+    // guess at what the name refers to, see `report_guessed_definitions`.
+    fn find_heuristic_definitions(&self, path: &Path, name: &str) -> Vec<Location> {
+        let folder = self.workspace_folder(path).map(Path::to_path_buf);
+        let files: Vec<PathBuf> = self
+            .shader_files()
+            .into_iter()
+            .filter(|file| file != path && self.workspace_folder(file).map(Path::to_path_buf) == folder)
+            .collect();
+
+        let cached = self.definition_cache.borrow_mut().get(path, name, DefinitionKind::Heuristic);
+        let locations: Vec<Location> = match cached {
+            Some(locations) => locations,
+            None => {
+                let locations: Vec<Location> = self
+                    .list_workspace_symbols(
+                        &files,
+                        |symbol| symbol.name == name && symbol.container_name.is_none(),
+                        None,
+                        &CancellationToken::default(),
+                    )
+                    .into_iter()
+                    .map(|symbol| symbol.location)
+                    .collect();
+                self.definition_cache
+                    .borrow_mut()
+                    .insert(path, name, DefinitionKind::Heuristic, locations.clone(), files);
+                locations
+            }
+        };
+
+        if !locations.is_empty() {
+            self.report_guessed_definitions(name, locations.len(), "elsewhere in the workspace");
+        }
+
+        locations
+    }
+
+    // marks the definitions found for the name by matching it alone as a guess in the log, the client being told
+    // that they're guesses the first time only rather than on every lookup.
+    fn report_guessed_definitions(&self, name: &str, count: usize, found: &str) {
+        warn!("guessed definitions by name"; "name" => name, "count" => count, "found" => found, "guess" => true);
+        if self.guessed_definitions_shown.replace(true) {
+            return;
+        }
+
+        let message = format!(
+            "`{}` isn't defined in any included file, showing definitions matched by name {}. Definitions matched by name are only guesses, further ones being logged without this message",
+            name, found
+        );
+        let sent = self.endpoint.send_notification(
+            ShowMessage::METHOD,
+            ShowMessageParams {
+                typ: MessageType::INFO,
+                message,
+            },
+        );
+        if let Err(e) = sent {
+            error!("failed to send popup/show message notification"; "error" => format!("{:?}", e));
+        }
+    }
+
+    pub fn lint(&self, uri: &Path) -> Result<HashMap<Url, Vec<Diagnostic>>> {
+        let unresolved = self.unresolved_includes(uri);
+        let cycles = self.include_cycles(uri);
+
+        // nor can it be built through an include cycle, which the cycle's own diagnostics explain
+        let compiled = match cycles.is_empty() {
+            true => self.lint_compiled(uri),
+            false => Ok(HashMap::new()),
+        };
+
+        let mut diagnostics = match compiled {
+            Ok(diagnostics) => diagnostics,
+            // the merged source can't be built with an include missing, the unresolved include is the reason why
+            Err(e) if !unresolved.is_empty() => {
+                warn!("skipping compilation with unresolved includes"; "error" => format!("{:?}", e), "path" => uri.to_str().unwrap());
+                HashMap::new()
+            }
+            Err(e) => return Err(e),
+        };
+
+        let own_diagnostics = diagnostics.entry(url_norm::url_for(uri)?).or_default();
+        own_diagnostics.extend(unresolved);
+        own_diagnostics.extend(cycles);
+        own_diagnostics.extend(self.lint_syntax_tree(uri));
+
+        match self.lint_stage_interface(uri) {
+            Ok(interface_diagnostics) => {
+                for (url, d) in interface_diagnostics {
+                    diagnostics.entry(url).or_default().extend(d);
+                }
+            }
+            Err(e) => error!("error checking stage interface"; "error" => format!("{:?}", e), "path" => uri.to_str().unwrap()),
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Lints every shader file of the workspace as each is linted when opened, for a view of the problems across the
+    /// whole pack. The diagnostics of each file are kept along with a hash of the files linting it reads, so that
+    /// listing them again only relints the files changed since, or including or included by those changed. Once
+    /// cancelled, only the diagnostics of the files linted until then are listed.
+    fn workspace_diagnostics(&self, token: Option<ProgressToken>, cancellation: &CancellationToken) -> HashMap<Url, Vec<Diagnostic>> {
+        let files = self.shader_files();
+        let mut progress = progress::ProgressReporter::begin(&self.endpoint, token, "Linting workspace", files.len());
+        let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+        // files are read once per listing however many of the files linted read them
+        let mut file_hashes = HashMap::new();
+
+        for (linted, file) in files.iter().enumerate() {
+            progress.report(linted);
+            if cancellation.is_cancelled() {
+                info!("workspace linting cancelled"; "unlinted_files" => files.len() - linted);
+                break;
+            }
+
+            let hash = self.lint_inputs_hash(file, &mut file_hashes);
+            let cached = self
+                .workspace_diagnostics
+                .borrow()
+                .get(file)
+                .filter(|(cached_hash, _)| *cached_hash == hash)
+                .map(|(_, diagnostics)| diagnostics.clone());
+            let file_diagnostics = match cached {
+                Some(file_diagnostics) => file_diagnostics,
+                None => match self.lint(file) {
+                    Ok(file_diagnostics) => {
+                        self.workspace_diagnostics
+                            .borrow_mut()
+                            .insert(file.clone(), (hash, file_diagnostics.clone()));
+                        file_diagnostics
+                    }
+                    Err(e) => {
+                        error!("error linting file"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap());
+                        continue;
+                    }
+                },
+            };
+
+            // a header's compiler diagnostics are found again by linting each file including it
+            for (url, file_diagnostics) in file_diagnostics {
+                let merged = diagnostics.entry(url).or_default();
+                for diagnostic in file_diagnostics {
+                    if !merged.contains(&diagnostic) {
+                        merged.push(diagnostic);
+                    }
+                }
+            }
+        }
+
+        diagnostics
+            .into_iter()
+            .map(|(url, diagnostics)| (url, severities::apply(diagnostics, &self.severity_overrides)))
+            .collect()
+    }
+
+    // Looks through the documents open in the client besides the file for file-level symbols of the name, as they're
+    // being edited rather than as saved, for names defined in files the file doesn't include yet. As with
+    // `find_heuristic_definitions` the match is by name alone, see `report_guessed_definitions`.
+    fn find_open_document_definitions(&self, path: &Path, name: &str) -> Vec<Location> {
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let mut documents: Vec<(&Url, &String)> = self.document_sources.iter().collect();
+        documents.sort();
+
+        let mut locations = vec![];
+        for (uri, source) in documents {
+            let document = PathBuf::from_url(uri.clone());
+            if document == path {
+                continue;
+            }
+
+            let symbols = navigation::ParserContext::from_source(parser, source.clone())
+                .and_then(|ctx| navigation::flatten_symbols(&document, ctx.list_symbols(&document)?.unwrap_or_default()));
+            match symbols {
+                Ok(symbols) => locations.extend(
+                    symbols
+                        .into_iter()
+                        .filter(|symbol| symbol.name == name && symbol.container_name.is_none())
+                        .map(|symbol| symbol.location),
+                ),
+                Err(e) => error!("error listing symbols"; "error" => format!("{:?}", e), "path" => document.to_str().unwrap()),
+            }
+        }
+
+        if !locations.is_empty() {
+            self.report_guessed_definitions(name, locations.len(), "in the open documents");
+        }
+
+        locations
+    }
+
+    // a hash of the files that linting the file reads, being those it's connected to through includes either way
+    // along with those of its paired stage. The hash of each file's content is kept in `file_hashes`, for the files
+    // read linting many files to be read once.
+    fn lint_inputs_hash(&self, path: &Path, file_hashes: &mut HashMap<PathBuf, u64>) -> u64 {
+        let connected = |path: &Path| -> Vec<PathBuf> {
+            let node = match self.graph.borrow_mut().find_node(path) {
+                Some(node) => node,
+                None => return vec![path.to_path_buf()],
+            };
+            let graph = self.graph.borrow();
+            graph
+                .proximity_distances(node)
+                .into_iter()
+                .map(|(node, _)| graph.get_node(node))
+                .collect()
+        };
+
+        let mut files = connected(path);
+        if let Some(counterpart) = interface::paired_stage(path) {
+            files.extend(connected(&counterpart));
+        }
+        files.sort();
+        files.dedup();
+
+        let mut hasher = DefaultHasher::new();
+        for file in files {
+            let content_hash = *file_hashes.entry(file.clone()).or_insert_with(|| {
+                let mut hasher = DefaultHasher::new();
+                fs::read(&file).ok().hash(&mut hasher);
+                hasher.finish()
+            });
+            file.hash(&mut hasher);
+            content_hash.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Checks the outputs of a vertex shader against the inputs of its paired fragment shader, see
+    /// `interface::interface_mismatches`. Only the diagnostics in the given file and the files it includes are
+    /// returned, those of the other stage are published when it is linted itself.
+    fn lint_stage_interface(&self, uri: &Path) -> Result<HashMap<Url, Vec<Diagnostic>>> {
+        let counterpart = match interface::paired_stage(uri) {
+            Some(counterpart) => counterpart,
+            None => return Ok(HashMap::new()),
+        };
+
+        let (vertex, fragment) = match uri.extension().and_then(|ext| ext.to_str()) {
+            Some("vsh") => (uri.to_path_buf(), counterpart),
+            _ => (counterpart, uri.to_path_buf()),
+        };
+
+        let own_files = self
+            .included_files(uri)
+            .iter()
+            .map(|file| url_norm::url_for(file))
+            .collect::<Result<HashSet<_>>>()?;
+
+        let diagnostics = interface::interface_mismatches(&self.stage_interface(&vertex)?, &self.stage_interface(&fragment)?)?;
+
+        Ok(diagnostics.into_iter().filter(|(url, _)| own_files.contains(url)).collect())
+    }
+
+    // an error at each #include directive of the file whose target doesn't exist, e.g. after the header was deleted.
+    fn unresolved_includes(&self, path: &Path) -> Vec<Diagnostic> {
+        if !path.is_file() {
+            return vec![];
+        }
+
+        let source = fs::read_to_string(path).unwrap_or_default();
+        let linemap = linemap::LineMap::new(&source);
+
+        self.find_includes(path)
+            .into_iter()
+            .filter(|(include, _)| !include.is_file())
+            .map(|(include, position)| Diagnostic {
+                range: include_range(&source, &linemap, &position),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String("unresolved-include".into())),
+                code_description: None,
+                source: Some(consts::SOURCE.into()),
+                message: format!("cannot find included file {:?}", include),
+                related_information: None,
+                tags: None,
+                data: None,
+            })
+            .collect()
+    }
+
+    // an error at each #include directive of the file leading back to it through the files it includes, which would
+    // be inlined into each other forever, naming the files of the shortest cycle the directive closes.
+    fn include_cycles(&self, path: &Path) -> Vec<Diagnostic> {
+        let node = match self.graph.borrow_mut().find_node(path) {
+            Some(node) => node,
+            None => return vec![],
+        };
+
+        let source = fs::read_to_string(path).unwrap_or_default();
+        let linemap = linemap::LineMap::new(&source);
+
+        let graph = self.graph.borrow();
+        graph
+            .include_cycles(node)
+            .into_iter()
+            .map(|(position, cycle)| {
+                let files: Vec<String> = cycle
+                    .iter()
+                    .map(|node| {
+                        let file = graph.get_node(*node);
+                        let relative = file
+                            .strip_prefix(self.workspace_folder(&file).unwrap_or(&self.root))
+                            .unwrap_or(&file);
+                        relative.to_str().unwrap().to_string()
+                    })
+                    .collect();
+
+                Diagnostic {
+                    range: include_range(&source, &linemap, &position),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: Some(NumberOrString::String("include-cycle".into())),
+                    code_description: None,
+                    source: Some(consts::SOURCE.into()),
+                    message: format!("include cycle: {}", files.join(" -> ")),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                }
+            })
+            .collect()
+    }
+
+    // the interface variables declared by a stage file and the files it includes, along with the file declaring each.
+    fn stage_interface(&self, path: &Path) -> Result<Vec<(PathBuf, interface::InterfaceVar)>> {
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let mut variables = vec![];
+
+        for file in self.included_files(path) {
+            let parser_ctx = navigation::ParserContext::new(parser, &file)?;
+            variables.extend(
+                interface::interface_variables(&parser_ctx)
+                    .into_iter()
+                    .map(|var| (file.clone(), var)),
+            );
+        }
+
+        Ok(variables)
+    }
 
-        debug!(
-            "include sets diff'd";
-            "for removal" => format!("{:?}", to_be_removed),
-            "for addition" => format!("{:?}", to_be_added)
-        );
+    // the file itself followed by all the files it includes, directly or transitively.
+    fn included_files(&self, path: &Path) -> Vec<PathBuf> {
+        let node = match self.graph.borrow_mut().find_node(path) {
+            Some(node) => node,
+            None => return vec![path.to_path_buf()],
+        };
 
-        for removal in to_be_removed {
-            let child = self.graph.borrow_mut().find_node(&removal.0).unwrap();
-            self.graph.borrow_mut().remove_edge(idx, child, removal.1);
+        let graph = self.graph.borrow();
+        graph
+            .include_distances(node)
+            .into_iter()
+            .map(|(node, _)| graph.get_node(node))
+            .collect()
+    }
+
+    // the functions declared in the files the file includes, directly or transitively, for the calls in it to be
+    // checked against. Unreadable includes are left out, their own diagnostics telling why.
+    fn included_function_arities(&self, parser: &mut Parser, path: &Path) -> Vec<lints::FunctionArity> {
+        let mut functions = vec![];
+        for file in self.included_files(path).into_iter().filter(|file| file != path) {
+            let arities = navigation::ParserContext::new(parser, &file).and_then(|ctx| lints::function_arities(&ctx, &file));
+            match arities {
+                Ok(arities) => functions.extend(arities),
+                Err(e) => warn!("skipping unreadable include"; "error" => format!("{:?}", e), "path" => file.to_str().unwrap()),
+            }
         }
+        functions
+    }
 
-        for insertion in to_be_added {
-            self.add_include(includes.iter().find(|f| f.0 == *insertion.0).unwrap().clone(), idx);
+    fn lint_syntax_tree(&self, uri: &Path) -> Vec<Diagnostic> {
+        let parser = &mut self.tree_sitter.borrow_mut();
+        let included = self.included_function_arities(parser, uri);
+        match self.lint_cache.borrow_mut().lint(parser, uri, &self.configuration, &included) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) if matches!(e.downcast_ref(), Some(navigation::NavigationError::ParseFailed)) => {
+                warn!("parsing timed out"; "path" => uri.to_str().unwrap());
+                vec![Diagnostic {
+                    range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String("parse-timeout".into())),
+                    code_description: None,
+                    source: Some(consts::SOURCE.into()),
+                    message: e.to_string(),
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                }]
+            }
+            Err(e) => {
+                error!("error linting syntax tree"; "error" => format!("{:?}", e), "path" => uri.to_str().unwrap());
+                vec![]
+            }
         }
     }
 
-    pub fn lint(&self, uri: &Path) -> Result<HashMap<Url, Vec<Diagnostic>>> {
+    fn lint_compiled(&self, uri: &Path) -> Result<HashMap<Url, Vec<Diagnostic>>> {
         // get all top level ancestors of this file
         let file_ancestors = match self.get_file_toplevel_ancestors(uri) {
             Ok(opt) => match opt {
@@ -399,8 +1611,8 @@ impl MinecraftShaderLanguageServer {
                 }
             };
 
-            if !is_top_level(root_path.strip_prefix(&self.root).unwrap()) {
-                warn!("got a non-valid toplevel file"; "root_ancestor" => root_path.to_str().unwrap(), "stripped" => root_path.strip_prefix(&self.root).unwrap().to_str().unwrap());
+            if !is_top_level(self.folder_relative(&root_path)) {
+                warn!("got a non-valid toplevel file"; "root_ancestor" => root_path.to_str().unwrap(), "stripped" => self.folder_relative(&root_path).to_str().unwrap());
                 back_fill(&all_sources, &mut diagnostics);
                 return Ok(diagnostics);
             }
@@ -447,8 +1659,8 @@ impl MinecraftShaderLanguageServer {
                     None => continue,
                 };
 
-                if !is_top_level(root_path.strip_prefix(&self.root).unwrap()) {
-                    warn!("got a non-valid toplevel file"; "root_ancestor" => root_path.to_str().unwrap(), "stripped" => root_path.strip_prefix(&self.root).unwrap().to_str().unwrap());
+                if !is_top_level(self.folder_relative(&root_path)) {
+                    warn!("got a non-valid toplevel file"; "root_ancestor" => root_path.to_str().unwrap(), "stripped" => self.folder_relative(&root_path).to_str().unwrap());
                     continue;
                 }
 
@@ -552,6 +1764,8 @@ impl MinecraftShaderLanguageServer {
     pub fn publish_diagnostic(&self, diagnostics: HashMap<Url, Vec<Diagnostic>>, document_version: Option<i32>) {
         // info!("DIAGNOSTICS:\n{:?}", diagnostics);
         for (uri, diagnostics) in diagnostics {
+            let diagnostics = severities::apply(diagnostics, &self.severity_overrides);
+            self.diagnostics.borrow_mut().insert(uri.clone(), diagnostics.clone());
             self.endpoint
                 .send_notification(
                     PublishDiagnostics::METHOD,
@@ -584,27 +1798,7 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
         logging::slog_with_trace_id(|| {
             info!("starting server...");
 
-            let capabilities = ServerCapabilities {
-                definition_provider: Some(OneOf::Left(true)),
-                references_provider: Some(OneOf::Left(true)),
-                document_symbol_provider: Some(OneOf::Left(true)),
-                document_link_provider: Some(DocumentLinkOptions {
-                    resolve_provider: None,
-                    work_done_progress_options: WorkDoneProgressOptions { work_done_progress: None },
-                }),
-                execute_command_provider: Some(ExecuteCommandOptions {
-                    commands: vec!["graphDot".into()],
-                    work_done_progress_options: WorkDoneProgressOptions { work_done_progress: None },
-                }),
-                text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
-                    open_close: Some(true),
-                    will_save: None,
-                    will_save_wait_until: None,
-                    change: Some(TextDocumentSyncKind::FULL),
-                    save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions { include_text: Some(true) })),
-                })),
-                ..ServerCapabilities::default()
-            };
+            let capabilities = server_capabilities();
 
             let root = match params.root_uri {
                 Some(uri) => PathBuf::from_url(uri),
@@ -625,8 +1819,29 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
 
             self.set_status("loading", "Building dependency graph...", "$(loading~spin)");
 
-            self.root = root;
+            let options: configuration::InitializationOptions = match params.initialization_options {
+                Some(options) => from_value(options).unwrap_or_else(|e| {
+                    error!("invalid initialization options, using defaults"; "error" => format!("{:?}", e));
+                    configuration::InitializationOptions::default()
+                }),
+                None => configuration::InitializationOptions::default(),
+            };
+            self.set_parse_timeout(options.parse_timeout_ms);
+            self.severity_overrides = options.diagnostic_severities;
+            let window = params.capabilities.window;
+            self.work_done_progress = window.and_then(|window| window.work_done_progress).unwrap_or(false);
+
+            let workspace_folders = params
+                .workspace_folders
+                .unwrap_or_default()
+                .into_iter()
+                .map(|folder| PathBuf::from_url(folder.uri))
+                .filter(|folder| *folder != root)
+                .collect();
 
+            self.root = root;
+            self.workspace_folders = workspace_folders;
+            self.set_include_roots(&options.include_roots);
 
             self.build_initial_graph();
 
@@ -645,28 +1860,44 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
 
     fn workspace_change_configuration(&mut self, params: DidChangeConfigurationParams) {
         logging::slog_with_trace_id(|| {
-            #[derive(Deserialize)]
-            struct Configuration {
-                #[serde(alias = "logLevel")]
-                log_level: String,
-            }
-
-            let config: Configuration = from_value(params.settings.as_object().unwrap().get("mcglsl").unwrap().to_owned()).unwrap();
+            let config: configuration::Configuration =
+                from_value(params.settings.as_object().unwrap().get("mcglsl").unwrap().to_owned()).unwrap();
 
             info!("got updated configuration"; "config" => params.settings.as_object().unwrap().get("mcglsl").unwrap().to_string());
 
-            configuration::handle_log_level_change(config.log_level, |level| {
+            configuration::handle_log_level_change(config.log_level.clone(), |level| {
                 self.log_guard = None; // set to None so Drop is invoked
-                self.log_guard = Some(logging::set_logger_with_level(level));
-            })
+                self.log_guard = Some(match config.log_file() {
+                    Some((path, file_level)) => match logging::set_logger_with_file(level, &path, file_level) {
+                        Ok(guard) => guard,
+                        Err(e) => {
+                            let guard = logging::set_logger_with_level(level);
+                            error!("failed to open log file"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap());
+                            guard
+                        }
+                    },
+                    None => logging::set_logger_with_level(level),
+                });
+            });
+
+            // cached diagnostics may be from lints the new configuration toggles
+            self.lint_cache.borrow_mut().clear();
+            self.workspace_diagnostics.borrow_mut().clear();
+            self.configuration = config;
         });
     }
 
     fn did_open_text_document(&mut self, params: DidOpenTextDocumentParams) {
         logging::slog_with_trace_id(|| {
             //info!("opened doc {}", params.text_document.uri);
+            self.document_versions
+                .insert(params.text_document.uri.clone(), params.text_document.version);
+            self.document_linemaps
+                .insert(params.text_document.uri.clone(), linemap::LineMap::new(&params.text_document.text));
+            self.document_sources
+                .insert(params.text_document.uri.clone(), params.text_document.text);
             let path = PathBuf::from_url(params.text_document.uri);
-            if !path.starts_with(&self.root) {
+            if self.workspace_folder(&path).is_none() {
                 return;
             }
 
@@ -680,17 +1911,58 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
         });
     }
 
-    fn did_change_text_document(&mut self, _: DidChangeTextDocumentParams) {}
+    fn did_change_text_document(&mut self, params: DidChangeTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.document_versions.insert(uri.clone(), params.text_document.version);
+
+        // documents are synced in full, so the last change is the whole of the new text
+        let new = match params.content_changes.into_iter().last() {
+            Some(change) => change.text,
+            None => return,
+        };
+        let old = match self.document_sources.insert(uri.clone(), new) {
+            Some(old) => old,
+            None => return,
+        };
+        let new = &self.document_sources[&uri];
+
+        self.definition_cache.borrow_mut().invalidate(&PathBuf::from_url(uri.clone()));
+
+        let edit = lint_cache::input_edit(&old, new);
+        let map = self
+            .document_linemaps
+            .entry(uri.clone())
+            .or_insert_with(|| linemap::LineMap::new(&old));
+
+        // the diagnostics navigated between are those of the last lint, so they follow the text until the next one
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        let held = diagnostics.get_mut(&uri);
+        // the map of the text before the edit is only needed to move the positions held onto from
+        let old_map = held.is_some().then(|| map.clone());
+        map.apply_edit(new, &edit);
+
+        if let (Some(held), Some(old_map)) = (held, old_map) {
+            let shift = linemap::PositionShift::new(&old, &old_map, new, map, edit);
+            for diagnostic in held.iter_mut() {
+                diagnostic.range = shift.range(diagnostic.range);
+            }
+        }
+    }
 
-    fn did_close_text_document(&mut self, _: DidCloseTextDocumentParams) {}
+    fn did_close_text_document(&mut self, params: DidCloseTextDocumentParams) {
+        self.document_versions.remove(&params.text_document.uri);
+        self.document_sources.remove(&params.text_document.uri);
+        self.document_linemaps.remove(&params.text_document.uri);
+    }
 
     fn did_save_text_document(&mut self, params: DidSaveTextDocumentParams) {
         logging::slog_with_trace_id(|| {
             let path = PathBuf::from_url(params.text_document.uri);
-            if !path.starts_with(&self.root) {
+            if self.workspace_folder(&path).is_none() {
                 return;
             }
             self.update_includes(&path);
+            self.definition_cache.borrow_mut().invalidate(&path);
 
             match self.lint(&path) {
                 Ok(diagnostics) => self.publish_diagnostic(diagnostics, None),
@@ -699,36 +1971,157 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
         });
     }
 
-    fn did_change_watched_files(&mut self, _: DidChangeWatchedFilesParams) {}
+    fn did_change_watched_files(&mut self, params: DidChangeWatchedFilesParams) {
+        logging::slog_with_trace_id(|| {
+            // files whose diagnostics may have changed along with the watched files
+            let mut to_lint: Vec<PathBuf> = vec![];
+
+            for change in params.changes {
+                let path = PathBuf::from_url(change.uri.clone());
+                if self.workspace_folder(&path).is_none() || !has_shader_extension(&path) {
+                    continue;
+                }
 
-    fn completion(&mut self, _: TextDocumentPositionParams, completable: LSCompletable<CompletionList>) {
-        completable.complete(Err(Self::error_not_available(())));
+                info!("watched file changed"; "path" => path.to_str().unwrap(), "type" => format!("{:?}", change.typ));
+
+                self.lint_cache.borrow_mut().evict(&path);
+                self.definition_cache.borrow_mut().invalidate(&path);
+
+                // files including this one already had a node for it, whether or not it existed before. Those
+                // including it transitively see its changes as much as those including it directly
+                let node = self.graph.borrow_mut().find_node(&path);
+                let ancestors: Vec<PathBuf> = match node {
+                    Some(node) => {
+                        let graph = self.graph.borrow();
+                        graph
+                            .dependents(node)
+                            .into_iter()
+                            .filter(|ancestor| *ancestor != node)
+                            .map(|ancestor| graph.get_node(ancestor))
+                            .collect()
+                    }
+                    None => vec![],
+                };
+
+                match change.typ {
+                    FileChangeType::DELETED => {
+                        if let Some(node) = node {
+                            self.remove_deleted_file(&path, node, ancestors.is_empty());
+                        }
+                        self.publish_diagnostic(HashMap::from([(change.uri, vec![])]), None);
+                    }
+                    _ => match node {
+                        Some(_) => self.update_includes(&path),
+                        None => self.add_file_and_includes_to_graph(&path),
+                    },
+                }
+
+                if change.typ != FileChangeType::DELETED {
+                    to_lint.push(path);
+                }
+                to_lint.extend(ancestors);
+            }
+
+            to_lint.sort();
+            to_lint.dedup();
+
+            for path in to_lint {
+                // headers without a node are only linted as part of the files including them
+                if !path.is_file() {
+                    continue;
+                }
+                match self.lint(&path) {
+                    Ok(diagnostics) => self.publish_diagnostic(diagnostics, None),
+                    Err(e) => error!("error linting"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap()),
+                }
+            }
+        });
+    }
+
+    fn completion(&mut self, params: TextDocumentPositionParams, completable: LSCompletable<CompletionList>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document.uri);
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = match navigation::ParserContext::new(parser, &path) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return completable.complete(Err(MethodError {
+                        code: 42069,
+                        message: format!("error building parser context: error={}, path={:?}", e, path),
+                        data: (),
+                    }))
+                }
+            };
+
+            match completion::completions(&parser_ctx, builtins::ShaderStage::from_path(&path)) {
+                Ok(items) => completable.complete(Ok(CompletionList {
+                    is_incomplete: false,
+                    items,
+                })),
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error building completions: error={}, path={:?}", e, path),
+                    data: (),
+                })),
+            }
+        });
     }
 
     fn resolve_completion_item(&mut self, _: CompletionItem, completable: LSCompletable<CompletionItem>) {
         completable.complete(Err(Self::error_not_available(())));
     }
 
-    fn hover(&mut self, _: TextDocumentPositionParams, _: LSCompletable<Hover>) {
-        /* completable.complete(Ok(Hover{
-            contents: HoverContents::Markup(MarkupContent{
-                kind: MarkupKind::Markdown,
-                value: String::from("# Hello World"),
-            }),
-            range: None,
-        })); */
+    fn hover(&mut self, params: TextDocumentPositionParams, completable: LSCompletable<Hover>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document.uri);
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = match navigation::ParserContext::new(parser, &path) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return completable.complete(Err(MethodError {
+                        code: 42069,
+                        message: format!("error building parser context: error={}, path={:?}", e, path),
+                        data: (),
+                    }))
+                }
+            };
+
+            match hover::hover(&parser_ctx, &path, params.position) {
+                Ok(hover) if self.configuration.hover_context => {
+                    completable.complete(Ok(hover::with_context(&parser_ctx, &path, params.position, hover)))
+                }
+                Ok(hover) => completable.complete(Ok(hover.unwrap_or(Hover {
+                    contents: HoverContents::Array(vec![]),
+                    range: None,
+                }))),
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error building hover: error={}, path={:?}", e, path),
+                    data: (),
+                })),
+            }
+        });
     }
 
     fn execute_command(&mut self, params: ExecuteCommandParams, completable: LSCompletable<Option<Value>>) {
         logging::slog_with_trace_id(|| {
+            // prepare rename requests are served by a command, which the client shows the errors of itself
+            let quiet = self.cancellations.is_prepare_rename();
+            let workspace = CommandWorkspace {
+                server: self,
+                token: params.work_done_progress_params.work_done_token,
+            };
             match self
                 .command_provider
                 .as_ref()
                 .unwrap()
-                .execute(&params.command, &params.arguments, &self.root)
+                .execute(&params.command, &params.arguments, &workspace)
             {
                 Ok(resp) => {
                     info!("executed command successfully"; "command" => params.command.clone());
+                    if quiet {
+                        return completable.complete(Ok(Some(resp)));
+                    }
                     self.endpoint
                         .send_notification(
                             ShowMessage::METHOD,
@@ -742,6 +2135,9 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
                 }
                 Err(err) => {
                     error!("failed to execute command"; "command" => params.command.clone(), "error" => format!("{:?}", err));
+                    if quiet {
+                        return completable.complete(Err(MethodError::new(32420, err.to_string(), ())));
+                    }
                     self.endpoint
                         .send_notification(
                             ShowMessage::METHOD,
@@ -762,28 +2158,120 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
     }
 
     fn goto_definition(&mut self, params: TextDocumentPositionParams, completable: LSCompletable<Vec<Location>>) {
+        if self.cancellations.is_declaration() {
+            return self.goto_declaration(params, completable);
+        }
+
         logging::slog_with_trace_id(|| {
             let path = PathBuf::from_url(params.text_document.uri);
-            if !path.starts_with(&self.root) {
-                return;
-            }
-            let parser = &mut self.tree_sitter.borrow_mut();
-            let parser_ctx = match navigation::ParserContext::new(parser, &path) {
-                Ok(ctx) => ctx,
-                Err(e) => {
-                    return completable.complete(Err(MethodError {
-                        code: 42069,
-                        message: format!("error building parser context: error={}, path={:?}", e, path),
-                        data: (),
-                    }))
+            // files opened outside of the workspace aren't part of the include graph, so only the file and the files it
+            // includes are searched, with its includes resolved next to it
+            let loose = self.workspace_folder(&path).is_none();
+
+            let (locations, name, conditional) = {
+                let parser = &mut self.tree_sitter.borrow_mut();
+                let parser_ctx = match navigation::ParserContext::new(parser, &path) {
+                    Ok(ctx) => ctx,
+                    Err(e) => {
+                        return completable.complete(Err(MethodError {
+                            code: 42069,
+                            message: format!("error building parser context: error={}, path={:?}", e, path),
+                            data: (),
+                        }))
+                    }
+                };
+
+                let resolver = self.include_resolver(&path);
+                match navigation::or_nothing_at_point(parser_ctx.find_include_definition(&path, params.position, &resolver)) {
+                    Ok(Some(locations)) => return completable.complete(Ok(locations)),
+                    Ok(None) => {}
+                    Err(e) => {
+                        return completable.complete(Err(MethodError {
+                            code: 42069,
+                            message: format!("error resolving include: error={}, path={:?}", e, path),
+                            data: (),
+                        }))
+                    }
                 }
+
+                let locations = match navigation::or_nothing_at_point(parser_ctx.find_definitions(&path, params.position)) {
+                    Ok(locations) => locations.unwrap_or_default(),
+                    Err(e) => {
+                        return completable.complete(Err(MethodError {
+                            code: 42069,
+                            message: format!("error finding definitions: error={}, path={:?}", e, path),
+                            data: (),
+                        }))
+                    }
+                };
+
+                let node = parser_ctx.find_node_at_point(params.position);
+                let name = node
+                    .filter(|node| node.kind() == "identifier")
+                    .map(|node| node.utf8_text(parser_ctx.source().as_bytes()).unwrap().to_string());
+                let conditional = node.map_or(false, preprocessor::is_condition_name);
+
+                (locations, name, conditional)
             };
 
-            match parser_ctx.find_definitions(&path, params.position) {
-                Ok(locations) => completable.complete(Ok(locations.unwrap_or_default())),
+            // not defined locally, so look through the file and the files it includes
+            let locations = match &name {
+                Some(name) if locations.is_empty() => match self.find_included_definitions(&path, name) {
+                    Ok(locations) => locations,
+                    Err(e) => {
+                        return completable.complete(Err(MethodError {
+                            code: 42069,
+                            message: format!("error finding definitions in includes: error={}, path={:?}", e, path),
+                            data: (),
+                        }))
+                    }
+                },
+                _ => locations,
+            };
+
+            // nor included, so guess by the name where enabled
+            let locations = match &name {
+                Some(name) if locations.is_empty() && !loose && self.configuration.definition_workspace_fallback => {
+                    self.find_heuristic_definitions(&path, name)
+                }
+                _ => locations,
+            };
+            let locations = match &name {
+                Some(name) if locations.is_empty() && self.configuration.definition_open_documents_fallback => {
+                    self.find_open_document_definitions(&path, name)
+                }
+                _ => locations,
+            };
+
+            // built-ins are defined by GLSL rather than the pack, so their generated declarations are shown instead
+            let locations = match &name {
+                Some(name) if locations.is_empty() => builtin_document::location(name).into_iter().collect(),
+                _ => locations,
+            };
+
+            // the flags conditionals test are often never defined in source, which is worth saying over showing nothing
+            if let Some(name) = name.as_ref().filter(|_| locations.is_empty() && conditional) {
+                let message = if preprocessor::is_optifine_macro(name) {
+                    format!("`{}` is defined by OptiFine when compiling the shader pack, not in source", name)
+                } else {
+                    format!("`{}` is never defined in this file or the files it includes", name)
+                };
+                self.endpoint
+                    .send_notification(
+                        ShowMessage::METHOD,
+                        ShowMessageParams {
+                            typ: MessageType::INFO,
+                            message,
+                        },
+                    )
+                    .expect("failed to send popup/show message notification");
+            }
+
+            match self.definitions_or_references(&path, params.position, locations) {
+                Ok(locations) => completable.complete(Ok(locations)),
                 Err(e) => completable.complete(Err(MethodError {
                     code: 42069,
-                    message: format!("error finding definitions: error={}, path={:?}", e, path),
+                    message: format!("error finding references of declaration: error={}, path={:?}", e, path),
                     data: (),
                 })),
             }
@@ -793,9 +2281,23 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
     fn references(&mut self, params: ReferenceParams, completable: LSCompletable<Vec<Location>>) {
         logging::slog_with_trace_id(|| {
             let path = PathBuf::from_url(params.text_document_position.text_document.uri);
-            if !path.starts_with(&self.root) {
-                return;
+            let position = params.text_document_position.position;
+
+            let token = params.work_done_progress_params.work_done_token;
+            match self.find_all_references(&path, position, token, &self.cancellations.current()) {
+                Ok(locations) => completable.complete(Ok(locations)),
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error finding references: error={}, path={:?}", e, path),
+                    data: (),
+                })),
             }
+        });
+    }
+
+    fn document_highlight(&mut self, params: TextDocumentPositionParams, completable: LSCompletable<Vec<DocumentHighlight>>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document.uri);
             let parser = &mut self.tree_sitter.borrow_mut();
             let parser_ctx = match navigation::ParserContext::new(parser, &path) {
                 Ok(ctx) => ctx,
@@ -808,27 +2310,27 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
                 }
             };
 
-            match parser_ctx.find_references(&path, params.text_document_position.position) {
-                Ok(locations) => completable.complete(Ok(locations.unwrap_or_default())),
+            match navigation::or_nothing_at_point(parser_ctx.document_highlights(&path, params.position)) {
+                Ok(ranges) => completable.complete(Ok(ranges
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|range| DocumentHighlight {
+                        range,
+                        kind: Some(DocumentHighlightKind::TEXT),
+                    })
+                    .collect())),
                 Err(e) => completable.complete(Err(MethodError {
                     code: 42069,
-                    message: format!("error finding definitions: error={}, path={:?}", e, path),
+                    message: format!("error finding highlights: error={}, path={:?}", e, path),
                     data: (),
                 })),
             }
         });
     }
 
-    fn document_highlight(&mut self, _: TextDocumentPositionParams, completable: LSCompletable<Vec<DocumentHighlight>>) {
-        completable.complete(Err(Self::error_not_available(())));
-    }
-
     fn document_symbols(&mut self, params: DocumentSymbolParams, completable: LSCompletable<DocumentSymbolResponse>) {
         logging::slog_with_trace_id(|| {
             let path = PathBuf::from_url(params.text_document.uri);
-            if !path.starts_with(&self.root) {
-                return;
-            }
             let parser = &mut self.tree_sitter.borrow_mut();
             let parser_ctx = match navigation::ParserContext::new(parser, &path) {
                 Ok(ctx) => ctx,
@@ -854,20 +2356,142 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
         });
     }
 
-    fn workspace_symbols(&mut self, _: WorkspaceSymbolParams, completable: LSCompletable<DocumentSymbolResponse>) {
-        completable.complete(Err(Self::error_not_available(())));
+    fn workspace_symbols(&mut self, params: WorkspaceSymbolParams, completable: LSCompletable<DocumentSymbolResponse>) {
+        logging::slog_with_trace_id(|| {
+            let files = self.shader_files();
+            let query = params.query;
+
+            let cancellation = self.cancellations.current();
+            let mut symbols = self.list_workspace_symbols(
+                &files,
+                |symbol| fuzzy::score(&query, &symbol.name).is_some(),
+                params.work_done_progress_params.work_done_token,
+                &cancellation,
+            );
+            // best matches first, ties in name order
+            symbols.sort_by_cached_key(|symbol| (Reverse(fuzzy::score(&query, &symbol.name)), symbol.name.clone()));
+
+            completable.complete(Ok(DocumentSymbolResponse::Flat(symbols)));
+        });
     }
 
-    fn code_action(&mut self, _: CodeActionParams, completable: LSCompletable<Vec<Command>>) {
-        completable.complete(Err(Self::error_not_available(())));
+    fn code_action(&mut self, params: CodeActionParams, completable: LSCompletable<Vec<Command>>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document.uri);
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = match navigation::ParserContext::new(parser, &path) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return completable.complete(Err(MethodError {
+                        code: 42069,
+                        message: format!("error building parser context: error={}, path={:?}", e, path),
+                        data: (),
+                    }))
+                }
+            };
+
+            // each refactoring with its title and what it does, for logging it failing
+            let mut edits = vec![
+                (
+                    "Organize includes",
+                    "organizing includes",
+                    organize_includes::organize_includes(&parser_ctx, &path, &self.include_resolver(&path)),
+                ),
+                (
+                    "Add include guard",
+                    "adding include guard",
+                    include_guard::add_include_guard(&parser_ctx, &path),
+                ),
+                (
+                    "Inline variable",
+                    "inlining variable",
+                    inline_variable::inline_variable(&parser_ctx, &path, params.range.start),
+                ),
+            ];
+            if params.range.start != params.range.end {
+                edits.push((
+                    "Extract function",
+                    "extracting function",
+                    extract_function::extract_function(&parser_ctx, &path, params.range),
+                ));
+            }
+
+            let mut actions = vec![];
+            for (title, action, edit) in edits {
+                match edit {
+                    Ok(Some(edit)) => actions.push(Command {
+                        title: title.into(),
+                        command: "mcglsl.applyWorkspaceEdit".into(),
+                        arguments: Some(vec![serde_json::to_value(edit).unwrap()]),
+                    }),
+                    Ok(None) => {}
+                    // one refactoring failing, as on an unreadable include, leaves the others to be offered still
+                    Err(e) => {
+                        error!("error building code action"; "action" => action, "error" => format!("{:?}", e), "path" => path.to_str().unwrap())
+                    }
+                }
+            }
+
+            completable.complete(Ok(actions))
+        });
     }
 
-    fn code_lens(&mut self, _: CodeLensParams, completable: LSCompletable<Vec<CodeLens>>) {
-        completable.complete(Err(Self::error_not_available(())));
+    fn code_lens(&mut self, params: CodeLensParams, completable: LSCompletable<Vec<CodeLens>>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document.uri);
+            let parser = &mut self.tree_sitter.borrow_mut();
+            let parser_ctx = match navigation::ParserContext::new(parser, &path) {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return completable.complete(Err(MethodError {
+                        code: 42069,
+                        message: format!("error building parser context: error={}, path={:?}", e, path),
+                        data: (),
+                    }))
+                }
+            };
+
+            match parser_ctx.code_lenses(&path) {
+                Ok(lenses) => completable.complete(Ok(lenses)),
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error finding code lenses: error={}, path={:?}", e, path),
+                    data: (),
+                })),
+            }
+        });
     }
 
-    fn code_lens_resolve(&mut self, _: CodeLens, completable: LSCompletable<CodeLens>) {
-        completable.complete(Err(Self::error_not_available(())));
+    fn code_lens_resolve(&mut self, lens: CodeLens, completable: LSCompletable<CodeLens>) {
+        logging::slog_with_trace_id(|| {
+            let data: lsp_ext::CodeLensData = match lens.data.clone().map(from_value) {
+                Some(Ok(data)) => data,
+                _ => return completable.complete(Ok(lens)),
+            };
+
+            // functions are counted across the files including or included by theirs, as those of library files
+            // are often only called by the files including them
+            let path = PathBuf::from_url(data.uri);
+            let references = match self.find_all_references(&path, data.position, None, &self.cancellations.current()) {
+                Ok(references) => references,
+                Err(e) => {
+                    return completable.complete(Err(MethodError {
+                        code: 42069,
+                        message: format!("error finding references: error={}, path={:?}", e, path),
+                        data: (),
+                    }))
+                }
+            };
+
+            match navigation::resolve_code_lens(lens, references) {
+                Ok(lens) => completable.complete(Ok(lens)),
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error resolving code lens: error={}, path={:?}", e, path),
+                    data: (),
+                })),
+            }
+        });
     }
 
     fn document_link(&mut self, params: DocumentLinkParams, completable: LSCompletable<Vec<DocumentLink>>) {
@@ -936,7 +2560,28 @@ impl LanguageServerHandling for MinecraftShaderLanguageServer {
         completable.complete(Err(Self::error_not_available(())));
     }
 
-    fn rename(&mut self, _: RenameParams, completable: LSCompletable<WorkspaceEdit>) {
-        completable.complete(Err(Self::error_not_available(())));
+    fn rename(&mut self, params: RenameParams, completable: LSCompletable<WorkspaceEdit>) {
+        logging::slog_with_trace_id(|| {
+            let path = PathBuf::from_url(params.text_document_position.text_document.uri);
+
+            // only struct fields are renamed, across the files including or included by the one they're renamed in
+            match self.rename_edit(&path, params.text_document_position.position, &params.new_name) {
+                Ok(Some(edit)) => completable.complete(Ok(edit)),
+                Ok(None) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: "only struct fields can be renamed".into(),
+                    data: (),
+                })),
+                // nothing to rename past the end of the file, which isn't worth reporting
+                Err(e) if matches!(e.downcast_ref(), Some(navigation::NavigationError::NoNodeAtPoint(_))) => {
+                    completable.complete(Ok(WorkspaceEdit::default()))
+                }
+                Err(e) => completable.complete(Err(MethodError {
+                    code: 42069,
+                    message: format!("error renaming: error={}, path={:?}", e, path),
+                    data: (),
+                })),
+            }
+        });
     }
 }
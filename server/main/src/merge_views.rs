@@ -1,7 +1,7 @@
 use std::cmp::min;
 use std::iter::Peekable;
 use std::{
-    collections::{HashMap, LinkedList, VecDeque},
+    collections::{HashMap, HashSet, LinkedList, VecDeque},
     path::{Path, PathBuf},
 };
 
@@ -42,6 +42,8 @@ pub struct MergeViewBuilder<'a> {
     // is included into the parent in line-sorted order. This is necessary for files that are imported
     // more than once into the same parent, so we can easily get the next include position.
     parent_child_edge_iterator: HashMap<FilialTuple, Box<(dyn Iterator<Item = IncludePosition> + 'a)>>,
+    // the `#pragma once` files merged so far. Later includes of them are dropped without inlining anything.
+    merged_once: HashSet<NodeIndex>,
 }
 
 impl<'a> MergeViewBuilder<'a> {
@@ -56,6 +58,7 @@ impl<'a> MergeViewBuilder<'a> {
             source_mapper,
             last_offset_set: HashMap::new(),
             parent_child_edge_iterator: HashMap::new(),
+            merged_once: HashSet::new(),
         }
     }
 
@@ -148,7 +151,12 @@ impl<'a> MergeViewBuilder<'a> {
             );
 
             merge_list.push_back(&parent_source[offset..char_for_line]);
-            self.add_opening_line_directive(&child_path, child, merge_list, extra_lines);
+
+            // the DFS doesn't descend into repeated #pragma once files, so they're always leaves here
+            let repeated = self.graph.is_pragma_once(child) && !self.merged_once.insert(child);
+            if !repeated {
+                self.add_opening_line_directive(&child_path, child, merge_list, extra_lines);
+            }
 
             match self.nodes_peeker.peek() {
                 Some(next) => {
@@ -163,7 +171,9 @@ impl<'a> MergeViewBuilder<'a> {
                                 false => child_source.len(),
                             }
                         };
-                        merge_list.push_back(&child_source[..offset]);
+                        if !repeated {
+                            merge_list.push_back(&child_source[..offset]);
+                        }
                         self.set_last_offset_for_tuple(Some(parent), child, 0);
                         // +2 because edge.line is 0 indexed but #line is 1 indexed and references the *following* line
                         self.add_closing_line_directive(edge.line + 2, &parent_path, parent, merge_list, extra_lines);
@@ -206,7 +216,9 @@ impl<'a> MergeViewBuilder<'a> {
                         true => child_source.len() - 1,
                         false => child_source.len(),
                     };
-                    merge_list.push_back(&child_source[..offset]);
+                    if !repeated {
+                        merge_list.push_back(&child_source[..offset]);
+                    }
                     self.set_last_offset_for_tuple(Some(parent), child, 0);
                     // +2 because edge.line is 0 indexed but #line is 1 indexed and references the *following* line
                     self.add_closing_line_directive(edge.line + 2, &parent_path, parent, merge_list, extra_lines);
@@ -642,4 +654,32 @@ mod merge_view_test {
 
         assert_eq!(result, truth);
     }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_generate_merge_list_pragma_once() {
+        let mut server = new_temp_server(None);
+
+        let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/09", &mut server);
+        server.endpoint.request_shutdown();
+        server.build_initial_graph();
+
+        let shaders = tmp_path.join("shaders");
+        let final_idx = server.graph.borrow_mut().find_node(&shaders.join("final.fsh")).unwrap();
+        let once_idx = server.graph.borrow_mut().find_node(&shaders.join("lib").join("once.glsl")).unwrap();
+        assert!(server.graph.borrow().is_pragma_once(once_idx));
+
+        let nodes = server.get_dfs_for_node(final_idx).unwrap();
+        let sources = server.load_sources(&nodes).unwrap();
+
+        let graph_borrow = server.graph.borrow();
+        let mut source_mapper = SourceMapper::new(0);
+        let result = MergeViewBuilder::new(&nodes, &sources, &graph_borrow, &mut source_mapper).build();
+
+        // inlined into a.glsl, then dropped from b.glsl along with its #include
+        assert_eq!(result.matches("float once()").count(), 1);
+        assert!(result.find("float once()").unwrap() < result.find("float a()").unwrap());
+        assert!(result.contains("float b()"));
+        assert!(!result.contains("#include"));
+    }
 }
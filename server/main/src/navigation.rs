@@ -1,12 +1,138 @@
-use std::{collections::HashMap, fs::read_to_string, path::Path, vec};
-
-use anyhow::Result;
-use rust_lsp::lsp_types::{DocumentSymbol, Location, Position, Range, SymbolKind};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs::read_to_string,
+    path::Path,
+    vec,
+};
+
+use anyhow::{anyhow, Result};
+use rust_lsp::lsp_types::{CodeLens, Command, Diagnostic, DocumentSymbol, Location, Position, Range, SymbolInformation, SymbolKind};
 use slog_scope::{debug, info, trace};
-use tree_sitter::{Node, Parser, Point, Query, QueryCursor, Tree};
+use tree_sitter::{Node, Parser, Point, Query, QueryCursor, QueryError, Tree};
 use url::Url;
 
+use crate::builtins;
+use crate::include_resolver::{self, IncludeResolver};
 use crate::linemap::LineMap;
+use crate::lsp_ext::CodeLensData;
+use crate::overloads;
+use crate::preprocessor;
+use crate::url_norm::url_for;
+
+/// The version of the format of the IDs built by `ParserContext::symbol_id`, to be bumped whenever the format
+/// changes so that tools holding on to IDs from earlier runs can tell they no longer correlate.
+pub const SYMBOL_ID_VERSION: u32 = 1;
+
+/// A call of a function found by `ParserContext::find_call_sites`, with the text of each argument expression so
+/// that a references view can preview how the function is called.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallSite {
+    pub location: Location,
+    pub arguments: Vec<String>,
+}
+
+/// A name used as a function or type, as listed by `ParserContext::symbol_uses`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymbolUse {
+    pub name: String,
+    // either `function` for the callee of a call, or `type` for a type named in a declaration
+    pub kind: &'static str,
+    pub location: Location,
+}
+
+/// A struct defined in a file, as listed by `ParserContext::struct_definitions`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDefinition {
+    pub name: String,
+    // the whole of the struct's definition, and the location of its name within it
+    pub range: Range,
+    pub location: Location,
+    // the names of the structs its fields are of, in order and without repeats
+    pub field_types: Vec<String>,
+}
+
+/// The types of the struct fields and global variables declared in a file, as listed by
+/// `ParserContext::declared_types`, for the files it's included with to resolve field accesses through.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeclaredTypes {
+    // the type of each field by the name of its struct and its own name
+    pub fields: HashMap<(String, String), String>,
+    pub globals: HashMap<String, String>,
+}
+
+impl DeclaredTypes {
+    // adds the types declared by another of the files included together
+    pub fn extend(&mut self, other: DeclaredTypes) {
+        self.fields.extend(other.fields);
+        self.globals.extend(other.globals);
+    }
+}
+
+/// A function defined or declared in a file, as listed by `ParserContext::functions`. Ranges are byte ranges
+/// into the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionInfo {
+    pub name: String,
+    // the return type, name and parameters with whitespace collapsed, e.g. `vec3 shade(vec3 normal, float depth)`
+    pub signature: String,
+    // the range of the whole definition or prototype
+    pub range: std::ops::Range<usize>,
+    pub name_range: std::ops::Range<usize>,
+    // whether this is a prototype such as `float luma(vec3 color);` rather than a definition with a body
+    pub is_prototype: bool,
+    // the preprocessor conditions to be met for the function to be compiled, innermost first
+    pub conditions: Vec<preprocessor::Condition>,
+}
+
+/// How much of the tree around the name at a point `select_node_at_point` returns, for features to pick the node
+/// they work with rather than each adjusting the name they're given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeSelection {
+    /// The name alone, as `find_node_at_point` returns it. Definitions, references, renames and everything else
+    /// resolving the name itself select it this way.
+    Name,
+    /// The expression accessing through the name: the field expression a field is selected by, as `light.color` for
+    /// `color`, or the subscript of an array or vector being indexed, as `weights[i]` for `weights`. Any other name,
+    /// including those used as indices, is returned alone. Hover selects this way, describing fields and elements by
+    /// what they're accessed through.
+    Expression,
+}
+
+/// The ways in which navigating a file can fail, carried by the `anyhow::Error`s returned so that callers can tell
+/// them apart with `downcast_ref`, e.g. to stay silent when there's merely nothing at the position requested.
+#[derive(Debug)]
+pub enum NavigationError {
+    IoError(std::io::Error),
+    // the parse was abandoned, as happens when the parser's timeout is hit, see `Parser::set_timeout_micros`
+    ParseFailed,
+    QueryError(QueryError),
+    NoNodeAtPoint(Position),
+    // the operation doesn't apply to the node at the position, with the reason why
+    Unsupported(String),
+}
+
+impl Display for NavigationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NavigationError::IoError(e) => write!(f, "error reading file: {}", e),
+            NavigationError::ParseFailed => write!(f, "parsing was abandoned before the end of the file"),
+            NavigationError::QueryError(e) => write!(f, "error compiling query: {}", e),
+            NavigationError::NoNodeAtPoint(point) => write!(f, "nothing found at {}:{}", point.line, point.character),
+            NavigationError::Unsupported(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for NavigationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NavigationError::IoError(e) => Some(e),
+            NavigationError::QueryError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Default)]
 struct SymbolName(String);
@@ -71,6 +197,36 @@ macro_rules! find_function_def_str {
     };
 }
 
+macro_rules! find_struct_def_str {
+    () => {
+        r#"
+            (
+                (struct_specifier
+                    name: (type_identifier) @struct
+                    body: (field_declaration_list))
+                (#match? @struct "^{}$")
+            )
+        "#
+    };
+}
+
+macro_rules! find_macro_def_str {
+    () => {
+        r#"
+            (
+                (preproc_def
+                    name: (identifier) @macro)
+                (#match? @macro "^{0}$")
+            )
+            (
+                (preproc_function_def
+                    name: (identifier) @macro)
+                (#match? @macro "^{0}$")
+            )
+        "#
+    };
+}
+
 macro_rules! find_function_refs_str {
     () => {
         r#"
@@ -83,18 +239,41 @@ macro_rules! find_function_refs_str {
     };
 }
 
+macro_rules! find_identifier_refs_str {
+    () => {
+        r#"
+            (
+                (identifier) @identifier
+                (#match? @identifier "^{}$")
+            )
+        "#
+    };
+}
+
 macro_rules! find_variable_def_str {
     () => {
         r#"
             [
-                (init_declarator 
-                    (identifier) @variable)
+                (init_declarator
+                    declarator: (identifier) @variable)
                     
                 (parameter_declaration
                     (identifier) @variable)
                     
                 (declaration
                     (identifier) @variable)
+
+                (init_declarator
+                    declarator: (array_declarator
+                        declarator: (identifier) @variable))
+
+                (parameter_declaration
+                    (array_declarator
+                        declarator: (identifier) @variable))
+
+                (declaration
+                    (array_declarator
+                        declarator: (identifier) @variable))
                 
                 (#match? @variable "^{}$")
             ]
@@ -102,6 +281,58 @@ macro_rules! find_variable_def_str {
     };
 }
 
+const LIST_FIELD_ACCESSES_STR: &str = r#"
+    (field_expression
+        field: (field_identifier) @field) @expression
+"#;
+
+const LIST_STRUCT_FIELDS_STR: &str = r#"
+    (struct_specifier
+        name: (type_identifier) @struct
+        body: (field_declaration_list
+            (field_declaration) @field))
+"#;
+
+const LIST_GLOBAL_VARIABLES_STR: &str = r#"
+    (translation_unit
+        (declaration
+            declarator: [
+                (identifier) @variable
+                (array_declarator
+                    declarator: (identifier) @variable)
+                (init_declarator
+                    declarator: (identifier) @variable)
+                (init_declarator
+                    declarator: (array_declarator
+                        declarator: (identifier) @variable))
+            ]))
+"#;
+
+const LIST_GLOBAL_DEFINITIONS_STR: &str = r#"
+    (function_definition
+        declarator: (function_declarator
+            declarator: (identifier) @definition))
+
+    (preproc_def
+        name: (identifier) @definition)
+
+    (preproc_function_def
+        name: (identifier) @definition)
+
+    (struct_specifier
+        name: (type_identifier) @definition
+        body: (field_declaration_list))
+
+    (translation_unit
+        (declaration
+            declarator: (identifier) @definition))
+
+    (translation_unit
+        (declaration
+            declarator: (init_declarator
+                declarator: (identifier) @definition)))
+"#;
+
 const LIST_SYMBOLS_STR: &str = r#"
     ; global consts
     (declaration
@@ -138,6 +369,29 @@ const LIST_SYMBOLS_STR: &str = r#"
                  ])) @field_list)
 "#;
 
+const LIST_FUNCTIONS_STR: &str = r#"
+    (function_definition
+        declarator: (function_declarator
+            declarator: (identifier) @name)) @definition
+
+    (declaration
+        declarator: (function_declarator
+            declarator: (identifier) @name)) @prototype
+"#;
+
+const LIST_SYMBOL_USES_STR: &str = r#"
+    (call_expression
+        function: (identifier) @function)
+
+    (type_identifier) @type
+"#;
+
+const LIST_STRUCT_DEFINITIONS_STR: &str = r#"
+    (struct_specifier
+        name: (type_identifier) @definition
+        body: (field_declaration_list))
+"#;
+
 pub struct ParserContext<'a> {
     source: String,
     tree: Tree,
@@ -147,9 +401,28 @@ pub struct ParserContext<'a> {
 
 impl<'a> ParserContext<'a> {
     pub fn new(parser: &'a mut Parser, path: &Path) -> Result<Self> {
-        let source = read_to_string(path)?;
+        let source = read_to_string(path).map_err(NavigationError::IoError)?;
+
+        Self::from_source(parser, source)
+    }
+
+    pub fn from_source(parser: &'a mut Parser, source: String) -> Result<Self> {
+        let tree = parse(parser, &source, None)?;
+
+        let linemap = LineMap::new(&source);
+
+        Ok(ParserContext {
+            source,
+            tree,
+            linemap,
+            parser,
+        })
+    }
 
-        let tree = parser.parse(&source, None).unwrap();
+    /// Parses the source reusing the unchanged parts of a previous tree of the file, which must have been updated
+    /// with `Tree::edit` to match the new source.
+    pub fn reparse(parser: &'a mut Parser, source: String, old_tree: &Tree) -> Result<Self> {
+        let tree = parse(parser, &source, Some(old_tree))?;
 
         let linemap = LineMap::new(&source);
 
@@ -162,7 +435,7 @@ impl<'a> ParserContext<'a> {
     }
 
     pub fn list_symbols(&self, _path: &Path) -> Result<Option<Vec<DocumentSymbol>>> {
-        let query = Query::new(tree_sitter_glsl::language(), LIST_SYMBOLS_STR)?;
+        let query = new_query(LIST_SYMBOLS_STR)?;
         let mut query_cursor = QueryCursor::new();
 
         let mut parent_child_vec: Vec<(Option<SymbolName>, DocumentSymbol)> = vec![];
@@ -190,16 +463,7 @@ impl<'a> ParserContext<'a> {
                 _ => (SymbolKind::NULL, capture.node),
             };
 
-            let range = Range {
-                start: Position {
-                    line: node.start_position().row as u32,
-                    character: node.start_position().column as u32,
-                },
-                end: Position {
-                    line: node.end_position().row as u32,
-                    character: node.end_position().column as u32,
-                },
-            };
+            let range = self.range_for_node(&node);
 
             let name = node.utf8_text(self.source.as_bytes()).unwrap().to_string();
 
@@ -244,38 +508,231 @@ impl<'a> ParserContext<'a> {
         Ok(Some(symbols))
     }
 
-    pub fn find_definitions(&self, path: &Path, point: Position) -> Result<Option<Vec<Location>>> {
-        let current_node = match self.find_node_at_point(point) {
+    /// Lists the function definitions and prototypes of the file in source order, including those in the branches
+    /// of preprocessor conditionals.
+    pub fn functions(&self) -> Result<Vec<FunctionInfo>> {
+        let query = new_query(LIST_FUNCTIONS_STR)?;
+        let mut query_cursor = QueryCursor::new();
+
+        let mut functions = vec![];
+
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            let (mut node, mut name) = (None, None);
+            for capture in m.captures {
+                match query.capture_names()[capture.index as usize].as_str() {
+                    "name" => name = Some(capture.node),
+                    kind => node = Some((capture.node, kind == "prototype")),
+                }
+            }
+            let ((node, is_prototype), name) = match (node, name) {
+                (Some(node), Some(name)) => (node, name),
+                _ => continue,
+            };
+
+            // the signature runs up to the body of a definition, or the semicolon of a prototype
+            let signature_end = match node.child_by_field_name("body") {
+                Some(body) => body.start_byte(),
+                None => node.end_byte(),
+            };
+            let signature = self.source[node.start_byte()..signature_end].trim_end().trim_end_matches(';');
+
+            functions.push(FunctionInfo {
+                name: name.utf8_text(self.source.as_bytes())?.to_string(),
+                signature: signature.split_whitespace().collect::<Vec<_>>().join(" "),
+                range: node.byte_range(),
+                name_range: name.byte_range(),
+                is_prototype,
+                conditions: preprocessor::gating_conditions(node, &self.source),
+            });
+        }
+
+        functions.sort_by_key(|function| function.range.start);
+
+        Ok(functions)
+    }
+
+    /// Lists the names the file calls as functions or names as types, in the order they're used. The names of
+    /// structs being defined aren't uses.
+    pub fn symbol_uses(&self, path: &Path) -> Result<Vec<SymbolUse>> {
+        let query = new_query(LIST_SYMBOL_USES_STR)?;
+        let mut query_cursor = QueryCursor::new();
+
+        let mut uses = vec![];
+
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            for capture in m.captures {
+                let is_definition = capture.node.parent().map_or(false, |parent| {
+                    parent.kind() == "struct_specifier" && parent.child_by_field_name("body").is_some()
+                });
+                if is_definition {
+                    continue;
+                }
+
+                let kind = match query.capture_names()[capture.index as usize].as_str() {
+                    "function" => "function",
+                    _ => "type",
+                };
+                let range = self.range_for_node(&capture.node);
+                uses.push(SymbolUse {
+                    name: capture.node.utf8_text(self.source.as_bytes())?.to_string(),
+                    kind,
+                    location: location_for(path, range.start, range.end)?,
+                });
+            }
+        }
+
+        Ok(uses)
+    }
+
+    /// Lists the structs the file defines in source order, along with the structs their fields are of.
+    pub fn struct_definitions(&self, path: &Path) -> Result<Vec<StructDefinition>> {
+        let query = new_query(LIST_STRUCT_DEFINITIONS_STR)?;
+        let mut query_cursor = QueryCursor::new();
+
+        let mut structs = vec![];
+
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            for capture in m.captures {
+                let specifier = capture.node.parent().unwrap();
+                let body = specifier.child_by_field_name("body").unwrap();
+
+                let mut field_types: Vec<String> = vec![];
+                let mut cursor = body.walk();
+                for field in body.named_children(&mut cursor).filter(|field| field.kind() == "field_declaration") {
+                    let ty = match field.child_by_field_name("type") {
+                        Some(ty) if ty.kind() == "type_identifier" => ty.utf8_text(self.source.as_bytes())?.to_string(),
+                        _ => continue,
+                    };
+                    if !field_types.contains(&ty) {
+                        field_types.push(ty);
+                    }
+                }
+
+                let name = self.range_for_node(&capture.node);
+                structs.push(StructDefinition {
+                    name: capture.node.utf8_text(self.source.as_bytes())?.to_string(),
+                    range: self.range_for_node(&specifier),
+                    location: location_for(path, name.start, name.end)?,
+                    field_types,
+                });
+            }
+        }
+
+        Ok(structs)
+    }
+
+    /// Returns the names the file defines at file level for other files to use. These are the names found by
+    /// `find_global_definitions`, along with the functions only declared by a prototype.
+    pub fn defined_names(&self) -> Result<HashSet<String>> {
+        let mut names: HashSet<String> = self.functions()?.into_iter().map(|function| function.name).collect();
+
+        let query = new_query(LIST_GLOBAL_DEFINITIONS_STR)?;
+        let mut query_cursor = QueryCursor::new();
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            for capture in m.captures {
+                names.insert(capture.node.utf8_text(self.source.as_bytes())?.to_string());
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Returns an unresolved `CodeLens` for every function definition in the file. The reference
+    /// count is filled in lazily by `resolve_code_lens`, keeping the initial lens list cheap.
+    pub fn code_lenses(&self, path: &Path) -> Result<Vec<CodeLens>> {
+        let uri = url_for(path)?;
+
+        let mut lenses = vec![];
+
+        for function in self.functions()?.into_iter().filter(|function| !function.is_prototype) {
+            let range = Range {
+                start: self.position_for_offset(function.name_range.start),
+                end: self.position_for_offset(function.name_range.end),
+            };
+
+            lenses.push(CodeLens {
+                range,
+                command: None,
+                data: Some(serde_json::to_value(CodeLensData {
+                    uri: uri.clone(),
+                    position: range.start,
+                })?),
+            });
+        }
+
+        debug!("found code lenses"; "count" => lenses.len());
+
+        Ok(lenses)
+    }
+
+    /// Resolves the `#include` directive under the cursor to the top of the included file, expanding the macro it
+    /// includes where it names one defined in the file. Returns `None` if the cursor is not on an include directive
+    /// or the included file does not exist.
+    pub fn find_include_definition(&self, path: &Path, point: Position, resolver: &IncludeResolver) -> Result<Option<Vec<Location>>> {
+        let mut node = match self.node_at_point(point)? {
             Some(node) => node,
             None => return Ok(None),
         };
 
-        let parent = match current_node.parent() {
-            Some(parent) => parent,
+        while node.kind() != "preproc_include" {
+            node = match node.parent() {
+                Some(parent) => parent,
+                None => return Ok(None),
+            };
+        }
+
+        let operand = match node.child_by_field_name("path") {
+            Some(operand) => operand.utf8_text(self.source.as_bytes())?,
             None => return Ok(None),
         };
+        let expand = |name: &str| {
+            preprocessor::find_define(self, name)
+                .filter(|define| define.kind() == "preproc_def")
+                .and_then(|define| define.child_by_field_name("value"))
+                .and_then(|value| value.utf8_text(self.source.as_bytes()).ok())
+        };
+        let include = match include_resolver::path_spec(operand, expand) {
+            Some(include) => include,
+            None => {
+                debug!("include operand doesn't expand to a path"; "operand" => operand);
+                return Ok(None);
+            }
+        };
 
-        debug!("matching location lookup method for parent-child tuple"; "parent" => parent.kind(), "child" => current_node.kind());
+        let target = resolver.resolve(path, include);
 
-        let locations = match (current_node.kind(), parent.kind()) {
-            (_, "call_expression") => {
-                let query_str = format!(find_function_def_str!(), current_node.utf8_text(self.source.as_bytes())?);
-                self.simple_global_search(path, &query_str)?
-            }
-            ("identifier", "argument_list")
-            | ("identifier", "field_expression")
-            | ("identifier", "binary_expression")
-            | ("identifier", "assignment_expression") => self.tree_climbing_search(path, current_node)?,
-            _ => return Ok(None),
+        debug!("resolved include directive"; "include" => include, "target" => target.to_str().unwrap());
+
+        if !target.is_file() {
+            return Ok(None);
+        }
+
+        Ok(Some(vec![location_for(&target, Position::default(), Position::default())?]))
+    }
+
+    /// Returns the range of the symbol under the cursor if it can be renamed, that is if it is a function, variable,
+    /// struct or field declared by the user. Built-ins are reported as an error so the client can explain why the
+    /// rename is refused, any other node (keywords, literals, types) as `None`.
+    pub fn prepare_rename(&self, point: Position) -> Result<Option<Range>> {
+        let node = match self.node_at_point(point)? {
+            Some(node) => node,
+            None => return Ok(None),
         };
 
-        info!("finished searching for definitions"; "count" => locations.len(), "definitions" => format!("{:?}", locations));
+        if !matches!(node.kind(), "identifier" | "field_identifier" | "type_identifier") {
+            return Ok(None);
+        }
 
-        Ok(Some(locations))
+        let name = node.utf8_text(self.source.as_bytes())?;
+        if builtins::is_builtin(name) {
+            return Err(NavigationError::Unsupported(format!("cannot rename built-in `{}`", name)).into());
+        }
+
+        Ok(Some(self.range_for_node(&node)))
     }
 
-    pub fn find_references(&self, path: &Path, point: Position) -> Result<Option<Vec<Location>>> {
-        let current_node = match self.find_node_at_point(point) {
+    pub fn find_definitions(&self, path: &Path, point: Position) -> Result<Option<Vec<Location>>> {
+        let current_node = match self.node_at_point(point)? {
             Some(node) => node,
             None => return Ok(None),
         };
@@ -285,145 +742,3019 @@ impl<'a> ParserContext<'a> {
             None => return Ok(None),
         };
 
+        debug!("matching location lookup method for parent-child tuple";
+            "parent" => parent.kind(),
+            "child" => current_node.kind(),
+            "scope" => self.scope_path(point).join("/"));
+
         let locations = match (current_node.kind(), parent.kind()) {
-            (_, "function_declarator") => {
-                let query_str = format!(find_function_refs_str!(), current_node.utf8_text(self.source.as_bytes())?);
+            (_, "call_expression") if is_callee(current_node) => {
+                let name = current_node.utf8_text(self.source.as_bytes())?;
+                let query_str = format!(find_function_def_str!(), name);
+                let mut locations = self.simple_global_search(path, &query_str)?;
+                // prototypes only declare the function, see `find_declarations`, so the body is the definition if any
+                let prototypes = self.function_locations(path, name, true)?;
+                if locations.len() > prototypes.len() {
+                    locations.retain(|location| !prototypes.contains(location));
+                }
+                // constructors of structs are called by the name of the struct, as in `Light(vec3(1.0), 2.0)`
+                if locations.is_empty() {
+                    self.type_definition_search(path, current_node.utf8_text(self.source.as_bytes())?)?
+                } else {
+                    locations
+                }
+            }
+            ("identifier", "argument_list")
+            | ("identifier", "field_expression")
+            | ("identifier", "binary_expression")
+            | ("identifier", "assignment_expression")
+            | ("identifier", "conditional_expression")
+            | ("identifier", "comma_expression")
+            | ("identifier", "subscript_expression")
+            | ("identifier", "case_statement")
+            | ("identifier", "parenthesized_expression")
+            | ("identifier", "return_statement")
+            | ("identifier", "initializer_list")
+            | ("identifier", "unary_expression") => self.tree_climbing_search(path, current_node)?,
+            ("identifier", "init_declarator") | ("identifier", "array_declarator") => {
+                self.init_declarator_search(path, current_node, parent)?
+            }
+            ("identifier", "function_declarator") if parent.parent().map_or(false, |p| p.kind() == "declaration") => {
+                self.function_locations(path, current_node.utf8_text(self.source.as_bytes())?, false)?
+            }
+            ("field_identifier", "field_expression") => self.field_definition_search(path, parent)?,
+            ("field_identifier", "field_designator") => self.designator_definition_search(path, parent)?,
+            ("type_identifier", _) => self.type_definition_search(path, current_node.utf8_text(self.source.as_bytes())?)?,
+            ("identifier", _) if preprocessor::is_condition_name(current_node) => {
+                let query_str = format!(find_macro_def_str!(), current_node.utf8_text(self.source.as_bytes())?);
                 self.simple_global_search(path, &query_str)?
             }
-            _ => return Ok(None),
+            ("preproc_arg", _) => self.macro_body_definition_search(path, point, current_node)?,
+            (child, parent_kind) => {
+                // logged above debug, as the tuple is what's needed to add support for looking definitions up here.
+                // Parents may span whole functions, so only their first line is logged.
+                info!("no definition lookup for parent-child tuple";
+                    "parent" => parent_kind,
+                    "child" => child,
+                    "parent_text" => parent.utf8_text(self.source.as_bytes())?.lines().next().unwrap_or_default(),
+                    "child_text" => current_node.utf8_text(self.source.as_bytes())?,
+                    "position" => format!("{}:{}", point.line, point.character));
+                return Ok(None);
+            }
         };
 
-        info!("finished searching for references"; "count" => locations.len(), "references" => format!("{:?}", locations));
+        info!("finished searching for definitions"; "count" => locations.len(), "definitions" => format!("{:?}", locations));
 
         Ok(Some(locations))
     }
 
-    fn tree_climbing_search(&self, path: &Path, start_node: Node) -> Result<Vec<Location>> {
-        let mut locations = vec![];
-
-        let node_text = start_node.utf8_text(self.source.as_bytes())?;
-
-        let query_str = format!(find_variable_def_str!(), node_text);
-
-        debug!("built query string"; "query" => &query_str);
-
-        let mut parent = start_node.parent();
-
-        loop {
-            if parent.is_none() {
-                trace!("no more parent left, found nothing");
-                break;
-            }
-
-            let query = Query::new(tree_sitter_glsl::language(), &query_str)?;
-            let mut query_cursor = QueryCursor::new();
-
-            trace!("running tree-sitter query for node"; "node" => format!("{:?}", parent.unwrap()), "node_text" => parent.unwrap().utf8_text(self.source.as_bytes()).unwrap());
-
-            for m in query_cursor.matches(&query, parent.unwrap(), self.source.as_bytes()) {
-                for capture in m.captures {
-                    let start = capture.node.start_position();
-                    let end = capture.node.end_position();
-
-                    locations.push(Location {
-                        uri: Url::from_file_path(path).unwrap(),
-                        range: Range {
-                            start: Position {
-                                line: start.row as u32,
-                                character: start.column as u32,
-                            },
-                            end: Position {
-                                line: end.row as u32,
-                                character: end.column as u32,
-                            },
-                        },
-                    });
-                }
-            }
+    /// Finds the declarations of the symbol at the point, told apart from its definitions as in C. Functions with a
+    /// prototype, as in `float luma(vec3 color);`, are declared by the prototype and defined by the body, while
+    /// anything else is declared where it's defined, as variables are.
+    pub fn find_declarations(&self, path: &Path, point: Position) -> Result<Option<Vec<Location>>> {
+        let node = match self.node_at_point(point)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
 
-            if !locations.is_empty() {
-                break;
+        let is_function = node.kind() == "identifier"
+            && (is_callee(node) || node.parent().map_or(false, |parent| parent.kind() == "function_declarator"));
+        if is_function {
+            let prototypes = self.function_locations(path, node.utf8_text(self.source.as_bytes())?, true)?;
+            if !prototypes.is_empty() {
+                return Ok(Some(prototypes));
             }
-
-            parent = parent.unwrap().parent();
         }
 
-        Ok(locations)
+        self.find_definitions(path, point)
     }
 
-    fn simple_global_search(&self, path: &Path, query_str: &str) -> Result<Vec<Location>> {
-        let query = Query::new(tree_sitter_glsl::language(), query_str)?;
+    // the locations of the names of the functions of the given name, either those declared by a prototype or those
+    // defined with a body.
+    fn function_locations(&self, path: &Path, name: &str, prototypes: bool) -> Result<Vec<Location>> {
+        self.functions()?
+            .into_iter()
+            .filter(|function| function.name == name && function.is_prototype == prototypes)
+            .map(|function| {
+                let start = self.position_for_offset(function.name_range.start);
+                let end = self.position_for_offset(function.name_range.end);
+                location_for(path, start, end)
+            })
+            .collect()
+    }
+
+    /// Finds the file-level definitions of the given name, being functions, macros, structs and global variables.
+    /// These are the definitions visible to other files including this one, so that struct constructors such as
+    /// `Light(vec3(1.0), 2.0)` resolve to a struct defined in an include just as calls resolve to its functions.
+    pub fn find_global_definitions(&self, path: &Path, name: &str) -> Result<Vec<Location>> {
+        let query = new_query(LIST_GLOBAL_DEFINITIONS_STR)?;
         let mut query_cursor = QueryCursor::new();
 
         let mut locations = vec![];
 
         for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
             for capture in m.captures {
-                let start = capture.node.start_position();
-                let end = capture.node.end_position();
-
-                locations.push(Location {
-                    uri: Url::from_file_path(path).unwrap(),
-                    range: Range {
-                        start: Position {
-                            line: start.row as u32,
-                            character: start.column as u32,
-                        },
-                        end: Position {
-                            line: end.row as u32,
-                            character: end.column as u32,
-                        },
-                    },
-                });
+                if capture.node.utf8_text(self.source.as_bytes())? == name {
+                    let range = self.range_for_node(&capture.node);
+                    locations.push(location_for(path, range.start, range.end)?);
+                }
             }
         }
 
         Ok(locations)
     }
 
-    fn root_node(&self) -> Node {
-        self.tree.root_node()
+    /// Finds the definitions of the symbol at the point as with `find_definitions`, pairing each with the text of
+    /// the statement declaring it for display in peek views. Function definitions are shortened to their signature.
+    pub fn peek_definition(&self, path: &Path, point: Position) -> Result<Option<Vec<(Location, String)>>> {
+        let locations = match self.find_definitions(path, point)? {
+            Some(locations) => locations,
+            None => return Ok(None),
+        };
+
+        let peeks = locations
+            .into_iter()
+            .map(|location| {
+                let context = self.peek_context(&location);
+                (location, context)
+            })
+            .collect();
+
+        Ok(Some(peeks))
     }
 
-    fn find_node_at_point(&self, pos: Position) -> Option<Node> {
-        // if we're at the end of an ident, we need to look _back_ one char instead
-        // for tree-sitter to find the right node.
-        let look_behind = {
-            let offset = self.linemap.offset_for_position(pos);
-            let char_at = self.source.as_bytes()[offset];
-            trace!("looking for non-alpha for point adjustment";
-                "offset" => offset, 
-                "char" => char_at as char,
-                "point" => format!("{:?}", pos),
-                "look_behind" => !char_at.is_ascii_alphabetic());
-            !char_at.is_ascii_alphabetic()
-        };
+    /// Returns the text of the statement declaring what's at the location as `peek_definition` does, the location
+    /// being in this context's file. Empty where nothing is declared there.
+    pub fn peek_context(&self, location: &Location) -> String {
+        let start = self.point_for_position(location.range.start);
+        let end = self.point_for_position(location.range.end);
+
+        match self
+            .root_node()
+            .named_descendant_for_point_range(start, end)
+            .and_then(enclosing_statement)
+        {
+            Some(statement) => self.statement_text(statement),
+            None => String::new(),
+        }
+    }
 
-        let mut start = Point {
-            row: pos.line as usize,
-            column: pos.character as usize,
+    /// Finds the declaration of the symbol at the point as with `find_definitions`, returning its text for copying:
+    /// the signature of a function without its body or semicolon, the whole of a struct, or the declaration of a
+    /// variable on its own, as `float offset = 1.0;` out of `float speed = 2.0, offset = 1.0;`.
+    pub fn signature(&self, path: &Path, point: Position) -> Result<Option<String>> {
+        let location = match self
+            .find_definitions(path, point)?
+            .and_then(|locations| locations.into_iter().next())
+        {
+            Some(location) => location,
+            None => return Ok(None),
         };
-        let mut end = Point {
-            row: pos.line as usize,
-            column: pos.character as usize,
+
+        let start = self.point_for_position(location.range.start);
+        let end = self.point_for_position(location.range.end);
+        let name = match self.root_node().named_descendant_for_point_range(start, end) {
+            Some(name) => name,
+            None => return Ok(None),
         };
 
-        if look_behind {
-            start.column -= 1;
-        } else {
-            end.column += 1;
-        }
+        Ok(self.declaration_signature(name))
+    }
 
-        match self.root_node().named_descendant_for_point_range(start, end) {
-            Some(node) => {
-                debug!("found a node"; 
-                    "node" => format!("{:?}", node),
-                    "text" => node.utf8_text(self.source.as_bytes()).unwrap(),
-                    "start" => format!("{}", start),
-                    "end" => format!("{}", end));
-                Some(node)
+    /// Returns the text `signature` gives for the declaration of the name, given the name where it's declared.
+    pub fn declaration_signature(&self, name: Node) -> Option<String> {
+        let statement = enclosing_statement(name)?;
+
+        let mut cursor = statement.walk();
+        let declarators: Vec<Node> = statement.children_by_field_name("declarator", &mut cursor).collect();
+
+        let is_function = declarators.first().map(|declarator| declarator.kind()) == Some("function_declarator");
+
+        let signature = match (statement.kind(), declarators.first()) {
+            ("function_definition" | "declaration", _) if is_function => {
+                let text = self.statement_text(statement);
+                text.trim_end_matches(';').split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+            ("declaration" | "field_declaration", Some(first)) => {
+                let declarator = declarators
+                    .iter()
+                    .find(|declarator| declarator.byte_range().contains(&name.start_byte()))?;
+                // the qualifiers and type ahead of the first declarator are shared by all of them
+                let specifiers = self.source[statement.start_byte()..first.start_byte()].trim_end();
+                format!("{} {};", specifiers, &self.source[declarator.byte_range()])
+            }
+            _ => self.statement_text(statement),
+        };
+
+        Some(signature)
+    }
+
+    fn statement_text(&self, statement: Node) -> String {
+        let end = match statement.child_by_field_name("body") {
+            Some(body) if statement.kind() == "function_definition" => body.start_byte(),
+            _ => statement.end_byte(),
+        };
+
+        self.source[statement.start_byte()..end].trim().to_string()
+    }
+
+    /// Finds the uses in the file of the function, struct or variable declared at the point.
+    pub fn find_references(&self, path: &Path, point: Position) -> Result<Option<Vec<Location>>> {
+        let current_node = match self.node_at_point(point)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+
+        let parent = match current_node.parent() {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+
+        let locations = match (current_node.kind(), parent.kind()) {
+            (_, "function_declarator") => self.find_function_references(path, current_node.utf8_text(self.source.as_bytes())?)?,
+            ("type_identifier", "struct_specifier") => self.find_type_references(path, current_node.utf8_text(self.source.as_bytes())?)?,
+            ("identifier", _) if self.variable_declaration(current_node)? == Some(current_node) => {
+                self.find_variable_references(path, current_node)?
+            }
+            _ => return Ok(None),
+        };
+
+        info!("finished searching for references"; "count" => locations.len(), "references" => format!("{:?}", locations));
+
+        Ok(Some(locations))
+    }
+
+    /// Finds the calls of the function of the given name made in the file, for searching the files including or
+    /// included by the one declaring it.
+    pub fn find_function_references(&self, path: &Path, name: &str) -> Result<Vec<Location>> {
+        let query_str = format!(find_function_refs_str!(), name);
+        self.simple_global_search(path, &query_str)
+    }
+
+    // the identifiers of the file naming the declared variable, other than its declaration.
+    fn find_variable_references(&self, path: &Path, declaration: Node) -> Result<Vec<Location>> {
+        let query_str = format!(find_identifier_refs_str!(), declaration.utf8_text(self.source.as_bytes())?);
+        let query = new_query(&query_str)?;
+        let mut query_cursor = QueryCursor::new();
+
+        let mut locations = vec![];
+
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            for capture in m.captures {
+                if capture.node != declaration && self.variable_declaration(capture.node)? == Some(declaration) {
+                    let range = self.range_for_node(&capture.node);
+                    locations.push(location_for(path, range.start, range.end)?);
+                }
+            }
+        }
+
+        Ok(locations)
+    }
+
+    // the uses of the struct as a type or a constructor in the file.
+    fn find_type_references(&self, path: &Path, name: &str) -> Result<Vec<Location>> {
+        Ok(self
+            .symbol_uses(path)?
+            .into_iter()
+            .filter(|symbol| symbol.name == name)
+            .map(|symbol| symbol.location)
+            .collect())
+    }
+
+    /// Finds the calls of the function declared at the point as with `find_references`, along with the text of the
+    /// arguments passed at each call.
+    pub fn find_call_sites(&self, path: &Path, point: Position) -> Result<Option<Vec<CallSite>>> {
+        let current_node = match self.node_at_point(point)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+
+        match current_node.parent() {
+            Some(parent) if parent.kind() == "function_declarator" => {}
+            _ => return Ok(None),
+        }
+
+        let query_str = format!(find_function_refs_str!(), current_node.utf8_text(self.source.as_bytes())?);
+        let query = new_query(&query_str)?;
+        let mut query_cursor = QueryCursor::new();
+
+        let mut call_sites = vec![];
+
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            for capture in m.captures {
+                let arguments = match capture.node.parent().and_then(|call| call.child_by_field_name("arguments")) {
+                    Some(arguments) => {
+                        let mut cursor = arguments.walk();
+                        arguments
+                            .named_children(&mut cursor)
+                            .filter(|arg| arg.kind() != "comment")
+                            .map(|arg| self.source[arg.byte_range()].to_string())
+                            .collect()
+                    }
+                    None => vec![],
+                };
+
+                let range = self.range_for_node(&capture.node);
+                call_sites.push(CallSite {
+                    location: location_for(path, range.start, range.end)?,
+                    arguments,
+                });
+            }
+        }
+
+        info!("finished searching for call sites"; "count" => call_sites.len());
+
+        Ok(Some(call_sites))
+    }
+
+    // Searches outwards from the start node for declarations of its identifier. As a scope may contain declarations of
+    // the same name in nested sibling branches, only the declarations whose scope encloses the start node are returned,
+    // ordered from the innermost scope outwards. If none govern the start node, all the declarations found in the
+    // nearest scope having any are returned instead.
+    fn tree_climbing_search(&self, path: &Path, start_node: Node) -> Result<Vec<Location>> {
+        let declarations = self.declarations_in_scope(start_node)?;
+
+        // names that aren't variables may be macros, as with the `STATE_IDLE` of `case STATE_IDLE:`
+        if declarations.is_empty() {
+            let query_str = format!(find_macro_def_str!(), start_node.utf8_text(self.source.as_bytes())?);
+            return self.simple_global_search(path, &query_str);
+        }
+
+        declarations
+            .into_iter()
+            .map(|node| {
+                let range = self.range_for_node(&node);
+                location_for(path, range.start, range.end)
+            })
+            .collect()
+    }
+
+    // The name declared by an `init_declarator` is its own definition, while a name used as the initializer is
+    // searched for like any other use. The same goes for an `array_declarator`, as the `kernel` of `float kernel[N]`,
+    // and the names used as its size.
+    fn init_declarator_search(&self, path: &Path, node: Node, declarator: Node) -> Result<Vec<Location>> {
+        if declarator.child_by_field_name("declarator") == Some(node) {
+            let range = self.range_for_node(&node);
+            return Ok(vec![location_for(path, range.start, range.end)?]);
+        }
+
+        self.tree_climbing_search(path, node)
+    }
+
+    /// Returns the ranges of every identifier of the file spelled as the name, in source order. There's no scope
+    /// analysis, so locals and globals of the same name are all listed, unlike with `find_references`. It's meant for
+    /// coarse uses where resolving each use isn't worth it or isn't possible.
+    pub fn usages_of_name(&self, name: &str) -> Vec<Range> {
+        let mut ranges = vec![];
+        let mut cursor = self.root_node().walk();
+        loop {
+            let node = cursor.node();
+            if node.kind() == "identifier" && node.utf8_text(self.source.as_bytes()) == Ok(name) {
+                ranges.push(self.range_for_node(&node));
+            }
+
+            if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                continue;
+            }
+            loop {
+                if !cursor.goto_parent() {
+                    return ranges;
+                }
+                if cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Finds the identifiers to highlight for the one at the point: the declaration of the variable it refers to
+    /// along with its uses, or where it doesn't resolve to a variable, as with functions, macros and names declared
+    /// in other files, every identifier spelled the same as given by `usages_of_name`.
+    pub fn document_highlights(&self, path: &Path, point: Position) -> Result<Option<Vec<Range>>> {
+        let node = match self.node_at_point(point)? {
+            Some(node) if node.kind() == "identifier" => node,
+            _ => return Ok(None),
+        };
+
+        let declaration = match self.variable_declaration(node)? {
+            Some(declaration) => declaration,
+            None => return Ok(Some(self.usages_of_name(node.utf8_text(self.source.as_bytes())?))),
+        };
+
+        let references = self.find_variable_references(path, declaration)?;
+        let mut ranges: Vec<Range> = references.into_iter().map(|location| location.range).collect();
+        ranges.push(self.range_for_node(&declaration));
+        ranges.sort_by_key(|range| range.start);
+
+        Ok(Some(ranges))
+    }
+
+    /// Returns the declared identifier of the variable or parameter the identifier refers to, being itself where it
+    /// is the name being declared. Returns `None` for names not declared in the file, such as built-ins.
+    pub fn variable_declaration<'tree>(&self, identifier: Node<'tree>) -> Result<Option<Node<'tree>>> {
+        Ok(self.declarations_in_scope(identifier)?.into_iter().next())
+    }
+
+    /// Returns the declared identifier of the variable or parameter the name would refer to if used where the node
+    /// is, looked up as with `variable_declaration`.
+    pub fn name_declaration<'tree>(&self, name: &str, node: Node<'tree>) -> Result<Option<Node<'tree>>> {
+        Ok(self.declarations_of_name(name, node)?.into_iter().next())
+    }
+
+    // the declared identifiers found by `tree_climbing_search`.
+    fn declarations_in_scope<'tree>(&self, start_node: Node<'tree>) -> Result<Vec<Node<'tree>>> {
+        self.declarations_of_name(start_node.utf8_text(self.source.as_bytes())?, start_node)
+    }
+
+    fn declarations_of_name<'tree>(&self, node_text: &str, start_node: Node<'tree>) -> Result<Vec<Node<'tree>>> {
+        let query_str = format!(find_variable_def_str!(), node_text);
+
+        debug!("built query string"; "query" => &query_str);
+
+        let query = new_query(&query_str)?;
+
+        let mut fallback = vec![];
+        let mut governing = vec![];
+
+        let mut parent = start_node.parent();
+
+        while let Some(node) = parent {
+            let mut query_cursor = QueryCursor::new();
+
+            trace!("running tree-sitter query for node"; "node" => format!("{:?}", node), "node_text" => node.utf8_text(self.source.as_bytes()).unwrap());
+
+            // a variable is only in scope after its initializer, so the `x` of `float x = x;` refers to another
+            let declarations: Vec<Node> = query_cursor
+                .matches(&query, node, self.source.as_bytes())
+                .flat_map(|m| m.captures.iter().map(|c| c.node).collect::<Vec<_>>())
+                .filter(|decl| !in_own_initializer(start_node, *decl))
+                .collect();
+
+            governing = declarations
+                .iter()
+                .filter_map(|decl| Some((ancestor_distance(start_node, enclosing_scope(*decl)?)?, *decl)))
+                .collect();
+
+            if !governing.is_empty() {
+                break;
+            }
+
+            if fallback.is_empty() {
+                fallback = declarations;
+            }
+
+            parent = node.parent();
+        }
+
+        if parent.is_none() {
+            trace!("no more parent left, found no governing declaration");
+        }
+
+        governing.sort_by_key(|(distance, _)| *distance);
+
+        if governing.is_empty() {
+            return Ok(fallback);
+        }
+
+        Ok(governing.into_iter().map(|(_, decl)| decl).collect())
+    }
+
+    fn field_definition_search(&self, path: &Path, expression: Node) -> Result<Vec<Location>> {
+        match self.resolve_field_expression(expression)? {
+            Some(declaration) => {
+                let range = self.range_for_node(&declaration);
+                Ok(vec![location_for(path, range.start, range.end)?])
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    // Finds the declaration of the field accessed by a field expression such as `a.b.c`, resolving the type of each
+    // link of the chain from left to right: the type of `a` comes from its declaration, `b` is looked up in that
+    // struct and `c` in the struct `b` is declared as. A chain may also start from a call, as in `getLight().color`,
+    // taking the return type of the function called, or the struct itself for constructors. Nothing is found if any
+    // link doesn't resolve to a struct defined in the file, as with swizzles of vectors.
+    fn resolve_field_expression<'tree>(&'tree self, expression: Node<'tree>) -> Result<Option<Node<'tree>>> {
+        Ok(self
+            .resolve_field_chain(expression, &DeclaredTypes::default())?
+            .and_then(|(_, declaration)| declaration))
+    }
+
+    // Resolves a field expression as `resolve_field_expression` does, to the name of the struct its last field is
+    // accessed on along with the field's declaration if that struct is defined in the file. Variables and fields
+    // that aren't declared in the file are typed through `external`, as with those of an included file.
+    fn resolve_field_chain<'tree>(
+        &'tree self, expression: Node<'tree>, external: &DeclaredTypes,
+    ) -> Result<Option<(String, Option<Node<'tree>>)>> {
+        // unwind the chain into the variable it starts from and the fields accessed on it, in order
+        let mut fields = vec![];
+        let mut base = expression;
+        loop {
+            let next = match base.kind() {
+                "field_expression" => {
+                    fields.push(base.child_by_field_name("field"));
+                    base.child_by_field_name("argument")
+                }
+                "subscript_expression" => base.child_by_field_name("argument"),
+                "parenthesized_expression" => base.named_child(0),
+                _ => break,
+            };
+            base = match next {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+        let fields = match fields.into_iter().rev().collect::<Option<Vec<Node>>>() {
+            Some(fields) => fields,
+            None => return Ok(None),
+        };
+
+        let mut ty = match base.kind() {
+            "identifier" => match self.declarations_in_scope(base)?.first() {
+                Some(declaration) => self.declared_type(*declaration),
+                None => external.globals.get(base.utf8_text(self.source.as_bytes())?).cloned(),
+            },
+            "call_expression" => {
+                let name = match base.child_by_field_name("function") {
+                    Some(function) if function.kind() == "identifier" => function.utf8_text(self.source.as_bytes())?,
+                    _ => return Ok(None),
+                };
+                // constructors of structs are called by the name of the struct
+                Some(self.return_type(name)?.unwrap_or_else(|| name.to_string()))
+            }
+            _ => return Ok(None),
+        };
+
+        let mut resolved = None;
+        for field in fields {
+            let struct_name = match ty {
+                Some(struct_name) => struct_name,
+                None => return Ok(None),
+            };
+            let field_name = field.utf8_text(self.source.as_bytes())?;
+            // swizzles, read or written as in `color.rgb = albedo;`, select components rather than declared fields
+            if matches!(overloads::components(&struct_name), Some((_, size)) if size > 1) {
+                debug!("field chain link is a swizzle"; "type" => struct_name, "field" => field_name);
+                return Ok(None);
+            }
+            match self.find_struct_field(&struct_name, field_name)? {
+                Some(field_declaration) => {
+                    ty = self.declared_type(field_declaration);
+                    // the struct as defined, rather than any alias it's accessed through
+                    let defined_name = field_struct_name(field_declaration, &self.source).unwrap_or(struct_name);
+                    resolved = Some((defined_name, Some(field_declaration)));
+                }
+                None => match external.fields.get(&(struct_name.clone(), field_name.to_string())) {
+                    Some(field_type) => {
+                        ty = Some(field_type.clone());
+                        resolved = Some((struct_name, None));
+                    }
+                    None => {
+                        debug!("field chain link didn't resolve"; "struct" => struct_name, "field" => field_name);
+                        return Ok(None);
+                    }
+                },
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    // the return type of the function of the given name defined or declared in the file. Overloads are taken to
+    // return the same type, as the arguments aren't matched against them.
+    fn return_type(&self, name: &str) -> Result<Option<String>> {
+        for function in self.functions()? {
+            if function.name != name {
+                continue;
+            }
+            let ty = self
+                .root_node()
+                .named_descendant_for_byte_range(function.range.start, function.range.end)
+                .and_then(|node| node.child_by_field_name("type"));
+            if let Some(ty) = ty {
+                return Ok(Some(ty.utf8_text(self.source.as_bytes())?.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    // Finds the struct field named by a designator of an initializer list, as the `color` of
+    // `Light light = { .color = vec3(1.0) };`. The grammar only models designated initializers, so fields initialized
+    // by position, whether in initializer lists or constructor calls, are not resolved.
+    fn designator_definition_search(&self, path: &Path, designator: Node) -> Result<Vec<Location>> {
+        let pair = match designator.parent().filter(|pair| pair.kind() == "initializer_pair") {
+            Some(pair) => pair,
+            None => return Ok(vec![]),
+        };
+
+        match self.resolve_designators(pair, Some(designator))? {
+            Some(declaration) => {
+                let range = self.range_for_node(&declaration);
+                Ok(vec![location_for(path, range.start, range.end)?])
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    // Resolves the designators of an initializer pair from left to right up to the given one, or all of them if
+    // `None`, returning the declaration of the last field designated. Subscripts such as the `[0]` of
+    // `.lights[0].color` designate an element of the same type as the array and are passed over.
+    fn resolve_designators<'tree>(&'tree self, pair: Node<'tree>, until: Option<Node>) -> Result<Option<Node<'tree>>> {
+        let mut struct_name = match pair.parent() {
+            Some(list) => match self.initializer_list_type(list)? {
+                Some(struct_name) => struct_name,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let mut cursor = pair.walk();
+        let designators: Vec<Node> = pair.children_by_field_name("designator", &mut cursor).collect();
+
+        let mut field: Option<Node> = None;
+        for designator in designators {
+            if designator.kind() == "field_designator" {
+                if let Some(previous) = field {
+                    struct_name = match self.declared_type(previous) {
+                        Some(struct_name) => struct_name,
+                        None => return Ok(None),
+                    };
+                }
+                let name = match designator.named_child(0) {
+                    Some(name) => name.utf8_text(self.source.as_bytes())?,
+                    None => return Ok(None),
+                };
+                field = match self.find_struct_field(&struct_name, name)? {
+                    Some(field) => Some(field),
+                    None => return Ok(None),
+                };
+            }
+            if until == Some(designator) {
+                break;
+            }
+        }
+
+        Ok(field)
+    }
+
+    // The name of the struct initialized by an initializer list, following the designators of the lists it's nested
+    // in. A list nested without a designator is taken to initialize an element of an array of the outer list's type.
+    fn initializer_list_type(&self, list: Node) -> Result<Option<String>> {
+        let parent = match list.parent() {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+
+        match parent.kind() {
+            "initializer_list" => self.initializer_list_type(parent),
+            "initializer_pair" => Ok(self.resolve_designators(parent, None)?.and_then(|field| self.declared_type(field))),
+            "init_declarator" => Ok(self.declared_type(parent)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the names of the struct and field of the struct field at the point, being either its declaration or
+    /// an access of it, with the types the file doesn't declare taken from `external`, see
+    /// `find_field_references`. Returns `None` if the point isn't on a struct field.
+    pub fn field_at_point(&self, point: Position, external: &DeclaredTypes) -> Result<Option<(String, String)>> {
+        let node = match self.node_at_point(point)? {
+            Some(node) if node.kind() == "field_identifier" => node,
+            _ => return Ok(None),
+        };
+        let name = node.utf8_text(self.source.as_bytes())?.to_string();
+
+        let parent = match node.parent() {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let struct_name = match parent.kind() {
+            "field_expression" => self.resolve_field_chain(parent, external)?.map(|(struct_name, _)| struct_name),
+            _ if enclosing_statement(node).map(|statement| statement.kind()) == Some("field_declaration") => {
+                field_struct_name(node, &self.source)
+            }
+            _ => None,
+        };
+
+        Ok(struct_name.map(|struct_name| (struct_name, name)))
+    }
+
+    /// Finds the declaration of the field of the struct of the given name and its accesses in the file, leaving
+    /// alone those of fields of the same name in other structs. The struct may be defined in another file included
+    /// with this one, the types of the fields and global variables of those files being given by `external`, so
+    /// that a field can be renamed across the files including or included by the one defining its struct.
+    pub fn find_field_references(&self, path: &Path, struct_name: &str, field: &str, external: &DeclaredTypes) -> Result<Vec<Location>> {
+        let mut ranges = vec![];
+
+        let query = new_query(LIST_STRUCT_FIELDS_STR)?;
+        let mut query_cursor = QueryCursor::new();
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            if m.captures[0].node.utf8_text(self.source.as_bytes())? != struct_name {
+                continue;
+            }
+            for identifier in field_identifiers(m.captures[1].node) {
+                if identifier.utf8_text(self.source.as_bytes())? == field {
+                    ranges.push(self.range_for_node(&identifier));
+                }
+            }
+        }
+
+        let query = new_query(LIST_FIELD_ACCESSES_STR)?;
+        let mut query_cursor = QueryCursor::new();
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            let (expression, access) = (m.captures[0].node, m.captures[1].node);
+            if access.utf8_text(self.source.as_bytes())? != field {
+                continue;
+            }
+            if matches!(self.resolve_field_chain(expression, external)?, Some((accessed, _)) if accessed == struct_name) {
+                ranges.push(self.range_for_node(&access));
+            }
+        }
+
+        debug!("found struct field references"; "struct" => struct_name, "field" => field, "count" => ranges.len());
+
+        ranges.into_iter().map(|range| location_for(path, range.start, range.end)).collect()
+    }
+
+    /// Lists the types of the struct fields and global variables declared in the file, for resolving the field
+    /// accesses of the files it's included with, see `find_field_references`.
+    pub fn declared_types(&self) -> Result<DeclaredTypes> {
+        let mut types = DeclaredTypes::default();
+
+        let query = new_query(LIST_STRUCT_FIELDS_STR)?;
+        let mut query_cursor = QueryCursor::new();
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            let struct_name = m.captures[0].node.utf8_text(self.source.as_bytes())?;
+            for identifier in field_identifiers(m.captures[1].node) {
+                if let Some(ty) = self.declared_type(identifier) {
+                    let field = identifier.utf8_text(self.source.as_bytes())?;
+                    types.fields.insert((struct_name.to_string(), field.to_string()), ty);
+                }
+            }
+        }
+
+        let query = new_query(LIST_GLOBAL_VARIABLES_STR)?;
+        let mut query_cursor = QueryCursor::new();
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            let variable = m.captures[0].node;
+            if let Some(ty) = self.declared_type(variable) {
+                types.globals.insert(variable.utf8_text(self.source.as_bytes())?.to_string(), ty);
             }
-            None => None,
         }
+
+        Ok(types)
+    }
+
+    /// Builds an ID for the symbol at the point that stays the same across runs, for tools indexing symbols to
+    /// correlate them. Uses are first resolved to their declaration in the file, so that all the uses of a symbol
+    /// share its ID. IDs have the form `mcglsl:<version>:<file url>#<kind>:<qualified name>`, where the version is
+    /// `SYMBOL_ID_VERSION` and the kinds and qualified names are:
+    ///
+    /// - `func:noise(vec2,float)` for functions, with the types of their parameters to tell overloads apart
+    /// - `macro:SHADOWS` for `#define`s
+    /// - `struct:Light` for structs, and `field:Light.color` for their fields
+    /// - `var:sunPosition` for global variables
+    /// - `param:noise(vec2,float)/p` and `local:main()/depth` for parameters and local variables
+    pub fn symbol_id(&self, path: &Path, point: Position) -> Result<Option<String>> {
+        let node = match self.node_at_point(point)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+
+        let declaration = match symbol_kind(node) {
+            Some(_) => node,
+            None => match self
+                .find_definitions(path, point)?
+                .and_then(|locations| locations.into_iter().next())
+            {
+                Some(location) => match self.find_node_at_point(location.range.start) {
+                    Some(declaration) => declaration,
+                    None => return Ok(None),
+                },
+                None => return Ok(None),
+            },
+        };
+
+        let kind = match symbol_kind(declaration) {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+        let name = declaration.utf8_text(self.source.as_bytes())?;
+
+        let qualified_name = match kind {
+            "func" => match declaration.parent() {
+                Some(declarator) => self.function_signature_id(declarator)?,
+                None => return Ok(None),
+            },
+            "field" => match field_struct_name(declaration, &self.source) {
+                Some(struct_name) => format!("{}.{}", struct_name, name),
+                None => return Ok(None),
+            },
+            "param" | "local" => {
+                let mut parent = declaration.parent();
+                while let Some(node) = parent.filter(|node| node.kind() != "function_definition") {
+                    parent = node.parent();
+                }
+                match parent.and_then(|function| function.child_by_field_name("declarator")) {
+                    Some(declarator) => format!("{}/{}", self.function_signature_id(declarator)?, name),
+                    None => return Ok(None),
+                }
+            }
+            _ => name.to_string(),
+        };
+
+        Ok(Some(format!(
+            "mcglsl:{}:{}#{}:{}",
+            SYMBOL_ID_VERSION,
+            url_for(path)?,
+            kind,
+            qualified_name
+        )))
+    }
+
+    // the name of the function declared by a function declarator along with the types of its parameters, as in
+    // `noise(vec2,float)` for `float noise(vec2 p, float scale)`.
+    fn function_signature_id(&self, declarator: Node) -> Result<String> {
+        let name = match declarator.child_by_field_name("declarator") {
+            Some(name) => name.utf8_text(self.source.as_bytes())?,
+            None => return Err(anyhow!("function declarator without a name")),
+        };
+
+        let mut types = vec![];
+        if let Some(parameters) = declarator.child_by_field_name("parameters") {
+            let mut cursor = parameters.walk();
+            for parameter in parameters
+                .named_children(&mut cursor)
+                .filter(|node| node.kind() == "parameter_declaration")
+            {
+                if let Some(type_node) = parameter.child_by_field_name("type") {
+                    types.push(type_node.utf8_text(self.source.as_bytes())?.split_whitespace().collect::<String>());
+                }
+            }
+        }
+
+        Ok(format!("{}({})", name, types.join(",")))
+    }
+
+    /// Returns the name of the type an identifier is declared with, as a variable, parameter or struct field.
+    pub fn declared_type(&self, identifier: Node) -> Option<String> {
+        let mut parent = identifier.parent();
+        while let Some(node) = parent {
+            if matches!(node.kind(), "declaration" | "parameter_declaration" | "field_declaration") {
+                let type_node = node.child_by_field_name("type")?;
+                // structs declared along with the variable, as in `struct Light { .. } light;`
+                let type_node = type_node.child_by_field_name("name").unwrap_or(type_node);
+                return type_node.utf8_text(self.source.as_bytes()).ok().map(String::from);
+            }
+            parent = node.parent();
+        }
+        None
+    }
+
+    // finds the identifier of a field declared in the struct of the given name, following any aliases of the name.
+    fn find_struct_field(&self, struct_name: &str, field: &str) -> Result<Option<Node>> {
+        let aliases = preprocessor::resolve_alias(self, struct_name);
+        let resolved = match aliases.last() {
+            Some(define) => preprocessor::alias_target(self, *define).unwrap(),
+            None => struct_name,
+        };
+
+        let query = new_query(&format!(find_struct_def_str!(), resolved))?;
+        let mut query_cursor = QueryCursor::new();
+
+        let body = match query_cursor
+            .matches(&query, self.root_node(), self.source.as_bytes())
+            .flat_map(|m| m.captures.iter().map(|c| c.node).collect::<Vec<_>>())
+            .find_map(|name| name.parent()?.child_by_field_name("body"))
+        {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+
+        let mut cursor = body.walk();
+        for field_declaration in body.named_children(&mut cursor).filter(|node| node.kind() == "field_declaration") {
+            for identifier in field_identifiers(field_declaration) {
+                if identifier.utf8_text(self.source.as_bytes())? == field {
+                    return Ok(Some(identifier));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Finds the definitions of a name used in the body of a `#define`, which the grammar leaves unparsed. The
+    // parameters of a function-like macro resolve to its own parameter list, while any other name resolves at file
+    // scope, as macro bodies are expanded where the macro is used rather than being scoped to where it's defined.
+    fn macro_body_definition_search(&self, path: &Path, point: Position, body: Node) -> Result<Vec<Location>> {
+        let name = match self.word_at(point, body) {
+            Some(name) if is_identifier(name) => name,
+            _ => return Ok(vec![]),
+        };
+
+        if let Some(parameters) = body
+            .parent()
+            .filter(|define| define.kind() == "preproc_function_def")
+            .and_then(|define| define.child_by_field_name("parameters"))
+        {
+            let mut cursor = parameters.walk();
+            let parameter = parameters
+                .named_children(&mut cursor)
+                .find(|parameter| parameter.kind() == "identifier" && parameter.utf8_text(self.source.as_bytes()).ok() == Some(name));
+            if let Some(parameter) = parameter {
+                let range = self.range_for_node(&parameter);
+                return Ok(vec![location_for(path, range.start, range.end)?]);
+            }
+        }
+
+        let locations = self.find_global_definitions(path, name)?;
+        if !locations.is_empty() {
+            return Ok(locations);
+        }
+
+        // struct constructors called through an alias of the struct, see `type_definition_search`
+        self.type_definition_search(path, name)
+    }
+
+    // the identifier-like word at the point within the node, looking back one character from the end of a word as
+    // `find_node_at_point` does.
+    fn word_at(&self, point: Position, node: Node) -> Option<&str> {
+        let text = &self.source[node.byte_range()];
+        let mut offset = self.offset_for_position(point).checked_sub(node.start_byte())?;
+
+        let is_word_char = |offset: usize| text.get(offset..).and_then(|rest| rest.chars().next()).map_or(false, is_ident_char);
+        if !is_word_char(offset) {
+            // the character before may be more than a byte long, such as in a comment after the macro
+            let (before, c) = text.get(..offset)?.char_indices().next_back()?;
+            if !is_ident_char(c) {
+                return None;
+            }
+            offset = before;
+        }
+
+        let start = text[..offset].rfind(|c| !is_ident_char(c)).map_or(0, |i| i + 1);
+        let end = text[offset..].find(|c| !is_ident_char(c)).map_or(text.len(), |i| offset + i);
+
+        Some(&text[start..end])
+    }
+
+    // Finds the struct a type name refers to, following any `#define` aliases of it. If the aliases bottom out at
+    // a built-in type, or anything else not defined in the file, the last alias is the definition instead.
+    fn type_definition_search(&self, path: &Path, name: &str) -> Result<Vec<Location>> {
+        let aliases = preprocessor::resolve_alias(self, name);
+        let resolved = match aliases.last() {
+            Some(define) => preprocessor::alias_target(self, *define).unwrap(),
+            None => name,
+        };
+
+        debug!("resolved type aliases"; "name" => name, "resolved" => resolved, "aliases" => aliases.len());
+
+        let locations = self.simple_global_search(path, &format!(find_struct_def_str!(), resolved))?;
+        if !locations.is_empty() {
+            return Ok(locations);
+        }
+
+        match aliases.last().and_then(|define| define.child_by_field_name("name")) {
+            Some(alias) => {
+                let range = self.range_for_node(&alias);
+                Ok(vec![location_for(path, range.start, range.end)?])
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    fn simple_global_search(&self, path: &Path, query_str: &str) -> Result<Vec<Location>> {
+        let query = new_query(query_str)?;
+        let mut query_cursor = QueryCursor::new();
+
+        let mut locations = vec![];
+
+        for m in query_cursor.matches(&query, self.root_node(), self.source.as_bytes()) {
+            for capture in m.captures {
+                let range = self.range_for_node(&capture.node);
+                locations.push(location_for(path, range.start, range.end)?);
+            }
+        }
+
+        Ok(locations)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn root_node(&self) -> Node {
+        self.tree.root_node()
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    pub fn position_for_offset(&self, offset: usize) -> Position {
+        self.linemap.position_for_offset(&self.source, offset)
+    }
+
+    pub fn offset_for_position(&self, position: Position) -> usize {
+        self.linemap.offset_for_position(&self.source, position)
+    }
+
+    /// Returns the tree-sitter point (byte column) for a UTF-16 encoded position.
+    pub fn point_for_position(&self, position: Position) -> Point {
+        let offset = self.offset_for_position(position);
+        Point {
+            row: position.line as usize,
+            column: offset - self.linemap.line_start(&self.source, position.line as usize),
+        }
+    }
+
+    /// Returns the UTF-16 encoded range spanned by the node.
+    pub fn range_for_node(&self, node: &Node) -> Range {
+        Range {
+            start: self.position_for_offset(node.start_byte()),
+            end: self.position_for_offset(node.end_byte()),
+        }
+    }
+
+    /// Names the scopes enclosing the point from the outermost inwards, as in `["main", "for-loop", "if-block"]`.
+    /// Functions are named by their name and nested blocks after the statement introducing them, while the global
+    /// scope has no name, leaving the path empty outside of any function.
+    pub fn scope_path(&self, point: Position) -> Vec<String> {
+        let mut child = match self.find_node_at_point(point) {
+            Some(node) => node,
+            None => return vec![],
+        };
+
+        let mut path = vec![];
+        while let Some(parent) = child.parent() {
+            let name = match parent.kind() {
+                "function_definition" => parent
+                    .child_by_field_name("declarator")
+                    .and_then(|declarator| declarator.child_by_field_name("declarator"))
+                    .map(|name| self.source[name.byte_range()].to_string()),
+                "for_statement" => Some("for-loop".into()),
+                "while_statement" => Some("while-loop".into()),
+                "do_statement" => Some("do-loop".into()),
+                "switch_statement" => Some("switch-block".into()),
+                // the `if` of an `else if` names the branch on its own
+                "if_statement" if parent.child_by_field_name("alternative") == Some(child) => {
+                    (child.kind() != "if_statement").then(|| "else-block".into())
+                }
+                "if_statement" => match parent.parent() {
+                    Some(outer) if outer.kind() == "if_statement" && outer.child_by_field_name("alternative") == Some(parent) => {
+                        Some("else-if-block".into())
+                    }
+                    _ => Some("if-block".into()),
+                },
+                // blocks belonging to a statement are named by it, leaving only the bare blocks nested in others
+                "compound_statement" if parent.parent().map(|grandparent| grandparent.kind()) == Some("compound_statement") => {
+                    Some("block".into())
+                }
+                _ => None,
+            };
+
+            path.extend(name);
+            child = parent;
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Returns the name at the point, or the expression accessing through it, as chosen by the selection.
+    pub fn select_node_at_point(&self, pos: Position, selection: NodeSelection) -> Option<Node> {
+        let node = self.find_node_at_point(pos)?;
+        if selection == NodeSelection::Name {
+            return Some(node);
+        }
+
+        let accessed = node.parent().filter(|parent| match parent.kind() {
+            "field_expression" => parent.child_by_field_name("field") == Some(node),
+            "subscript_expression" => parent.child_by_field_name("argument") == Some(node),
+            _ => false,
+        });
+        Some(accessed.unwrap_or(node))
+    }
+
+    // the node at the point as found by `find_node_at_point`, failing with `NavigationError::NoNodeAtPoint` where
+    // there's none or only the root, as between top-level items. A source of nothing but whitespace and comments
+    // has nothing to navigate at any point, for which `None` is returned instead.
+    fn node_at_point(&self, point: Position) -> Result<Option<Node>> {
+        let mut cursor = self.root_node().walk();
+        if self.root_node().named_children(&mut cursor).all(|child| child.kind() == "comment") {
+            return Ok(None);
+        }
+
+        match self.find_node_at_point(point) {
+            Some(node) if node.parent().is_some() => Ok(Some(node)),
+            _ => Err(NavigationError::NoNodeAtPoint(point).into()),
+        }
+    }
+
+    pub fn find_node_at_point(&self, pos: Position) -> Option<Node> {
+        // the point may touch both a name and punctuation, as in `a|.b` or `f|(x)`. The character behind the point
+        // is tried first and then the one ahead of it, taking whichever is part of a name.
+        let offset = self.offset_for_position(pos);
+        let behind = offset.checked_sub(1).map(|start| (start, offset));
+        let ahead = (offset < self.source.len()).then_some((offset, offset + 1));
+
+        let named = behind.into_iter().chain(ahead).find(|&(start, end)| {
+            self.root_node().named_descendant_for_byte_range(start, end).map_or(false, |node| {
+                matches!(
+                    node.kind(),
+                    "identifier" | "type_identifier" | "field_identifier" | "primitive_type"
+                )
+            })
+        });
+
+        // otherwise if we're at the end of an ident, we need to look _back_ one char instead
+        // for tree-sitter to find the right node.
+        let (start, end) = match named {
+            Some(range) => range,
+            None => {
+                let char_at = self.source[offset..].chars().next().unwrap_or('\n');
+                trace!("looking for non-identifier char for point adjustment";
+                    "offset" => offset, 
+                    "char" => char_at,
+                    "point" => format!("{:?}", pos),
+                    "look_behind" => !is_ident_char(char_at));
+                if !is_ident_char(char_at) && offset > 0 {
+                    (offset - 1, offset)
+                } else {
+                    (offset, offset + 1)
+                }
+            }
+        };
+
+        match self.root_node().named_descendant_for_byte_range(start, end) {
+            Some(node) => {
+                debug!("found a node"; 
+                    "node" => format!("{:?}", node),
+                    "text" => node.utf8_text(self.source.as_bytes()).unwrap(),
+                    "start" => format!("{}", start),
+                    "end" => format!("{}", end));
+                Some(node)
+            }
+            None => None,
+        }
+    }
+}
+
+/// Finds the diagnostic following or preceding the position in the file, ordered by where they start. Past the last
+/// diagnostic the search wraps around to the first and vice versa, so that stepping through them cycles.
+pub fn adjacent_diagnostic(path: &Path, diagnostics: &[Diagnostic], position: Position, forward: bool) -> Result<Option<Location>> {
+    let mut ranges: Vec<Range> = diagnostics.iter().map(|diagnostic| diagnostic.range).collect();
+    ranges.sort_by_key(|range| (range.start, range.end));
+
+    let adjacent = if forward {
+        ranges.iter().find(|range| range.start > position).or_else(|| ranges.first())
+    } else {
+        ranges.iter().rev().find(|range| range.start < position).or_else(|| ranges.last())
+    };
+
+    adjacent.map(|range| location_for(path, range.start, range.end)).transpose()
+}
+
+/// Sorts locations by the URL of their file, then by where they start in it, so that results gathered in the order
+/// files were searched are listed the same way however the search went.
+pub fn sort_locations(locations: &mut [Location]) {
+    locations.sort_by(|a, b| {
+        (a.uri.as_str(), a.range.start.line, a.range.start.character).cmp(&(b.uri.as_str(), b.range.start.line, b.range.start.character))
+    });
+}
+
+/// Fills in the reference count command for a `CodeLens` returned by `ParserContext::code_lenses`, given the
+/// references to its function. Functions without any references are flagged as potentially dead code, so the
+/// references should be those of every file the function may be called from, not only its own.
+pub fn resolve_code_lens(mut lens: CodeLens, references: Vec<Location>) -> Result<CodeLens> {
+    let data: CodeLensData = match lens.data.take() {
+        Some(data) => serde_json::from_value(data)?,
+        None => return Ok(lens),
+    };
+
+    let title = match references.len() {
+        0 => "0 references (unused)".to_string(),
+        1 => "1 reference".to_string(),
+        n => format!("{} references", n),
+    };
+
+    lens.command = Some(Command {
+        title,
+        command: "mcglsl.showReferences".into(),
+        arguments: Some(vec![
+            serde_json::to_value(&data.uri)?,
+            serde_json::to_value(data.position)?,
+            serde_json::to_value(references)?,
+        ]),
+    });
+
+    Ok(lens)
+}
+
+/// Whether the character may start a GLSL identifier, being an ASCII letter or an underscore.
+pub fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+/// Whether the character may appear in a GLSL identifier after the first, being an ASCII letter, digit or underscore.
+pub fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Whether the text is a single GLSL identifier, such as `_private`, `v2` or `MAX_N`.
+pub fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if is_ident_start(c)) && chars.all(is_ident_char)
+}
+
+// parses the source, failing with `NavigationError::ParseFailed` if the parser's timeout is hit. The parser is reset
+// on timing out, as it would otherwise resume the abandoned parse on its next use.
+fn parse(parser: &mut Parser, source: &str, old_tree: Option<&Tree>) -> Result<Tree> {
+    match parser.parse(source, old_tree) {
+        Some(tree) => Ok(tree),
+        None => {
+            parser.reset();
+            Err(NavigationError::ParseFailed.into())
+        }
+    }
+}
+
+/// Answers with nothing where navigating failed with `NavigationError::NoNodeAtPoint`, for the requests made at a
+/// position that isn't on anything, which aren't worth reporting.
+pub fn or_nothing_at_point<T>(result: Result<Option<T>>) -> Result<Option<T>> {
+    match result {
+        Err(e) if matches!(e.downcast_ref(), Some(NavigationError::NoNodeAtPoint(_))) => Ok(None),
+        result => result,
+    }
+}
+
+fn new_query(source: &str) -> Result<Query> {
+    Query::new(tree_sitter_glsl::language(), source).map_err(|e| NavigationError::QueryError(e).into())
+}
+
+/// Flattens a document symbol tree into a list of symbols in the given file, each carrying the name of the
+/// symbol containing it, for use in workspace wide symbol listings.
+pub fn flatten_symbols(path: &Path, symbols: Vec<DocumentSymbol>) -> Result<Vec<SymbolInformation>> {
+    fn flatten(uri: &Url, symbols: Vec<DocumentSymbol>, container: Option<&str>, flattened: &mut Vec<SymbolInformation>) {
+        for symbol in symbols {
+            #[allow(deprecated)]
+            flattened.push(SymbolInformation {
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                tags: symbol.tags,
+                deprecated: None,
+                location: Location {
+                    uri: uri.clone(),
+                    range: symbol.selection_range,
+                },
+                container_name: container.map(String::from),
+            });
+            flatten(uri, symbol.children.unwrap_or_default(), Some(&symbol.name), flattened);
+        }
+    }
+
+    let mut flattened = vec![];
+    flatten(&url_for(path)?, symbols, None, &mut flattened);
+    Ok(flattened)
+}
+
+/// Builds the location of the range in the file, erroring on paths that can't be converted to a URL instead
+/// of panicking.
+pub fn location_for(path: &Path, start: Position, end: Position) -> Result<Location> {
+    Ok(Location {
+        uri: url_for(path)?,
+        range: Range { start, end },
+    })
+}
+
+// whether the node names the function of a call, rather than being passed to it. In `outer(inner(x))` both `outer`
+// and `inner` are called while `x` is an argument, even though `inner`'s call is itself an argument of `outer`.
+fn is_callee(node: Node) -> bool {
+    node.parent()
+        .filter(|parent| parent.kind() == "call_expression")
+        .and_then(|parent| parent.child_by_field_name("function"))
+        == Some(node)
+}
+
+// the identifiers of the fields a field declaration declares, including those of arrays as in `float values[4];`.
+fn field_identifiers(field_declaration: Node) -> Vec<Node> {
+    let mut cursor = field_declaration.walk();
+    field_declaration
+        .children_by_field_name("declarator", &mut cursor)
+        .filter_map(|declarator| {
+            let mut identifier = declarator;
+            while identifier.kind() != "field_identifier" {
+                identifier = identifier.child_by_field_name("declarator")?;
+            }
+            Some(identifier)
+        })
+        .collect()
+}
+
+// the name of the struct a field is declared in, `None` for structs declared without one.
+fn field_struct_name(field: Node, source: &str) -> Option<String> {
+    let mut parent = field.parent();
+    while let Some(node) = parent.filter(|node| node.kind() != "struct_specifier") {
+        parent = node.parent();
+    }
+    let name = parent?.child_by_field_name("name")?;
+    name.utf8_text(source.as_bytes()).ok().map(String::from)
+}
+
+// the statement a declared name belongs to, e.g. the whole declaration for a variable declarator.
+fn enclosing_statement(node: Node) -> Option<Node> {
+    let mut current = Some(node);
+    while let Some(n) = current {
+        match n.kind() {
+            "declaration"
+            | "parameter_declaration"
+            | "field_declaration"
+            | "function_definition"
+            | "struct_specifier"
+            | "preproc_def"
+            | "preproc_function_def" => return Some(n),
+            "compound_statement" | "translation_unit" => return None,
+            _ => current = n.parent(),
+        }
+    }
+    None
+}
+
+// the node delimiting the scope a declaration is visible in.
+fn enclosing_scope(node: Node) -> Option<Node> {
+    let mut parent = node.parent();
+    while let Some(p) = parent {
+        match p.kind() {
+            "compound_statement" | "for_statement" | "function_definition" | "translation_unit" => return Some(p),
+            _ => parent = p.parent(),
+        }
+    }
+    None
+}
+
+// the kind of symbol the node declares as named in symbol IDs, or `None` if it isn't the name of a declaration.
+fn symbol_kind(node: Node) -> Option<&'static str> {
+    let parent = node.parent()?;
+    match (node.kind(), parent.kind()) {
+        ("identifier", "function_declarator") => return Some("func"),
+        ("identifier", "preproc_def" | "preproc_function_def") => return Some("macro"),
+        ("type_identifier", "struct_specifier") => return Some("struct"),
+        ("field_identifier", _) => {
+            return (enclosing_statement(node).map(|statement| statement.kind()) == Some("field_declaration")).then_some("field")
+        }
+        ("identifier", _) => {}
+        _ => return None,
+    }
+
+    // variables are declared through any array and init declarators, as in `float weights[4] = ...;`
+    let (mut child, mut parent) = (node, parent);
+    loop {
+        let mut cursor = parent.walk();
+        if !parent
+            .children_by_field_name("declarator", &mut cursor)
+            .any(|declarator| declarator == child)
+        {
+            return None;
+        }
+        match parent.kind() {
+            "array_declarator" | "init_declarator" => {}
+            "parameter_declaration" => return Some("param"),
+            "declaration" => {
+                let mut ancestor = parent.parent();
+                while let Some(node) = ancestor {
+                    if node.kind() == "function_definition" {
+                        return Some("local");
+                    }
+                    ancestor = node.parent();
+                }
+                return Some("var");
+            }
+            _ => return None,
+        }
+        child = parent;
+        parent = parent.parent()?;
+    }
+}
+
+// whether the node is within the initializer of the variable its declaration declares.
+fn in_own_initializer(node: Node, declaration: Node) -> bool {
+    let mut declarator = declaration.parent();
+    while let Some(array) = declarator.filter(|parent| parent.kind() == "array_declarator") {
+        declarator = array.parent();
+    }
+
+    let initializer = declarator
+        .filter(|parent| parent.kind() == "init_declarator")
+        .and_then(|declarator| declarator.child_by_field_name("value"));
+
+    match initializer {
+        Some(initializer) => initializer.start_byte() <= node.start_byte() && node.end_byte() <= initializer.end_byte(),
+        None => false,
+    }
+}
+
+// the number of steps up the tree from node to ancestor, or None if it isn't an ancestor.
+fn ancestor_distance(node: Node, ancestor: Node) -> Option<usize> {
+    let mut distance = 0;
+    let mut current = node;
+    while current != ancestor {
+        current = current.parent()?;
+        distance += 1;
+    }
+    Some(distance)
+}
+
+#[cfg(test)]
+mod navigation_test {
+    use std::env::temp_dir;
+
+    use rust_lsp::lsp_types::{Diagnostic, Location, Position, Range};
+    use serde_json::json;
+    use tree_sitter::Parser;
+
+    use crate::navigation::{
+        adjacent_diagnostic, is_identifier, new_query, or_nothing_at_point, resolve_code_lens, DeclaredTypes, NavigationError,
+        ParserContext, SYMBOL_ID_VERSION,
+    };
+    use crate::url_norm::url_for;
+
+    fn new_parser() -> Parser {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        parser
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_empty_sources() {
+        let path = temp_dir().join("test.fsh");
+
+        for source in ["", "  \n\t\n", "// nothing yet\n/* still nothing */"] {
+            let mut parser = new_parser();
+            let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+            assert_eq!(ctx.list_symbols(&path).unwrap(), Some(vec![]));
+            assert!(ctx.code_lenses(&path).unwrap().is_empty());
+            assert!(ctx.functions().unwrap().is_empty());
+            assert!(ctx.find_global_definitions(&path, "main").unwrap().is_empty());
+
+            // before, within and past the end of the source
+            for (line, character) in [(0, 0), (0, 3), (1, 1), (5, 2)] {
+                let point = Position { line, character };
+
+                assert!(ctx.find_node_at_point(point).map_or(true, |node| node.kind() != "identifier"));
+                assert_eq!(ctx.find_definitions(&path, point).unwrap(), None);
+                assert_eq!(ctx.peek_definition(&path, point).unwrap(), None);
+                assert_eq!(ctx.find_references(&path, point).unwrap(), None);
+                assert_eq!(ctx.find_call_sites(&path, point).unwrap(), None);
+                assert_eq!(ctx.prepare_rename(point).unwrap(), None);
+                assert_eq!(ctx.field_at_point(point, &DeclaredTypes::default()).unwrap(), None);
+                assert_eq!(ctx.document_highlights(&path, point).unwrap(), None);
+                assert_eq!(ctx.symbol_id(&path, point).unwrap(), None);
+            }
+        }
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_utf16_definition_ranges() {
+        // ä and ö are 2 bytes in UTF-8 but a single UTF-16 code unit
+        let source = r#"/* ä */ float value = 1.0;
+void main() {
+    /* ö */ float other = value * 2.0;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let locations = ctx
+            .find_definitions(&temp_dir().join("test.fsh"), Position { line: 2, character: 26 })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(locations.len(), 1);
+        assert_eq!(
+            locations[0].range,
+            Range {
+                start: Position { line: 0, character: 14 },
+                end: Position { line: 0, character: 19 },
+            }
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_initializer_definitions() {
+        let source = r#"uniform vec3 pos;
+
+void main() {
+    vec3 n = normalize(pos);
+    vec3 m = n;
+    float x = 1.0;
+    {
+        float x = x * 2.0;
+    }
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        // `pos` within the initializer
+        let locations = ctx.find_definitions(&path, Position { line: 3, character: 24 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 0, character: 13 });
+
+        // `n` as the declared name
+        let locations = ctx.find_definitions(&path, Position { line: 3, character: 9 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 3, character: 9 });
+
+        // `n` as the whole initializer
+        let locations = ctx.find_definitions(&path, Position { line: 4, character: 13 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 3, character: 9 });
+
+        // `x` in its own initializer refers to the outer `x`
+        let locations = ctx.find_definitions(&path, Position { line: 7, character: 18 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 5, character: 10 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_initializer_field_definitions() {
+        let source = r#"struct Light { vec3 color; float power; };
+struct Scene { Light sun; Light lamps[2]; };
+
+void main() {
+    Light light = { .color = vec3(1.0), .power = 2.0 };
+    Scene scene = { .sun = { .power = 1.0 }, .lamps = { { .color = vec3(0.5) }, { .power = 0.5 } } };
+    Light built = Light(vec3(1.0), 2.0);
+    Light positional = { vec3(1.0), 2.0 };
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definition = |line, character| {
+            let locations = ctx.find_definitions(&path, Position { line, character }).unwrap().unwrap();
+            assert_eq!(locations.len(), 1);
+            locations[0].range.start
+        };
+
+        assert_eq!(definition(4, 22), Position { line: 0, character: 20 });
+        assert_eq!(definition(4, 41), Position { line: 0, character: 33 });
+
+        // designators within nested lists, and in lists of array elements
+        assert_eq!(definition(5, 21), Position { line: 1, character: 21 });
+        assert_eq!(definition(5, 31), Position { line: 0, character: 33 });
+        assert_eq!(definition(5, 61), Position { line: 0, character: 20 });
+        assert_eq!(definition(5, 84), Position { line: 0, character: 33 });
+
+        // constructors resolve to the struct
+        assert_eq!(definition(6, 20), Position { line: 0, character: 7 });
+
+        // positional initializers have no field to resolve
+        assert_eq!(
+            ctx.find_definitions(&path, Position { line: 7, character: 27 }).unwrap().unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_macro_body_definitions() {
+        let source = r#"struct Light { vec3 color; };
+uniform float strength;
+float luma(vec3 color) { return dot(color, vec3(0.2126, 0.7152, 0.0722)); }
+
+#define SCALED(color, amount) (luma(color) * amount * strength)
+#define SUN Light(vec3(1.0))
+#define GAIN ä strength
+
+void main() {
+    float strength = 2.0;
+    float l = SCALED(vec3(1.0), 0.5);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect::<Vec<_>>()
+        };
+
+        // parameters resolve to the macro's own parameter list
+        assert_eq!(definitions(4, 36), vec![Position { line: 4, character: 15 }]);
+        assert_eq!(definitions(4, 45), vec![Position { line: 4, character: 22 }]);
+        // from the end of the word
+        assert_eq!(definitions(4, 51), vec![Position { line: 4, character: 22 }]);
+
+        // anything else at file scope, regardless of the local `strength` where the macro is used
+        assert_eq!(definitions(4, 31), vec![Position { line: 2, character: 6 }]);
+        assert_eq!(definitions(4, 62), vec![Position { line: 1, character: 14 }]);
+        assert_eq!(definitions(5, 12), vec![Position { line: 0, character: 7 }]);
+
+        // operators and literals aren't names
+        assert_eq!(definitions(4, 43), vec![]);
+        assert_eq!(definitions(5, 23), vec![]);
+
+        // looking back from after a character longer than a byte
+        assert_eq!(definitions(6, 14), vec![]);
+        assert_eq!(definitions(6, 16), vec![Position { line: 1, character: 14 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_ternary_and_comma_definitions() {
+        let source = r#"void main() {
+    float a = 1.0;
+    float b = 2.0;
+    float c = a > 0.5 ? a : b;
+    float d = (a, b);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        // `b` in the alternative branch of the ternary
+        let locations = ctx.find_definitions(&path, Position { line: 3, character: 28 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 2, character: 10 });
+
+        // `a` in the consequence branch of the ternary
+        let locations = ctx.find_definitions(&path, Position { line: 3, character: 24 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 1, character: 10 });
+
+        // `b` as the last operand of a comma expression
+        let locations = ctx.find_definitions(&path, Position { line: 4, character: 18 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 2, character: 10 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_return_definitions() {
+        let source = r#"uniform float exposure;
+
+float scale(float value, float factor) {
+    float scaled = value * factor;
+    if (scaled > 1.0) {
+        return scaled;
+    }
+    return (value + exposure) * -factor;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        // the whole returned expression, from inside a nested block
+        assert_eq!(definitions(5, 15), vec![Position { line: 3, character: 10 }]);
+        // operands of the returned expression, resolving to parameters and globals
+        assert_eq!(definitions(7, 12), vec![Position { line: 2, character: 18 }]);
+        assert_eq!(definitions(7, 20), vec![Position { line: 0, character: 14 }]);
+        assert_eq!(definitions(7, 33), vec![Position { line: 2, character: 31 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_nested_call_definitions() {
+        let source = r#"float inner(float value);
+
+float inner(float value) {
+    return value * 2.0;
+}
+
+float outer(float value, float weight) {
+    return value * weight;
+}
+
+void main() {
+    float inner_weight = 0.5;
+    float result = outer(inner(inner_weight), inner(outer(1.0, inner_weight)));
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let starts = |locations: Option<Vec<Location>>| -> Vec<Position> {
+            locations.unwrap().into_iter().map(|location| location.range.start).collect()
+        };
+        let definitions = |line, character| starts(ctx.find_definitions(&path, Position { line, character }).unwrap());
+        let declarations = |line, character| starts(ctx.find_declarations(&path, Position { line, character }).unwrap());
+
+        // calls passed as arguments resolve to the functions called, at any depth and from either end of the name
+        assert_eq!(definitions(12, 19), vec![Position { line: 6, character: 6 }]);
+        assert_eq!(definitions(12, 27), vec![Position { line: 2, character: 6 }]);
+        assert_eq!(definitions(12, 30), vec![Position { line: 2, character: 6 }]);
+        assert_eq!(definitions(12, 48), vec![Position { line: 2, character: 6 }]);
+        assert_eq!(definitions(12, 54), vec![Position { line: 6, character: 6 }]);
+        // while the names passed to them are still variables
+        assert_eq!(definitions(12, 35), vec![Position { line: 11, character: 10 }]);
+        assert_eq!(definitions(12, 66), vec![Position { line: 11, character: 10 }]);
+
+        // the nested callee is declared by its prototype
+        assert_eq!(declarations(12, 48), vec![Position { line: 0, character: 6 }]);
+        assert_eq!(declarations(12, 54), vec![Position { line: 6, character: 6 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_subscript_definitions() {
+        let source = r#"uniform float weights[4];
+
+void main() {
+    float total = 0.0;
+    for (int i = 0; i < 4; i++) {
+        total += weights[i];
+    }
+    float local[2];
+    local[1] = weights[0];
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        // the array being subscripted, and the index it's subscripted with
+        assert_eq!(definitions(5, 17), vec![Position { line: 0, character: 14 }]);
+        assert_eq!(definitions(5, 25), vec![Position { line: 4, character: 13 }]);
+        // arrays declared without an initializer, subscripted on the left of an assignment
+        assert_eq!(definitions(8, 4), vec![Position { line: 7, character: 10 }]);
+        assert_eq!(definitions(8, 15), vec![Position { line: 0, character: 14 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_array_initializer_definitions() {
+        let source = r#"const int TAPS = 3;
+
+void main() {
+    float edge = 0.25;
+    float center = 0.5;
+    float kernel[TAPS] = float[](edge, center, edge);
+    float weights[2] = {center, edge};
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        // the arrays being declared are their own definitions, while their sizes resolve like any other use
+        assert_eq!(definitions(5, 12), vec![Position { line: 5, character: 10 }]);
+        assert_eq!(definitions(5, 19), vec![Position { line: 0, character: 10 }]);
+        assert_eq!(definitions(6, 12), vec![Position { line: 6, character: 10 }]);
+
+        // the elements of array constructors and initializer lists
+        assert_eq!(definitions(5, 33), vec![Position { line: 3, character: 10 }]);
+        assert_eq!(definitions(5, 39), vec![Position { line: 4, character: 10 }]);
+        assert_eq!(definitions(6, 24), vec![Position { line: 4, character: 10 }]);
+        assert_eq!(definitions(6, 32), vec![Position { line: 3, character: 10 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_loop_variable_definitions() {
+        let source = r#"void main() {
+    float total = 0.0;
+    for (int i = 0; i < 4; i++) {
+        for (int i = 0; i < 2; i++) {
+            total += float(i);
+        }
+        total *= float(i);
+    }
+    for (int i = 1; i < 3; i++) total -= float(i);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let positions = |locations: Option<Vec<Location>>| -> Vec<Position> {
+            locations.unwrap().into_iter().map(|location| location.range.start).collect()
+        };
+        let definitions = |line, character| positions(ctx.find_definitions(&path, Position { line, character }).unwrap());
+        let references = |line, character| positions(ctx.find_references(&path, Position { line, character }).unwrap());
+
+        // each loop's variable is declared by its own initializer, the inner loop's shadowing the outer's
+        assert_eq!(definitions(4, 27), vec![Position { line: 3, character: 17 }]);
+        assert_eq!(definitions(6, 23), vec![Position { line: 2, character: 13 }]);
+        assert_eq!(definitions(2, 20), vec![Position { line: 2, character: 13 }]);
+        // including a loop without a block for its body
+        assert_eq!(definitions(8, 47), vec![Position { line: 8, character: 13 }]);
+
+        // so the uses of the outer loop's variable are the ones in its header and outside the inner loop
+        assert_eq!(
+            references(2, 13),
+            vec![
+                Position { line: 2, character: 20 },
+                Position { line: 2, character: 27 },
+                Position { line: 6, character: 23 },
+            ]
+        );
+        assert_eq!(
+            references(8, 13),
+            vec![
+                Position { line: 8, character: 20 },
+                Position { line: 8, character: 27 },
+                Position { line: 8, character: 47 },
+            ]
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_macro_constant_definitions() {
+        let source = r#"#define STATE_IDLE 0
+#define STATE_RUNNING 1
+
+uniform int state;
+
+vec3 stateColor() {
+    switch (state) {
+        case STATE_IDLE:
+            return vec3(0.0);
+        case STATE_RUNNING:
+            return vec3(1.0);
+    }
+    if (state == STATE_RUNNING || !(state > STATE_IDLE)) {
+        return vec3(0.5);
+    }
+    return vec3(-STATE_RUNNING);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        let idle = vec![Position { line: 0, character: 8 }];
+        let running = vec![Position { line: 1, character: 8 }];
+
+        // case labels, comparisons and negations
+        assert_eq!(definitions(7, 13), idle);
+        assert_eq!(definitions(9, 13), running);
+        assert_eq!(definitions(12, 17), running);
+        assert_eq!(definitions(12, 44), idle);
+        assert_eq!(definitions(15, 17), running);
+        // variables are still preferred, as with the switched on value
+        assert_eq!(definitions(6, 12), vec![Position { line: 3, character: 12 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_conditional_macro_definitions() {
+        let source = r#"#define SHADOWS
+#define SHADOW_RES 2048
+
+#ifdef SHADOWS
+#endif
+#if defined(SHADOWS) && SHADOW_RES > 1024
+#elif !defined(SHADOWS)
+#endif
+#ifndef BLOOM
+#endif
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        let shadows = vec![Position { line: 0, character: 8 }];
+
+        assert_eq!(definitions(3, 8), shadows);
+        assert_eq!(definitions(5, 14), shadows);
+        assert_eq!(definitions(5, 26), vec![Position { line: 1, character: 8 }]);
+        assert_eq!(definitions(6, 16), shadows);
+        // macros never defined are found to have no definitions, rather than not being looked up
+        assert_eq!(definitions(8, 9), vec![]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_function_declarations() {
+        let source = r#"float luma(vec3 color);
+float exposure(float ev);
+
+void main() {
+    float l = luma(vec3(1.0)) * exposure(1.0);
+    float scale = l * 2.0;
+}
+
+float luma(vec3 color) { return dot(color, vec3(0.2126, 0.7152, 0.0722)); }
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let starts = |locations: Option<Vec<Location>>| -> Vec<Position> {
+            locations.unwrap().into_iter().map(|location| location.range.start).collect()
+        };
+        let declarations = |line, character| starts(ctx.find_declarations(&path, Position { line, character }).unwrap());
+        let definitions = |line, character| starts(ctx.find_definitions(&path, Position { line, character }).unwrap());
+
+        let prototype = vec![Position { line: 0, character: 6 }];
+        let body = vec![Position { line: 8, character: 6 }];
+
+        // the prototype declares the function and the body defines it, whether looked up from a call or either one
+        assert_eq!(declarations(4, 16), prototype);
+        assert_eq!(definitions(4, 16), body);
+        assert_eq!(declarations(8, 7), prototype);
+        assert_eq!(definitions(0, 7), body);
+
+        // without a body in the file, the prototype is all there is
+        assert_eq!(declarations(4, 36), vec![Position { line: 1, character: 6 }]);
+        assert_eq!(definitions(4, 36), vec![Position { line: 1, character: 6 }]);
+
+        // variables are declared where they're defined
+        assert_eq!(declarations(5, 18), definitions(5, 18));
+        assert_eq!(declarations(5, 18), vec![Position { line: 4, character: 10 }]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_scope_path() {
+        let source = r#"float global = 1.0;
+
+void main() {
+    for (int i = 0; i < 4; i++) {
+        if (i > 1) {
+            float a = 1.0;
+        } else if (i > 0) {
+            float b = 2.0;
+        } else {
+            float c = 3.0;
+        }
+    }
+    {
+        float d = 4.0;
+    }
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let scope_path = |line, character| ctx.scope_path(Position { line, character });
+
+        assert_eq!(scope_path(0, 6), Vec::<String>::new());
+        assert_eq!(scope_path(3, 13), vec!["main", "for-loop"]);
+        assert_eq!(scope_path(5, 18), vec!["main", "for-loop", "if-block"]);
+        assert_eq!(scope_path(7, 18), vec!["main", "for-loop", "else-if-block"]);
+        assert_eq!(scope_path(9, 18), vec!["main", "for-loop", "else-block"]);
+        assert_eq!(scope_path(13, 14), vec!["main", "block"]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_type_alias_definitions() {
+        let source = r#"#define Color vec3
+#define Albedo Color
+#define LightData Light
+#define Loop Loop2
+#define Loop2 Loop
+
+struct Light {
+    vec3 color;
+};
+
+void main() {
+    Albedo a = vec3(1.0);
+    LightData l;
+    Loop x;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        // the chain ends at a built-in, so the last alias is the definition
+        let locations = ctx.find_definitions(&path, Position { line: 11, character: 4 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 0, character: 8 });
+
+        // aliases of structs resolve to the struct
+        let locations = ctx.find_definitions(&path, Position { line: 12, character: 4 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 6, character: 7 });
+
+        // cyclic aliases stop once they loop back
+        let locations = ctx.find_definitions(&path, Position { line: 13, character: 4 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 4, character: 8 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_field_chain_definitions() {
+        let source = r#"#define MaterialData Material
+
+struct Material {
+    vec3 albedo;
+    float roughness;
+};
+
+struct Surface {
+    MaterialData material;
+    vec3 normals[4];
+};
+
+struct Fragment {
+    Surface surface;
+};
+
+void main() {
+    Fragment frag;
+    float r = frag.surface.material.roughness;
+    vec3 n = frag.surface.normals[0].xyz;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        // each link resolves through the struct the previous one is declared as, including aliases
+        let locations = ctx.find_definitions(&path, Position { line: 18, character: 37 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 4, character: 10 });
+
+        let locations = ctx.find_definitions(&path, Position { line: 18, character: 28 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 8, character: 17 });
+
+        let locations = ctx.find_definitions(&path, Position { line: 18, character: 19 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 13, character: 12 });
+
+        // array fields resolve, swizzles of vectors don't
+        let locations = ctx.find_definitions(&path, Position { line: 19, character: 27 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 9, character: 9 });
+
+        let locations = ctx.find_definitions(&path, Position { line: 19, character: 38 }).unwrap().unwrap();
+        assert!(locations.is_empty());
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_call_field_definitions() {
+        let source = r#"struct Light {
+    vec3 color;
+    float power;
+};
+
+struct Scene {
+    Light sun;
+};
+
+Light getLight();
+Scene getScene() { return Scene(Light(vec3(1.0), 1.0)); }
+
+void main() {
+    vec3 c = getLight().color;
+    float p = getScene().sun.power;
+    float q = Light(vec3(0.0), 2.0).power;
+    float r = texture2D(tex, uv).r;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        // the return type of the function called, whether declared by a prototype or a definition
+        assert_eq!(definitions(13, 25), vec![Position { line: 1, character: 9 }]);
+        assert_eq!(definitions(14, 25), vec![Position { line: 6, character: 10 }]);
+        assert_eq!(definitions(14, 30), vec![Position { line: 2, character: 10 }]);
+        // constructors are of the struct they're named after
+        assert_eq!(definitions(15, 37), vec![Position { line: 2, character: 10 }]);
+        // while built-ins return no struct of the file's
+        assert!(definitions(16, 34).is_empty());
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_swizzle_write_definitions() {
+        let source = r#"struct Material {
+    vec3 albedo;
+};
+
+out vec4 color;
+
+void main() {
+    Material material;
+    color.rgb = material.albedo;
+    color.a *= 0.5;
+    material.albedo.xy = vec2(0.0);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let definitions = |line, character| -> Vec<Position> {
+            ctx.find_definitions(&path, Position { line, character })
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect()
+        };
+
+        // the vector written through a swizzle resolves to its declaration, whether assigned or compound assigned
+        assert_eq!(definitions(8, 6), vec![Position { line: 4, character: 9 }]);
+        assert_eq!(definitions(9, 6), vec![Position { line: 4, character: 9 }]);
+        assert_eq!(definitions(10, 14), vec![Position { line: 1, character: 9 }]);
+
+        // while the swizzles themselves declare nothing
+        assert_eq!(definitions(8, 11), vec![]);
+        assert_eq!(definitions(9, 10), vec![]);
+        assert_eq!(definitions(10, 21), vec![]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_functions() {
+        let source = r#"float luma(vec3 color);
+
+vec3 shade(vec3 normal,
+           float depth) {
+    return normal * depth;
+}
+
+#ifdef SHADOWS
+float shadow(vec3 pos) { return 1.0; }
+#else
+float shadow(vec3 pos) { return 0.0; }
+#endif
+
+float luma(vec3 color) { return dot(color, vec3(0.2126, 0.7152, 0.0722)); }
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let functions = ctx.functions().unwrap();
+        let summary: Vec<(&str, &str, bool)> = functions
+            .iter()
+            .map(|function| (function.name.as_str(), function.signature.as_str(), function.is_prototype))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("luma", "float luma(vec3 color)", true),
+                ("shade", "vec3 shade(vec3 normal, float depth)", false),
+                ("shadow", "float shadow(vec3 pos)", false),
+                ("shadow", "float shadow(vec3 pos)", false),
+                ("luma", "float luma(vec3 color)", false),
+            ]
+        );
+
+        assert_eq!(&source[functions[1].name_range.clone()], "shade");
+        assert!(source[functions[1].range.clone()].ends_with('}'));
+
+        assert!(functions[1].conditions.is_empty());
+        assert_eq!(functions[2].conditions[0].to_string(), "SHADOWS");
+        assert_eq!(functions[3].conditions[0].to_string(), "!SHADOWS");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_symbol_uses() {
+        let source = r#"#define Color vec3
+struct Light { Color color; Falloff falloff; };
+float luma(vec3 color);
+
+void main() {
+    Light light = Light(vec3(1.0), Falloff(2.0));
+    float l = luma(light.color) + attenuate(light);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let uses: Vec<(String, &str, Position)> = ctx
+            .symbol_uses(&path)
+            .unwrap()
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.location.range.start))
+            .collect();
+
+        let used = |name: &str, kind: &str| uses.iter().filter(|u| u.0 == name && u.1 == kind).count();
+
+        // the struct being defined isn't a use of it, but its fields' types and its constructor are
+        assert_eq!(used("Light", "type"), 1);
+        assert_eq!(used("Light", "function"), 1);
+        assert_eq!(used("Color", "type"), 1);
+        assert_eq!(used("Falloff", "type"), 1);
+        assert_eq!(used("Falloff", "function"), 1);
+        assert_eq!(used("luma", "function"), 1);
+        assert_eq!(used("attenuate", "function"), 1);
+        assert_eq!(uses.first().unwrap().2, Position { line: 1, character: 15 });
+
+        let mut defined: Vec<String> = ctx.defined_names().unwrap().into_iter().collect();
+        defined.sort();
+        assert_eq!(defined, vec!["Color", "Light", "luma", "main"]);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_code_lenses() {
+        let source = "float luma(vec3 color);\n\nfloat luma(vec3 color) {\n    return color.g;\n}\n\nvoid main() {}\n";
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+        let uri = url_for(&path).unwrap();
+
+        // prototypes get no lens of their own, only definitions
+        let lenses = ctx.code_lenses(&path).unwrap();
+        let ranges: Vec<Range> = lenses.iter().map(|lens| lens.range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Position::new(2, 6), Position::new(2, 10)),
+                Range::new(Position::new(6, 5), Position::new(6, 9)),
+            ]
+        );
+        assert!(lenses.iter().all(|lens| lens.command.is_none()));
+        assert_eq!(
+            lenses[0].data,
+            Some(json!({ "uri": uri, "position": { "line": 2, "character": 6 } }))
+        );
+
+        let location = |line| Location {
+            uri: uri.clone(),
+            range: Range::new(Position::new(line, 4), Position::new(line, 8)),
+        };
+        let title = |references: Vec<Location>| resolve_code_lens(lenses[0].clone(), references).unwrap().command.unwrap().title;
+        assert_eq!(title(vec![]), "0 references (unused)");
+        assert_eq!(title(vec![location(1)]), "1 reference");
+        assert_eq!(title(vec![location(1), location(3), location(5)]), "3 references");
+
+        let lens = resolve_code_lens(lenses[0].clone(), vec![location(1)]).unwrap();
+        assert_eq!(lens.data, None);
+        let command = lens.command.unwrap();
+        assert_eq!(command.command, "mcglsl.showReferences");
+        assert_eq!(
+            command.arguments,
+            Some(vec![
+                json!(uri),
+                json!({ "line": 2, "character": 6 }),
+                json!([{ "uri": uri, "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 8 } } }]),
+            ])
+        );
+
+        // lenses without data are left as they are
+        let mut unresolved = lenses[1].clone();
+        unresolved.data = None;
+        assert_eq!(resolve_code_lens(unresolved.clone(), vec![]).unwrap(), unresolved);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_navigation_errors() {
+        let mut parser = new_parser();
+        let err = ParserContext::new(&mut parser, &temp_dir().join("missing.fsh")).err().unwrap();
+        assert!(matches!(err.downcast_ref(), Some(NavigationError::IoError(_))));
+
+        let source = "void main() {\n    float a = sin(1.0);\n}\n";
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let err = ctx.prepare_rename(Position { line: 1, character: 15 }).err().unwrap();
+        assert!(matches!(err.downcast_ref(), Some(NavigationError::Unsupported(_))));
+        assert_eq!(err.to_string(), "cannot rename built-in `sin`");
+
+        let err = ctx
+            .field_at_point(Position { line: 3, character: 0 }, &DeclaredTypes::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err.downcast_ref(), Some(NavigationError::NoNodeAtPoint(_))));
+        let definitions = ctx.find_definitions(&temp_dir().join("test.fsh"), Position { line: 3, character: 0 });
+        assert_eq!(or_nothing_at_point(definitions).unwrap(), None);
+
+        let err = new_query("(unknown_node) @node").err().unwrap();
+        assert!(matches!(err.downcast_ref(), Some(NavigationError::QueryError(_))));
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_symbol_ids() {
+        let source = r#"#define SCALE 2.0
+struct Light {
+    vec3 color;
+};
+uniform Light sun;
+
+float noise(vec2 p) { return p.x; }
+float noise(vec2 p, float scale) { return noise(p * scale); }
+
+void main() {
+    float depth = noise(vec2(SCALE), 1.0);
+    vec3 tint = sun.color * depth;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+        let prefix = format!("mcglsl:{}:{}#", SYMBOL_ID_VERSION, url_for(&path).unwrap());
+
+        let id = |line, character| {
+            ctx.symbol_id(&path, Position { line, character })
+                .unwrap()
+                .map(|id| id.strip_prefix(&prefix).unwrap().to_string())
+        };
+
+        // declarations
+        assert_eq!(id(0, 8).as_deref(), Some("macro:SCALE"));
+        assert_eq!(id(1, 7).as_deref(), Some("struct:Light"));
+        assert_eq!(id(2, 9).as_deref(), Some("field:Light.color"));
+        assert_eq!(id(4, 14).as_deref(), Some("var:sun"));
+        assert_eq!(id(6, 6).as_deref(), Some("func:noise(vec2)"));
+        assert_eq!(id(7, 6).as_deref(), Some("func:noise(vec2,float)"));
+        assert_eq!(id(7, 17).as_deref(), Some("param:noise(vec2,float)/p"));
+        assert_eq!(id(10, 10).as_deref(), Some("local:main()/depth"));
+
+        // uses share the ID of their declaration
+        assert_eq!(id(7, 52).as_deref(), Some("param:noise(vec2,float)/scale"));
+        assert_eq!(id(11, 16).as_deref(), Some("var:sun"));
+        assert_eq!(id(11, 20).as_deref(), Some("field:Light.color"));
+        assert_eq!(id(11, 28).as_deref(), Some("local:main()/depth"));
+
+        // keywords and literals aren't symbols
+        assert_eq!(id(6, 2), None);
+        assert_eq!(id(10, 38), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_find_field_references() {
+        let source = r#"struct Light {
+    vec3 color;
+};
+
+struct Material {
+    vec3 color;
+};
+
+uniform Light lights[4];
+
+void main() {
+    Material material;
+    Light sun = lights[0];
+    vec3 c = sun.color + lights[1].color * material.color;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let starts = |point| {
+            let (struct_name, field) = ctx.field_at_point(point, &DeclaredTypes::default()).unwrap().unwrap();
+            let mut starts: Vec<Position> = ctx
+                .find_field_references(&path, &struct_name, &field, &DeclaredTypes::default())
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range.start)
+                .collect();
+            starts.sort();
+            starts
+        };
+
+        let light_fields = vec![
+            Position { line: 1, character: 9 },
+            Position { line: 13, character: 17 },
+            Position { line: 13, character: 35 },
+        ];
+        // from an access and from the declaration, leaving `Material.color` alone
+        assert_eq!(starts(Position { line: 13, character: 17 }), light_fields);
+        assert_eq!(starts(Position { line: 1, character: 9 }), light_fields);
+
+        assert_eq!(
+            starts(Position { line: 13, character: 52 }),
+            vec![Position { line: 5, character: 9 }, Position { line: 13, character: 52 }]
+        );
+
+        assert_eq!(
+            ctx.field_at_point(Position { line: 12, character: 10 }, &DeclaredTypes::default())
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_find_field_references_external() {
+        let header = r#"struct Light {
+    vec3 color;
+    Light next;
+};
+
+uniform Light sun;
+"#;
+        let source = r#"void main() {
+    Light moon = sun;
+    vec3 c = sun.color + moon.next.color;
+}
+"#;
+
+        let mut parser = new_parser();
+        let external = ParserContext::from_source(&mut parser, header.to_string())
+            .unwrap()
+            .declared_types()
+            .unwrap();
+        assert_eq!(external.globals.get("sun").map(String::as_str), Some("Light"));
+        assert_eq!(
+            external.fields.get(&("Light".to_string(), "next".to_string())).map(String::as_str),
+            Some("Light")
+        );
+
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        // the struct isn't defined in the file, so its accesses only resolve through the types of the header
+        assert_eq!(
+            ctx.field_at_point(Position { line: 2, character: 18 }, &DeclaredTypes::default())
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            ctx.field_at_point(Position { line: 2, character: 18 }, &external).unwrap(),
+            Some(("Light".to_string(), "color".to_string()))
+        );
+
+        let starts: Vec<Position> = ctx
+            .find_field_references(&path, "Light", "color", &external)
+            .unwrap()
+            .into_iter()
+            .map(|location| location.range.start)
+            .collect();
+        assert_eq!(
+            starts,
+            vec![Position { line: 2, character: 17 }, Position { line: 2, character: 35 }]
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_scope_correct_definitions() {
+        let source = r#"void main() {
+    float v = 0.0;
+    if (true) {
+        v = 1.0;
+    } else {
+        float v = 2.0;
+        v += 1.0;
+    }
+    v = v + 1.0;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        // the use in the branch without its own declaration resolves to the function scoped declaration,
+        // not the one in the sibling branch
+        let locations = ctx.find_definitions(&path, Position { line: 3, character: 8 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 1, character: 10 });
+
+        // the shadowing declaration is returned first within its own branch
+        let locations = ctx.find_definitions(&path, Position { line: 6, character: 8 }).unwrap().unwrap();
+        assert_eq!(locations[0].range.start, Position { line: 5, character: 14 });
+
+        // after the branches only the function scoped declaration governs
+        let locations = ctx.find_definitions(&path, Position { line: 8, character: 8 }).unwrap().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position { line: 1, character: 10 });
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_prepare_rename() {
+        let source = r#"struct Light { vec3 color; };
+void main() {
+    float value = 1.0;
+    gl_FragColor = vec4(mix(value, 2.0, 0.5));
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        // user declared symbols
+        assert_eq!(
+            ctx.prepare_rename(Position { line: 2, character: 12 }).unwrap(),
+            Some(Range {
+                start: Position { line: 2, character: 10 },
+                end: Position { line: 2, character: 15 },
+            })
+        );
+        assert!(ctx.prepare_rename(Position { line: 0, character: 8 }).unwrap().is_some());
+        assert!(ctx.prepare_rename(Position { line: 0, character: 21 }).unwrap().is_some());
+
+        // built-in variables and functions
+        assert!(ctx.prepare_rename(Position { line: 3, character: 6 }).is_err());
+        assert!(ctx.prepare_rename(Position { line: 3, character: 25 }).is_err());
+
+        // keywords and literals
+        assert_eq!(ctx.prepare_rename(Position { line: 1, character: 1 }).unwrap(), None);
+        assert_eq!(ctx.prepare_rename(Position { line: 2, character: 19 }).unwrap(), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_peek_definition() {
+        let source = r#"uniform float frameTimeCounter;
+float wave(float t) {
+    return sin(t);
+}
+void main() {
+    float speed = 2.0,
+          offset = 1.0;
+    float x = wave(frameTimeCounter * speed + offset);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let peeks = ctx.peek_definition(&path, Position { line: 7, character: 15 }).unwrap().unwrap();
+        assert_eq!(peeks.len(), 1);
+        assert_eq!(peeks[0].1, "float wave(float t)");
+
+        let peeks = ctx.peek_definition(&path, Position { line: 7, character: 47 }).unwrap().unwrap();
+        assert_eq!(peeks.len(), 1);
+        assert_eq!(peeks[0].0.range.start, Position { line: 6, character: 10 });
+        assert_eq!(peeks[0].1, "float speed = 2.0,\n          offset = 1.0;");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_signature() {
+        let source = r#"struct Light {
+    vec3 color;
+    float radius, falloff;
+};
+uniform float frameTimeCounter;
+vec3 shade(Light light,
+           float depth);
+vec3 shade(Light light, float depth) {
+    return light.color * depth;
+}
+void main() {
+    float speed = 2.0,
+          offset = 1.0;
+    Light sun = Light(vec3(1.0), 1.0, 2.0);
+    vec3 color = shade(sun, frameTimeCounter * speed + offset) * sun.falloff;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let signature = |line: u32, character: u32| ctx.signature(&path, Position { line, character }).unwrap();
+
+        // functions up to their body, from a call or the definition itself
+        assert_eq!(signature(14, 19).as_deref(), Some("vec3 shade(Light light, float depth)"));
+        assert_eq!(signature(7, 6).as_deref(), Some("vec3 shade(Light light, float depth)"));
+
+        // variables on their own, even where declared along with others
+        assert_eq!(signature(14, 57).as_deref(), Some("float offset = 1.0;"));
+        assert_eq!(signature(14, 30).as_deref(), Some("uniform float frameTimeCounter;"));
+        assert_eq!(signature(14, 70).as_deref(), Some("float falloff;"));
+
+        // structs as a whole
+        assert_eq!(
+            signature(13, 4).as_deref(),
+            Some("struct Light {\n    vec3 color;\n    float radius, falloff;\n}")
+        );
+
+        assert_eq!(signature(11, 18), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_find_call_sites() {
+        let source = r#"float wave(float t, float amplitude) {
+    return sin(t) * amplitude;
+}
+void main() {
+    float a = wave(1.0, 0.5);
+    float b = wave(a * 2.0, wave(a, /* tiny */ 0.1));
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let call_sites = ctx.find_call_sites(&path, Position { line: 0, character: 7 }).unwrap().unwrap();
+        let arguments: Vec<Vec<&str>> = call_sites
+            .iter()
+            .map(|call| call.arguments.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            arguments,
+            vec![vec!["1.0", "0.5"], vec!["a * 2.0", "wave(a, /* tiny */ 0.1)"], vec!["a", "0.1"]]
+        );
+        assert_eq!(call_sites[0].location.range.start, Position { line: 4, character: 14 });
+
+        // the plain references are the same call sites
+        let references = ctx.find_references(&path, Position { line: 0, character: 7 }).unwrap().unwrap();
+        assert_eq!(references, call_sites.into_iter().map(|call| call.location).collect::<Vec<_>>());
+
+        assert_eq!(ctx.find_call_sites(&path, Position { line: 4, character: 10 }).unwrap(), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_branch_local_declarations() {
+        let source = r#"float consume(float value) {
+    return value;
+}
+
+void main() {
+    float a = 1.0, b = 2.0;
+    bool c = a > b;
+    if (c) {
+        float x = a;
+        consume(x);
+    } else {
+        float x = b;
+        consume(x);
+        x *= 2.0;
+    }
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let starts = |locations: Option<Vec<Location>>| -> Vec<(u32, u32)> {
+            locations
+                .unwrap()
+                .into_iter()
+                .map(|location| (location.range.start.line, location.range.start.character))
+                .collect()
+        };
+
+        // each use resolves to the declaration of its own branch alone, never the one of the same name beside it
+        assert_eq!(starts(ctx.find_definitions(&path, Position::new(9, 16)).unwrap()), vec![(8, 14)]);
+        assert_eq!(starts(ctx.find_definitions(&path, Position::new(12, 16)).unwrap()), vec![(11, 14)]);
+        assert_eq!(starts(ctx.find_definitions(&path, Position::new(13, 8)).unwrap()), vec![(11, 14)]);
+
+        // as do the references of each declaration
+        assert_eq!(starts(ctx.find_references(&path, Position::new(8, 14)).unwrap()), vec![(9, 16)]);
+        assert_eq!(
+            starts(ctx.find_references(&path, Position::new(11, 14)).unwrap()),
+            vec![(12, 16), (13, 8)]
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_usages_of_name() {
+        let source = r#"struct Light { float weight; };
+float weight = 1.0;
+float blend(float weight) {
+    return weight * 2.0;
+}
+#define weight_scale 2.0
+
+void main() {
+    float weight2 = weight;
+    gl_FragColor = vec4(blend(weight));
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let starts =
+            |ranges: Vec<Range>| -> Vec<(u32, u32)> { ranges.into_iter().map(|range| (range.start.line, range.start.character)).collect() };
+
+        // every identifier spelled the same whatever it's declared as, but not fields or longer names
+        assert_eq!(
+            starts(ctx.usages_of_name("weight")),
+            vec![(1, 6), (2, 18), (3, 11), (8, 20), (9, 30)]
+        );
+        assert!(ctx.usages_of_name("missing").is_empty());
+
+        // highlights follow the scope of variables, falling back to every identifier of the name for functions
+        let highlights = |line, character| starts(ctx.document_highlights(&path, Position { line, character }).unwrap().unwrap());
+        assert_eq!(highlights(3, 12), vec![(2, 18), (3, 11)]);
+        assert_eq!(highlights(8, 21), vec![(1, 6), (8, 20), (9, 30)]);
+        assert_eq!(highlights(9, 25), vec![(2, 6), (9, 24)]);
+        assert_eq!(ctx.document_highlights(&path, Position { line: 0, character: 2 }).unwrap(), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_variable_and_struct_references() {
+        let source = r#"struct Light { vec3 color; };
+
+uniform Light sun;
+
+vec3 shade(Light light, float shadow) {
+    float lit = shadow * 0.5;
+    {
+        float lit = 1.0;
+        shadow += lit;
+    }
+    return light.color * lit + shadow;
+}
+
+void main() {
+    Light moon = Light(vec3(0.1));
+    gl_FragColor = vec4(shade(moon, 1.0), 1.0);
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = temp_dir().join("test.fsh");
+
+        let starts = |point: Position| -> Vec<(u32, u32)> {
+            ctx.find_references(&path, point)
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|location| (location.range.start.line, location.range.start.character))
+                .collect()
+        };
+
+        // locals skip the uses of the shadowing variable, parameters include assignments
+        assert_eq!(starts(Position::new(5, 10)), vec![(10, 25)]);
+        assert_eq!(starts(Position::new(7, 14)), vec![(8, 18)]);
+        assert_eq!(starts(Position::new(4, 30)), vec![(5, 16), (8, 8), (10, 31)]);
+        // globals, and structs as types as well as constructors
+        assert!(starts(Position::new(2, 14)).is_empty());
+        assert_eq!(starts(Position::new(0, 7)), vec![(2, 8), (4, 11), (14, 4), (14, 17)]);
+
+        // only declarations have references
+        assert_eq!(ctx.find_references(&path, Position::new(10, 25)).unwrap(), None);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_parse_timeout() {
+        let source = "float f() { return 1.0 + 2.0 * 3.0; }\n".repeat(20_000);
+
+        let mut parser = new_parser();
+        parser.set_timeout_micros(1);
+        let err = ParserContext::from_source(&mut parser, source).err().unwrap();
+        assert!(matches!(err.downcast_ref(), Some(NavigationError::ParseFailed)));
+
+        // the abandoned parse doesn't carry over once the timeout is lifted
+        parser.set_timeout_micros(0);
+        let ctx = ParserContext::from_source(&mut parser, "void main() {}".to_string()).unwrap();
+        assert_eq!(ctx.root_node().named_child_count(), 1);
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_identifier_boundaries() {
+        for name in ["_private", "v2", "MAX_N", "a"] {
+            assert!(is_identifier(name), "{}", name);
+        }
+        for text in ["2v", "", "a.b", "max-n", "\u{e4}pfel"] {
+            assert!(!is_identifier(text), "{}", text);
+        }
+
+        let source = r#"#define MAX_N 4
+float _private = 1.0;
+vec2 v2 = vec2(0.0);
+void main() {
+    float x = _private + v2.x * MAX_N;
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let text_at = |line, character| {
+            let node = ctx.find_node_at_point(Position { line, character }).unwrap();
+            node.utf8_text(ctx.source().as_bytes()).unwrap().to_string()
+        };
+
+        // on the leading underscore, on a digit, on an inner underscore and just past the end of a name
+        assert_eq!(text_at(4, 14), "_private");
+        assert_eq!(text_at(4, 26), "v2");
+        assert_eq!(text_at(4, 35), "MAX_N");
+        assert_eq!(text_at(4, 37), "MAX_N");
+        assert_eq!(text_at(1, 14), "_private");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_punctuation_boundaries() {
+        let source = r#"void main() {
+    vec3 color = light.color;
+    float s = scale(color);
+    float w = weights[index];
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let node_at = |line, character| {
+            let node = ctx.find_node_at_point(Position { line, character }).unwrap();
+            (node.kind(), node.utf8_text(ctx.source().as_bytes()).unwrap().to_string())
+        };
+
+        // either side of `.`
+        assert_eq!(node_at(1, 22), ("identifier", "light".to_string()));
+        assert_eq!(node_at(1, 23), ("field_identifier", "color".to_string()));
+        // either side of `(` and `)`
+        assert_eq!(node_at(2, 19), ("identifier", "scale".to_string()));
+        assert_eq!(node_at(2, 20), ("identifier", "color".to_string()));
+        assert_eq!(node_at(2, 25), ("identifier", "color".to_string()));
+        // either side of `[` and `]`
+        assert_eq!(node_at(3, 21), ("identifier", "weights".to_string()));
+        assert_eq!(node_at(3, 22), ("identifier", "index".to_string()));
+        assert_eq!(node_at(3, 27), ("identifier", "index".to_string()));
+        // the name behind is preferred where there's one either side, as after a type name
+        assert_eq!(node_at(1, 8).1, "vec3");
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_node_selection() {
+        let source = r#"void main() {
+    vec3 color = light.color;
+    float w = weights[index] + grid[0][1];
+}
+"#;
+
+        let mut parser = new_parser();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let selected = |line, character, selection| {
+            let node = ctx.select_node_at_point(Position { line, character }, selection).unwrap();
+            (node.kind(), node.utf8_text(ctx.source().as_bytes()).unwrap().to_string())
+        };
+
+        // a field is the name alone, or the field expression selecting it
+        assert_eq!(selected(1, 24, NodeSelection::Name), ("field_identifier", "color".to_string()));
+        assert_eq!(
+            selected(1, 24, NodeSelection::Expression),
+            ("field_expression", "light.color".to_string())
+        );
+        // while the struct accessed is left alone either way
+        assert_eq!(selected(1, 18, NodeSelection::Name), ("identifier", "light".to_string()));
+        assert_eq!(selected(1, 18, NodeSelection::Expression), ("identifier", "light".to_string()));
+
+        // an array being indexed, but not its index
+        assert_eq!(selected(2, 14, NodeSelection::Name), ("identifier", "weights".to_string()));
+        assert_eq!(
+            selected(2, 14, NodeSelection::Expression),
+            ("subscript_expression", "weights[index]".to_string())
+        );
+        assert_eq!(selected(2, 23, NodeSelection::Expression), ("identifier", "index".to_string()));
+        // the innermost subscript of a multi-dimensional array, being the one indexing it
+        assert_eq!(
+            selected(2, 31, NodeSelection::Expression),
+            ("subscript_expression", "grid[0]".to_string())
+        );
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_adjacent_diagnostic() {
+        let path = temp_dir().join("test.fsh");
+        let diagnostic_at = |line, character| Diagnostic {
+            range: Range::new(
+                Position { line, character },
+                Position {
+                    line,
+                    character: character + 1,
+                },
+            ),
+            ..Diagnostic::default()
+        };
+        // out of order, as merged from several lints
+        let diagnostics = vec![diagnostic_at(7, 2), diagnostic_at(1, 4), diagnostic_at(3, 0)];
+
+        let start_of = |location: Option<Location>| location.unwrap().range.start;
+
+        assert_eq!(
+            start_of(adjacent_diagnostic(&path, &diagnostics, Position { line: 2, character: 0 }, true).unwrap()),
+            Position { line: 3, character: 0 }
+        );
+        // a diagnostic at the position itself is skipped over
+        assert_eq!(
+            start_of(adjacent_diagnostic(&path, &diagnostics, Position { line: 3, character: 0 }, true).unwrap()),
+            Position { line: 7, character: 2 }
+        );
+        assert_eq!(
+            start_of(adjacent_diagnostic(&path, &diagnostics, Position { line: 3, character: 0 }, false).unwrap()),
+            Position { line: 1, character: 4 }
+        );
+
+        // wrapping around at either end
+        assert_eq!(
+            start_of(adjacent_diagnostic(&path, &diagnostics, Position { line: 9, character: 0 }, true).unwrap()),
+            Position { line: 1, character: 4 }
+        );
+        assert_eq!(
+            start_of(adjacent_diagnostic(&path, &diagnostics, Position { line: 0, character: 0 }, false).unwrap()),
+            Position { line: 7, character: 2 }
+        );
+
+        assert_eq!(
+            adjacent_diagnostic(&path, &[], Position { line: 0, character: 0 }, true).unwrap(),
+            None
+        );
     }
 }
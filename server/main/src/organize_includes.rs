@@ -0,0 +1,162 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{Range, TextEdit, WorkspaceEdit};
+use tree_sitter::Node;
+
+use crate::{
+    include_resolver::IncludeResolver,
+    navigation::ParserContext,
+    url_norm::{normalize_path, url_for},
+};
+
+struct Include<'tree> {
+    node: Node<'tree>,
+    // the include path as written, without the surrounding quotes
+    path: String,
+    target: PathBuf,
+}
+
+/// Builds the edit that sorts the `#include` directives at the top of the file and removes duplicates, that is
+/// directives resolving to a file already included above. Only runs of consecutive directives before the first
+/// declaration are touched, and nothing is moved across another directive such as a `#define`, as includes may
+/// depend on it. Absolute includes are sorted before relative ones. Returns `None` if the includes are already
+/// organized.
+pub fn organize_includes(ctx: &ParserContext, path: &Path, resolver: &IncludeResolver) -> Result<Option<WorkspaceEdit>> {
+    let mut edits = vec![];
+    let mut seen = HashSet::new();
+
+    for block in include_blocks(ctx, path, resolver) {
+        let original: Vec<&str> = block.iter().map(|include| include_text(ctx, include)).collect();
+
+        let mut organized: Vec<&Include> = block.iter().filter(|include| seen.insert(include.target.clone())).collect();
+        organized.sort_by(|a, b| (!a.path.starts_with('/'), &a.path).cmp(&(!b.path.starts_with('/'), &b.path)));
+        let organized: Vec<&str> = organized.into_iter().map(|include| include_text(ctx, include)).collect();
+
+        if organized == original {
+            continue;
+        }
+
+        let start = block.first().unwrap().node;
+        let end = block.last().unwrap().node;
+
+        let mut new_text = organized.join("\n");
+        // the directive node spans its trailing newline, which the replaced text needs to keep
+        if ctx.source()[..end.end_byte()].ends_with('\n') {
+            new_text.push('\n');
+        }
+
+        edits.push(TextEdit {
+            range: Range {
+                start: ctx.position_for_offset(start.start_byte()),
+                end: ctx.position_for_offset(end.end_byte()),
+            },
+            new_text,
+        });
+    }
+
+    if edits.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(HashMap::from([(url_for(path)?, edits)])),
+        ..WorkspaceEdit::default()
+    }))
+}
+
+// Collects the runs of consecutive top-level include directives that precede the first non-preprocessor item.
+fn include_blocks<'tree>(ctx: &'tree ParserContext, path: &Path, resolver: &IncludeResolver) -> Vec<Vec<Include<'tree>>> {
+    let mut blocks = vec![];
+    let mut block = vec![];
+
+    let root = ctx.root_node();
+    let mut cursor = root.walk();
+    for node in root.named_children(&mut cursor) {
+        match node.kind() {
+            "preproc_include" => {
+                let include = match node.child_by_field_name("path") {
+                    Some(include) => include.utf8_text(ctx.source().as_bytes()).unwrap(),
+                    None => break,
+                };
+                let include = include.trim_matches(|c| c == '"' || c == '<' || c == '>').to_string();
+                let target = normalize_path(&resolver.resolve(path, &include));
+
+                block.push(Include {
+                    node,
+                    target,
+                    path: include,
+                });
+            }
+            // comments between includes may document them, so they end a block rather than getting reordered
+            kind if kind == "comment" || kind.starts_with("preproc_") => {
+                if !block.is_empty() {
+                    blocks.push(std::mem::take(&mut block));
+                }
+            }
+            _ => break,
+        }
+    }
+
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+
+    blocks
+}
+
+fn include_text<'a>(ctx: &'a ParserContext, include: &Include) -> &'a str {
+    include.node.utf8_text(ctx.source().as_bytes()).unwrap().trim_end()
+}
+
+#[cfg(test)]
+mod organize_includes_test {
+    use std::path::PathBuf;
+
+    use tree_sitter::Parser;
+    use url::Url;
+
+    use crate::include_resolver::IncludeResolver;
+    use crate::navigation::ParserContext;
+    use crate::organize_includes::organize_includes;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_organize_includes() {
+        let source = r#"#version 120
+
+#include "utils/noise.glsl"
+#include "/lib/common.glsl"
+#include "../world0/utils/noise.glsl"
+#define SHADOWS
+#include "/lib/shadows.glsl"
+#include "/lib/blur.glsl"
+
+void main() {}
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        let root = PathBuf::from("/pack");
+        let path = root.join("shaders").join("world0").join("final.fsh");
+
+        let edit = organize_includes(&ctx, &path, &IncludeResolver::new(&root)).unwrap().unwrap();
+        let edits = &edit.changes.unwrap()[&Url::from_file_path(&path).unwrap()];
+
+        assert_eq!(edits.len(), 2);
+
+        assert_eq!(edits[0].range.start.line, 2);
+        assert_eq!(edits[0].range.end.line, 5);
+        assert_eq!(edits[0].new_text, "#include \"/lib/common.glsl\"\n#include \"utils/noise.glsl\"\n");
+
+        // the include after the #define is sorted separately
+        assert_eq!(edits[1].range.start.line, 6);
+        assert_eq!(edits[1].range.end.line, 8);
+        assert_eq!(edits[1].new_text, "#include \"/lib/blur.glsl\"\n#include \"/lib/shadows.glsl\"\n");
+    }
+}
@@ -0,0 +1,356 @@
+use std::fmt::{self, Display, Formatter};
+
+/// The overloads of the most used built-in functions, as their signatures are written in the GLSL specification.
+/// Generic types stand for a family of types: `genType` for `float` and `vec2` to `vec4`, with `genIType`,
+/// `genUType` and `genBType` their `int`, `uint` and `bool` counterparts, all of the same size throughout a
+/// signature. Types prefixed with `g`, as `gsampler2D` and `gvec4`, stand for their float, `i` and `u` variants,
+/// the prefix being the same throughout a signature.
+const BUILTIN_SIGNATURES: &[&str] = &[
+    // angle and trigonometry
+    "genType radians(genType degrees)",
+    "genType degrees(genType radians)",
+    "genType sin(genType angle)",
+    "genType cos(genType angle)",
+    "genType tan(genType angle)",
+    "genType asin(genType x)",
+    "genType acos(genType x)",
+    "genType atan(genType y, genType x)",
+    "genType atan(genType y_over_x)",
+    // exponential
+    "genType pow(genType x, genType y)",
+    "genType exp(genType x)",
+    "genType log(genType x)",
+    "genType exp2(genType x)",
+    "genType log2(genType x)",
+    "genType sqrt(genType x)",
+    "genType inversesqrt(genType x)",
+    // common
+    "genType abs(genType x)",
+    "genIType abs(genIType x)",
+    "genType sign(genType x)",
+    "genIType sign(genIType x)",
+    "genType floor(genType x)",
+    "genType trunc(genType x)",
+    "genType round(genType x)",
+    "genType ceil(genType x)",
+    "genType fract(genType x)",
+    "genType mod(genType x, float y)",
+    "genType mod(genType x, genType y)",
+    "genType min(genType x, genType y)",
+    "genType min(genType x, float y)",
+    "genIType min(genIType x, genIType y)",
+    "genIType min(genIType x, int y)",
+    "genUType min(genUType x, genUType y)",
+    "genUType min(genUType x, uint y)",
+    "genType max(genType x, genType y)",
+    "genType max(genType x, float y)",
+    "genIType max(genIType x, genIType y)",
+    "genIType max(genIType x, int y)",
+    "genUType max(genUType x, genUType y)",
+    "genUType max(genUType x, uint y)",
+    "genType clamp(genType x, genType minVal, genType maxVal)",
+    "genType clamp(genType x, float minVal, float maxVal)",
+    "genIType clamp(genIType x, genIType minVal, genIType maxVal)",
+    "genIType clamp(genIType x, int minVal, int maxVal)",
+    "genUType clamp(genUType x, genUType minVal, genUType maxVal)",
+    "genUType clamp(genUType x, uint minVal, uint maxVal)",
+    "genType mix(genType x, genType y, genType a)",
+    "genType mix(genType x, genType y, float a)",
+    "genType mix(genType x, genType y, genBType a)",
+    "genType step(genType edge, genType x)",
+    "genType step(float edge, genType x)",
+    "genType smoothstep(genType edge0, genType edge1, genType x)",
+    "genType smoothstep(float edge0, float edge1, genType x)",
+    "genIType floatBitsToInt(genType value)",
+    "genUType floatBitsToUint(genType value)",
+    "genType intBitsToFloat(genIType value)",
+    "genType uintBitsToFloat(genUType value)",
+    // geometric
+    "float length(genType x)",
+    "float distance(genType p0, genType p1)",
+    "float dot(genType x, genType y)",
+    "vec3 cross(vec3 x, vec3 y)",
+    "genType normalize(genType x)",
+    "genType faceforward(genType N, genType I, genType Nref)",
+    "genType reflect(genType I, genType N)",
+    "genType refract(genType I, genType N, float eta)",
+    // texture
+    "gvec4 texture(gsampler2D sampler, vec2 P)",
+    "gvec4 texture(gsampler2D sampler, vec2 P, float bias)",
+    "gvec4 texture(gsampler3D sampler, vec3 P)",
+    "gvec4 texture(gsampler3D sampler, vec3 P, float bias)",
+    "gvec4 texture(gsampler2DArray sampler, vec3 P)",
+    "float texture(sampler2DShadow sampler, vec3 P)",
+    "gvec4 textureLod(gsampler2D sampler, vec2 P, float lod)",
+    "gvec4 textureLod(gsampler3D sampler, vec3 P, float lod)",
+    "gvec4 texelFetch(gsampler2D sampler, ivec2 P, int lod)",
+    "gvec4 texelFetch(gsampler3D sampler, ivec3 P, int lod)",
+    "ivec2 textureSize(gsampler2D sampler, int lod)",
+    "ivec3 textureSize(gsampler3D sampler, int lod)",
+    "vec4 texture2D(sampler2D sampler, vec2 coord)",
+    "vec4 texture2D(sampler2D sampler, vec2 coord, float bias)",
+    "vec4 texture2DLod(sampler2D sampler, vec2 coord, float lod)",
+    "vec4 texture3D(sampler3D sampler, vec3 coord)",
+    "vec4 shadow2D(sampler2DShadow sampler, vec3 coord)",
+];
+
+// the scalar types whose vectors generic types stand for, along with the prefix of their vector types.
+const GENERIC_TYPES: &[(&str, &str, &str)] = &[
+    ("genType", "float", "vec"),
+    ("genIType", "int", "ivec"),
+    ("genUType", "uint", "uvec"),
+    ("genBType", "bool", "bvec"),
+];
+
+// the prefixes the `g` of generic samplers and vectors stands for.
+const SAMPLER_PREFIXES: &[&str] = &["", "i", "u"];
+
+/// How well an overload fits the arguments of a call, from worst to best. Arguments of a type that couldn't be
+/// inferred fit any parameter, but the fit is only as good as a guess.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fit {
+    Unknown,
+    // an argument is implicitly converted, as an `int` passed for a `float`
+    Converted,
+    Exact,
+}
+
+/// An overload of a built-in function, see `BUILTIN_SIGNATURES`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overload {
+    pub ret: &'static str,
+    pub name: &'static str,
+    // the type and name of each parameter
+    pub params: Vec<(&'static str, &'static str)>,
+}
+
+impl Overload {
+    fn parse(signature: &'static str) -> Overload {
+        let (head, params) = signature.trim_end_matches(')').split_once('(').unwrap();
+        let (ret, name) = head.split_once(' ').unwrap();
+        let params = params
+            .split(", ")
+            .filter(|param| !param.is_empty())
+            .map(|param| param.split_once(' ').unwrap())
+            .collect();
+
+        Overload { ret, name, params }
+    }
+
+    /// Returns how well the overload fits the types of the arguments, which are `None` where they couldn't be
+    /// inferred, along with the overload with the generic types replaced by those the arguments bind them to.
+    /// Generic types bound by no argument are left as they are.
+    pub fn fit(&self, arguments: &[Option<String>]) -> Option<(Fit, String)> {
+        if arguments.len() != self.params.len() {
+            return None;
+        }
+
+        let mut bindings = Bindings::default();
+        let mut fit = Fit::Exact;
+        for ((ty, _), argument) in self.params.iter().zip(arguments) {
+            let param_fit = match argument {
+                Some(argument) => bindings.bind(ty, argument)?,
+                None => Fit::Unknown,
+            };
+            fit = fit.min(param_fit);
+        }
+
+        let params = self
+            .params
+            .iter()
+            .map(|(ty, name)| format!("{} {}", bindings.specialize(ty), name))
+            .collect::<Vec<_>>();
+        Some((
+            fit,
+            format!("{} {}({})", bindings.specialize(self.ret), self.name, params.join(", ")),
+        ))
+    }
+}
+
+impl Display for Overload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let params = self.params.iter().map(|(ty, name)| format!("{} {}", ty, name)).collect::<Vec<_>>();
+        write!(f, "{} {}({})", self.ret, self.name, params.join(", "))
+    }
+}
+
+// the concrete types the generic types of a signature are bound to by the arguments matched so far.
+#[derive(Default)]
+struct Bindings {
+    size: Option<usize>,
+    prefix: Option<&'static str>,
+}
+
+impl Bindings {
+    fn bind(&mut self, param: &str, argument: &str) -> Option<Fit> {
+        if let Some((_, scalar, _)) = GENERIC_TYPES.iter().find(|(generic, _, _)| *generic == param) {
+            let (argument_scalar, size) = components(argument)?;
+            if self.size.map_or(false, |bound| bound != size) {
+                return None;
+            }
+            let fit = scalar_fit(scalar, argument_scalar)?;
+            self.size = Some(size);
+            return Some(fit);
+        }
+
+        if let Some(generic) = param.strip_prefix('g') {
+            let prefix = SAMPLER_PREFIXES
+                .iter()
+                .find(|prefix| format!("{}{}", prefix, generic) == argument)?;
+            if self.prefix.map_or(false, |bound| bound != *prefix) {
+                return None;
+            }
+            self.prefix = Some(*prefix);
+            return Some(Fit::Exact);
+        }
+
+        if param == argument {
+            return Some(Fit::Exact);
+        }
+        match (components(param), components(argument)) {
+            (Some((scalar, size)), Some((argument_scalar, argument_size))) if size == argument_size => scalar_fit(scalar, argument_scalar),
+            _ => None,
+        }
+    }
+
+    fn specialize(&self, ty: &str) -> String {
+        if let Some((_, scalar, vector)) = GENERIC_TYPES.iter().find(|(generic, _, _)| *generic == ty) {
+            return match self.size {
+                Some(1) => scalar.to_string(),
+                Some(size) => format!("{}{}", vector, size),
+                None => ty.to_string(),
+            };
+        }
+
+        match (ty.strip_prefix('g'), self.prefix) {
+            (Some(generic), Some(prefix)) => format!("{}{}", prefix, generic),
+            _ => ty.to_string(),
+        }
+    }
+}
+
+// the fit of an argument of the scalar type for a parameter of the other, integers being implicitly converted to
+// floats but not the other way around.
+fn scalar_fit(param: &str, argument: &str) -> Option<Fit> {
+    match (param, argument) {
+        _ if param == argument => Some(Fit::Exact),
+        ("float", "int" | "uint") | ("uint", "int") => Some(Fit::Converted),
+        _ => None,
+    }
+}
+
+/// Returns the scalar type and size of a scalar or vector type, as `("float", 3)` for a `vec3`.
+pub fn components(ty: &str) -> Option<(&'static str, usize)> {
+    for &(_, scalar, vector) in GENERIC_TYPES {
+        if ty == scalar {
+            return Some((scalar, 1));
+        }
+        if let Some(size @ 2..=4) = ty.strip_prefix(vector).and_then(|size| size.parse().ok()) {
+            return Some((scalar, size));
+        }
+    }
+    None
+}
+
+/// Returns the vector type of the size with the scalar type's components, or the scalar type itself for a size of 1.
+pub fn vector_type(scalar: &str, size: usize) -> Option<String> {
+    let (_, scalar, vector) = GENERIC_TYPES.iter().find(|(_, s, _)| *s == scalar)?;
+    match size {
+        1 => Some(scalar.to_string()),
+        2..=4 => Some(format!("{}{}", vector, size)),
+        _ => None,
+    }
+}
+
+/// Returns the overloads of the built-in function of the given name, in the order the specification lists them.
+/// Functions without a listed signature have none.
+pub fn overloads(name: &str) -> Vec<Overload> {
+    BUILTIN_SIGNATURES
+        .iter()
+        .map(|signature| Overload::parse(signature))
+        .filter(|overload| overload.name == name)
+        .collect()
+}
+
+/// Returns the index of the overload best fitting the arguments along with it specialized to them. There's no best
+/// fit when several overloads fit equally well, as when the arguments deciding between them couldn't be inferred,
+/// unless they specialize to the same signature, as `clamp` does for floats, where the first is taken.
+pub fn best_fit(overloads: &[Overload], arguments: &[Option<String>]) -> Option<(usize, String)> {
+    let fits: Vec<(usize, Fit, String)> = overloads
+        .iter()
+        .enumerate()
+        .filter_map(|(i, overload)| overload.fit(arguments).map(|(fit, signature)| (i, fit, signature)))
+        .collect();
+
+    let best = fits.iter().map(|(_, fit, _)| *fit).max()?;
+    let mut best_fits = fits.into_iter().filter(|(_, fit, _)| *fit == best);
+    let (i, _, signature) = best_fits.next()?;
+
+    best_fits.all(|(_, _, other)| other == signature).then_some((i, signature))
+}
+
+#[cfg(test)]
+mod overloads_test {
+    use crate::overloads::{best_fit, components, overloads, vector_type, Fit};
+
+    fn types(types: &[Option<&str>]) -> Vec<Option<String>> {
+        types.iter().map(|ty| ty.map(String::from)).collect()
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_overload_fit() {
+        let mix = overloads("mix");
+        assert_eq!(mix.len(), 3);
+        assert_eq!(mix[1].to_string(), "genType mix(genType x, genType y, float a)");
+
+        // the generic type is bound to the size of the first argument throughout
+        assert_eq!(
+            best_fit(&mix, &types(&[Some("vec3"), Some("vec3"), Some("float")])),
+            Some((1, "vec3 mix(vec3 x, vec3 y, float a)".to_string()))
+        );
+        assert_eq!(best_fit(&mix, &types(&[Some("vec3"), Some("vec2"), Some("float")])), None);
+        assert_eq!(
+            best_fit(&mix, &types(&[Some("vec3"), Some("vec3"), Some("bvec3")])),
+            Some((2, "vec3 mix(vec3 x, vec3 y, bvec3 a)".to_string()))
+        );
+
+        // converting an integer to a float only fits where there's no exact overload
+        let clamp = overloads("clamp");
+        assert_eq!(
+            best_fit(&clamp, &types(&[Some("float"), Some("int"), Some("int")])),
+            Some((0, "float clamp(float x, float minVal, float maxVal)".to_string()))
+        );
+        assert_eq!(
+            clamp[3].fit(&types(&[Some("int"), Some("int"), Some("int")])).unwrap().0,
+            Fit::Exact
+        );
+
+        // samplers decide the prefix of the vector returned
+        let texture = overloads("texture");
+        assert_eq!(
+            best_fit(&texture, &types(&[Some("usampler2D"), Some("vec2")])),
+            Some((0, "uvec4 texture(usampler2D sampler, vec2 P)".to_string()))
+        );
+
+        // unknown arguments leave overloads of the same fit undecided
+        assert_eq!(best_fit(&mix, &types(&[Some("vec2"), Some("vec2"), None])), None);
+        assert_eq!(
+            best_fit(&texture, &types(&[None, Some("vec2"), Some("float")])),
+            Some((1, "gvec4 texture(gsampler2D sampler, vec2 P, float bias)".to_string()))
+        );
+
+        assert!(overloads("luma").is_empty());
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_components() {
+        assert_eq!(components("float"), Some(("float", 1)));
+        assert_eq!(components("ivec3"), Some(("int", 3)));
+        assert_eq!(components("vec5"), None);
+        assert_eq!(components("mat3"), None);
+        assert_eq!(vector_type("uint", 2), Some("uvec2".to_string()));
+        assert_eq!(vector_type("float", 1), Some("float".to_string()));
+    }
+}
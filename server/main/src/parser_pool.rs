@@ -0,0 +1,109 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use tree_sitter::Parser;
+
+/// A fixed set of parsers, one per worker thread, for parsing many files concurrently. As a `Parser` can't be
+/// shared between threads, each worker exclusively borrows its own for the duration of a batch.
+pub struct ParserPool {
+    parsers: Vec<Parser>,
+}
+
+impl ParserPool {
+    pub fn new(workers: usize) -> Self {
+        let parsers = (0..workers.max(1))
+            .map(|_| {
+                let mut parser = Parser::new();
+                parser.set_language(tree_sitter_glsl::language()).unwrap();
+                parser
+            })
+            .collect();
+
+        ParserPool { parsers }
+    }
+
+    /// Sets the timeout of each of the pool's parsers, see `Parser::set_timeout_micros`.
+    pub fn set_timeout_micros(&mut self, timeout: u64) {
+        for parser in self.parsers.iter_mut() {
+            parser.set_timeout_micros(timeout);
+        }
+    }
+
+    /// Runs `f` for each of the files spread across the pool's workers, returning the results in the same order
+    /// as the files regardless of which worker processed them or when.
+    pub fn map<T, F>(&mut self, files: &[PathBuf], f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&mut Parser, &PathBuf) -> T + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(files.len()));
+
+        thread::scope(|scope| {
+            for parser in self.parsers.iter_mut() {
+                let (next, results, f) = (&next, &results, &f);
+                scope.spawn(move || loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let file = match files.get(idx) {
+                        Some(file) => file,
+                        None => break,
+                    };
+                    let result = f(parser, file);
+                    results.lock().unwrap().push((idx, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(idx, _)| *idx);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod parser_pool_test {
+    use std::{fs, path::PathBuf};
+
+    use tempdir::TempDir;
+    use tree_sitter::Parser;
+
+    use crate::navigation::ParserContext;
+    use crate::parser_pool::ParserPool;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_map_is_deterministic() {
+        let tmp_dir = TempDir::new("mcshader").unwrap();
+
+        let files: Vec<_> = (0..320)
+            .map(|i| {
+                let path = tmp_dir.path().join(format!("file{}.glsl", i));
+                fs::write(&path, format!("float func{}() {{ return {}.0; }}\n", i, i)).unwrap();
+                path
+            })
+            .collect();
+
+        let list_functions = |parser: &mut Parser, path: &PathBuf| {
+            let ctx = ParserContext::new(parser, path).unwrap();
+            ctx.list_symbols(path)
+                .unwrap()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|s| s.name)
+                .collect::<Vec<_>>()
+        };
+
+        let sequential = ParserPool::new(1).map(&files, list_functions);
+        let parallel = ParserPool::new(8).map(&files, list_functions);
+
+        assert_eq!(sequential.len(), 320);
+        assert_eq!(sequential, parallel);
+        assert_eq!(parallel[42], vec!["func42".to_string()]);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{collections::HashSet, fmt::Display, path::Path};
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use petgraph::stable_graph::NodeIndex;
+use regex::Regex;
+use rust_lsp::lsp_types::{Location, Position};
+use slog_scope::warn;
+use tree_sitter::{Node, Parser, Query, QueryCursor};
+
+use crate::graph::CachedStableGraph;
+use crate::navigation::{self, ParserContext};
+
+lazy_static! {
+    static ref RE_DEFINED_CONDITION: Regex = Regex::new(r#"^\s*(!)?\s*(?:defined\s*\(?\s*)?(\w+)\s*\)?\s*$"#).unwrap();
+}
+
+// predefined by GLSL in every shader.
+const GLSL_MACROS: &[&str] = &["__LINE__", "__FILE__", "__VERSION__"];
+
+// defined by OptiFine in every shader, leaving out those only defined on some platforms, as `MC_OS_WINDOWS` is.
+const OPTIFINE_MACROS: &[&str] = &[
+    "MC_VERSION",
+    "MC_GL_VERSION",
+    "MC_GLSL_VERSION",
+    "MC_RENDER_QUALITY",
+    "MC_SHADOW_QUALITY",
+    "MC_HAND_DEPTH",
+];
+
+// defined by OptiFine depending on the platform, the driver and the video settings, as documented by OptiFine's
+// `shaders.txt`. These aren't in effect everywhere, but are still never defined in source.
+const OPTIFINE_CONDITIONAL_MACROS: &[&str] = &[
+    "MC_OS_WINDOWS",
+    "MC_OS_MAC",
+    "MC_OS_LINUX",
+    "MC_OS_OTHER",
+    "MC_GL_VENDOR_AMD",
+    "MC_GL_VENDOR_ATI",
+    "MC_GL_VENDOR_INTEL",
+    "MC_GL_VENDOR_MESA",
+    "MC_GL_VENDOR_NVIDIA",
+    "MC_GL_VENDOR_XORG",
+    "MC_GL_VENDOR_OTHER",
+    "MC_GL_RENDERER_RADEON",
+    "MC_GL_RENDERER_GEFORCE",
+    "MC_GL_RENDERER_QUADRO",
+    "MC_GL_RENDERER_INTEL",
+    "MC_GL_RENDERER_GALLIUM",
+    "MC_GL_RENDERER_MESA",
+    "MC_GL_RENDERER_OTHER",
+    "MC_FXAA_LEVEL",
+    "MC_NORMAL_MAP",
+    "MC_SPECULAR_MAP",
+    "MC_OLD_HAND_LIGHT",
+    "MC_OLD_LIGHTING",
+    "MC_ANISOTROPIC_FILTERING",
+];
+
+const LIST_DEFINES_STR: &str = r#"
+    (preproc_def
+        name: (identifier) @define)
+
+    (preproc_function_def
+        name: (identifier) @define)
+"#;
+
+const LIST_DIRECTIVES_STR: &str = r#"
+    (preproc_call
+        directive: (preproc_directive) @directive
+        argument: (preproc_arg) @argument)
+"#;
+
+/// A condition imposed on a region of source by a preprocessor conditional, e.g. `#ifdef SHADOWS`
+/// requires `SHADOWS` to be defined while its `#else` branch requires it not to be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub name: String,
+    pub defined: bool,
+}
+
+impl Condition {
+    fn negate(self) -> Self {
+        Condition {
+            name: self.name,
+            defined: !self.defined,
+        }
+    }
+
+    pub fn is_met(&self, defines: &HashSet<String>) -> bool {
+        defines.contains(&self.name) == self.defined
+    }
+}
+
+/// Where a macro in effect comes from, as told apart by `active_macros`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MacroSource {
+    /// Predefined by GLSL, as `__VERSION__` is.
+    Glsl,
+    /// Defined by OptiFine when compiling the shader pack, as `MC_VERSION` is.
+    OptiFine,
+    /// Defined by a `#define` in the shader pack, at the location of its name.
+    Define(Location),
+}
+
+/// A macro in effect at a point, as listed by `active_macros`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroInfo {
+    pub name: String,
+    // the parameters of a function-like macro, as the `x` of `#define SQUARE(x) ((x) * (x))`
+    pub parameters: Option<Vec<String>>,
+    // the body the macro expands to, unknown for predefined macros as their values depend on the client
+    pub value: Option<String>,
+    pub source: MacroSource,
+}
+
+// a directive changing which macros are in effect, in the order they're met in a file.
+enum Directive {
+    Define(MacroInfo),
+    Undef(String),
+    Include(NodeIndex),
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.defined {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "!{}", self.name)
+        }
+    }
+}
+
+/// Returns the set of macros defined anywhere in the file.
+pub fn defined_macros(ctx: &ParserContext) -> HashSet<String> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_DEFINES_STR).unwrap();
+    let mut query_cursor = QueryCursor::new();
+
+    query_cursor
+        .matches(&query, ctx.root_node(), ctx.source().as_bytes())
+        .flat_map(|m| {
+            m.captures
+                .iter()
+                .map(|c| c.node.utf8_text(ctx.source().as_bytes()).unwrap().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Lists the macros in effect at the point of the file, in the order they were defined: those predefined by GLSL
+/// and OptiFine come first, followed by those `#define`d ahead of the point in the file or in the files it includes
+/// ahead of the point, walking their own includes in turn. Macros `#undef`'d ahead of the point are left out, and a
+/// macro defined again replaces its earlier definition. Conditionals aren't evaluated, so the macros defined in any
+/// of their branches are listed. Files are walked once, as with those guarded against being included twice.
+pub fn active_macros(graph: &CachedStableGraph, parser: &mut Parser, node: NodeIndex, point: Position) -> Result<Vec<MacroInfo>> {
+    let mut macros: Vec<MacroInfo> = GLSL_MACROS
+        .iter()
+        .map(|name| (name, MacroSource::Glsl))
+        .chain(OPTIFINE_MACROS.iter().map(|name| (name, MacroSource::OptiFine)))
+        .map(|(name, source)| MacroInfo {
+            name: name.to_string(),
+            parameters: None,
+            value: None,
+            source,
+        })
+        .collect();
+
+    walk_macros(graph, parser, node, Some(point), &mut HashSet::new(), &mut macros)?;
+
+    Ok(macros)
+}
+
+// applies the directives of the file ahead of the end to the macros, or all of them without an end.
+fn walk_macros(
+    graph: &CachedStableGraph, parser: &mut Parser, node: NodeIndex, end: Option<Position>, visited: &mut HashSet<NodeIndex>,
+    macros: &mut Vec<MacroInfo>,
+) -> Result<()> {
+    if !visited.insert(node) {
+        return Ok(());
+    }
+
+    let path = graph.get_node(node);
+    let mut directives = {
+        let ctx = ParserContext::new(parser, &path)?;
+        macro_directives(&ctx, &path)?
+    };
+    directives.extend(
+        graph
+            .get_all_child_positions(node)
+            .map(|(child, position)| (Position::new(position.line as u32, 0), Directive::Include(child))),
+    );
+    directives.sort_by_key(|(position, _)| (position.line, position.character));
+
+    for (position, directive) in directives {
+        if end.map_or(false, |end| (position.line, position.character) >= (end.line, end.character)) {
+            break;
+        }
+
+        match directive {
+            Directive::Define(info) => {
+                macros.retain(|existing| existing.name != info.name);
+                macros.push(info);
+            }
+            Directive::Undef(name) => macros.retain(|existing| existing.name != name),
+            Directive::Include(child) => {
+                if let Err(e) = walk_macros(graph, parser, child, None, visited, macros) {
+                    warn!("skipping unreadable include"; "error" => format!("{:?}", e), "path" => graph.get_node(child).to_str().unwrap());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the `#define` and `#undef` directives of the file, at the positions they start at.
+fn macro_directives(ctx: &ParserContext, path: &Path) -> Result<Vec<(Position, Directive)>> {
+    let defines = Query::new(tree_sitter_glsl::language(), LIST_DEFINES_STR).unwrap();
+    let undefs = Query::new(tree_sitter_glsl::language(), LIST_DIRECTIVES_STR).unwrap();
+    let mut query_cursor = QueryCursor::new();
+
+    let source = ctx.source().as_bytes();
+    let mut directives = vec![];
+
+    for m in query_cursor.matches(&defines, ctx.root_node(), source) {
+        for capture in m.captures {
+            let define = match capture.node.parent() {
+                Some(define) => define,
+                None => continue,
+            };
+
+            let parameters = define.child_by_field_name("parameters").map(|parameters| {
+                let mut cursor = parameters.walk();
+                parameters
+                    .named_children(&mut cursor)
+                    .filter_map(|parameter| parameter.utf8_text(source).ok())
+                    .map(str::to_string)
+                    .collect()
+            });
+            let value = define
+                .child_by_field_name("value")
+                .and_then(|value| value.utf8_text(source).ok())
+                .map(|value| value.trim().to_string());
+
+            let range = ctx.range_for_node(&capture.node);
+            let info = MacroInfo {
+                name: capture.node.utf8_text(source)?.to_string(),
+                parameters,
+                value,
+                source: MacroSource::Define(navigation::location_for(path, range.start, range.end)?),
+            };
+            directives.push((ctx.position_for_offset(define.start_byte()), Directive::Define(info)));
+        }
+    }
+
+    for m in query_cursor.matches(&undefs, ctx.root_node(), source) {
+        let (directive, argument) = match m.captures {
+            [directive, argument] => (directive.node, argument.node),
+            _ => continue,
+        };
+        if directive.utf8_text(source)? == "#undef" {
+            let name = argument.utf8_text(source)?.trim().to_string();
+            directives.push((ctx.position_for_offset(directive.start_byte()), Directive::Undef(name)));
+        }
+    }
+
+    Ok(directives)
+}
+
+/// Finds the `#define` directive defining the macro with the given name, if the file defines it.
+pub fn find_define<'tree>(ctx: &'tree ParserContext, name: &str) -> Option<Node<'tree>> {
+    let query = Query::new(tree_sitter_glsl::language(), LIST_DEFINES_STR).unwrap();
+    let mut query_cursor = QueryCursor::new();
+
+    let define = query_cursor
+        .matches(&query, ctx.root_node(), ctx.source().as_bytes())
+        .flat_map(|m| m.captures.iter().map(|c| c.node).collect::<Vec<_>>())
+        .find(|node| node.utf8_text(ctx.source().as_bytes()).unwrap() == name);
+
+    define.and_then(|node| node.parent())
+}
+
+/// Finds the `#undef` directive undefining the macro ahead of the byte offset, if the macro is undefined there. A
+/// macro `#define`d again after being undefined is in effect again.
+pub fn find_undef<'tree>(ctx: &'tree ParserContext, name: &str, offset: usize) -> Option<Node<'tree>> {
+    let defines = Query::new(tree_sitter_glsl::language(), LIST_DEFINES_STR).unwrap();
+    let directives = Query::new(tree_sitter_glsl::language(), LIST_DIRECTIVES_STR).unwrap();
+    let mut query_cursor = QueryCursor::new();
+
+    let source = ctx.source().as_bytes();
+    let mut last: Option<(usize, Option<Node>)> = None;
+    let mut track = |start: usize, undef: Option<Node<'tree>>| {
+        if start < offset && last.map_or(true, |(last_start, _)| start > last_start) {
+            last = Some((start, undef));
+        }
+    };
+
+    for m in query_cursor.matches(&defines, ctx.root_node(), source) {
+        for capture in m.captures.iter().filter(|c| c.node.utf8_text(source).unwrap() == name) {
+            track(capture.node.start_byte(), None);
+        }
+    }
+
+    for m in query_cursor.matches(&directives, ctx.root_node(), source) {
+        let (directive, argument) = match m.captures {
+            [directive, argument] => (directive.node, argument.node),
+            _ => continue,
+        };
+        if directive.utf8_text(source).unwrap() == "#undef" && argument.utf8_text(source).unwrap().trim() == name {
+            track(directive.start_byte(), directive.parent());
+        }
+    }
+
+    last.and_then(|(_, undef)| undef)
+}
+
+/// Follows a name through the chain of `#define`s aliasing it to another name, as in `#define Color vec3`,
+/// returning the defines followed in order. The chain ends at the first name that isn't an object-like macro
+/// expanding to a single name, or when it would loop back onto a name already followed.
+pub fn resolve_alias<'tree>(ctx: &'tree ParserContext, name: &str) -> Vec<Node<'tree>> {
+    let mut aliases = vec![];
+    let mut visited = HashSet::from([name.to_string()]);
+
+    let mut name = name.to_string();
+    while let Some(define) = find_define(ctx, &name).filter(|define| define.kind() == "preproc_def") {
+        let target = match alias_target(ctx, define) {
+            Some(target) => target.to_string(),
+            None => break,
+        };
+
+        aliases.push(define);
+        if !visited.insert(target.clone()) {
+            break;
+        }
+        name = target;
+    }
+
+    aliases
+}
+
+/// Whether the node is a name tested by a preprocessor conditional, as the `SHADOWS` of `#ifdef SHADOWS` or of
+/// `#if defined(SHADOWS) && SHADOW_RES > 1024`.
+pub fn is_condition_name(node: Node) -> bool {
+    node.kind() == "identifier"
+        && node.parent().map_or(false, |parent| {
+            matches!(
+                parent.kind(),
+                "preproc_ifdef"
+                    | "preproc_if"
+                    | "preproc_elif"
+                    | "preproc_defined"
+                    | "preproc_unary_expression"
+                    | "preproc_binary_expression"
+                    | "preproc_parenthesized_expression"
+            )
+        })
+}
+
+/// Whether the macro is one OptiFine defines when compiling the shader pack, rather than one defined in its source,
+/// whether in every shader or only on some platforms and settings.
+pub fn is_optifine_macro(name: &str) -> bool {
+    OPTIFINE_MACROS.contains(&name) || OPTIFINE_CONDITIONAL_MACROS.contains(&name)
+}
+
+/// Returns the name an object-like macro expands to, if its body is a single name.
+pub fn alias_target<'tree>(ctx: &'tree ParserContext, define: Node) -> Option<&'tree str> {
+    let value = define.child_by_field_name("value")?.utf8_text(ctx.source().as_bytes()).ok()?.trim();
+    navigation::is_identifier(value).then_some(value)
+}
+
+/// Returns the preprocessor conditions that must hold for the node to be compiled, innermost first.
+/// Conditions too complex to be understood (anything but `defined(X)`-style checks) are omitted.
+pub fn gating_conditions(node: Node, source: &str) -> Vec<Condition> {
+    let mut conditions = vec![];
+
+    let mut child = node;
+    let mut parent = node.parent();
+
+    while let Some(p) = parent {
+        if matches!(p.kind(), "preproc_ifdef" | "preproc_if" | "preproc_elif") {
+            let is_condition = p.child_by_field_name("name") == Some(child) || p.child_by_field_name("condition") == Some(child);
+            let in_alternative = p.child_by_field_name("alternative") == Some(child);
+
+            if !is_condition {
+                if let Some(condition) = condition_of(p, source) {
+                    conditions.push(if in_alternative { condition.negate() } else { condition });
+                }
+            }
+        }
+
+        child = p;
+        parent = p.parent();
+    }
+
+    conditions
+}
+
+/// Whether the two lists of conditions can't both be met, as with those of the two branches of an `#ifdef`, by one
+/// requiring a name to be defined that the other requires not to be.
+pub fn mutually_exclusive(a: &[Condition], b: &[Condition]) -> bool {
+    a.iter().any(|condition| b.contains(&condition.clone().negate()))
+}
+
+fn condition_of(node: Node, source: &str) -> Option<Condition> {
+    match node.kind() {
+        "preproc_ifdef" => {
+            let name = node.child_by_field_name("name")?.utf8_text(source.as_bytes()).ok()?.to_string();
+            let is_ifndef = node.child(0)?.utf8_text(source.as_bytes()).ok()?.starts_with("#ifndef");
+            Some(Condition { name, defined: !is_ifndef })
+        }
+        _ => {
+            let condition = node.child_by_field_name("condition")?.utf8_text(source.as_bytes()).ok()?;
+            let captures = RE_DEFINED_CONDITION.captures(condition)?;
+            Some(Condition {
+                name: captures.get(2)?.as_str().to_string(),
+                defined: captures.get(1).is_none(),
+            })
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use rust_lsp::jsonrpc::Endpoint;
+use rust_lsp::lsp_types::{
+    notification::{Notification, Progress},
+    request::{Request, WorkDoneProgressCreate},
+    ProgressParams, ProgressParamsValue, ProgressToken, WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressCreateParams,
+    WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+use slog_scope::warn;
+
+/// Reports how far a request searching many files has got through the work done token the client sent along with
+/// it, or one the server created with `create`, for the client to show while it waits. Work without a token reports
+/// nothing. Searches are stopped
+/// by cancelling their request, see `cancellation::Cancellations`, rather than through their progress, so it's
+/// reported as not cancellable. The progress ends once the reporter is dropped, however the search ended.
+pub struct ProgressReporter {
+    endpoint: Endpoint,
+    token: Option<ProgressToken>,
+    total: usize,
+    // the percentage last reported, so that only changes are sent
+    percentage: u32,
+}
+
+impl ProgressReporter {
+    pub fn begin(endpoint: &Endpoint, token: Option<ProgressToken>, title: &str, total: usize) -> Self {
+        let reporter = ProgressReporter {
+            endpoint: endpoint.clone(),
+            token,
+            total,
+            percentage: 0,
+        };
+        reporter.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.into(),
+            cancellable: Some(false),
+            message: None,
+            percentage: Some(0),
+        }));
+        reporter
+    }
+
+    /// Reports the number of files done so far out of the total given when beginning.
+    pub fn report(&mut self, done: usize) {
+        let percentage = percentage(done, self.total);
+        if percentage == self.percentage {
+            return;
+        }
+        self.percentage = percentage;
+
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(false),
+            message: Some(format!("{}/{} files", done, self.total)),
+            percentage: Some(percentage),
+        }));
+    }
+
+    fn send(&self, progress: WorkDoneProgress) {
+        let token = match &self.token {
+            Some(token) => token.clone(),
+            None => return,
+        };
+
+        let params = ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(progress),
+        };
+        if let Err(e) = self.endpoint.send_notification(Progress::METHOD, params) {
+            warn!("failed to send progress"; "error" => format!("{:?}", e));
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd { message: None }));
+    }
+}
+
+/// Asks the client to create a progress for work the server starts itself, such as indexing, there being no request
+/// with a token to report it through. Clients that can't create progresses aren't asked, and get no token.
+pub fn create(endpoint: &Endpoint, supported: bool, token: &str) -> Option<ProgressToken> {
+    if !supported {
+        return None;
+    }
+
+    let token = ProgressToken::String(token.into());
+    let params = WorkDoneProgressCreateParams { token: token.clone() };
+    // the progress is reported through without waiting on the client's answer, which carries nothing
+    if let Err(e) = endpoint.clone().send_request::<_, (), ()>(WorkDoneProgressCreate::METHOD, params) {
+        warn!("failed to create progress"; "error" => format!("{:?}", e));
+        return None;
+    }
+    Some(token)
+}
+
+/// The percentage of the total done, clamped to 100 and rounded down, so that 100 is only reached once all is done.
+pub fn percentage(done: usize, total: usize) -> u32 {
+    match total {
+        0 => 100,
+        total => (done.min(total) * 100 / total) as u32,
+    }
+}
+
+#[cfg(test)]
+mod progress_test {
+    use crate::progress::percentage;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_percentage() {
+        assert_eq!(percentage(0, 3), 0);
+        assert_eq!(percentage(1, 3), 33);
+        assert_eq!(percentage(299, 300), 99);
+        assert_eq!(percentage(300, 300), 100);
+        assert_eq!(percentage(4, 3), 100);
+        assert_eq!(percentage(0, 0), 100);
+    }
+}
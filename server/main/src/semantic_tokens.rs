@@ -0,0 +1,198 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_lsp::lsp_types::{Position, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensLegend};
+use tree_sitter::Node;
+
+use crate::{layout, navigation::ParserContext};
+
+lazy_static! {
+    static ref RE_COMMENT_MARKER: Regex = Regex::new(r"\b(TODO|FIXME)\b").unwrap();
+}
+
+/// The token types emitted by the server, in legend order. A token's type is its index into this list.
+pub const TOKEN_TYPES: &[SemanticTokenType] = &[SemanticTokenType::PROPERTY, SemanticTokenType::NUMBER, SemanticTokenType::COMMENT];
+
+/// The token modifiers emitted by the server, in legend order. A token's modifiers are a bitset of their indexes into
+/// this list. `todo` marks the `TODO` and `FIXME` markers of comments, which are otherwise comment tokens too.
+pub const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[SemanticTokenModifier::new("todo")];
+
+const PROPERTY: u32 = 0;
+const NUMBER: u32 = 1;
+const COMMENT: u32 = 2;
+
+const TODO: u32 = 1 << 0;
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+struct RawToken {
+    start: Position,
+    length: u32,
+    token_type: u32,
+    token_modifiers: u32,
+}
+
+/// Computes the semantic tokens for the file, delta-encoded as per the LSP spec. Comments are only included when
+/// asked for, as most clients already highlight them with their grammar.
+pub fn semantic_tokens(ctx: &ParserContext, comments: bool) -> SemanticTokens {
+    let mut tokens = vec![];
+
+    collect_layout_tokens(ctx, ctx.root_node(), &mut tokens);
+    if comments {
+        collect_comment_tokens(ctx, ctx.root_node(), &mut tokens);
+    }
+
+    tokens.sort_by(|a, b| (a.start.line, a.start.character).cmp(&(b.start.line, b.start.character)));
+
+    SemanticTokens {
+        result_id: None,
+        data: encode(&tokens),
+    }
+}
+
+fn collect_layout_tokens(ctx: &ParserContext, node: Node, tokens: &mut Vec<RawToken>) {
+    if node.kind().starts_with("layout") {
+        for qualifier in layout::parse_layout_qualifiers(node, ctx.source()) {
+            tokens.push(RawToken {
+                start: ctx.position_for_offset(qualifier.key_span.start),
+                length: qualifier.key_span.len() as u32,
+                token_type: PROPERTY,
+                token_modifiers: 0,
+            });
+            if let Some(span) = qualifier.value_span {
+                if qualifier.value.as_deref().map_or(false, |v| v.parse::<f64>().is_ok()) {
+                    tokens.push(RawToken {
+                        start: ctx.position_for_offset(span.start),
+                        length: span.len() as u32,
+                        token_type: NUMBER,
+                        token_modifiers: 0,
+                    });
+                }
+            }
+        }
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_layout_tokens(ctx, child, tokens);
+    }
+}
+
+// tokens can't span lines without the client supporting it, so block comments are split into a token per line.
+// Markers within the comments are tokens of their own, splitting the comment around them.
+fn collect_comment_tokens(ctx: &ParserContext, node: Node, tokens: &mut Vec<RawToken>) {
+    if node.kind() == "comment" {
+        let mut line_start = node.start_byte();
+        for line in ctx.source()[node.byte_range()].split('\n') {
+            let text = line.trim_end_matches('\r');
+
+            let mut segment_start = 0;
+            for marker in RE_COMMENT_MARKER.find_iter(text) {
+                push_comment_token(ctx, line_start, &text[segment_start..marker.start()], 0, tokens);
+                push_comment_token(ctx, line_start + marker.start(), marker.as_str(), TODO, tokens);
+                segment_start = marker.end();
+            }
+            push_comment_token(ctx, line_start + segment_start, &text[segment_start..], 0, tokens);
+
+            line_start += line.len() + 1;
+        }
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_comment_tokens(ctx, child, tokens);
+    }
+}
+
+fn push_comment_token(ctx: &ParserContext, offset: usize, text: &str, token_modifiers: u32, tokens: &mut Vec<RawToken>) {
+    // the indentation of a block comment's lines isn't part of the comment's text
+    let trimmed = text.trim_start();
+    if trimmed.trim_end().is_empty() {
+        return;
+    }
+
+    tokens.push(RawToken {
+        start: ctx.position_for_offset(offset + text.len() - trimmed.len()),
+        length: trimmed.encode_utf16().count() as u32,
+        token_type: COMMENT,
+        token_modifiers,
+    });
+}
+
+fn encode(tokens: &[RawToken]) -> Vec<SemanticToken> {
+    let mut prev = Position::new(0, 0);
+
+    tokens
+        .iter()
+        .map(|token| {
+            let delta_line = token.start.line - prev.line;
+            let delta_start = if delta_line == 0 {
+                token.start.character - prev.character
+            } else {
+                token.start.character
+            };
+            prev = token.start;
+
+            SemanticToken {
+                delta_line,
+                delta_start,
+                length: token.length,
+                token_type: token.token_type,
+                token_modifiers_bitset: token.token_modifiers,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod semantic_tokens_test {
+    use tree_sitter::Parser;
+
+    use crate::navigation::ParserContext;
+    use crate::semantic_tokens::{semantic_tokens, COMMENT, TODO};
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_comment_tokens() {
+        let source = "/* shading\n   TODO: é shadows */\nvoid main() {} // FIXME\n";
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+
+        // comments are left to the client's grammar unless asked for
+        assert!(semantic_tokens(&ctx, false).data.is_empty());
+
+        let tokens: Vec<(u32, u32, u32, u32, u32)> = semantic_tokens(&ctx, true)
+            .data
+            .into_iter()
+            .map(|token| {
+                (
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                )
+            })
+            .collect();
+
+        // a token per line of the block comment, without its indentation, and the markers split out of the comment
+        assert_eq!(
+            tokens,
+            vec![
+                (0, 0, 10, COMMENT, 0),
+                (1, 3, 4, COMMENT, TODO),
+                (0, 4, 14, COMMENT, 0),
+                (1, 15, 3, COMMENT, 0),
+                (0, 3, 5, COMMENT, TODO),
+            ]
+        );
+    }
+}
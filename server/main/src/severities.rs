@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use rust_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::configuration::SeverityOverride;
+
+/// The code diagnostics of the driver's compiler are overridden by, as they come without a code of their own.
+pub const COMPILER_CODE: &str = "compiler";
+
+/// Gives diagnostics the severity configured for their code, as `-Werror` does for a compiler's warnings, and drops
+/// those turned off altogether. Diagnostics of codes without an override keep their own severity.
+pub fn apply(diagnostics: Vec<Diagnostic>, overrides: &HashMap<String, SeverityOverride>) -> Vec<Diagnostic> {
+    if overrides.is_empty() {
+        return diagnostics;
+    }
+
+    diagnostics
+        .into_iter()
+        .filter_map(|mut diagnostic| {
+            let code = match &diagnostic.code {
+                Some(NumberOrString::String(code)) => code.clone(),
+                Some(NumberOrString::Number(code)) => code.to_string(),
+                None => COMPILER_CODE.to_string(),
+            };
+
+            diagnostic.severity = match overrides.get(&code) {
+                Some(SeverityOverride::Error) => Some(DiagnosticSeverity::ERROR),
+                Some(SeverityOverride::Warning) => Some(DiagnosticSeverity::WARNING),
+                Some(SeverityOverride::Information) => Some(DiagnosticSeverity::INFORMATION),
+                Some(SeverityOverride::Hint) => Some(DiagnosticSeverity::HINT),
+                Some(SeverityOverride::Off) => return None,
+                None => diagnostic.severity,
+            };
+            Some(diagnostic)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod severities_test {
+    use std::collections::HashMap;
+
+    use rust_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+
+    use crate::configuration::{InitializationOptions, SeverityOverride};
+    use crate::severities;
+
+    fn diagnostic(code: Option<&str>, severity: DiagnosticSeverity) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(0, 0), Position::new(0, 1)),
+            severity: Some(severity),
+            code: code.map(|code| NumberOrString::String(code.into())),
+            message: "message".into(),
+            ..Diagnostic::default()
+        }
+    }
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_severity_overrides() {
+        let options: InitializationOptions = serde_json::from_value(serde_json::json!({
+            "diagnosticSeverities": {
+                "integer-division": "error",
+                "unreachable-code": "off",
+                "compiler": "warning",
+            }
+        }))
+        .unwrap();
+        assert_eq!(options.diagnostic_severities["integer-division"], SeverityOverride::Error);
+
+        let diagnostics = severities::apply(
+            vec![
+                diagnostic(Some("integer-division"), DiagnosticSeverity::HINT),
+                diagnostic(Some("unreachable-code"), DiagnosticSeverity::WARNING),
+                diagnostic(Some("syntax-error"), DiagnosticSeverity::ERROR),
+                diagnostic(None, DiagnosticSeverity::ERROR),
+            ],
+            &options.diagnostic_severities,
+        );
+
+        // elevated, suppressed, untouched and the compiler's own in turn
+        let severities: Vec<_> = diagnostics.iter().map(|d| (d.code.clone(), d.severity)).collect();
+        assert_eq!(
+            severities,
+            vec![
+                (
+                    Some(NumberOrString::String("integer-division".into())),
+                    Some(DiagnosticSeverity::ERROR)
+                ),
+                (Some(NumberOrString::String("syntax-error".into())), Some(DiagnosticSeverity::ERROR)),
+                (None, Some(DiagnosticSeverity::WARNING)),
+            ]
+        );
+
+        assert_eq!(
+            severities::apply(vec![diagnostic(None, DiagnosticSeverity::HINT)], &HashMap::new()).len(),
+            1
+        );
+    }
+}
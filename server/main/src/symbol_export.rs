@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use rust_lsp::lsp_types::{SymbolInformation, SymbolKind};
+use serde_json::json;
+use slog_scope::warn;
+use tree_sitter::Parser;
+use url::Url;
+
+use crate::navigation::ParserContext;
+use crate::url_norm::FromUrl;
+
+/// The version of the schema written by `write_symbols`. It's bumped whenever a field is removed or changes meaning,
+/// while fields may be added without bumping it, so tooling should ignore the fields it doesn't know.
+pub const VERSION: u32 = 1;
+
+/// Writes the symbols as a JSON document for tooling built on the server's index, of the form
+/// `{"version": 1, "symbols": [...]}`. Each symbol has its `name`, `kind`, the `container` it's declared in if any,
+/// the `uri` and `range` of its name, and the `signature` of its declaration as copied by `copySignature`. The
+/// symbols are ordered by file and position, and serialized to the writer one at a time rather than as one string,
+/// as the index of a large pack can be. Returns the number of symbols written.
+pub fn write_symbols<W: Write>(parser: &mut Parser, symbols: Vec<SymbolInformation>, mut writer: W) -> Result<usize> {
+    let mut by_file: BTreeMap<Url, Vec<SymbolInformation>> = BTreeMap::new();
+    for symbol in symbols {
+        by_file.entry(symbol.location.uri.clone()).or_default().push(symbol);
+    }
+
+    write!(writer, "{{\"version\":{},\"symbols\":[", VERSION)?;
+
+    let mut count = 0;
+    for (uri, mut symbols) in by_file {
+        symbols.sort_by_key(|symbol| symbol.location.range.start);
+
+        // the symbols are still written without signatures if the file can't be read again
+        let path = PathBuf::from_url(uri);
+        let ctx = match ParserContext::new(parser, &path) {
+            Ok(ctx) => Some(ctx),
+            Err(e) => {
+                warn!("exporting symbols without signatures"; "error" => format!("{:?}", e), "path" => path.to_str().unwrap());
+                None
+            }
+        };
+
+        for symbol in symbols {
+            let signature = ctx.as_ref().and_then(|ctx| {
+                let start = ctx.point_for_position(symbol.location.range.start);
+                let end = ctx.point_for_position(symbol.location.range.end);
+                let name = ctx.root_node().named_descendant_for_point_range(start, end)?;
+                ctx.declaration_signature(name)
+            });
+
+            if count > 0 {
+                writer.write_all(b",")?;
+            }
+            serde_json::to_writer(
+                &mut writer,
+                &json!({
+                    "name": symbol.name,
+                    "kind": kind_name(symbol.kind),
+                    "container": symbol.container_name,
+                    "uri": symbol.location.uri,
+                    "range": symbol.location.range,
+                    "signature": signature,
+                }),
+            )?;
+            count += 1;
+        }
+    }
+
+    writer.write_all(b"]}")?;
+    writer.flush()?;
+
+    Ok(count)
+}
+
+// the names of the kinds of symbols listed for workspace symbols, which mark macros as strings.
+fn kind_name(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::FUNCTION => "function",
+        SymbolKind::VARIABLE => "variable",
+        SymbolKind::CONSTANT => "constant",
+        SymbolKind::STRING => "macro",
+        SymbolKind::STRUCT => "struct",
+        SymbolKind::FIELD => "field",
+        _ => "other",
+    }
+}
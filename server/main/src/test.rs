@@ -2,6 +2,7 @@ use super::*;
 use std::fs;
 use std::io;
 use std::io::Result;
+use std::sync::{Arc, Mutex};
 
 use pretty_assertions::assert_eq;
 
@@ -36,15 +37,34 @@ pub fn new_temp_server(opengl_context: Option<Box<dyn opengl::ShaderValidator>>)
 
     let context = opengl_context.unwrap_or_else(|| Box::new(opengl::MockShaderValidator::new()));
 
+    let mut parser = Parser::new();
+    parser.set_language(tree_sitter_glsl::language()).unwrap();
+
     MinecraftShaderLanguageServer {
         endpoint,
         graph: Rc::new(RefCell::new(graph::CachedStableGraph::new())),
         root: "".into(),
+        workspace_folders: vec![],
         command_provider: None,
         opengl_context: context.into(),
         log_guard: None,
-        tree_sitter: Rc::new(RefCell::new(Parser::new())),
+        tree_sitter: Rc::new(RefCell::new(parser)),
+        parser_pool: RefCell::new(parser_pool::ParserPool::new(1)),
+        lint_cache: RefCell::new(lint_cache::LintCache::default()),
+        workspace_diagnostics: RefCell::new(HashMap::new()),
+        definition_cache: RefCell::new(definition_cache::DefinitionCache::default()),
+        guessed_definitions_shown: Cell::new(false),
+        diagnostics: Rc::new(RefCell::new(HashMap::new())),
+        configuration: configuration::Configuration::default(),
+        severity_overrides: HashMap::new(),
+        include_roots: HashMap::new(),
+        document_versions: HashMap::new(),
+        document_sources: HashMap::new(),
+        document_linemaps: HashMap::new(),
+        cancellations: cancellation::Cancellations::default(),
+        work_done_progress: false,
     }
+    .with_parser_pool(2)
 }
 
 fn copy_files(files: &str, dest: &TempDir) {
@@ -279,3 +299,1203 @@ fn test_05_initialize() {
         assert!(contains, "doesn't contain ({:?}, {:?})", first, second);
     }
 }
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_07_definitions_ranked_by_include_proximity() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let locations = server.find_included_definitions(&shaders.join("final.fsh"), "SCALE").unwrap();
+
+    let paths: Vec<PathBuf> = locations.iter().map(|l| l.uri.to_file_path().unwrap()).collect();
+    // direct includes come first, ordered by path, then the transitive include
+    assert_eq!(
+        paths,
+        vec![
+            shaders.join("lib").join("a.glsl"),
+            shaders.join("lib").join("b.glsl"),
+            shaders.join("lib").join("common.glsl"),
+        ]
+    );
+    assert_eq!(locations[0].range.start.line, 3);
+
+    // function definitions are found as well
+    let locations = server.find_included_definitions(&shaders.join("final.fsh"), "luma").unwrap();
+    assert_eq!(locations.len(), 1);
+
+    assert!(server
+        .find_included_definitions(&shaders.join("final.fsh"), "missing")
+        .unwrap()
+        .is_empty());
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_07_included_definitions_encoding() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let common = shaders.join("lib").join("common.glsl");
+
+    // unlike the including file, the included one has CRLF line endings and characters taking two UTF-16 code units
+    fs::write(
+        &common,
+        "#define SCALE 1.0\r\n\r\n/* é𝄞 */ float luma(vec3 color) {\r\n\treturn dot(color, vec3(0.2126, 0.7152, 0.0722));\r\n}\r\n",
+    )
+    .unwrap();
+
+    // so the location is encoded by the included file's lines, rather than those of the file it was looked up from
+    let locations = server.find_included_definitions(&shaders.join("final.fsh"), "luma").unwrap();
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].uri.to_file_path().unwrap(), common);
+    assert_eq!(locations[0].range, Range::new(Position::new(2, 16), Position::new(2, 20)));
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_07_include_chain() {
+    use commands::Invokeable;
+
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let command = commands::include_chain::IncludeChainCommand {
+        graph: server.graph.clone(),
+        tree_sitter: server.tree_sitter.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+    let chain = |path: &Path, position: Position| {
+        command
+            .run_command(
+                &workspace,
+                &[
+                    serde_json::Value::String(path.to_str().unwrap().into()),
+                    serde_json::to_value(position).unwrap(),
+                ],
+            )
+            .unwrap()
+    };
+
+    // of the direct includes defining the macro the first by path is taken, included from the file itself
+    let result = chain(&final_fsh, Position::new(6, 23));
+    assert_eq!(
+        result["definition"]["uri"],
+        Url::from_file_path(shaders.join("lib").join("a.glsl")).unwrap().as_str()
+    );
+    let includes = result["includes"].as_array().unwrap();
+    assert_eq!(includes.len(), 1);
+    assert_eq!(includes[0]["location"]["uri"], Url::from_file_path(&final_fsh).unwrap().as_str());
+    assert_eq!(
+        includes[0]["location"]["range"]["start"],
+        serde_json::json!({"line": 3, "character": 10})
+    );
+
+    // names defined in the file itself need no includes, and names defined nowhere have no chain at all
+    assert!(chain(&final_fsh, Position::new(5, 6))["includes"].as_array().unwrap().is_empty());
+    assert_eq!(chain(&final_fsh, Position::new(6, 3)), serde_json::Value::Null);
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_07_peek_included_definitions() {
+    use commands::Invokeable;
+
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let command = commands::peek_definition::PeekDefinitionCommand {
+        tree_sitter: server.tree_sitter.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+
+    let final_fsh = tmp_path.join("shaders").join("final.fsh");
+    let peeks = command
+        .run_command(
+            &workspace,
+            &[
+                serde_json::Value::String(final_fsh.to_str().unwrap().into()),
+                serde_json::to_value(Position::new(6, 23)).unwrap(),
+            ],
+        )
+        .unwrap();
+
+    // each definition's statement is read from the included file it's in
+    let contexts: Vec<&str> = peeks
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|peek| peek["context"].as_str().unwrap())
+        .collect();
+    assert_eq!(contexts, vec!["#define SCALE 0.5", "#define SCALE 2.0", "#define SCALE 1.0"]);
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_07_active_macros() {
+    use commands::Invokeable;
+
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let command = commands::active_macros::ActiveMacrosCommand {
+        graph: server.graph.clone(),
+        tree_sitter: server.tree_sitter.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+    let macros = |position: Position| {
+        let macros = command
+            .run_command(
+                &workspace,
+                &[
+                    serde_json::Value::String(final_fsh.to_str().unwrap().into()),
+                    serde_json::to_value(position).unwrap(),
+                ],
+            )
+            .unwrap();
+        macros.as_array().unwrap().clone()
+    };
+    let defined = |position: Position| {
+        macros(position)
+            .into_iter()
+            .filter(|info| info["source"] == "define")
+            .map(|info| (info["value"].as_str().unwrap().to_string(), info["location"]["uri"].clone()))
+            .collect::<Vec<_>>()
+    };
+    let uri = |file: &str| serde_json::Value::String(Url::from_file_path(shaders.join("lib").join(file)).unwrap().to_string());
+
+    // predefined macros are always in effect
+    let predefined = macros(Position::new(0, 0));
+    assert!(predefined
+        .iter()
+        .any(|info| info["name"] == "MC_VERSION" && info["source"] == "optifine"));
+    assert!(predefined.iter().all(|info| info["source"] != "define"));
+
+    // only the includes ahead of the position count, in the order they're included
+    assert_eq!(defined(Position::new(3, 0)), vec![("2.0".to_string(), uri("b.glsl"))]);
+
+    // where `a.glsl` includes `common.glsl` redefining `SCALE` again, then undefines and redefines it
+    assert_eq!(defined(Position::new(6, 0)), vec![("0.5".to_string(), uri("a.glsl"))]);
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_08_stage_interface_mismatches() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/08", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let vertex = shaders.join("composite.vsh");
+    let fragment = shaders.join("composite.fsh");
+
+    // only the fragment stage's own declarations are reported when linting it
+    let diagnostics = server.lint_stage_interface(&fragment).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    let fragment_diagnostics = &diagnostics[&Url::from_file_path(&fragment).unwrap()];
+    assert_eq!(fragment_diagnostics.len(), 1);
+    assert_eq!(fragment_diagnostics[0].range.start, Position::new(4, 8));
+    assert_eq!(
+        fragment_diagnostics[0].message,
+        "`vec3 normal` doesn't match the type of the vertex stage output `vec4 normal`"
+    );
+    let related = &fragment_diagnostics[0].related_information.as_ref().unwrap()[0];
+    assert_eq!(related.location.uri, Url::from_file_path(&vertex).unwrap());
+    assert_eq!(related.location.range.start, Position::new(4, 9));
+
+    // the varying shared through the include matches on both sides
+    let diagnostics = server.lint_stage_interface(&vertex).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    let mut vertex_diagnostics = diagnostics[&Url::from_file_path(&vertex).unwrap()].clone();
+    vertex_diagnostics.sort_by_key(|d| d.range.start.line);
+    assert_eq!(vertex_diagnostics.len(), 2);
+    assert_eq!(vertex_diagnostics[0].range.start.line, 4);
+    assert_eq!(vertex_diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(vertex_diagnostics[1].range.start.line, 5);
+    assert_eq!(vertex_diagnostics[1].severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(
+        vertex_diagnostics[1].message,
+        "vertex stage output `unused` is not an input of the fragment stage"
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_07_watched_header_deleted() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+    let header = shaders.join("lib").join("a.glsl");
+
+    fs::remove_file(&header).unwrap();
+    server.did_change_watched_files(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: Url::from_file_path(&header).unwrap(),
+            typ: FileChangeType::DELETED,
+        }],
+    });
+
+    // the header is still included, but no longer brings in its own includes
+    let header_node = server.graph.borrow_mut().find_node(&header).unwrap();
+    assert_eq!(server.graph.borrow().get_all_child_positions(header_node).count(), 0);
+
+    let locations = server.find_included_definitions(&final_fsh, "SCALE").unwrap();
+    let paths: Vec<PathBuf> = locations.iter().map(|l| l.uri.to_file_path().unwrap()).collect();
+    assert_eq!(paths, vec![shaders.join("lib").join("b.glsl")]);
+
+    let diagnostics = server.unresolved_includes(&final_fsh);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].range.start, Position::new(3, 10));
+    assert_eq!(diagnostics[0].code, Some(NumberOrString::String("unresolved-include".into())));
+
+    // the range is in UTF-16 characters, rather than the bytes of a path that isn't ASCII
+    let accented = shaders.join("accented.fsh");
+    fs::write(&accented, "#include \"/lib/\u{fc}mlaut.glsl\"\n").unwrap();
+    let diagnostics = server.unresolved_includes(&accented);
+    assert_eq!(diagnostics[0].range, Range::new(Position::new(0, 10), Position::new(0, 26)));
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_cached_definitions_follow_includes() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/07", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+    let common = shaders.join("lib").join("common.glsl");
+
+    // peeking at the same function again is answered from the cache
+    let locations = server.find_included_definitions(&final_fsh, "luma").unwrap();
+    assert_eq!(locations[0].range.start, Position::new(2, 6));
+    assert_eq!(server.find_included_definitions(&final_fsh, "luma").unwrap(), locations);
+    assert_eq!(server.definition_cache.borrow().hit_rate(), 0.5);
+
+    // the function moving in the transitively included file invalidates the cached definition
+    let source = fs::read_to_string(&common).unwrap();
+    fs::write(&common, format!("// moved down\n{}", source)).unwrap();
+    server.did_change_watched_files(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: Url::from_file_path(&common).unwrap(),
+            typ: FileChangeType::CHANGED,
+        }],
+    });
+
+    let locations = server.find_included_definitions(&final_fsh, "luma").unwrap();
+    assert_eq!(locations[0].range.start, Position::new(3, 6));
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_include_roots() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/01", &mut server);
+
+    let external = tmp_path.join("external");
+    fs::create_dir(&external).unwrap();
+    fs::rename(tmp_path.join("shaders").join("common.glsl"), external.join("common.glsl")).unwrap();
+
+    // roots that don't exist are left out
+    server.set_include_roots(&["missing".into(), "external".into(), "external/".into()]);
+    assert_eq!(server.include_roots[&tmp_path], vec![external.clone()]);
+
+    server.build_initial_graph();
+
+    let final_node = server
+        .graph
+        .borrow_mut()
+        .find_node(&tmp_path.join("shaders").join("final.fsh"))
+        .unwrap();
+    let children: Vec<PathBuf> = server
+        .graph
+        .borrow()
+        .get_all_child_positions(final_node)
+        .map(|(child, _)| server.graph.borrow().get_node(child))
+        .collect();
+    assert_eq!(children, vec![external.join("common.glsl")]);
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_macro_includes() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/01", &mut server);
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+    fs::write(
+        &final_fsh,
+        "#version 120\n#define COMMON_HEADER \"/common.glsl\"\n#define HEADER COMMON_HEADER\n#include HEADER\n#include QUALITY\n",
+    )
+    .unwrap();
+
+    // the macro is expanded through the macros it expands to, while one expanding to no path includes nothing
+    let includes = server.find_includes(&final_fsh);
+    assert_eq!(includes.len(), 1);
+    assert_eq!(includes[0].0, shaders.join("common.glsl"));
+    assert_eq!((includes[0].1.line, includes[0].1.start, includes[0].1.end), (3, 9, 15));
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_rename_document_changes() {
+    let mut server = new_temp_server(None);
+
+    let tmp_dir = TempDir::new("mcshader").unwrap();
+    let path = tmp_dir.path().join("lib.glsl");
+    let uri = Url::from_file_path(&path).unwrap();
+    let source = "struct Light {\n    vec3 color;\n};\n\nvec3 tint(Light light) {\n    return light.color;\n}\n";
+    fs::write(&path, source).unwrap();
+
+    let change = |server: &mut MinecraftShaderLanguageServer, version: i32, text: &str| {
+        server.did_change_text_document(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier { uri: uri.clone(), version },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.to_string(),
+            }],
+        })
+    };
+
+    server.did_open_text_document(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: uri.clone(),
+            language_id: "glsl".into(),
+            version: 3,
+            text: source.to_string(),
+        },
+    });
+    change(&mut server, 4, source);
+
+    let rename = |server: &MinecraftShaderLanguageServer, position| server.rename_edit(&path, position, "albedo").unwrap().unwrap();
+    let starts = |edit: &TextDocumentEdit| -> Vec<Position> {
+        edit.edits
+            .iter()
+            .map(|edit| match edit {
+                OneOf::Left(edit) => edit.range.start,
+                OneOf::Right(edit) => edit.text_edit.range.start,
+            })
+            .collect()
+    };
+
+    let edit = rename(&server, Position::new(5, 18));
+    assert_eq!(edit.changes, None);
+    let document_edits = match edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => edits,
+        other => panic!("expected text document edits, got {:?}", other),
+    };
+    assert_eq!(document_edits.len(), 1);
+    assert_eq!(document_edits[0].text_document.uri, uri);
+    assert_eq!(document_edits[0].text_document.version, Some(4));
+    assert_eq!(starts(&document_edits[0]), vec![Position::new(1, 9), Position::new(5, 17)]);
+
+    // unsaved changes are edited as they are in the document, carrying its version
+    change(&mut server, 5, &source.replace("light", "l"));
+    match rename(&server, Position::new(5, 14)).document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            assert_eq!(edits[0].text_document.version, Some(5));
+            assert_eq!(starts(&edits[0]), vec![Position::new(1, 9), Position::new(5, 13)]);
+        }
+        other => panic!("expected text document edits, got {:?}", other),
+    }
+
+    // once closed, the file is edited as it is on disk
+    server.did_close_text_document(DidCloseTextDocumentParams {
+        text_document: TextDocumentIdentifier { uri },
+    });
+    match rename(&server, Position::new(5, 18)).document_changes {
+        Some(DocumentChanges::Edits(edits)) => assert_eq!(edits[0].text_document.version, None),
+        other => panic!("expected text document edits, got {:?}", other),
+    }
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_11_references_limit() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/11", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let common = shaders.join("lib").join("common.glsl");
+
+    let references = |server: &MinecraftShaderLanguageServer| -> Vec<(PathBuf, u32)> {
+        server
+            .find_workspace_references(&common, "luma", None, &CancellationToken::default())
+            .unwrap()
+            .iter()
+            .map(|l| (l.uri.to_file_path().unwrap(), l.range.start.line))
+            .collect()
+    };
+
+    // the files including the declaring file are searched nearest first, and unrelated files not at all
+    assert_eq!(
+        references(&server),
+        vec![
+            (shaders.join("composite.fsh"), 5),
+            (shaders.join("lib").join("tonemap.glsl"), 3),
+            (shaders.join("lib").join("tonemap.glsl"), 3),
+            (shaders.join("final.fsh"), 5),
+        ]
+    );
+
+    // while the references to the declaration under the cursor are sorted by file, then by position
+    let sorted: Vec<(PathBuf, u32, u32)> = server
+        .find_all_references(&common, Position::new(0, 7), None, &CancellationToken::default())
+        .unwrap()
+        .iter()
+        .map(|l| (l.uri.to_file_path().unwrap(), l.range.start.line, l.range.start.character))
+        .collect();
+    assert_eq!(
+        sorted,
+        vec![
+            (shaders.join("composite.fsh"), 5, 26),
+            (shaders.join("final.fsh"), 5, 45),
+            (shaders.join("lib").join("tonemap.glsl"), 3, 23),
+            (shaders.join("lib").join("tonemap.glsl"), 3, 48),
+        ]
+    );
+
+    server.configuration.references_limit = 2;
+    assert_eq!(
+        references(&server),
+        vec![(shaders.join("composite.fsh"), 5), (shaders.join("lib").join("tonemap.glsl"), 3)]
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_11_code_lens_references() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/11", &mut server);
+    server.build_initial_graph();
+
+    let common = tmp_path.join("shaders").join("lib").join("common.glsl");
+
+    let result = Arc::new(Mutex::new(None));
+    let response = result.clone();
+    let on_response = move |resp: Option<Response>| match resp.unwrap().result_or_error {
+        ResponseResult::Result(value) => *response.lock().unwrap() = Some(value),
+        ResponseResult::Error(e) => panic!("expected ResponseResult::Result(..), got {:?}", e),
+    };
+
+    let completable = MethodCompletable::new(ResponseCompletable::new(Some(Id::Number(1)), Box::new(on_response.clone())));
+    server.code_lens(
+        CodeLensParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(&common).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        },
+        completable,
+    );
+    let lenses: Vec<CodeLens> = from_value(result.lock().unwrap().take().unwrap()).unwrap();
+    assert_eq!(lenses.len(), 1);
+    assert_eq!(lenses[0].range, Range::new(Position::new(0, 6), Position::new(0, 10)));
+
+    let completable = MethodCompletable::new(ResponseCompletable::new(Some(Id::Number(2)), Box::new(on_response)));
+    server.code_lens_resolve(lenses[0].clone(), completable);
+    let lens: CodeLens = from_value(result.lock().unwrap().take().unwrap()).unwrap();
+
+    // `luma` isn't called in its own file, only by the files including it
+    let command = lens.command.unwrap();
+    assert_eq!(command.title, "4 references");
+    let references: Vec<Location> = from_value(command.arguments.unwrap()[2].clone()).unwrap();
+    assert_eq!(
+        references.iter().map(|l| l.uri.to_file_path().unwrap()).collect::<Vec<_>>(),
+        vec![
+            tmp_path.join("shaders").join("composite.fsh"),
+            tmp_path.join("shaders").join("final.fsh"),
+            tmp_path.join("shaders").join("lib").join("tonemap.glsl"),
+            tmp_path.join("shaders").join("lib").join("tonemap.glsl"),
+        ]
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_12_unresolved_symbols() {
+    use commands::Invokeable;
+
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/12", &mut server);
+    server.build_initial_graph();
+
+    let command = commands::unresolved_symbols::UnresolvedSymbolsCommand {
+        graph: server.graph.clone(),
+        tree_sitter: server.tree_sitter.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+
+    let shaders = tmp_path.join("shaders");
+    let unresolved = |path: PathBuf| -> Vec<(String, String, usize)> {
+        let result = command
+            .run_command(&workspace, &[serde_json::Value::String(path.to_str().unwrap().into())])
+            .unwrap();
+        result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol["name"].as_str().unwrap().to_string(),
+                    symbol["kind"].as_str().unwrap().to_string(),
+                    symbol["locations"].as_array().unwrap().len(),
+                )
+            })
+            .collect()
+    };
+
+    // each name is listed once, by the kind of its first use, while built-ins and included functions resolve
+    assert_eq!(
+        unresolved(shaders.join("final.fsh")),
+        vec![
+            ("Material".to_string(), "type".to_string(), 2),
+            ("tonemap".to_string(), "function".to_string(), 1),
+            ("sampleShadow".to_string(), "function".to_string(), 2),
+        ]
+    );
+
+    // includes see the definitions of the other files included by the files including them
+    assert_eq!(
+        unresolved(shaders.join("lib").join("shading.glsl")),
+        vec![("exposure".to_string(), "function".to_string(), 1)]
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_13_definition_on_declaration() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/11", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let common = shaders.join("lib").join("common.glsl");
+    let tonemap = shaders.join("lib").join("tonemap.glsl");
+
+    let declaration = Position::new(0, 6);
+    let definitions = server.find_included_definitions(&common, "luma").unwrap();
+    assert_eq!(definitions.iter().map(|l| l.range.start).collect::<Vec<_>>(), vec![declaration]);
+
+    let lines = |locations: Vec<Location>| -> Vec<(PathBuf, u32)> {
+        locations
+            .iter()
+            .map(|l| (l.uri.to_file_path().unwrap(), l.range.start.line))
+            .collect()
+    };
+
+    // already on the declaration, so the calls of the function are given instead
+    assert_eq!(
+        lines(server.definitions_or_references(&common, declaration, definitions.clone()).unwrap()),
+        vec![
+            (shaders.join("composite.fsh"), 5),
+            (tonemap.clone(), 3),
+            (tonemap.clone(), 3),
+            (shaders.join("final.fsh"), 5),
+        ]
+    );
+
+    server.configuration.definition_on_declaration = configuration::DefinitionOnDeclaration::None;
+    assert!(server
+        .definitions_or_references(&common, declaration, definitions.clone())
+        .unwrap()
+        .is_empty());
+
+    // going from a call is left alone
+    assert_eq!(
+        server
+            .definitions_or_references(&tonemap, Position::new(3, 23), definitions.clone())
+            .unwrap(),
+        definitions
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_14_workspace_folders() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_tmp_dir("./testdata/14");
+    let pack_a = tmp_path.join("shaders").join("pack_a");
+    let pack_b = tmp_path.join("shaders").join("pack_b");
+
+    server.root = pack_a.clone();
+    server.workspace_folders = vec![pack_b.clone()];
+    server.build_initial_graph();
+
+    assert_eq!(
+        server.workspace_folder(&pack_b.join("shaders").join("final.fsh")),
+        Some(pack_b.as_path())
+    );
+    assert_eq!(server.workspace_folder(&tmp_path.join("final.fsh")), None);
+
+    let command = commands::affected_outputs::AffectedOutputsCommand {
+        graph: server.graph.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+
+    // both packs define the function, but each only sees its own
+    for pack in [&pack_a, &pack_b] {
+        let final_fsh = pack.join("shaders").join("final.fsh");
+        let common = pack.join("shaders").join("lib").join("common.glsl");
+
+        let files = |locations: Vec<Location>| -> Vec<PathBuf> { locations.iter().map(|l| l.uri.to_file_path().unwrap()).collect() };
+
+        assert_eq!(
+            files(server.find_included_definitions(&final_fsh, "luma").unwrap()),
+            vec![common.clone()]
+        );
+        // and each pack's stage files are told apart by their path within the pack
+        assert_eq!(command.affected_outputs(&workspace, &common), vec![final_fsh.clone()]);
+        assert_eq!(
+            files(
+                server
+                    .find_workspace_references(&common, "luma", None, &CancellationToken::default())
+                    .unwrap()
+            ),
+            vec![final_fsh]
+        );
+    }
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_15_heuristic_definitions() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/15", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+
+    // the file defining the function isn't included
+    assert!(server.find_included_definitions(&final_fsh, "tonemap").unwrap().is_empty());
+
+    // so it's only found by its name, and not confused with the field of the same name
+    let definitions = server.find_heuristic_definitions(&final_fsh, "tonemap");
+    assert_eq!(
+        definitions
+            .iter()
+            .map(|l| (l.uri.to_file_path().unwrap(), l.range.start))
+            .collect::<Vec<_>>(),
+        vec![(shaders.join("lib").join("tonemap.glsl"), Position::new(4, 5))]
+    );
+
+    assert!(server.find_heuristic_definitions(&final_fsh, "exposure").is_empty());
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_16_include_cycles() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/16", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let cycles = |path: PathBuf| -> Vec<(Position, String)> {
+        server
+            .include_cycles(&path)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.range.start, diagnostic.message))
+            .collect()
+    };
+
+    // each header's include of the other closes the cycle, named from the header including
+    assert_eq!(
+        cycles(shaders.join("lib").join("a.glsl")),
+        vec![(
+            Position::new(0, 10),
+            "include cycle: shaders/lib/a.glsl -> shaders/lib/b.glsl -> shaders/lib/a.glsl".to_string()
+        )]
+    );
+    assert_eq!(
+        cycles(shaders.join("lib").join("b.glsl")),
+        vec![(
+            Position::new(0, 10),
+            "include cycle: shaders/lib/b.glsl -> shaders/lib/a.glsl -> shaders/lib/b.glsl".to_string()
+        )]
+    );
+
+    // while the file including the cycle isn't part of it
+    assert!(cycles(shaders.join("final.fsh")).is_empty());
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_17_included_struct_constructor() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/17", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let final_fsh = shaders.join("final.fsh");
+
+    // the constructor call isn't defined in the file calling it
+    {
+        let parser = &mut server.tree_sitter.borrow_mut();
+        let ctx = navigation::ParserContext::new(parser, &final_fsh).unwrap();
+        let locations = ctx.find_definitions(&final_fsh, Position::new(5, 14)).unwrap().unwrap();
+        assert!(locations.is_empty());
+    }
+
+    // so it's looked up in the includes, finding the struct rather than a function
+    let locations = server.find_included_definitions(&final_fsh, "Light").unwrap();
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].uri.to_file_path().unwrap(), shaders.join("lib").join("light.glsl"));
+    assert_eq!(locations[0].range, Range::new(Position::new(0, 7), Position::new(0, 12)));
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_18_loose_file_definitions() {
+    let mut server = new_temp_server(None);
+
+    // the file is opened from outside of the workspace, which is empty
+    let workspace = TempDir::new("mcshader").unwrap();
+    server.root = workspace.path().to_path_buf();
+    let (_tmp_dir, tmp_path) = copy_to_tmp_dir("./testdata/18");
+    let loose = tmp_path.join("shaders");
+    let standalone = loose.join("standalone.fsh");
+
+    let definitions = |server: &mut MinecraftShaderLanguageServer, position: Position| -> Vec<Location> {
+        let result = Arc::new(Mutex::new(None));
+        let response = result.clone();
+        let on_response = move |resp: Option<Response>| match resp.unwrap().result_or_error {
+            ResponseResult::Result(value) => *response.lock().unwrap() = Some(from_value(value).unwrap()),
+            ResponseResult::Error(e) => panic!("expected ResponseResult::Result(..), got {:?}", e),
+        };
+        let completable = MethodCompletable::new(ResponseCompletable::new(Some(Id::Number(1)), Box::new(on_response)));
+        server.goto_definition(
+            TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(&standalone).unwrap(),
+                },
+                position,
+            },
+            completable,
+        );
+        let locations = result.lock().unwrap().take();
+        locations.expect("no response to the definition request")
+    };
+
+    // the relative include resolves next to the file
+    let locations = definitions(&mut server, Position::new(2, 12));
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].uri.to_file_path().unwrap(), loose.join("common.glsl"));
+
+    // names declared in the file resolve within it
+    let locations = definitions(&mut server, Position::new(6, 28));
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].range.start, Position::new(5, 7));
+
+    // and names declared in its includes within those, the includes being followed from disk as the file isn't
+    // part of the include graph
+    let locations = definitions(&mut server, Position::new(5, 21));
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].uri.to_file_path().unwrap(), loose.join("common.glsl"));
+    assert_eq!(locations[0].range.start, Position::new(0, 6));
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_19_open_document_definitions() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/19", &mut server);
+    server.build_initial_graph();
+
+    let final_fsh = tmp_path.join("shaders").join("final.fsh");
+    // the file defining `luma` is open with its definition unsaved, and included by nothing yet
+    let helpers = tmp_path.join("shaders").join("lib").join("helpers.glsl");
+    let helpers_source = "// unsaved\n\nfloat luma(vec3 color) {\n\treturn dot(color, vec3(0.2126, 0.7152, 0.0722));\n}\n";
+    server
+        .document_sources
+        .insert(Url::from_file_path(&final_fsh).unwrap(), fs::read_to_string(&final_fsh).unwrap());
+    server
+        .document_sources
+        .insert(Url::from_file_path(&helpers).unwrap(), helpers_source.into());
+
+    let definitions = |server: &mut MinecraftShaderLanguageServer| -> Vec<Location> {
+        let result = Arc::new(Mutex::new(None));
+        let response = result.clone();
+        let on_response = move |resp: Option<Response>| match resp.unwrap().result_or_error {
+            ResponseResult::Result(value) => *response.lock().unwrap() = Some(from_value(value).unwrap()),
+            ResponseResult::Error(e) => panic!("expected ResponseResult::Result(..), got {:?}", e),
+        };
+        let completable = MethodCompletable::new(ResponseCompletable::new(Some(Id::Number(1)), Box::new(on_response)));
+        server.goto_definition(
+            TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(&final_fsh).unwrap(),
+                },
+                position: Position::new(3, 27),
+            },
+            completable,
+        );
+        let locations = result.lock().unwrap().take();
+        locations.expect("no response to the definition request")
+    };
+
+    // the open documents are only searched when enabled
+    assert!(definitions(&mut server).is_empty());
+
+    server.configuration.definition_open_documents_fallback = true;
+    let locations = definitions(&mut server);
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].uri.to_file_path().unwrap(), helpers);
+    assert_eq!(locations[0].range.start, Position::new(2, 6));
+
+    // and once closed, the document isn't searched anymore
+    server.document_sources.remove(&Url::from_file_path(&helpers).unwrap());
+    assert!(definitions(&mut server).is_empty());
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_20_rename_included_field() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/20", &mut server);
+    server.build_initial_graph();
+
+    let final_fsh = tmp_path.join("shaders").join("final.fsh");
+    let light = tmp_path.join("shaders").join("lib").join("light.glsl");
+
+    let rename = |path: &Path, position| -> Vec<(PathBuf, Vec<Position>)> {
+        let edit = server.rename_edit(path, position, "albedo").unwrap().unwrap();
+        let document_edits = match edit.document_changes {
+            Some(DocumentChanges::Edits(edits)) => edits,
+            other => panic!("expected text document edits, got {:?}", other),
+        };
+        document_edits
+            .into_iter()
+            .map(|document_edit| {
+                assert_eq!(document_edit.text_document.version, None);
+                let starts = document_edit
+                    .edits
+                    .iter()
+                    .map(|edit| match edit {
+                        OneOf::Left(edit) => edit.range.start,
+                        OneOf::Right(edit) => edit.text_edit.range.start,
+                    })
+                    .collect();
+                (document_edit.text_document.uri.to_file_path().unwrap(), starts)
+            })
+            .collect()
+    };
+
+    // the field of the header's struct is renamed in both files, from either of them
+    let light_color = vec![
+        (final_fsh.clone(), vec![Position::new(10, 25)]),
+        (light.clone(), vec![Position::new(1, 6), Position::new(8, 14)]),
+    ];
+    assert_eq!(rename(&final_fsh, Position::new(10, 26)), light_color);
+    assert_eq!(rename(&light, Position::new(1, 7)), light_color);
+
+    // while the field of the same name in the including file's own struct stays in that file
+    assert_eq!(
+        rename(&final_fsh, Position::new(10, 43)),
+        vec![(final_fsh.clone(), vec![Position::new(5, 6), Position::new(10, 42)])]
+    );
+
+    assert!(server.rename_edit(&final_fsh, Position::new(10, 26), "2albedo").is_err());
+    assert_eq!(server.rename_edit(&final_fsh, Position::new(10, 22), "moon").unwrap(), None);
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_affected_outputs() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/11", &mut server);
+    server.build_initial_graph();
+
+    let command = commands::affected_outputs::AffectedOutputsCommand {
+        graph: server.graph.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+
+    let shaders = tmp_path.join("shaders");
+    let lib = shaders.join("lib");
+
+    // the stage files including the file, however indirectly, sorted by path
+    assert_eq!(
+        command.affected_outputs(&workspace, &lib.join("common.glsl")),
+        vec![shaders.join("composite.fsh"), shaders.join("final.fsh")]
+    );
+    assert_eq!(
+        command.affected_outputs(&workspace, &lib.join("tonemap.glsl")),
+        vec![shaders.join("final.fsh")]
+    );
+    // a stage file is affected by its own edits only
+    assert_eq!(
+        command.affected_outputs(&workspace, &shaders.join("gbuffers_basic.fsh")),
+        vec![shaders.join("gbuffers_basic.fsh")]
+    );
+
+    // headers including each other are both affected by either, through the stage file including the cycle
+    let mut server = new_temp_server(None);
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/16", &mut server);
+    server.build_initial_graph();
+
+    let command = commands::affected_outputs::AffectedOutputsCommand {
+        graph: server.graph.clone(),
+    };
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+    let shaders = tmp_path.join("shaders");
+    for header in ["a.glsl", "b.glsl"] {
+        assert_eq!(
+            command.affected_outputs(&workspace, &shaders.join("lib").join(header)),
+            vec![shaders.join("final.fsh")]
+        );
+    }
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_workspace_diagnostics() {
+    let mut mockgl = opengl::MockShaderValidator::new();
+    mockgl.expect_validate().returning(|_, _| None);
+    let mut server = new_temp_server(Some(Box::new(mockgl)));
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/08", &mut server);
+    server.build_initial_graph();
+
+    let shaders = tmp_path.join("shaders");
+    let vertex = shaders.join("composite.vsh");
+    let fragment = shaders.join("composite.fsh");
+
+    // the stage interface diagnostics found linting either stage are listed once each
+    let diagnostics = server.workspace_diagnostics(None, &CancellationToken::default());
+    let messages = |url: Url| -> Vec<String> {
+        let mut messages: Vec<String> = diagnostics[&url].iter().map(|d| d.message.clone()).collect();
+        messages.sort();
+        messages
+    };
+    assert!(messages(Url::from_file_path(&fragment).unwrap())
+        .contains(&"`vec3 normal` doesn't match the type of the vertex stage output `vec4 normal`".to_string()));
+    let vertex_messages = messages(Url::from_file_path(&vertex).unwrap());
+    let unused = "vertex stage output `unused` is not an input of the fragment stage".to_string();
+    assert_eq!(vertex_messages.iter().filter(|message| **message == unused).count(), 1);
+
+    // every file is linted once, and listing the diagnostics again reuses them
+    assert_eq!(server.workspace_diagnostics.borrow().len(), server.shader_files().len());
+    let hash = server.workspace_diagnostics.borrow()[&vertex].0;
+    assert_eq!(server.workspace_diagnostics(None, &CancellationToken::default()), diagnostics);
+    assert_eq!(server.workspace_diagnostics.borrow()[&vertex].0, hash);
+
+    // while an edit to the fragment stage relints the vertex stage checked against it
+    fs::write(&fragment, fs::read_to_string(&fragment).unwrap() + "\n").unwrap();
+    server.workspace_diagnostics(None, &CancellationToken::default());
+    assert_ne!(server.workspace_diagnostics.borrow()[&vertex].0, hash);
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_export_symbols() {
+    let mut server = new_temp_server(None);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/11", &mut server);
+    server.build_initial_graph();
+
+    let output = tmp_path.join("symbols.json");
+    let count = server.export_symbols(&output, None, &CancellationToken::default()).unwrap();
+
+    let exported: Value = serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(exported["version"], symbol_export::VERSION);
+    let symbols = exported["symbols"].as_array().unwrap();
+    assert_eq!(symbols.len(), count);
+
+    // the functions of each file in order, with the signatures they're declared with
+    let shaders = Url::from_file_path(tmp_path.join("shaders")).unwrap().to_string();
+    let functions: Vec<(String, &str)> = symbols
+        .iter()
+        .filter(|symbol| symbol["kind"] == "function")
+        .map(|symbol| {
+            let file = symbol["uri"].as_str().unwrap().strip_prefix(&shaders).unwrap();
+            (
+                format!("{}:{}", file, symbol["name"].as_str().unwrap()),
+                symbol["signature"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        functions,
+        vec![
+            ("/composite.fsh:main".to_string(), "void main()"),
+            ("/final.fsh:main".to_string(), "void main()"),
+            ("/gbuffers_basic.fsh:luma".to_string(), "float luma(vec3 color)"),
+            ("/gbuffers_basic.fsh:main".to_string(), "void main()"),
+            ("/lib/common.glsl:luma".to_string(), "float luma(vec3 color)"),
+            ("/lib/tonemap.glsl:tonemap".to_string(), "vec3 tonemap(vec3 color)"),
+        ]
+    );
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_cancelled_workspace_searches() {
+    let mut server = new_temp_server(None).with_parser_pool(1);
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/11", &mut server);
+    server.build_initial_graph();
+
+    let files = server.shader_files();
+    let uris = |symbols: Vec<SymbolInformation>| -> HashSet<Url> { symbols.into_iter().map(|symbol| symbol.location.uri).collect() };
+
+    let all = uris(server.list_workspace_symbols(&files, |_| true, None, &CancellationToken::default()));
+    assert!(all.len() > 1);
+
+    // cancelled while listing the symbols of the first file declaring any, the files after it aren't parsed
+    let cancellation = CancellationToken::default();
+    let filter = |_: &SymbolInformation| {
+        cancellation.cancel();
+        true
+    };
+    assert_eq!(uris(server.list_workspace_symbols(&files, filter, None, &cancellation)).len(), 1);
+
+    // searches cancelled before they start find nothing, and an export of nothing isn't written
+    let common = tmp_path.join("shaders").join("lib").join("common.glsl");
+    assert!(server
+        .find_workspace_references(&common, "luma", None, &cancellation)
+        .unwrap()
+        .is_empty());
+    assert!(server.workspace_diagnostics(None, &cancellation).is_empty());
+
+    let output = tmp_path.join("symbols.json");
+    assert!(server.export_symbols(&output, None, &cancellation).is_err());
+    assert!(!output.exists());
+}
+
+#[allow(deprecated)]
+#[test]
+#[logging_macro::log_scope]
+fn test_server_info() {
+    let mut mockgl = opengl::MockShaderValidator::new();
+    mockgl.expect_vendor().returning(|| "NVIDIA Corporation".into());
+    let mut server = new_temp_server(Some(Box::new(mockgl)));
+
+    let (_tmp_dir, tmp_path) = copy_to_and_set_root("./testdata/01", &mut server);
+    fs::create_dir(tmp_path.join("external")).unwrap();
+    server.set_include_roots(&["external".into()]);
+    server.command_provider = Some(commands::CustomCommandProvider::new(vec![
+        (
+            "graphDot",
+            Box::new(commands::graph_dot::GraphDotCommand {
+                graph: server.graph.clone(),
+            }),
+        ),
+        (
+            "serverInfo",
+            Box::new(commands::server_info::ServerInfoCommand {
+                opengl_context: server.opengl_context.clone(),
+            }),
+        ),
+    ]));
+
+    let workspace = CommandWorkspace {
+        server: &server,
+        token: None,
+    };
+    let info = server
+        .command_provider
+        .as_ref()
+        .unwrap()
+        .execute("serverInfo", &[], &workspace)
+        .unwrap();
+
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["capabilities"]["definitionProvider"], true);
+    assert_eq!(info["commands"], serde_json::json!(["graphDot", "serverInfo"]));
+    assert_eq!(info["workspaceFolders"], serde_json::json!([tmp_path]));
+    assert_eq!(
+        info["includeRoots"][tmp_path.to_str().unwrap()],
+        serde_json::json!([tmp_path.join("external")])
+    );
+    assert_eq!(info["grammar"]["abiVersion"], tree_sitter_glsl::language().version());
+    assert_eq!(info["validator"]["vendor"], "NVIDIA Corporation");
+}
@@ -0,0 +1,145 @@
+use rust_lsp::lsp_types::{Position, SymbolKind};
+
+use crate::{
+    lsp_ext::TypeHierarchyItem,
+    navigation::{ParserContext, StructDefinition},
+};
+
+/// Relates the structs defined by a set of files through composition, as GLSL has no inheritance. The supertypes
+/// of a struct are the structs having a field of it, and its subtypes are the structs its own fields are of.
+pub struct StructIndex {
+    structs: Vec<StructDefinition>,
+}
+
+impl StructIndex {
+    pub fn new(structs: Vec<StructDefinition>) -> Self {
+        StructIndex { structs }
+    }
+
+    /// Returns the items of the struct named at the point, whether where it's defined or where it's used as a type.
+    pub fn prepare(&self, ctx: &ParserContext, point: Position) -> Option<Vec<TypeHierarchyItem>> {
+        let node = ctx.find_node_at_point(point).filter(|node| node.kind() == "type_identifier")?;
+        let name = node.utf8_text(ctx.source().as_bytes()).ok()?;
+
+        let items = self.items_named(name);
+        (!items.is_empty()).then_some(items)
+    }
+
+    pub fn supertypes(&self, item: &TypeHierarchyItem) -> Vec<TypeHierarchyItem> {
+        let mut items = vec![];
+        for definition in &self.structs {
+            // a struct can't contain itself, but a malformed one may claim to
+            if definition.name != item.name && definition.field_types.contains(&item.name) {
+                push_unique(&mut items, to_item(definition));
+            }
+        }
+        items
+    }
+
+    pub fn subtypes(&self, item: &TypeHierarchyItem) -> Vec<TypeHierarchyItem> {
+        let definition = self
+            .structs
+            .iter()
+            .find(|definition| definition.location.uri == item.uri && definition.location.range == item.selection_range);
+
+        let mut items = vec![];
+        for ty in definition.map_or(&[][..], |definition| definition.field_types.as_slice()) {
+            if *ty != item.name {
+                for field_item in self.items_named(ty) {
+                    push_unique(&mut items, field_item);
+                }
+            }
+        }
+        items
+    }
+
+    fn items_named(&self, name: &str) -> Vec<TypeHierarchyItem> {
+        self.structs
+            .iter()
+            .filter(|definition| definition.name == name)
+            .map(to_item)
+            .collect()
+    }
+}
+
+fn to_item(definition: &StructDefinition) -> TypeHierarchyItem {
+    TypeHierarchyItem {
+        name: definition.name.clone(),
+        kind: SymbolKind::STRUCT,
+        uri: definition.location.uri.clone(),
+        range: definition.range,
+        selection_range: definition.location.range,
+    }
+}
+
+// structs included into several files are listed once.
+fn push_unique(items: &mut Vec<TypeHierarchyItem>, item: TypeHierarchyItem) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+#[cfg(test)]
+mod type_hierarchy_test {
+    use std::path::PathBuf;
+
+    use rust_lsp::lsp_types::Position;
+    use tree_sitter::Parser;
+
+    use crate::lsp_ext::TypeHierarchyItem;
+    use crate::navigation::ParserContext;
+    use crate::type_hierarchy::StructIndex;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_type_hierarchy() {
+        let source = r#"struct Attenuation {
+    float constant;
+    float linear;
+};
+
+struct Light {
+    vec3 color;
+    Attenuation attenuation;
+};
+
+struct Scene {
+    Light sun;
+    Light lamps[4];
+    Attenuation fog;
+    Scene nested;
+};
+
+uniform Scene scene;
+"#;
+
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_glsl::language()).unwrap();
+        let ctx = ParserContext::from_source(&mut parser, source.to_string()).unwrap();
+        let path = PathBuf::from("/pack/shaders/lib/lighting.glsl");
+
+        let index = StructIndex::new(ctx.struct_definitions(&path).unwrap());
+        let names = |items: Vec<TypeHierarchyItem>| items.into_iter().map(|item| item.name).collect::<Vec<_>>();
+
+        // from the definition, and from a use as a type
+        let light = index.prepare(&ctx, Position::new(5, 8)).unwrap();
+        assert_eq!(light, index.prepare(&ctx, Position::new(11, 5)).unwrap());
+        assert_eq!(light[0].selection_range.start, Position::new(5, 7));
+        assert_eq!(light[0].range.end, Position::new(8, 1));
+
+        assert_eq!(names(index.supertypes(&light[0])), vec!["Scene"]);
+        assert_eq!(names(index.subtypes(&light[0])), vec!["Attenuation"]);
+
+        let attenuation = index.prepare(&ctx, Position::new(0, 7)).unwrap();
+        assert_eq!(names(index.supertypes(&attenuation[0])), vec!["Light", "Scene"]);
+        assert!(index.subtypes(&attenuation[0]).is_empty());
+
+        // the struct refers to itself, which isn't composition
+        let scene = index.prepare(&ctx, Position::new(17, 8)).unwrap();
+        assert!(index.supertypes(&scene[0]).is_empty());
+        assert_eq!(names(index.subtypes(&scene[0])), vec!["Light", "Attenuation"]);
+
+        // not a struct
+        assert_eq!(index.prepare(&ctx, Position::new(6, 5)), None);
+    }
+}
@@ -1,7 +1,7 @@
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 use slog_scope::trace;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use path_slash::PathBufExt;
 use url::Url;
 
@@ -15,6 +15,51 @@ pub trait FromJson {
         Self: Sized;
 }
 
+/// Builds the `file://` URL for a path, erroring rather than panicking on paths that can't be represented as one,
+/// such as relative paths. The path is normalized first, so that differently written paths to a file, such as
+/// those resolved from relative includes, result in the same URL. On Windows it is additionally normalized to
+/// match the URLs sent by clients, see `normalize_windows_path`.
+pub fn url_for(path: &Path) -> Result<Url> {
+    #[cfg(target_family = "windows")]
+    let path = PathBuf::from(normalize_windows_path(&path.to_string_lossy()));
+
+    let path = normalize_path(&path);
+    Url::from_file_path(&path).map_err(|_| anyhow!("cannot convert path {:?} to a url", path))
+}
+
+/// Lexically resolves `.` and `..` components, so that differently written paths to the same file compare equal
+/// regardless of whether the file exists.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Strips the `\\?\` verbatim prefix added by `fs::canonicalize`, turning verbatim UNC paths back into `\\server\share`
+/// paths, and lowercases the drive letter as clients such as VSCode do, so that a path built from a client's URL
+/// converts back to an equivalent URL.
+#[cfg_attr(target_family = "unix", allow(dead_code))]
+pub fn normalize_windows_path(path: &str) -> String {
+    let path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(unc) => return format!(r"\\{}", unc),
+        None => path.strip_prefix(r"\\?\").unwrap_or(path),
+    };
+
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => format!("{}{}", drive.to_ascii_lowercase(), &path[1..]),
+        _ => path.to_string(),
+    }
+}
+
 impl FromUrl for PathBuf {
     #[cfg(target_family = "windows")]
     fn from_url(u: Url) -> Self {
@@ -71,3 +116,43 @@ impl FromJson for PathBuf {
         Ok(PathBuf::from_slash(path))
     }
 }
+
+#[cfg(test)]
+mod url_norm_test {
+    use crate::url_norm::normalize_windows_path;
+
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_normalize_windows_path() {
+        let cases = [
+            // as converted from the client's file:///c%3A/Users/pack/final.fsh
+            (r"c:\Users\pack\final.fsh", r"c:\Users\pack\final.fsh"),
+            (r"C:\Users\pack\final.fsh", r"c:\Users\pack\final.fsh"),
+            (r"\\?\C:\Users\pack\final.fsh", r"c:\Users\pack\final.fsh"),
+            (r"\\?\UNC\server\share\final.fsh", r"\\server\share\final.fsh"),
+            (r"\\server\share\final.fsh", r"\\server\share\final.fsh"),
+            ("/home/pack/final.fsh", "/home/pack/final.fsh"),
+        ];
+
+        for (path, expected) in cases {
+            assert_eq!(normalize_windows_path(path), expected, "{}", path);
+        }
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    #[logging_macro::log_scope]
+    fn test_url_for() {
+        use std::path::{Path, PathBuf};
+
+        use url::Url;
+
+        use crate::url_norm::{url_for, FromUrl};
+
+        let url = Url::parse("file:///home/pack/shaders/my%20world/final.fsh").unwrap();
+        assert_eq!(url_for(&PathBuf::from_url(url.clone())).unwrap(), url);
+        assert_eq!(url_for(Path::new("/home/pack/shaders/lib/../my world/./final.fsh")).unwrap(), url);
+
+        assert!(url_for(Path::new("shaders/final.fsh")).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+#include "/lib/common.glsl"
+
+#undef SCALE
+#define SCALE 0.5
@@ -0,0 +1,5 @@
+#define SCALE 1.0
+
+float luma(vec3 color) {
+	return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1 @@
+varying vec2 texcoord;
@@ -0,0 +1,3 @@
+#include "/lib/once.glsl"
+
+float a() { return once(); }
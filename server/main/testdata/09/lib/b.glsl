@@ -0,0 +1,3 @@
+#include "/lib/once.glsl"
+
+float b() { return once() * 2.0; }
@@ -0,0 +1,4 @@
+// included by both a.glsl and b.glsl
+#pragma once
+
+float once() { return 1.0; }
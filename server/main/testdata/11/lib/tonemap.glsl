@@ -0,0 +1,5 @@
+#include "/lib/common.glsl"
+
+vec3 tonemap(vec3 color) {
+	return color / (1.0 + luma(color)) * step(0.0, luma(color));
+}
@@ -0,0 +1,3 @@
+vec3 shade(vec3 color) {
+	return color * luma(color) * exposure(color);
+}
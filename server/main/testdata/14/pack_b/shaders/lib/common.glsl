@@ -0,0 +1,3 @@
+float luma(vec3 color) {
+	return max(color.r, max(color.g, color.b));
+}
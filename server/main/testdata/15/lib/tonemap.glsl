@@ -0,0 +1,7 @@
+struct Curve {
+	float tonemap;
+};
+
+vec3 tonemap(vec3 color) {
+	return color / (1.0 + luma(color));
+}
@@ -0,0 +1,5 @@
+#include "/lib/b.glsl"
+
+float a() {
+	return 1.0;
+}
@@ -0,0 +1,4 @@
+struct Light {
+	vec3 color;
+	float power;
+};
@@ -0,0 +1,10 @@
+struct Light {
+	vec3 color;
+	float intensity;
+};
+
+uniform Light sun;
+
+vec3 radiance(Light light) {
+	return light.color * light.intensity;
+}